@@ -0,0 +1,999 @@
+// requests to Yahoo Finance. charts are parsed with yahoo_finance_api's types,
+// but fetched with our own HTTP client since the library's can't be given a
+// proxy. the quote and quote summary endpoints need a session cookie plus a
+// "crumb" token, so the client holds on to both once it has them.
+use crate::config::redact_url;
+use crate::error::PortfolioError;
+use crate::metadata::{save_metadata, EarningsDate, Fundamentals, MetadataCache};
+use crate::portfolio::{is_asset_held, Asset};
+use crate::quotes::QuoteProvider;
+use chrono::{DateTime, NaiveDate};
+use reqwest::header::HeaderValue;
+use reqwest::StatusCode;
+use serde_json::Value;
+use std::collections::HashSet;
+use std::env;
+use std::sync::Mutex;
+use std::time::Instant;
+use tracing::{debug, warn};
+use yahoo_finance_api as yf;
+
+// where every endpoint below lives, unless the api-url setting points at a
+// mirror or caching proxy instead
+pub const DEFAULT_API_URL: &str = "https://query2.finance.yahoo.com";
+
+const CHART_PATH: &str = "/v8/finance/chart";
+
+// only sets the session cookie the crumb needs, so it isn't under the API URL
+const COOKIE_URL: &str = "https://fc.yahoo.com";
+
+const CRUMB_PATH: &str = "/v1/test/getcrumb";
+
+const SEARCH_PATH: &str = "/v1/finance/search";
+
+const QUOTE_SUMMARY_PATH: &str = "/v10/finance/quoteSummary";
+
+// the latest quotes of many tickers at once, given comma-separated
+const QUOTE_PATH: &str = "/v7/finance/quote";
+
+// how many tickers go in one request to QUOTE_PATH
+pub const QUOTE_BATCH_SIZE: usize = 20;
+
+// Yahoo rejects requests without a browser-like user agent
+pub const DEFAULT_USER_AGENT: &str =
+    "Mozilla/5.0 (X11; Linux x86_64; rv:109.0) Gecko/20100101 Firefox/118.0";
+
+pub struct YahooClient {
+    client: reqwest::Client,
+    // without a trailing slash
+    api_url: String,
+    crumb: Mutex<Option<String>>,
+}
+
+// the proxy requests go through and where it was set: the proxy setting, or
+// else the usual environment variables. every Yahoo URL is https, so
+// HTTP_PROXY is only used when HTTPS_PROXY isn't set
+pub fn proxy_url(
+    configured: Option<&str>,
+    env_var: impl Fn(&str) -> Option<String>,
+) -> Option<(String, &'static str)> {
+    if let Some(url) = configured {
+        return Some((url.to_string(), "the proxy setting"));
+    }
+    ["HTTPS_PROXY", "https_proxy", "HTTP_PROXY", "http_proxy"]
+        .into_iter()
+        .find_map(|name| {
+            env_var(name)
+                .filter(|url| !url.trim().is_empty())
+                .map(|url| (url, name))
+        })
+}
+
+// whether NO_PROXY, a comma separated list of hosts and domains, says to
+// reach the host directly. "yahoo.com" and ".yahoo.com" both cover every
+// subdomain, and "*" covers everything
+pub fn bypasses_proxy(no_proxy: &str, host: &str) -> bool {
+    let host = host.to_lowercase();
+    no_proxy
+        .split(',')
+        .map(|entry| entry.trim().trim_start_matches('.').to_lowercase())
+        .filter(|entry| !entry.is_empty())
+        .any(|entry| {
+            entry == "*"
+                || host == entry
+                || host
+                    .strip_suffix(&entry)
+                    .is_some_and(|rest| rest.ends_with('.'))
+        })
+}
+
+// the one HTTP client every Yahoo request goes through. reqwest's own
+// environment lookup is turned off so the proxy in use is the one logged
+pub fn http_client(configured_proxy: Option<&str>, user_agent: &str) -> reqwest::Client {
+    // an invalid header would make the client fail to build
+    let user_agent = if HeaderValue::from_str(user_agent).is_ok() {
+        user_agent
+    } else {
+        warn!(user_agent, "ignoring invalid user agent");
+        DEFAULT_USER_AGENT
+    };
+    debug!(user_agent, "HTTP user agent");
+    let mut builder = reqwest::Client::builder()
+        .cookie_store(true)
+        .user_agent(user_agent)
+        .no_proxy();
+    match proxy_url(configured_proxy, |name| env::var(name).ok()) {
+        None => debug!("not using a proxy"),
+        Some((url, source)) => match reqwest::Url::parse(&url) {
+            Ok(proxy) => {
+                let no_proxy = env::var("NO_PROXY")
+                    .or_else(|_| env::var("no_proxy"))
+                    .unwrap_or_default();
+                debug!(
+                    proxy = %redact_url(&url),
+                    from = source,
+                    no_proxy = %no_proxy,
+                    "using a proxy"
+                );
+                builder = builder.proxy(reqwest::Proxy::custom(move |target| {
+                    if bypasses_proxy(&no_proxy, target.host_str().unwrap_or_default()) {
+                        None
+                    } else {
+                        Some(proxy.clone())
+                    }
+                }));
+            }
+            Err(e) => {
+                warn!(proxy = %redact_url(&url), from = source, error = %e, "ignoring invalid proxy URL")
+            }
+        },
+    }
+    builder.build().expect("failed to build HTTP client")
+}
+
+impl YahooClient {
+    pub fn new(client: reqwest::Client, api_url: &str) -> YahooClient {
+        YahooClient {
+            client,
+            api_url: api_url.trim_end_matches('/').to_string(),
+            crumb: Mutex::new(None),
+        }
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("{}{}", self.api_url, path)
+    }
+
+    // the bars of a chart, with the same errors yahoo_finance_api gives so
+    // yahoo_error and quote_failure_reason describe them the same way
+    pub async fn chart(
+        &self,
+        ticker: &str,
+        interval: &str,
+        range: &str,
+    ) -> Result<yf::YResponse, yf::YahooError> {
+        let response = self
+            .client
+            .get(format!("{}/{}", self.url(CHART_PATH), ticker))
+            .query(&[
+                ("interval", interval),
+                ("range", range),
+                ("events", "div|split"),
+            ])
+            .send()
+            .await?;
+        match response.status() {
+            StatusCode::OK => yf::YResponse::from_json(response.json().await?),
+            status => Err(yf::YahooError::FetchFailed(status.to_string())),
+        }
+    }
+
+    async fn crumb(&self) -> Result<String, PortfolioError> {
+        if let Some(crumb) = self.crumb.lock().unwrap().clone() {
+            return Ok(crumb);
+        }
+        // this responds with an error status, but still sets the cookie we
+        // need. a mirror at the api-url may not need it, so not reaching it
+        // is left for the crumb request to fail on
+        if let Err(e) = self.client.get(COOKIE_URL).send().await {
+            debug!(error = %e, "failed to fetch the session cookie");
+        }
+        let response = self
+            .client
+            .get(self.url(CRUMB_PATH))
+            .send()
+            .await
+            .map_err(request_error)?;
+        let crumb = check_status(response, "crumb")?
+            .text()
+            .await
+            .map_err(request_error)?;
+        *self.crumb.lock().unwrap() = Some(crumb.clone());
+        Ok(crumb)
+    }
+
+    // fetches the given quote summary modules (e.g. "assetProfile") for a ticker,
+    // returning the result object that the modules are keyed under
+    pub async fn quote_summary(
+        &self,
+        ticker: &str,
+        modules: &[&str],
+    ) -> Result<Value, PortfolioError> {
+        let crumb = self.crumb().await?;
+        let url = format!("{}/{}", self.url(QUOTE_SUMMARY_PATH), ticker);
+        let started = Instant::now();
+        let response = self
+            .client
+            .get(url)
+            .query(&[("modules", modules.join(",")), ("crumb", crumb)])
+            .send()
+            .await
+            .map_err(request_error)?;
+        debug!(
+            ticker,
+            modules = %modules.join(","),
+            status = %response.status(),
+            elapsed_ms = started.elapsed().as_millis() as u64,
+            "quote summary response"
+        );
+        let mut json: Value = check_status(response, ticker)?
+            .json()
+            .await
+            .map_err(|e| PortfolioError::ParseError(e.to_string()))?;
+        let result = json["quoteSummary"]["result"][0].take();
+        if result.is_object() {
+            Ok(result)
+        } else {
+            Err(PortfolioError::ParseError(format!(
+                "no quote summary in response for ticker {}",
+                ticker
+            )))
+        }
+    }
+
+    // the latest quotes of up to QUOTE_BATCH_SIZE tickers in one request
+    pub async fn batch_quotes(
+        &self,
+        tickers: &[&str],
+    ) -> Result<Vec<(String, Option<TickerQuote>)>, PortfolioError> {
+        let crumb = self.crumb().await?;
+        let symbols = tickers.join(",");
+        let started = Instant::now();
+        let response = self
+            .client
+            .get(self.url(QUOTE_PATH))
+            .query(&[("symbols", symbols.as_str()), ("crumb", crumb.as_str())])
+            .send()
+            .await
+            .map_err(request_error)?;
+        debug!(
+            tickers = tickers.len(),
+            status = %response.status(),
+            elapsed_ms = started.elapsed().as_millis() as u64,
+            "batch quote response"
+        );
+        let json = check_status(response, &symbols)?
+            .json()
+            .await
+            .map_err(|e| PortfolioError::ParseError(e.to_string()))?;
+        parse_batch_quotes(&json)
+    }
+}
+
+impl YahooClient {
+    // recent headlines mentioning a ticker, newest first
+    pub async fn news(&self, ticker: &str, count: usize) -> Result<Vec<NewsItem>, PortfolioError> {
+        let response = self
+            .client
+            .get(self.url(SEARCH_PATH))
+            .query(&[
+                ("q", ticker.to_string()),
+                ("quotesCount", "0".to_string()),
+                ("newsCount", count.to_string()),
+            ])
+            .send()
+            .await
+            .map_err(request_error)?;
+        let json = check_status(response, ticker)?
+            .json()
+            .await
+            .map_err(|e| PortfolioError::ParseError(e.to_string()))?;
+        parse_news(&json)
+    }
+}
+
+fn request_error(e: reqwest::Error) -> PortfolioError {
+    PortfolioError::ApiError(e.to_string())
+}
+
+// Yahoo answers unknown tickers with a 404
+fn check_status(
+    response: reqwest::Response,
+    what: &str,
+) -> Result<reqwest::Response, PortfolioError> {
+    match response.status() {
+        status if status.is_success() => Ok(response),
+        StatusCode::NOT_FOUND => Err(PortfolioError::NotFound(what.to_string())),
+        status => Err(PortfolioError::ApiError(format!(
+            "Yahoo returned {} for {}",
+            status, what
+        ))),
+    }
+}
+
+// yahoo_finance_api's messages don't say which ticker failed, or the status
+// for failed fetches, so this adds both
+pub fn yahoo_error(ticker: &str, e: yf::YahooError) -> PortfolioError {
+    match e {
+        yf::YahooError::FetchFailed(status) if status.starts_with("404") => {
+            PortfolioError::NotFound(ticker.to_string())
+        }
+        yf::YahooError::FetchFailed(status) => {
+            PortfolioError::ApiError(format!("Yahoo returned {} for {}", status, ticker))
+        }
+        yf::YahooError::EmptyDataSet => {
+            PortfolioError::NotFound(format!("no price data for {}", ticker))
+        }
+        e => PortfolioError::ApiError(format!("{} ({})", e, ticker)),
+    }
+}
+
+impl Default for YahooClient {
+    fn default() -> Self {
+        YahooClient::new(http_client(None, DEFAULT_USER_AGENT), DEFAULT_API_URL)
+    }
+}
+
+// the quote summary modules parse_sector needs
+pub const SECTOR_MODULES: &[&str] = &["assetProfile", "quoteType"];
+
+// funds hold many sectors, so rather than looking through them they are
+// classified as a whole
+pub fn parse_sector(summary: &Value) -> Option<String> {
+    if is_fund(summary) {
+        return Some("Fund".to_string());
+    }
+    summary["assetProfile"]["sector"]
+        .as_str()
+        .filter(|sector| !sector.is_empty())
+        .map(str::to_string)
+}
+
+// the quote summary modules parse_fundamentals needs
+pub const FUNDAMENTALS_MODULES: &[&str] = &["summaryDetail", "quoteType"];
+
+fn is_fund(summary: &Value) -> bool {
+    matches!(
+        summary["quoteType"]["quoteType"].as_str(),
+        Some("ETF") | Some("MUTUALFUND")
+    )
+}
+
+// returns (P/E ratio, market cap). funds and companies without (positive)
+// earnings have no meaningful P/E, so it is None for them
+pub fn parse_fundamentals(summary: &Value) -> (Option<f64>, Option<u64>) {
+    let detail = &summary["summaryDetail"];
+    let pe_ratio = if is_fund(summary) {
+        None
+    } else {
+        detail["trailingPE"]["raw"]
+            .as_f64()
+            .filter(|pe| pe.is_finite() && *pe > 0.0)
+    };
+    let market_cap = detail["marketCap"]["raw"].as_u64();
+    (pe_ratio, market_cap)
+}
+
+// in cents per share, from the same summary as the other fundamentals. None
+// for tickers that paid nothing in the last twelve months
+pub fn parse_trailing_dividend(summary: &Value) -> Option<f64> {
+    summary["summaryDetail"]["trailingAnnualDividendRate"]["raw"]
+        .as_f64()
+        .filter(|rate| rate.is_finite() && *rate > 0.0)
+        .map(|rate| rate * 100.0)
+}
+
+// the quote summary modules parse_next_earnings_date needs
+pub const EARNINGS_MODULES: &[&str] = &["calendarEvents"];
+
+// Yahoo lists one or two dates (a range when the exact day isn't confirmed),
+// so this takes the earliest one that hasn't passed
+pub fn parse_next_earnings_date(summary: &Value, today: NaiveDate) -> Option<NaiveDate> {
+    summary["calendarEvents"]["earnings"]["earningsDate"]
+        .as_array()?
+        .iter()
+        .filter_map(|date| date["raw"].as_i64())
+        .filter_map(|timestamp| DateTime::from_timestamp(timestamp, 0))
+        .map(|date| date.date_naive())
+        .filter(|date| *date >= today)
+        .min()
+}
+
+#[derive(Debug, PartialEq)]
+pub struct NewsItem {
+    pub title: String,
+    pub publisher: String,
+    // unix timestamp
+    pub published_at: i64,
+}
+
+// the news section of a search response, newest first. items missing a
+// title are skipped
+pub fn parse_news(search: &Value) -> Result<Vec<NewsItem>, PortfolioError> {
+    let mut items: Vec<NewsItem> = search["news"]
+        .as_array()
+        .ok_or_else(|| PortfolioError::ParseError("no news in search response".to_string()))?
+        .iter()
+        .filter_map(|item| {
+            Some(NewsItem {
+                title: item["title"].as_str()?.to_string(),
+                publisher: item["publisher"].as_str().unwrap_or("Unknown").to_string(),
+                published_at: item["providerPublishTime"].as_i64().unwrap_or(0),
+            })
+        })
+        .collect();
+    items.sort_by_key(|item| std::cmp::Reverse(item.published_at));
+    Ok(items)
+}
+
+// the latest price of a ticker along with its trading range over the past year
+#[derive(Debug, Clone, PartialEq)]
+pub struct TickerQuote {
+    // in hundredths of `currency`, even for quotes Yahoo gives in pence
+    pub price_cents: u32,
+    pub previous_close_cents: Option<u32>,
+    // (low, high)
+    pub fifty_two_week_range_cents: Option<(u32, u32)>,
+    // the ISO code the prices are in, e.g. "CAD" for SHOP.TO, if known
+    pub currency: Option<String>,
+}
+
+// currencies Yahoo quotes some exchanges in that are a hundredth of an ISO
+// currency, e.g. London prices in pence (GBp)
+const MINOR_UNIT_CURRENCIES: [(&str, &str); 4] = [
+    ("GBp", "GBP"),
+    ("GBX", "GBP"),
+    ("ZAc", "ZAR"),
+    ("ILA", "ILS"),
+];
+
+// the ISO currency for a currency as Yahoo gives it, and what prices in it
+// are divided by to be in the ISO one: 100 for pence and the like, otherwise 1
+pub fn iso_currency(code: &str) -> (Option<String>, f64) {
+    if code.is_empty() {
+        return (None, 1.0);
+    }
+    // case matters: GBP is pounds but GBp is pence
+    match MINOR_UNIT_CURRENCIES
+        .iter()
+        .find(|(minor, _)| *minor == code)
+    {
+        Some((_, major)) => (Some(major.to_string()), 100.0),
+        None => (Some(code.to_uppercase()), 1.0),
+    }
+}
+
+pub fn quote_currency(response: &yf::YResponse) -> (Option<String>, f64) {
+    response
+        .chart
+        .result
+        .first()
+        .map_or((None, 1.0), |result| iso_currency(&result.meta.currency))
+}
+
+// hundredths of a price given in whole units divided by `divisor`
+pub fn scaled_cents(price: f64, divisor: f64) -> u32 {
+    (price * (100.0 / divisor)) as u32
+}
+
+// how much history to request so the 52-week range can be worked out from the
+// daily bars, since the chart metadata doesn't include it
+pub const QUOTE_RANGE: &str = "1y";
+
+pub fn parse_ticker_quote(
+    ticker: &str,
+    response: &yf::YResponse,
+) -> Result<TickerQuote, PortfolioError> {
+    parse_quote(response).map_err(|e| yahoo_error(ticker, e))
+}
+
+// parse_ticker_quote, keeping yahoo_finance_api's error for callers that
+// want to say why it failed
+pub fn parse_quote(response: &yf::YResponse) -> Result<TickerQuote, yf::YahooError> {
+    let last = response.last_quote()?;
+    let quotes = response.quotes()?;
+    let (currency, divisor) = quote_currency(response);
+    // the daily bar before the latest one, skipping any with a missing close
+    let previous_close = quotes
+        .iter()
+        .rev()
+        .skip(1)
+        .find(|quote| quote.close > 0.0)
+        .map(|quote| scaled_cents(quote.close, divisor));
+    let range = if divisor == 1.0 {
+        price_range_cents(&quotes)
+    } else {
+        price_range_cents(&quotes).map(|(low, high)| {
+            (
+                (low as f64 / divisor).round() as u32,
+                (high as f64 / divisor).round() as u32,
+            )
+        })
+    };
+    Ok(TickerQuote {
+        price_cents: scaled_cents(last.close, divisor),
+        previous_close_cents: previous_close,
+        fifty_two_week_range_cents: range,
+        currency,
+    })
+}
+
+// (uppercased symbol, quote) for each result from QUOTE_PATH. Yahoo leaves
+// out symbols it doesn't list at all, and gives some it can't price, e.g.
+// delisted ones, without a price, which are None here
+pub fn parse_batch_quotes(
+    json: &Value,
+) -> Result<Vec<(String, Option<TickerQuote>)>, PortfolioError> {
+    let results = json["quoteResponse"]["result"]
+        .as_array()
+        .ok_or_else(|| PortfolioError::ParseError("no quotes in response".to_string()))?;
+    Ok(results
+        .iter()
+        .filter_map(|result| {
+            let symbol = result["symbol"].as_str()?.to_uppercase();
+            Some((symbol, parse_batch_quote(result)))
+        })
+        .collect())
+}
+
+fn parse_batch_quote(result: &Value) -> Option<TickerQuote> {
+    let (currency, divisor) = iso_currency(result["currency"].as_str().unwrap_or(""));
+    let cents = |key: &str| {
+        result[key]
+            .as_f64()
+            .filter(|price| *price > 0.0)
+            .map(|price| scaled_cents(price, divisor))
+    };
+    Some(TickerQuote {
+        price_cents: cents("regularMarketPrice")?,
+        previous_close_cents: cents("regularMarketPreviousClose"),
+        fifty_two_week_range_cents: cents("fiftyTwoWeekLow").zip(cents("fiftyTwoWeekHigh")),
+        currency,
+    })
+}
+
+// a few words on why a quote couldn't be fetched, e.g. "timeout", for
+// listing many failures on one line
+pub fn quote_failure_reason(e: &yf::YahooError) -> String {
+    match e {
+        yf::YahooError::FetchFailed(status) if status.starts_with("404") => {
+            "unknown symbol".to_string()
+        }
+        yf::YahooError::FetchFailed(status) => format!("HTTP {}", status),
+        yf::YahooError::ConnectionFailed(e) if e.is_timeout() => "timeout".to_string(),
+        yf::YahooError::ConnectionFailed(_) => "connection failed".to_string(),
+        yf::YahooError::EmptyDataSet => "no data".to_string(),
+        yf::YahooError::DeserializeFailed(_)
+        | yf::YahooError::InvalidJson
+        | yf::YahooError::DataInconsistency => "bad response".to_string(),
+        yf::YahooError::BuilderFailed => "client setup failed".to_string(),
+    }
+}
+
+// lowest low and highest high across the given bars. yahoo_finance_api fills
+// missing values with 0, so those are ignored
+pub fn price_range_cents(quotes: &[yf::Quote]) -> Option<(u32, u32)> {
+    let low = quotes
+        .iter()
+        .map(|quote| quote.low)
+        .filter(|low| *low > 0.0)
+        .reduce(f64::min)?;
+    let high = quotes
+        .iter()
+        .map(|quote| quote.high)
+        .filter(|high| *high > 0.0)
+        .reduce(f64::max)?;
+    Some(((low * 100.0) as u32, (high * 100.0) as u32))
+}
+
+// (date, close) for each bar, leaving out the ones yahoo_finance_api filled
+// with 0 for a missing close
+pub fn closing_prices(quotes: &[yf::Quote]) -> Vec<(NaiveDate, u32)> {
+    quotes
+        .iter()
+        .filter(|quote| quote.close > 0.0)
+        .filter_map(|quote| {
+            let date = DateTime::from_timestamp(quote.timestamp as i64, 0)?.date_naive();
+            Some((date, (quote.close * 100.0).round() as u32))
+        })
+        .collect()
+}
+
+// (ex-dividend date, amount per share in cents) for each dividend, oldest
+// first. amounts stay fractional since many are paid in fractions of a cent
+pub fn dividend_payments(dividends: &[yf::Dividend], divisor: f64) -> Vec<(NaiveDate, f64)> {
+    let mut payments: Vec<(NaiveDate, f64)> = dividends
+        .iter()
+        .filter(|dividend| dividend.amount > 0.0)
+        .filter_map(|dividend| {
+            let date = DateTime::from_timestamp(dividend.date as i64, 0)?.date_naive();
+            Some((date, dividend.amount * 100.0 / divisor))
+        })
+        .collect();
+    payments.sort_by_key(|(date, _)| *date);
+    payments
+}
+
+// looks up the sector of every held ticker that doesn't have one cached yet
+pub async fn fetch_missing_sectors(
+    client: &YahooClient,
+    metadata: &mut MetadataCache,
+    assets: &[Asset],
+) {
+    let mut attempted: HashSet<String> = HashSet::new();
+    let mut fetched_any = false;
+    for asset in assets.iter().filter(|asset| is_asset_held(asset)) {
+        let cached = metadata
+            .get(&asset.ticker)
+            .is_some_and(|entry| entry.sector.is_some());
+        if cached || !attempted.insert(asset.ticker.to_uppercase()) {
+            continue;
+        }
+        let summary = client.quote_summary(&asset.ticker, SECTOR_MODULES).await;
+        let summary = summary
+            .inspect_err(|e| debug!(ticker = %asset.ticker, error = %e, "sector lookup failed"));
+        if let Some(sector) = summary.ok().as_ref().and_then(parse_sector) {
+            metadata.entry(&asset.ticker).sector = Some(sector);
+            fetched_any = true;
+        }
+    }
+    if fetched_any {
+        save_metadata(metadata);
+    }
+}
+
+// refetches fundamentals for held tickers that haven't been fetched today
+pub async fn fetch_stale_fundamentals(
+    client: &YahooClient,
+    metadata: &mut MetadataCache,
+    assets: &[Asset],
+    today: NaiveDate,
+) {
+    let mut attempted: HashSet<String> = HashSet::new();
+    let mut fetched_any = false;
+    for asset in assets.iter().filter(|asset| is_asset_held(asset)) {
+        let fresh = metadata
+            .get(&asset.ticker)
+            .and_then(|entry| entry.fundamentals.as_ref())
+            .is_some_and(|fundamentals| fundamentals.fetched_on == today);
+        if fresh || !attempted.insert(asset.ticker.to_uppercase()) {
+            continue;
+        }
+        let summary = client
+            .quote_summary(&asset.ticker, FUNDAMENTALS_MODULES)
+            .await;
+        match summary {
+            Ok(summary) => {
+                let (pe_ratio, market_cap) = parse_fundamentals(&summary);
+                metadata.entry(&asset.ticker).fundamentals = Some(Fundamentals {
+                    fetched_on: today,
+                    pe_ratio,
+                    market_cap,
+                    dividend_cents: parse_trailing_dividend(&summary),
+                });
+                fetched_any = true;
+            }
+            Err(e) => println!(
+                "Error when fetching fundamentals for ticker {}: {}",
+                asset.ticker, e
+            ),
+        }
+    }
+    if fetched_any {
+        save_metadata(metadata);
+    }
+}
+
+// refetches the next earnings date for held tickers whose cached date is missing or stale
+pub async fn fetch_stale_earnings(
+    client: &YahooClient,
+    metadata: &mut MetadataCache,
+    assets: &[Asset],
+    today: NaiveDate,
+) {
+    let mut attempted: HashSet<String> = HashSet::new();
+    let mut fetched_any = false;
+    for asset in assets.iter().filter(|asset| is_asset_held(asset)) {
+        let fresh = metadata
+            .get(&asset.ticker)
+            .and_then(|entry| entry.earnings.as_ref())
+            .is_some_and(|earnings| !earnings.needs_refetch(today));
+        if fresh || !attempted.insert(asset.ticker.to_uppercase()) {
+            continue;
+        }
+        let summary = client.quote_summary(&asset.ticker, EARNINGS_MODULES).await;
+        match summary {
+            Ok(summary) => {
+                metadata.entry(&asset.ticker).earnings = Some(EarningsDate {
+                    fetched_on: today,
+                    date: parse_next_earnings_date(&summary, today),
+                });
+                fetched_any = true;
+            }
+            Err(e) => debug!(ticker = %asset.ticker, error = %e, "earnings lookup failed"),
+        }
+    }
+    if fetched_any {
+        save_metadata(metadata);
+    }
+}
+
+#[tracing::instrument(level = "debug", skip(quotes))]
+pub async fn fetch_ticker_quote(
+    quotes: &QuoteProvider,
+    ticker: &str,
+) -> Result<TickerQuote, yf::YahooError> {
+    let started = Instant::now();
+    let quote = quotes.quote(ticker).await;
+    debug!(
+        elapsed_ms = started.elapsed().as_millis() as u64,
+        ok = quote.is_ok(),
+        "quote response"
+    );
+    quote
+}
+
+pub fn cache_quote_range(metadata: &mut MetadataCache, ticker: &str, quote: &TickerQuote) {
+    if quote.fifty_two_week_range_cents.is_some() {
+        metadata.entry(ticker).fifty_two_week_range_cents = quote.fifty_two_week_range_cents;
+    }
+}
+
+// fetches a ticker's quote, caching the 52-week range that comes with it
+pub async fn get_ticker_quote(
+    quotes: &QuoteProvider,
+    metadata: &mut MetadataCache,
+    ticker: &str,
+) -> Result<TickerQuote, PortfolioError> {
+    let quote = fetch_ticker_quote(quotes, ticker)
+        .await
+        .map_err(|e| yahoo_error(ticker, e))?;
+    cache_quote_range(metadata, ticker, &quote);
+    Ok(quote)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn parse_fundamentals_for_equity() {
+        let summary = json!({
+            "summaryDetail": {
+                "trailingPE": {"raw": 29.41, "fmt": "29.41"},
+                "marketCap": {"raw": 2_910_000_000_000u64, "fmt": "2.91T"}
+            },
+            "quoteType": {"quoteType": "EQUITY"}
+        });
+        assert_eq!(
+            parse_fundamentals(&summary),
+            (Some(29.41), Some(2_910_000_000_000))
+        );
+    }
+
+    #[test]
+    fn parse_fundamentals_without_earnings() {
+        let fund = json!({
+            "summaryDetail": {"trailingPE": {"raw": 24.0}},
+            "quoteType": {"quoteType": "ETF"}
+        });
+        assert_eq!(parse_fundamentals(&fund), (None, None));
+        let unprofitable = json!({
+            "summaryDetail": {"trailingPE": {"raw": -3.5}, "marketCap": {"raw": 1000}},
+            "quoteType": {"quoteType": "EQUITY"}
+        });
+        assert_eq!(parse_fundamentals(&unprofitable), (None, Some(1000)));
+    }
+
+    #[test]
+    fn trailing_dividend_is_in_cents() {
+        let payer = json!({"summaryDetail": {"trailingAnnualDividendRate": {"raw": 0.96}}});
+        assert_eq!(parse_trailing_dividend(&payer), Some(96.0));
+        let non_payer = json!({"summaryDetail": {"trailingAnnualDividendRate": {"raw": 0.0}}});
+        assert_eq!(parse_trailing_dividend(&non_payer), None);
+        assert_eq!(parse_trailing_dividend(&json!({})), None);
+    }
+
+    #[test]
+    fn parse_next_earnings_date_skips_past_dates() {
+        // 2024-01-25 and 2024-01-30, both 21:00 UTC
+        let summary = json!({
+            "calendarEvents": {"earnings": {"earningsDate": [
+                {"raw": 1706216400, "fmt": "2024-01-25"},
+                {"raw": 1706648400, "fmt": "2024-01-30"}
+            ]}}
+        });
+        let date = |day| NaiveDate::from_ymd_opt(2024, 1, day).unwrap();
+        assert_eq!(parse_next_earnings_date(&summary, date(20)), Some(date(25)));
+        assert_eq!(parse_next_earnings_date(&summary, date(27)), Some(date(30)));
+        assert_eq!(parse_next_earnings_date(&summary, date(31)), None);
+        assert_eq!(parse_next_earnings_date(&json!({}), date(1)), None);
+    }
+
+    #[test]
+    fn parse_news_sorts_newest_first() {
+        let search = json!({"news": [
+            {"title": "Older", "publisher": "Reuters", "providerPublishTime": 100},
+            {"publisher": "Nobody", "providerPublishTime": 300},
+            {"title": "Newer", "publisher": "Bloomberg", "providerPublishTime": 200}
+        ]});
+        let titles: Vec<String> = parse_news(&search)
+            .unwrap()
+            .into_iter()
+            .map(|item| item.title)
+            .collect();
+        assert_eq!(titles, vec!["Newer", "Older"]);
+        assert!(matches!(
+            parse_news(&json!({})),
+            Err(PortfolioError::ParseError(_))
+        ));
+    }
+
+    #[test]
+    fn yahoo_error_maps_unknown_ticker_to_not_found() {
+        let e = yahoo_error(
+            "XXII",
+            yf::YahooError::FetchFailed("404 Not Found".to_string()),
+        );
+        assert!(matches!(e, PortfolioError::NotFound(ticker) if ticker == "XXII"));
+        let e = yahoo_error("AAPL", yf::YahooError::FetchFailed("500".to_string()));
+        assert!(matches!(e, PortfolioError::ApiError(_)));
+    }
+
+    #[test]
+    fn quote_failures_have_short_reasons() {
+        let reason = |e: yf::YahooError| quote_failure_reason(&e);
+        assert_eq!(
+            reason(yf::YahooError::FetchFailed("404 Not Found".to_string())),
+            "unknown symbol"
+        );
+        assert_eq!(
+            reason(yf::YahooError::FetchFailed(
+                "503 Service Unavailable".to_string()
+            )),
+            "HTTP 503 Service Unavailable"
+        );
+        assert_eq!(reason(yf::YahooError::EmptyDataSet), "no data");
+        assert_eq!(reason(yf::YahooError::InvalidJson), "bad response");
+    }
+
+    #[test]
+    fn proxy_comes_from_the_setting_then_the_environment() {
+        let env = |vars: &'static [(&'static str, &'static str)]| {
+            move |name: &str| {
+                vars.iter()
+                    .find(|(known, _)| *known == name)
+                    .map(|(_, value)| value.to_string())
+            }
+        };
+        let vars: &[(&str, &str)] = &[
+            ("HTTP_PROXY", "http://plain:3128"),
+            ("HTTPS_PROXY", "http://secure:3128"),
+        ];
+        assert_eq!(
+            proxy_url(Some("http://configured:8080"), env(vars)),
+            Some(("http://configured:8080".to_string(), "the proxy setting"))
+        );
+        assert_eq!(
+            proxy_url(None, env(vars)),
+            Some(("http://secure:3128".to_string(), "HTTPS_PROXY"))
+        );
+        assert_eq!(
+            proxy_url(None, env(&[("http_proxy", "http://plain:3128")])),
+            Some(("http://plain:3128".to_string(), "http_proxy"))
+        );
+        assert_eq!(proxy_url(None, env(&[("HTTPS_PROXY", " ")])), None);
+    }
+
+    #[test]
+    fn no_proxy_matches_hosts_and_domains() {
+        assert!(bypasses_proxy(
+            "localhost, yahoo.com",
+            "query1.finance.yahoo.com"
+        ));
+        assert!(bypasses_proxy(".yahoo.com", "yahoo.com"));
+        assert!(bypasses_proxy("*", "fc.yahoo.com"));
+        assert!(!bypasses_proxy("notyahoo.com", "yahoo.com"));
+        assert!(!bypasses_proxy("ahoo.com", "query2.finance.yahoo.com"));
+        assert!(!bypasses_proxy("", "fc.yahoo.com"));
+    }
+
+    #[test]
+    fn batch_quotes_are_parsed_per_symbol() {
+        let json = json!({"quoteResponse": {"error": null, "result": [
+            {
+                "symbol": "AAPL",
+                "currency": "USD",
+                "regularMarketPrice": 190.25,
+                "regularMarketPreviousClose": 188.5,
+                "fiftyTwoWeekLow": 164.08,
+                "fiftyTwoWeekHigh": 199.62
+            },
+            {"symbol": "VOD.L", "currency": "GBp", "regularMarketPrice": 71.5},
+            {"symbol": "OLDCO", "currency": "USD"}
+        ]}});
+        let quotes = parse_batch_quotes(&json).unwrap();
+        assert_eq!(
+            quotes[0],
+            (
+                "AAPL".to_string(),
+                Some(TickerQuote {
+                    price_cents: 19025,
+                    previous_close_cents: Some(18850),
+                    fifty_two_week_range_cents: Some((16408, 19962)),
+                    currency: Some("USD".to_string()),
+                })
+            )
+        );
+        let pounds = quotes[1].1.as_ref().unwrap();
+        assert_eq!(pounds.price_cents, 71);
+        assert_eq!(pounds.currency.as_deref(), Some("GBP"));
+        assert_eq!(quotes[2], ("OLDCO".to_string(), None));
+        assert!(parse_batch_quotes(&json!({"finance": {"error": {}}})).is_err());
+    }
+
+    #[test]
+    fn pence_are_converted_to_pounds() {
+        assert_eq!(iso_currency("GBp"), (Some("GBP".to_string()), 100.0));
+        assert_eq!(iso_currency("GBP"), (Some("GBP".to_string()), 1.0));
+        assert_eq!(iso_currency("JPY"), (Some("JPY".to_string()), 1.0));
+        assert_eq!(iso_currency(""), (None, 1.0));
+        assert_eq!(scaled_cents(2505.0, 100.0), 2505);
+        assert_eq!(scaled_cents(26.5, 1.0), 2650);
+    }
+
+    fn bar(low: f64, high: f64) -> yf::Quote {
+        yf::Quote {
+            timestamp: 0,
+            open: low,
+            high,
+            low,
+            volume: 0,
+            close: high,
+            adjclose: high,
+        }
+    }
+
+    #[test]
+    fn closing_prices_skip_missing_closes() {
+        let mut monday = bar(10.0, 12.345);
+        monday.timestamp = 1_704_672_000; // 2024-01-08
+        let missing = bar(0.0, 0.0);
+        let mut next = bar(11.0, 13.0);
+        next.timestamp = 1_705_276_800; // 2024-01-15
+        assert_eq!(
+            closing_prices(&[monday, missing, next]),
+            vec![
+                (NaiveDate::from_ymd_opt(2024, 1, 8).unwrap(), 1235),
+                (NaiveDate::from_ymd_opt(2024, 1, 15).unwrap(), 1300),
+            ]
+        );
+    }
+
+    #[test]
+    fn price_range_spans_all_bars() {
+        let quotes = [bar(10.0, 12.5), bar(8.25, 9.0), bar(11.0, 15.0)];
+        assert_eq!(price_range_cents(&quotes), Some((825, 1500)));
+    }
+
+    #[test]
+    fn price_range_ignores_missing_values() {
+        let quotes = [bar(0.0, 0.0), bar(10.0, 11.0)];
+        assert_eq!(price_range_cents(&quotes), Some((1000, 1100)));
+        assert_eq!(price_range_cents(&[bar(0.0, 0.0)]), None);
+        assert_eq!(price_range_cents(&[]), None);
+    }
+
+    #[test]
+    fn parse_sector_for_equity() {
+        let summary = json!({
+            "assetProfile": {"sector": "Technology", "industry": "Consumer Electronics"},
+            "quoteType": {"quoteType": "EQUITY"}
+        });
+        assert_eq!(parse_sector(&summary), Some("Technology".to_string()));
+    }
+
+    #[test]
+    fn parse_sector_classifies_funds() {
+        let summary = json!({"assetProfile": {}, "quoteType": {"quoteType": "ETF"}});
+        assert_eq!(parse_sector(&summary), Some("Fund".to_string()));
+    }
+
+    #[test]
+    fn parse_sector_missing() {
+        let summary = json!({"assetProfile": {"sector": ""}, "quoteType": {"quoteType": "EQUITY"}});
+        assert_eq!(parse_sector(&summary), None);
+        assert_eq!(parse_sector(&json!({})), None);
+    }
+}
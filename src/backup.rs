@@ -0,0 +1,162 @@
+// scheduled backups: when the tracker starts and the backup-schedule says
+// one is due, the portfolio file is copied into the data directory, e.g.
+// ~/.local/share/portfolio-tracker/backups/2024-06-01-portfolio.json.
+// the time of the last one is kept next to them in `last_backup`
+use crate::error::PortfolioError;
+use chrono::{DateTime, Local, Utc};
+use cron::Schedule;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+// midnight every day, so the first start of each day makes a backup
+pub const DAILY_BACKUP_SCHEDULE: &str = "0 0 0 * * *";
+
+fn data_dir() -> Option<PathBuf> {
+    dirs_next::data_dir().map(|dir| dir.join("portfolio-tracker"))
+}
+
+pub fn backup_dir() -> Option<PathBuf> {
+    data_dir().map(|dir| dir.join("backups"))
+}
+
+fn last_backup_path() -> Option<PathBuf> {
+    data_dir().map(|dir| dir.join("last_backup"))
+}
+
+// a cron expression with or without the leading seconds field, so both
+// "0 3 * * *" and "0 0 3 * * *" are 3am every day. "daily" is the default
+pub fn parse_schedule(expression: &str) -> Result<Schedule, String> {
+    let expression = expression.trim();
+    let expression = if expression.eq_ignore_ascii_case("daily") {
+        DAILY_BACKUP_SCHEDULE.to_string()
+    } else if expression.split_whitespace().count() == 5 {
+        format!("0 {}", expression)
+    } else {
+        expression.to_string()
+    };
+    // the parse errors point at the bad field with a caret over several
+    // lines; the last one with words in it says what's wrong
+    Schedule::from_str(&expression).map_err(|e| {
+        let message = e.to_string();
+        message
+            .lines()
+            .rev()
+            .find(|line| line.chars().any(char::is_alphabetic))
+            .unwrap_or("not a cron expression")
+            .trim()
+            .to_string()
+    })
+}
+
+// due when the schedule came round at least once since the last backup, or
+// when there's never been one. times are local, as cron expressions are
+pub fn backup_due(
+    schedule: &Schedule,
+    last_backup: Option<DateTime<Utc>>,
+    now: DateTime<Utc>,
+) -> bool {
+    let Some(last_backup) = last_backup else {
+        return true;
+    };
+    schedule
+        .after(&last_backup.with_timezone(&Local))
+        .next()
+        .is_some_and(|next| next.with_timezone(&Utc) <= now)
+}
+
+// a missing or unreadable timestamp is treated as no backup yet
+pub fn last_backup() -> Option<DateTime<Utc>> {
+    let raw = fs::read_to_string(last_backup_path()?).ok()?;
+    DateTime::parse_from_rfc3339(raw.trim())
+        .ok()
+        .map(|at| at.with_timezone(&Utc))
+}
+
+// e.g. backups/2024-06-01-portfolio.json, keeping the file's extensions so
+// a gzipped or TOML portfolio still opens as one. a schedule that runs more
+// than once a day keeps the day's latest
+pub fn backup_file_path(dir: &Path, file: &Path, now: DateTime<Utc>) -> PathBuf {
+    let name = file.file_name().map(|name| name.to_string_lossy());
+    let extensions = name
+        .as_deref()
+        .and_then(|name| name.trim_start_matches('.').split_once('.'))
+        .map_or("json", |(_, extensions)| extensions);
+    dir.join(format!(
+        "{}-portfolio.{}",
+        now.with_timezone(&Local).format("%Y-%m-%d"),
+        extensions
+    ))
+}
+
+// copies the file as it is, in whatever format it's in, and records the time
+pub fn back_up(file: &Path, now: DateTime<Utc>) -> Result<PathBuf, PortfolioError> {
+    let dir = backup_dir()
+        .ok_or_else(|| PortfolioError::NotFound("platform data directory".to_string()))?;
+    fs::create_dir_all(&dir)?;
+    let backup = backup_file_path(&dir, file, now);
+    fs::copy(file, &backup)?;
+    if let Some(path) = last_backup_path() {
+        fs::write(path, now.to_rfc3339())?;
+    }
+    Ok(backup)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, TimeZone};
+
+    fn local(day: u32, hour: u32) -> DateTime<Utc> {
+        Local
+            .with_ymd_and_hms(2024, 6, day, hour, 0, 0)
+            .unwrap()
+            .with_timezone(&Utc)
+    }
+
+    #[test]
+    fn daily_backups_are_due_once_a_day() {
+        let daily = parse_schedule("daily").unwrap();
+        assert!(backup_due(&daily, None, local(1, 9)));
+        assert!(!backup_due(&daily, Some(local(1, 9)), local(1, 23)));
+        assert!(backup_due(&daily, Some(local(1, 23)), local(2, 0)));
+        assert!(backup_due(&daily, Some(local(1, 9)), local(5, 9)));
+    }
+
+    #[test]
+    fn schedules_take_five_or_six_fields() {
+        // 3am on Mondays, 2024-06-03 being one
+        let weekly = parse_schedule("0 3 * * Mon").unwrap();
+        assert!(!backup_due(&weekly, Some(local(1, 9)), local(3, 2)));
+        assert!(backup_due(&weekly, Some(local(1, 9)), local(3, 3)));
+        let hourly = parse_schedule("0 0 * * * *").unwrap();
+        let last = local(1, 9);
+        assert!(!backup_due(
+            &hourly,
+            Some(last),
+            last + Duration::minutes(59)
+        ));
+        assert!(backup_due(
+            &hourly,
+            Some(last),
+            last + Duration::minutes(60)
+        ));
+        assert!(parse_schedule("every day").is_err());
+    }
+
+    #[test]
+    fn backups_are_named_by_date_and_file() {
+        assert_eq!(
+            backup_file_path(
+                Path::new("backups"),
+                Path::new("data/main.json.gz"),
+                local(1, 9)
+            ),
+            Path::new("backups/2024-06-01-portfolio.json.gz")
+        );
+        assert_eq!(
+            backup_file_path(Path::new("backups"), Path::new("main"), local(1, 9)),
+            Path::new("backups/2024-06-01-portfolio.json")
+        );
+    }
+}
@@ -0,0 +1,3637 @@
+// the interactive prompt: parsing the command line, running commands and
+// asking for whatever they need
+use crate::api::{
+    cache_quote_range, fetch_missing_sectors, fetch_stale_earnings, fetch_stale_fundamentals,
+    get_ticker_quote, http_client, quote_failure_reason, yahoo_error, TickerQuote, YahooClient,
+    QUOTE_BATCH_SIZE,
+};
+use crate::backup::{back_up, backup_due, last_backup};
+use crate::commands::{parse_batch, CommandHelper, COMMANDS};
+use crate::config::{
+    default_portfolio_file, display_settings, Config, CostBasisMethod, DateOrder, MergeStrategy,
+    COST_BASIS_METHOD_NAMES,
+};
+use crate::demo::demo_portfolio;
+use crate::display::{
+    apply_table_display_settings, assets_copy_text, assets_table, describe_count,
+    describe_stop_loss, fit_columns, format_asset_money, format_bytes, format_money,
+    format_money_with_sign, format_percent, format_relative_time, gain_loss_cell,
+    print_account_summary, print_activity_gaps, print_connection, print_cost_basis_report,
+    print_cost_summary, print_dca_preview, print_dividend_reinvestment, print_duplicate_warnings,
+    print_earnings, print_earnings_warnings, print_fundamentals, print_goals, print_health,
+    print_help, print_holding_ages, print_lint, print_loaded, print_portfolio_file,
+    print_portfolio_size, print_qr_code, print_ranges, print_sector_chart, print_sectors,
+    print_settings, print_snapshot_diff, print_snapshots, print_stale_warning, print_stats,
+    print_stops, print_summary, print_yields, refresh_failed_legend, summary_copy_text,
+    terminal_width, text_column, truncate_to_width, version_text, warn_about_total_prices,
+};
+use crate::dividends::reinvest_dividends;
+use crate::error::PortfolioError;
+use crate::export::{export_to_path, read_bundle, write_bundle, ExportFormat, EXPORT_FORMAT_NAMES};
+use crate::goals::Goal;
+use crate::health::health_score;
+use crate::import::{import_file, ImportFormat, IMPORT_FORMAT_NAMES};
+use crate::input::{
+    parse_buy_amount, parse_buy_date, parse_choice, parse_date, parse_goal_target,
+    parse_history_period, parse_optional_date, parse_price, parse_quantity, parse_sell_date,
+    parse_sell_price, parse_stop_loss, parse_tags, split_quoted, HISTORY_PERIODS, PRICE_FORMATS,
+};
+use crate::lock::{self, LockAttempt, PortfolioLock};
+use crate::metadata::{save_metadata, MetadataCache};
+use crate::persistence::{
+    backup_path, dump_portfolio_to_path, load_portfolio_detecting, FileFormat, FILE_FORMAT_NAMES,
+};
+use crate::portfolio::{
+    account_summaries, cost_basis, dca_preview, distinct_tickers, is_asset_held, merge_portfolios,
+    per_share_price, picker_order, realized_gain_loss, recently_added, record_portfolio_value,
+    snapshot_diff, sold_before, sorted_assets, targets_crossed, Asset, AssetOrdering, MergeReport,
+    Portfolio, PortfolioAnalytics, PRICE_BASIS_PER_SHARE, SORT_KEYS,
+};
+use crate::quotes::{save_quote_cache, QuoteCache, QuoteFixture, QuoteProvider};
+use crate::recent::{display_path, RecentFiles};
+use crate::report::markdown_report;
+use arboard::Clipboard;
+use chrono::{DateTime, Local, NaiveDate, TimeZone, Utc};
+use comfy_table::{Cell, Table};
+use crossterm::{event, terminal};
+use qrcode::{EcLevel, QrCode};
+use rustyline::{Cmd, Editor, KeyCode, KeyEvent, Modifiers};
+use signal_hook::consts::SIGINT;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::env;
+use std::fmt;
+use std::fs;
+use std::future::Future;
+use std::io::{self, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration as StdDuration, Instant, SystemTime, UNIX_EPOCH};
+use tracing::{debug, warn};
+use tracing_subscriber::EnvFilter;
+
+// with a page size of 0 everything is printed at once
+fn print_assets(assets: &[Asset], page_size: usize) {
+    let legend = refresh_failed_legend(assets);
+    if page_size == 0 || assets.len() <= page_size {
+        println!("{}", assets_table(assets));
+        if let Some(legend) = legend {
+            println!("{}", legend);
+        }
+        return;
+    }
+    let pages = assets.len().div_ceil(page_size);
+    for (page, chunk) in assets.chunks(page_size).enumerate() {
+        println!("{}", assets_table(chunk));
+        if page + 1 < pages {
+            prompt("Press Enter for next page...");
+        }
+    }
+    if let Some(legend) = legend {
+        println!("{}", legend);
+    }
+    println!("End of results ({} total assets)", assets.len());
+}
+
+// copies text for the `copy-*` commands, or prints it to copy by hand when
+// there's no clipboard, e.g. over ssh. the clipboard is kept open for the
+// session, since on Linux what's copied is only there while it's open
+fn copy_to_clipboard(clipboard: &mut Option<Clipboard>, what: &str, text: &str) {
+    let copied = match clipboard.take().map_or_else(Clipboard::new, Ok) {
+        Ok(mut open) => {
+            let copied = open.set_text(text);
+            *clipboard = Some(open);
+            copied
+        }
+        Err(e) => Err(e),
+    };
+    match copied {
+        Ok(()) => println!("Copied {} to the clipboard.", what),
+        Err(e) => {
+            warn!(error = %e, "failed to copy to the clipboard");
+            println!("Couldn't copy to the clipboard ({}), so here it is:", e);
+            println!("{}", text.trim_end());
+        }
+    }
+}
+
+// portfolio JSON longer than this makes a code too dense to scan easily
+// from most terminals, though it can hold up to QR_CODE_MAX_BYTES
+const QR_CODE_DENSE_BYTES: usize = 1000;
+
+// what a QR code holds at its lowest error correction, which is all a code
+// on a screen needs
+const QR_CODE_MAX_BYTES: usize = 2953;
+
+// `qr-code [json]`: the headline with the date, or the whole portfolio
+fn print_portfolio_qr_code(
+    portfolio: &Portfolio,
+    analytics: &PortfolioAnalytics,
+    json: bool,
+) -> Result<(), PortfolioError> {
+    let content = if json {
+        portfolio.to_json_str()?
+    } else {
+        format!(
+            "{} │ As of {}",
+            analytics.headline(&portfolio.name),
+            Local::now().format("%Y-%m-%d %H:%M")
+        )
+    };
+    if content.len() > QR_CODE_MAX_BYTES {
+        return Err(PortfolioError::InvalidInput(format!(
+            "the portfolio JSON is {}, more than the {} a QR code holds",
+            format_bytes(content.len()),
+            format_bytes(QR_CODE_MAX_BYTES)
+        )));
+    }
+    let code = QrCode::with_error_correction_level(&content, EcLevel::L)
+        .map_err(|e| PortfolioError::InvalidInput(format!("couldn't make a QR code: {}", e)))?;
+    print_qr_code(&code);
+    if content.len() > QR_CODE_DENSE_BYTES {
+        println!(
+            "⚠ The portfolio JSON is {}, so the code is dense; it may only scan from a large terminal.",
+            format_bytes(content.len())
+        );
+    }
+    Ok(())
+}
+
+fn save_recent_files(recent: &RecentFiles) {
+    // like the metadata cache, this is only a convenience
+    if let Err(e) = recent.save() {
+        warn!(error = %e, "failed to save recent files");
+    }
+}
+
+// how many headlines `news <ticker>` shows
+const NEWS_COUNT: usize = 10;
+
+// `dca <ticker> <amount> [price]`: what buying more would do to the held
+// position, at the given price or else the latest quote. the portfolio isn't
+// changed
+async fn preview_dca(
+    quotes: &QuoteProvider,
+    metadata: &mut MetadataCache,
+    portfolio: &Portfolio,
+    args: &[&str],
+) -> Result<(), String> {
+    let (ticker, amount, price) = match args {
+        [ticker, amount] => (ticker, amount, None),
+        [ticker, amount, price] => (ticker, amount, Some(price)),
+        _ => {
+            return Err(
+                "usage: dca <ticker> <shares, or dollars like $500> [price per share]".to_string(),
+            )
+        }
+    };
+    let amount = parse_buy_amount(amount)?;
+    let price = match price {
+        Some(price) => parse_price(price)?,
+        None => {
+            get_ticker_quote(quotes, metadata, ticker)
+                .await
+                .map_err(|e| e.to_string())?
+                .price_cents
+        }
+    };
+    if price == 0 {
+        return Err("The price must be more than $0".to_string());
+    }
+    let shares = amount.shares_at(price);
+    if shares == 0 {
+        return Err(format!(
+            "That doesn't buy a whole share of {} at {}",
+            ticker.to_uppercase(),
+            format_money(price as i64)
+        ));
+    }
+    let (before, after) = dca_preview(
+        &portfolio.assets,
+        ticker,
+        shares,
+        price,
+        Local::now().date_naive(),
+    );
+    print_dca_preview(before.as_ref(), &after, price);
+    Ok(())
+}
+
+// weekly closes over a period like 1y, oldest first
+async fn print_price_history(quotes: &QuoteProvider, args: &[&str]) {
+    let (ticker, period) = match args {
+        [ticker, period] => (ticker.to_uppercase(), period),
+        _ => {
+            println!(
+                "usage: price-history <ticker> <period>, where period is one of {}",
+                HISTORY_PERIODS.join(", ")
+            );
+            return;
+        }
+    };
+    let period = match parse_history_period(period) {
+        Ok(period) => period,
+        Err(e) => {
+            println!("{}", e);
+            return;
+        }
+    };
+    let closes = quotes.closing_prices(&ticker, "1wk", period).await;
+    let closes = match closes {
+        Ok(closes) if !closes.is_empty() => closes,
+        Ok(_) => {
+            println!("No price history for ticker {} over {}.", ticker, period);
+            return;
+        }
+        Err(e) => {
+            println!("{}", yahoo_error(&ticker, e));
+            return;
+        }
+    };
+
+    let mut table = Table::new();
+    apply_table_display_settings(&mut table);
+    table.set_header(vec!["Date", "Close Price", "Change From Previous Week"]);
+    let mut previous: Option<u32> = None;
+    for (date, close) in closes {
+        let change = match previous {
+            Some(previous) => {
+                let change = close as i64 - previous as i64;
+                let percent = change as f64 / previous as f64 * 100.0;
+                gain_loss_cell(
+                    format!(
+                        "{} ({})",
+                        format_money_with_sign(change),
+                        format_percent(percent)
+                    ),
+                    percent,
+                )
+            }
+            None => Cell::new("-"),
+        };
+        table.add_row(vec![
+            Cell::new(date),
+            Cell::new(format_money(close as i64)),
+            change,
+        ]);
+        previous = Some(close);
+    }
+    println!("{table}");
+}
+
+// how far back `total-return-with-dividends` looks without a period
+const DIVIDEND_PERIOD: &str = "5y";
+
+async fn print_total_return_with_dividends(quotes: &QuoteProvider, args: &[&str]) {
+    let (ticker, period) = match args {
+        [ticker] => (ticker.to_uppercase(), DIVIDEND_PERIOD),
+        [ticker, period] => (ticker.to_uppercase(), *period),
+        _ => {
+            println!("usage: total-return-with-dividends <ticker> [period]");
+            return;
+        }
+    };
+    let period = match parse_history_period(period) {
+        Ok(period) => period,
+        Err(e) => {
+            println!("{}", e);
+            return;
+        }
+    };
+    let history = match quotes.dividend_history(&ticker, period).await {
+        Ok(history) => history,
+        Err(e) => {
+            println!("{}", yahoo_error(&ticker, e));
+            return;
+        }
+    };
+    match reinvest_dividends(&history) {
+        Some(result) => print_dividend_reinvestment(&ticker, &result),
+        None => println!("No price history for ticker {} over {}.", ticker, period),
+    }
+}
+
+async fn print_news(client: &YahooClient, ticker: &str) {
+    let items = match client.news(ticker, NEWS_COUNT).await {
+        Ok(items) => items,
+        Err(e) => {
+            println!("Error when fetching news for ticker {}: {}", ticker, e);
+            return;
+        }
+    };
+    if items.is_empty() {
+        println!("No recent news for ticker {}.", ticker);
+        return;
+    }
+    let now = Utc::now().timestamp();
+    let width = terminal_width();
+    for item in items {
+        let line = format!(
+            "{:>8}  {}: {}",
+            format_relative_time(now - item.published_at),
+            item.publisher,
+            item.title
+        );
+        println!("{}", truncate_to_width(&line, width));
+    }
+}
+
+// the latest headline for each held ticker that has news from today
+async fn print_held_news(client: &YahooClient, assets: &[Asset]) {
+    let today = Local::now().date_naive();
+    let now = Utc::now().timestamp();
+    let width = terminal_width();
+    let mut shown: HashSet<String> = HashSet::new();
+    let mut failed: Vec<String> = vec![];
+    let mut any_news = false;
+    for asset in assets.iter().filter(|asset| is_asset_held(asset)) {
+        if !shown.insert(asset.ticker.to_uppercase()) {
+            continue;
+        }
+        let Ok(items) = client.news(&asset.ticker, 3).await else {
+            failed.push(asset.ticker.clone());
+            continue;
+        };
+        let todays = items.into_iter().next().filter(|item| {
+            Local
+                .timestamp_opt(item.published_at, 0)
+                .single()
+                .is_some_and(|published| published.date_naive() == today)
+        });
+        if let Some(item) = todays {
+            any_news = true;
+            let line = format!(
+                "{}: {} ({}, {})",
+                asset.ticker,
+                item.title,
+                item.publisher,
+                format_relative_time(now - item.published_at)
+            );
+            println!("{}", truncate_to_width(&line, width));
+        }
+    }
+    if !failed.is_empty() {
+        println!("Error when fetching news for: {}.", failed.join(", "));
+    } else if !any_news {
+        println!("No news today for any held ticker.");
+    }
+}
+
+// every prompt accepts 'cancel', Ctrl-C or ESC to abandon the asset; nothing
+// is kept until all of them have been answered
+async fn add_asset(
+    quotes: &QuoteProvider,
+    metadata: &mut MetadataCache,
+    date_order: DateOrder,
+) -> Result<Asset, PortfolioError> {
+    let symbol: String = read_field("Enter ticker: ")?;
+
+    let buy_price: u32 = prompt_price(&format!("Enter buy price per share ({}): ", PRICE_FORMATS))?;
+
+    let sell_price_cents: Option<u32> = prompt_number(
+        &format!(
+            "Enter sell price per share ({}), otherwise enter 'held': ",
+            PRICE_FORMATS
+        ),
+        parse_sell_price,
+    )?;
+    if let Some(sell_price) = sell_price_cents {
+        echo_price(sell_price);
+        if sell_price == 0 && !confirm_zero_price()? {
+            return Err(PortfolioError::Cancelled);
+        }
+    }
+
+    let n: u32 = prompt_number("Enter quantity: ", parse_quantity)?;
+    let total = format_money(buy_price as i64 * n as i64);
+    let answer = read_field(&format!(
+        "Total cost: {} ({} × {}). Correct? [Y/n] ",
+        total,
+        n,
+        format_money(buy_price as i64)
+    ))?;
+    if answer.eq_ignore_ascii_case("n") || answer.eq_ignore_ascii_case("no") {
+        return Err(PortfolioError::Cancelled);
+    }
+
+    let today = Local::now().date_naive();
+    let buy_date: Option<NaiveDate> = read_optional_date(&date_prompt("buy", date_order), |raw| {
+        parse_buy_date(raw, date_order, today)
+    })?;
+
+    let sell_date: Option<NaiveDate> = if sell_price_cents.is_some() {
+        read_optional_date(&date_prompt("sell", date_order), |raw| {
+            parse_sell_date(raw, date_order, today, buy_date)
+        })?
+    } else {
+        None
+    };
+
+    let quote = get_ticker_quote(quotes, metadata, &symbol).await?;
+    // the prices typed in are taken to be in the ticker's own currency
+    let currency = display_settings().currency;
+    if let Some(code) = quote
+        .currency
+        .as_deref()
+        .filter(|code| !code.eq_ignore_ascii_case(currency))
+    {
+        let answer = read_field(&format!(
+            "{} is priced in {} (now {:.2} {}), so the prices entered are taken as {} and it's left out of the {} totals. Correct? [Y/n] ",
+            symbol.to_uppercase(),
+            code,
+            quote.price_cents as f64 / 100.0,
+            code,
+            code,
+            currency
+        ))?;
+        if answer.eq_ignore_ascii_case("n") || answer.eq_ignore_ascii_case("no") {
+            return Err(PortfolioError::Cancelled);
+        }
+    }
+    let mut asset = new_asset(symbol, buy_price, n, quote);
+    asset.buy_date = buy_date;
+    asset.sell_price_cents = sell_price_cents;
+    asset.sell_date = sell_date;
+    Ok(asset)
+}
+
+// `buy <ticker> <quantity>`: a lot bought today at the current price, once
+// the total has been confirmed
+async fn buy_at_market(
+    quotes: &QuoteProvider,
+    metadata: &mut MetadataCache,
+    ticker: &str,
+    quantity: &str,
+) -> Result<Asset, PortfolioError> {
+    let quantity = parse_quantity(quantity).map_err(PortfolioError::InvalidInput)?;
+    let quote = get_ticker_quote(quotes, metadata, ticker).await?;
+    let price = quote.price_cents;
+    let mut asset = new_asset(ticker.to_uppercase(), price, quantity, quote);
+    asset.buy_date = Some(Local::now().date_naive());
+    let bought = confirm(&format!(
+        "Buy {} {} @ {} = {}? (y/N) ",
+        quantity,
+        asset.ticker,
+        format_asset_money(&asset, price as i64),
+        format_asset_money(&asset, cost_basis(&asset))
+    ))?;
+    if !bought {
+        return Err(PortfolioError::Cancelled);
+    }
+    Ok(asset)
+}
+
+// a held lot priced with a quote that was just fetched, without dates
+fn new_asset(ticker: String, buy_price_cents: u32, quantity: u32, quote: TickerQuote) -> Asset {
+    Asset {
+        ticker,
+        buy_price_cents,
+        buy_date: None,
+        current_price_cents: quote.price_cents,
+        sell_price_cents: None,
+        sell_date: None,
+        quantity,
+        price_updated_at: Some(Utc::now()),
+        refresh_failed_at: None,
+        currency: quote.currency,
+        note: None,
+        tags: vec![],
+        account: None,
+        added_at: Some(Utc::now()),
+        price_history: vec![],
+        target_price_cents: None,
+        stop_loss: None,
+        stop_breached_at: None,
+        price_from_cache: false,
+    }
+}
+
+// more bad answers in a row than this and prompt_number offers to give up
+const PROMPT_ATTEMPTS: u32 = 3;
+
+// asks until the answer parses, printing why each bad answer was rejected
+fn prompt_number<T>(
+    text: &str,
+    parse: impl Fn(&str) -> Result<T, String>,
+) -> Result<T, PortfolioError> {
+    let mut failures = 0;
+    loop {
+        match parse(&read_field(text)?) {
+            Ok(value) => return Ok(value),
+            Err(message) => {
+                println!("{}", message);
+                failures += 1;
+                if failures == PROMPT_ATTEMPTS {
+                    if confirm("Cancel instead? [y/N] ")? {
+                        return Err(PortfolioError::Cancelled);
+                    }
+                    failures = 0;
+                }
+            }
+        }
+    }
+}
+
+// a price, where 0 is allowed but has to be confirmed since it's usually a typo
+fn prompt_price(text: &str) -> Result<u32, PortfolioError> {
+    loop {
+        let price = prompt_number(text, parse_price)?;
+        echo_price(price);
+        if price != 0 || confirm_zero_price()? {
+            return Ok(price);
+        }
+    }
+}
+
+// shows how a typed price was read, so entering cents for dollars (or the
+// reverse) is noticed straight away
+fn echo_price(cents: u32) {
+    println!("  = {}", format_money(cents as i64));
+}
+
+fn confirm_zero_price() -> Result<bool, PortfolioError> {
+    confirm("A price of $0.00 is unusual. Keep it? [y/N] ")
+}
+
+fn confirm(text: &str) -> Result<bool, PortfolioError> {
+    Ok(read_field(text)?.eq_ignore_ascii_case("y"))
+}
+
+// one answer in a multi-step prompt. 'cancel', Ctrl-C, ESC and end of input
+// all give PortfolioError::Cancelled so the caller can drop what it has so far
+fn read_field(text: &str) -> Result<String, PortfolioError> {
+    let mut editor = Editor::<()>::new().expect("failed to set up the prompt");
+    editor.bind_sequence(KeyEvent(KeyCode::Esc, Modifiers::NONE), Cmd::Interrupt);
+    // prompts are answered mid-command, from sync code, so the runtime is
+    // told this worker is about to block instead
+    match tokio::task::block_in_place(|| editor.readline(text)) {
+        Ok(line) if line.trim().eq_ignore_ascii_case("cancel") => Err(PortfolioError::Cancelled),
+        Ok(line) => Ok(line.trim().to_string()),
+        Err(_) => Err(PortfolioError::Cancelled),
+    }
+}
+
+// a date in any format parse_date accepts, or None for 'unknown'
+fn read_optional_date(
+    text: &str,
+    parse: impl Fn(&str) -> Result<NaiveDate, String>,
+) -> Result<Option<NaiveDate>, PortfolioError> {
+    prompt_number(text, |raw| parse_optional_date(raw, &parse))
+}
+
+// e.g. "Enter buy date (e.g. 2024-03-15, 3/15/2024, today) or 'unknown': "
+fn date_prompt(which: &str, date_order: DateOrder) -> String {
+    let example = match date_order {
+        DateOrder::Mdy => "3/15/2024",
+        DateOrder::Dmy => "15.3.2024",
+    };
+    format!(
+        "Enter {} date (e.g. 2024-03-15, {}, today) or 'unknown': ",
+        which, example
+    )
+}
+
+// shows the assets `include` allows (only those with the ticker in `args`,
+// if there is one) as a numbered table and asks which one. the numbers are
+// positions in that table, which is sorted, so they're mapped back to the
+// asset's index in the portfolio
+fn pick_asset(
+    assets: &[Asset],
+    args: &[&str],
+    include: impl Fn(&Asset) -> bool,
+    verb: &str,
+) -> Result<usize, PortfolioError> {
+    let ticker = args.first().copied();
+    let candidates = picker_order(assets, ticker, include);
+    if candidates.is_empty() {
+        return Err(PortfolioError::NotFound(match ticker {
+            Some(ticker) => format!("no assets to {} with the ticker {}", verb, ticker),
+            None => format!("no assets to {}", verb),
+        }));
+    }
+    let mut table = Table::new();
+    apply_table_display_settings(&mut table);
+    table.set_header(vec![
+        "#",
+        "Ticker",
+        "Quantity",
+        "Buy Price",
+        "Buy Date",
+        "Status",
+        "Tags",
+    ]);
+    for (number, &index) in candidates.iter().enumerate() {
+        let asset = &assets[index];
+        table.add_row(vec![
+            (number + 1).to_string(),
+            text_column(&asset.ticker),
+            asset.quantity.to_string(),
+            format_money(asset.buy_price_cents as i64),
+            asset
+                .buy_date
+                .map_or_else(|| "unknown".to_string(), |date| date.to_string()),
+            match asset.sell_price_cents {
+                Some(price) => format!("sold at {}", format_money(price as i64)),
+                None => "held".to_string(),
+            },
+            text_column(&asset.tags.join(", ")),
+        ]);
+    }
+    // tags, then status and the buy date go first on a narrow terminal
+    fit_columns(&mut table, &[0, 1, 2, 3, 4, 5, 6]);
+    println!("{table}");
+    let position = prompt_number(
+        &format!(
+            "Which asset to {}? (1-{}, or 'cancel') ",
+            verb,
+            candidates.len()
+        ),
+        |raw| parse_choice(raw, candidates.len()),
+    )?;
+    Ok(candidates[position])
+}
+
+// returns a line describing the sale
+fn sell_asset(
+    portfolio: &mut Portfolio,
+    args: &[&str],
+    date_order: DateOrder,
+) -> Result<String, PortfolioError> {
+    // a lot with no shares, e.g. from a hand-edited file, has nothing to sell
+    let index = pick_asset(
+        &portfolio.assets,
+        args,
+        |asset| is_asset_held(asset) && asset.quantity > 0,
+        "sell",
+    )?;
+    let held = portfolio.assets[index].quantity;
+    let quantity = if held == 1 {
+        1
+    } else {
+        prompt_number(
+            &format!("Quantity to sell (1-{}, Enter for all): ", held),
+            |raw| match raw {
+                "" => Ok(held),
+                raw => match parse_quantity(raw)? {
+                    quantity if quantity > held => {
+                        Err(format!("Only {} shares are held in this lot", held))
+                    }
+                    quantity => Ok(quantity),
+                },
+            },
+        )?
+    };
+    let price = prompt_price(&format!("Enter sell price per share ({}): ", PRICE_FORMATS))?;
+    let today = Local::now().date_naive();
+    let buy_date = portfolio.assets[index].buy_date;
+    let date = read_optional_date(&date_prompt("sell", date_order), |raw| {
+        parse_sell_date(raw, date_order, today, buy_date)
+    })?;
+    let sold = portfolio.sell_lot(index, quantity, price, date)?;
+    let asset = &portfolio.assets[sold];
+    Ok(format!(
+        "Sold {} of {} at {} for {} ({} realized).",
+        describe_count(asset.quantity as usize, "share"),
+        asset.ticker,
+        format_money(price as i64),
+        format_money(price as i64 * asset.quantity as i64),
+        format_money_with_sign(realized_gain_loss(asset).unwrap_or(0))
+    ))
+}
+
+// asks for each field in turn, showing the current value; Enter keeps it.
+// nothing changes unless every prompt is answered
+fn edit_asset(
+    portfolio: &mut Portfolio,
+    args: &[&str],
+    date_order: DateOrder,
+) -> Result<(), PortfolioError> {
+    let index = pick_asset(&portfolio.assets, args, |_| true, "edit")?;
+    let mut asset = portfolio.assets[index].clone();
+    let today = Local::now().date_naive();
+    let optional_date = |date: Option<NaiveDate>| {
+        date.map_or_else(|| "unknown".to_string(), |date| date.to_string())
+    };
+    println!("Press Enter to keep a value.");
+
+    let ticker = read_field(&format!("Ticker [{}]: ", asset.ticker))?;
+    if !ticker.is_empty() {
+        asset.ticker = ticker.to_uppercase();
+    }
+    asset.buy_price_cents = prompt_number(
+        &format!(
+            "Buy price per share [{}]: ",
+            format_money(asset.buy_price_cents as i64)
+        ),
+        |raw| match raw {
+            "" => Ok(asset.buy_price_cents),
+            raw => parse_price(raw),
+        },
+    )?;
+    asset.quantity = prompt_number(
+        &format!("Quantity [{}]: ", asset.quantity),
+        |raw| match raw {
+            "" => Ok(asset.quantity),
+            raw => parse_quantity(raw),
+        },
+    )?;
+    asset.buy_date = prompt_number(
+        &format!("Buy date [{}]: ", optional_date(asset.buy_date)),
+        |raw| match raw {
+            "" => Ok(asset.buy_date),
+            raw => parse_optional_date(raw, |raw| parse_buy_date(raw, date_order, today)),
+        },
+    )?;
+    let current_sell_price = asset
+        .sell_price_cents
+        .map_or_else(|| "held".to_string(), |price| format_money(price as i64));
+    asset.sell_price_cents = prompt_number(
+        &format!("Sell price per share, or 'held' [{}]: ", current_sell_price),
+        |raw| match raw {
+            "" => Ok(asset.sell_price_cents),
+            raw => parse_sell_price(raw),
+        },
+    )?;
+    asset.sell_date = match asset.sell_price_cents {
+        Some(_) => prompt_number(
+            &format!("Sell date [{}]: ", optional_date(asset.sell_date)),
+            |raw| match raw {
+                "" => Ok(asset.sell_date),
+                raw => parse_optional_date(raw, |raw| {
+                    parse_sell_date(raw, date_order, today, asset.buy_date)
+                }),
+            },
+        )?,
+        None => None,
+    };
+    if is_asset_held(&asset) {
+        let current_target = asset
+            .target_price_cents
+            .map_or_else(|| "none".to_string(), |price| format_money(price as i64));
+        asset.target_price_cents = prompt_number(
+            &format!("Target price per share, or 'none' [{}]: ", current_target),
+            |raw| match raw {
+                "" => Ok(asset.target_price_cents),
+                raw if raw.eq_ignore_ascii_case("none") => Ok(None),
+                raw => parse_price(raw).map(Some),
+            },
+        )?;
+    }
+    println!("Now {}", asset);
+    portfolio.assets[index] = asset;
+    Ok(())
+}
+
+fn remove_asset(portfolio: &mut Portfolio, args: &[&str]) -> Result<(), PortfolioError> {
+    let index = pick_asset(&portfolio.assets, args, |_| true, "remove")?;
+    if !confirm(&format!("Remove {}? [y/N] ", portfolio.assets[index]))? {
+        return Err(PortfolioError::Cancelled);
+    }
+    let removed = portfolio.assets.remove(index);
+    println!("Removed {}", removed);
+    Ok(())
+}
+
+fn note_asset(portfolio: &mut Portfolio, args: &[&str]) -> Result<(), PortfolioError> {
+    let index = pick_asset(&portfolio.assets, args, |_| true, "note")?;
+    let asset = &mut portfolio.assets[index];
+    if let Some(note) = &asset.note {
+        println!("Current note: {}", note);
+    }
+    let note = read_field("Enter note (empty to clear): ")?;
+    asset.note = if note.is_empty() { None } else { Some(note) };
+    Ok(())
+}
+
+fn set_asset_target(portfolio: &mut Portfolio, args: &[&str]) -> Result<(), PortfolioError> {
+    let index = pick_asset(&portfolio.assets, args, is_asset_held, "set a target for")?;
+    let asset = &mut portfolio.assets[index];
+    if let Some(target) = asset.target_price_cents {
+        println!(
+            "Current target: {}",
+            format_asset_money(asset, target as i64)
+        );
+    }
+    asset.target_price_cents = prompt_number(
+        "Enter target price per share (empty to clear): ",
+        |raw| match raw {
+            "" => Ok(None),
+            raw => parse_price(raw).map(Some),
+        },
+    )?;
+    Ok(())
+}
+
+// after a refresh: the held lots whose new price reached their target, once
+// per ticker and target
+fn print_target_crossings(before: &[u32], portfolio: &Portfolio) {
+    let mut shown: Vec<(String, u32)> = vec![];
+    for index in targets_crossed(before, &portfolio.assets) {
+        let asset = &portfolio.assets[index];
+        let key = (
+            asset.ticker.to_uppercase(),
+            asset.target_price_cents.unwrap_or_default(),
+        );
+        if shown.contains(&key) {
+            continue;
+        }
+        println!(
+            "🎯 {} reached its target of {} (now {}, {} from {})",
+            key.0,
+            format_asset_money(asset, key.1 as i64),
+            format_asset_money(asset, asset.current_price_cents as i64),
+            if asset.current_price_cents >= before[index] {
+                "up"
+            } else {
+                "down"
+            },
+            format_asset_money(asset, before[index] as i64)
+        );
+        shown.push(key);
+    }
+}
+
+// a new or removed stop replaces any breach of the old one. a stop set at or
+// above the price is breached straight away, since no refresh would cross it
+fn set_asset_stop(portfolio: &mut Portfolio, args: &[&str]) -> Result<(), PortfolioError> {
+    let index = pick_asset(
+        &portfolio.assets,
+        args,
+        is_asset_held,
+        "set a stop-loss for",
+    )?;
+    let asset = &mut portfolio.assets[index];
+    if let Some(stop) = asset.stop_loss {
+        println!(
+            "Current stop-loss: {}",
+            describe_stop_loss(stop, asset.buy_price_cents)
+        );
+    }
+    asset.stop_loss = prompt_number(
+        "Enter a stop-loss price per share, or a percent below the buy price like 10% (empty to remove): ",
+        parse_stop_loss,
+    )?;
+    asset.stop_breached_at = None;
+    if let Some(stop) = asset.stop_loss {
+        let price = asset.current_price_cents;
+        if price > 0 && price <= stop.price_cents(asset.buy_price_cents) {
+            asset.stop_breached_at = Some(Utc::now());
+            print_stop_breaches(&[index], portfolio);
+        }
+    }
+    Ok(())
+}
+
+// after a refresh: a loud line for each lot that just fell to its stop, and
+// a reminder of the older breaches still waiting to be acknowledged
+fn print_stop_breaches(breached: &[usize], portfolio: &Portfolio) {
+    for index in breached {
+        let asset = &portfolio.assets[*index];
+        let Some(stop) = asset.stop_loss else {
+            continue;
+        };
+        println!(
+            "🛑 STOP-LOSS BREACHED: {} is at {}, at or below its stop of {}",
+            asset.ticker.to_uppercase(),
+            format_asset_money(asset, asset.current_price_cents as i64),
+            describe_stop_loss(stop, asset.buy_price_cents)
+        );
+    }
+    let waiting = portfolio
+        .assets
+        .iter()
+        .enumerate()
+        .filter(|(index, asset)| {
+            asset.stop_breached_at.is_some() && is_asset_held(asset) && !breached.contains(index)
+        })
+        .count();
+    if waiting > 0 {
+        println!(
+            "🛑 {} breached earlier and not yet acknowledged; see 'stops'.",
+            describe_count(waiting, "stop")
+        );
+    }
+}
+
+fn set_asset_account(portfolio: &mut Portfolio, args: &[&str]) -> Result<(), PortfolioError> {
+    let index = pick_asset(&portfolio.assets, args, |_| true, "move to an account")?;
+    let asset = &mut portfolio.assets[index];
+    if let Some(account) = &asset.account {
+        println!("Current account: {}", account);
+    }
+    let account = read_field("Enter account, e.g. IRA (empty to clear): ")?;
+    asset.account = if account.is_empty() {
+        None
+    } else {
+        Some(account)
+    };
+    Ok(())
+}
+
+fn tag_asset(portfolio: &mut Portfolio, args: &[&str]) -> Result<(), PortfolioError> {
+    let index = pick_asset(&portfolio.assets, args, |_| true, "tag")?;
+    let asset = &mut portfolio.assets[index];
+    if !asset.tags.is_empty() {
+        println!("Current tags: {}", asset.tags.join(", "));
+    }
+    asset.tags = parse_tags(&read_field(
+        "Enter tags, comma separated (empty to clear): ",
+    )?);
+    Ok(())
+}
+
+// Ctrl-C normally ends the program, just as it would without a handler.
+// while a refresh is catching it, it only sets a flag instead, so the
+// refresh can stop between tickers and keep the prices it already has
+#[derive(Clone)]
+struct Interrupts {
+    // whether Ctrl-C ends the program, false while caught
+    exits: Arc<AtomicBool>,
+    interrupted: Arc<AtomicBool>,
+}
+
+impl Interrupts {
+    fn install() -> Interrupts {
+        let interrupts = Interrupts {
+            exits: Arc::new(AtomicBool::new(true)),
+            interrupted: Arc::new(AtomicBool::new(false)),
+        };
+        // 130 is the exit status shells report for a process ended by SIGINT
+        let registered = signal_hook::flag::register_conditional_shutdown(
+            SIGINT,
+            130,
+            Arc::clone(&interrupts.exits),
+        )
+        .and_then(|_| signal_hook::flag::register(SIGINT, Arc::clone(&interrupts.interrupted)));
+        if let Err(e) = registered {
+            warn!(error = %e, "failed to install the Ctrl-C handler");
+        }
+        interrupts
+    }
+
+    // Ctrl-C is caught until the returned guard is dropped. catching again
+    // while already caught, as a refresh inside `live` does, leaves a Ctrl-C
+    // for the outer guard to see too
+    fn catch(&self) -> CaughtInterrupts<'_> {
+        let outermost = self.exits.swap(false, Ordering::SeqCst);
+        if outermost {
+            self.interrupted.store(false, Ordering::SeqCst);
+        }
+        CaughtInterrupts {
+            interrupts: self,
+            outermost,
+        }
+    }
+}
+
+struct CaughtInterrupts<'a> {
+    interrupts: &'a Interrupts,
+    outermost: bool,
+}
+
+impl CaughtInterrupts<'_> {
+    fn interrupted(&self) -> bool {
+        self.interrupts.interrupted.load(Ordering::SeqCst)
+    }
+}
+
+impl Drop for CaughtInterrupts<'_> {
+    fn drop(&mut self) {
+        if self.outermost {
+            self.interrupts.exits.store(true, Ordering::SeqCst);
+            self.interrupts.interrupted.store(false, Ordering::SeqCst);
+        }
+    }
+}
+
+// "Refreshing 14/60: MSFT", rewritten in place on a terminal and printed as
+// plain lines otherwise so logs and pipes stay readable
+struct Progress {
+    total: usize,
+    in_place: bool,
+    // whether a progress line is currently showing and needs clearing
+    showing: bool,
+}
+
+impl Progress {
+    fn new(total: usize) -> Progress {
+        Progress {
+            total,
+            in_place: io::stdout().is_terminal(),
+            showing: false,
+        }
+    }
+
+    fn update(&mut self, done: usize, label: &str) {
+        let line = format!("Refreshing {}/{}: {}", done, self.total, label);
+        if self.in_place {
+            print!("\r\x1b[2K{}", line);
+            let _ = io::stdout().flush();
+            self.showing = true;
+        } else {
+            println!("{}", line);
+        }
+    }
+
+    // prints a message on its own line without mangling the progress line
+    fn println(&mut self, message: &str) {
+        self.finish();
+        println!("{}", message);
+    }
+
+    fn finish(&mut self) {
+        if self.showing {
+            print!("\r\x1b[2K");
+            let _ = io::stdout().flush();
+            self.showing = false;
+        }
+    }
+}
+
+// fetches current prices for every asset; a ticker that fails keeps its
+// stored price so the portfolio stays usable. returns how many failed
+// failures are listed together at the end, unless verbose-refresh asks for
+// each one as it happens. with `use_cache`, tickers fetched within
+// quote-cache-minutes take their price from the quote cache instead
+async fn refresh_portfolio(
+    session: &mut Session,
+    interrupts: &Interrupts,
+    use_cache: bool,
+) -> usize {
+    let Session {
+        quotes,
+        yahoo_client,
+        metadata,
+        quote_cache,
+        portfolio,
+        config,
+        ..
+    } = session;
+    let started = Instant::now();
+    let verbose = config.verbose_refresh;
+    let history_points = config.price_history_points;
+    let today = Local::now().date_naive();
+    // fixed quotes aren't real prices, so they're never cached
+    let caching = !quotes.is_fixture();
+    let ttl = chrono::Duration::minutes(config.quote_cache_minutes as i64);
+    // lots of the same ticker share one request
+    let mut tickers = distinct_tickers(&portfolio.assets);
+    // the oldest cached quote used, if any were
+    let mut oldest_cached: Option<DateTime<Utc>> = None;
+    let mut cached = 0;
+    if use_cache && caching && config.quote_cache_minutes > 0 {
+        tickers.retain(|(ticker, _)| {
+            let Some(quote) = quote_cache.fresh(ticker, ttl, Utc::now()) else {
+                return true;
+            };
+            portfolio.set_cached_price(ticker, quote.price_cents, quote.fetched_at);
+            portfolio.set_currency(ticker, quote.currency.as_deref());
+            portfolio.record_price_history(ticker, quote.price_cents, today, history_points);
+            oldest_cached =
+                Some(oldest_cached.map_or(quote.fetched_at, |t| t.min(quote.fetched_at)));
+            cached += 1;
+            false
+        });
+    }
+    let before: Vec<u32> = portfolio
+        .assets
+        .iter()
+        .map(|asset| asset.current_price_cents)
+        .collect();
+    let mut progress = Progress::new(tickers.len());
+    // "GME (timeout)" for each ticker that failed
+    let mut failures: Vec<String> = vec![];
+    // requests already under way are allowed to finish, then no more are made
+    let caught = interrupts.catch();
+    let mut finished = 0;
+    for batch in tickers.chunks(QUOTE_BATCH_SIZE) {
+        if caught.interrupted() {
+            break;
+        }
+        let names: Vec<&str> = batch.iter().map(|(ticker, _)| ticker.as_str()).collect();
+        let fetched = quotes.latest_prices(&names).await;
+        for ((ticker, lots), quote) in batch.iter().zip(fetched) {
+            finished += 1;
+            progress.update(finished, ticker);
+            debug!(ticker = %ticker, lots, ok = quote.is_ok(), "refreshed ticker");
+            match quote {
+                Ok(quote) => {
+                    cache_quote_range(metadata, ticker, &quote);
+                    if caching {
+                        quote_cache.insert(ticker, &quote, Utc::now());
+                    }
+                    portfolio.set_price(ticker, quote.price_cents, Utc::now());
+                    portfolio.set_currency(ticker, quote.currency.as_deref());
+                    portfolio.record_price_history(
+                        ticker,
+                        quote.price_cents,
+                        today,
+                        history_points,
+                    );
+                }
+                Err(e) => {
+                    portfolio.mark_refresh_failed(ticker, Utc::now());
+                    failures.push(format!("{} ({})", ticker, quote_failure_reason(&e)));
+                    if verbose {
+                        progress.println(&format!(
+                            "Error when fetching current price for ticker {}{}: {}",
+                            ticker,
+                            if *lots > 1 {
+                                format!(" ({} lots keep their stored price)", lots)
+                            } else {
+                                String::new()
+                            },
+                            yahoo_error(ticker, e)
+                        ));
+                    }
+                }
+            }
+        }
+    }
+    progress.finish();
+    print_target_crossings(&before, portfolio);
+    let breached = portfolio.check_stop_losses(&before, Utc::now());
+    print_stop_breaches(&breached, portfolio);
+    let interrupted = caught.interrupted();
+    drop(caught);
+    let updated = finished - failures.len() + cached;
+    // e.g. " (3 from the quote cache, fetched up to 12m ago)"
+    let from_cache = match oldest_cached {
+        Some(oldest) => format!(
+            " ({} from the quote cache, fetched up to {}; 'refresh' fetches them again)",
+            cached,
+            format_relative_time((Utc::now() - oldest).num_seconds())
+        ),
+        None => String::new(),
+    };
+    let failed = if failures.is_empty() {
+        String::new()
+    } else {
+        format!("; failed: {}", failures.join(", "))
+    };
+    if interrupted {
+        println!(
+            "Refresh interrupted after {}/{} tickers. Updated {}{}{}. The rest keep their stored prices.",
+            finished + cached,
+            tickers.len() + cached,
+            describe_count(updated, "ticker"),
+            from_cache,
+            failed
+        );
+        // a value made from part old and part new prices would be misleading
+        // in the history, so nothing is recorded
+        save_metadata(metadata);
+        return failures.len() + tickers.len() - finished;
+    }
+    println!(
+        "Updated {} in {:.1}s{}{}",
+        describe_count(updated, "ticker"),
+        started.elapsed().as_secs_f64(),
+        from_cache,
+        failed
+    );
+    record_portfolio_value(portfolio, today);
+    save_metadata(metadata);
+    if !quotes.is_fixture() {
+        fetch_stale_earnings(yahoo_client, metadata, &portfolio.assets, today).await;
+    }
+    failures.len()
+}
+
+// the target is in dollars and the date has to be in the future
+fn new_goal(
+    name: &str,
+    target: &str,
+    date: &str,
+    date_order: DateOrder,
+    today: NaiveDate,
+) -> Result<Goal, PortfolioError> {
+    if name.trim().is_empty() {
+        return Err(PortfolioError::InvalidInput(
+            "the goal needs a name".to_string(),
+        ));
+    }
+    let target_value_cents = parse_goal_target(target).map_err(PortfolioError::InvalidInput)?;
+    let target_date = parse_date(date, date_order, today).map_err(PortfolioError::InvalidInput)?;
+    if target_date <= today {
+        return Err(PortfolioError::InvalidInput(format!(
+            "the target date {} has to be after today",
+            target_date
+        )));
+    }
+    Ok(Goal {
+        name: name.trim().to_string(),
+        target_value_cents,
+        target_date,
+    })
+}
+
+// e.g. "Added 4 assets from old.json. AAPL, MSFT were already held, so
+// the incoming shares were added to the held lots (merge-strategy sum-quantity)."
+fn describe_merge(report: &MergeReport, filename: &str, strategy: MergeStrategy) -> String {
+    let added = format!(
+        "Added {} from {}.",
+        describe_count(report.added, "asset"),
+        filename
+    );
+    if report.duplicates.is_empty() {
+        return added;
+    }
+    let outcome = match strategy {
+        MergeStrategy::KeepBoth => "the incoming ones were added as separate lots",
+        MergeStrategy::KeepExisting => "the incoming ones were left out",
+        MergeStrategy::KeepIncoming => "the incoming ones replaced the held lots",
+        MergeStrategy::SumQuantity => "the incoming shares were added to the held lots",
+    };
+    format!(
+        "{} {} {} already held, so {} (merge-strategy {}).",
+        added,
+        report.duplicates.join(", "),
+        if report.duplicates.len() == 1 {
+            "was"
+        } else {
+            "were"
+        },
+        outcome,
+        strategy.name()
+    )
+}
+
+// `live [--no-refresh] <seconds>`
+fn parse_live_args(args: &[&str]) -> Result<(u64, bool), PortfolioError> {
+    let refresh = !args.contains(&"--no-refresh");
+    let rest: Vec<&str> = args
+        .iter()
+        .copied()
+        .filter(|arg| *arg != "--no-refresh")
+        .collect();
+    match rest.as_slice() {
+        [seconds] => seconds
+            .parse()
+            .ok()
+            .filter(|seconds| *seconds > 0)
+            .map(|seconds| (seconds, refresh))
+            .ok_or_else(|| {
+                PortfolioError::InvalidInput(format!(
+                    "'{}' isn't a number of seconds above zero",
+                    seconds
+                ))
+            }),
+        _ => Err(PortfolioError::InvalidInput(
+            "usage: live [--no-refresh] <seconds>".to_string(),
+        )),
+    }
+}
+
+// clears the screen and shows the assets every `seconds`, refreshing them
+// first unless `refresh` is false, until Ctrl-C. the first time is straight
+// away
+async fn live_mode(session: &mut Session, interrupts: &Interrupts, seconds: u64, refresh: bool) {
+    let caught = interrupts.catch();
+    let mut ticks = tokio::time::interval(StdDuration::from_secs(seconds));
+    // how often a Ctrl-C is looked for while waiting
+    let mut checks = tokio::time::interval(StdDuration::from_millis(100));
+    let clear = io::stdout().is_terminal();
+    while !caught.interrupted() {
+        tokio::select! {
+            _ = ticks.tick() => {}
+            _ = checks.tick() => continue,
+        }
+        if clear {
+            print!("\x1B[2J\x1B[H");
+        }
+        println!(
+            "{} │ every {}s{} │ Ctrl-C to stop",
+            Local::now().format("%Y-%m-%d %H:%M:%S"),
+            seconds,
+            if refresh { "" } else { ", cached prices" }
+        );
+        if refresh {
+            refresh_portfolio(session, interrupts, false).await;
+        }
+        println!("{}", session.portfolio);
+        println!("{}", assets_table(&session.portfolio.assets));
+        if let Some(legend) = refresh_failed_legend(&session.portfolio.assets) {
+            println!("{}", legend);
+        }
+        let _ = io::stdout().flush();
+    }
+    println!("Left live mode.");
+}
+
+// the shortest `watch` interval, so a watched portfolio doesn't ask Yahoo
+// for every ticker every second
+const MIN_WATCH_SECONDS: u64 = 10;
+
+fn parse_watch_args(args: &[&str]) -> Result<u64, PortfolioError> {
+    match args {
+        [] => Ok(60),
+        [seconds] => seconds
+            .parse()
+            .ok()
+            .filter(|seconds| *seconds >= MIN_WATCH_SECONDS)
+            .ok_or_else(|| {
+                PortfolioError::InvalidInput(format!(
+                    "'{}' isn't a number of seconds of at least {}",
+                    seconds, MIN_WATCH_SECONDS
+                ))
+            }),
+        _ => Err(PortfolioError::InvalidInput(
+            "usage: watch [seconds]".to_string(),
+        )),
+    }
+}
+
+// counts down to the next update on the last line, returning false when q or
+// Ctrl-C is pressed first. the terminal is raw only while waiting, so Ctrl-C
+// arrives as a key rather than a signal
+fn watch_countdown(seconds: u64) -> io::Result<bool> {
+    terminal::enable_raw_mode()?;
+    let deadline = Instant::now() + StdDuration::from_secs(seconds);
+    let result = loop {
+        let left = deadline.saturating_duration_since(Instant::now());
+        if left.is_zero() {
+            break Ok(true);
+        }
+        print!(
+            "\r\x1b[2KNext update in {}s │ q or Ctrl-C to stop",
+            left.as_secs_f64().ceil() as u64
+        );
+        let _ = io::stdout().flush();
+        match event::poll(left.min(StdDuration::from_millis(250))) {
+            Ok(false) => {}
+            Ok(true) => match event::read() {
+                Ok(event::Event::Key(key))
+                    if key.code == event::KeyCode::Char('q')
+                        || (key.code == event::KeyCode::Char('c')
+                            && key.modifiers.contains(event::KeyModifiers::CONTROL)) =>
+                {
+                    break Ok(false)
+                }
+                Ok(_) => {}
+                Err(e) => break Err(e),
+            },
+            Err(e) => break Err(e),
+        }
+    };
+    let _ = terminal::disable_raw_mode();
+    print!("\r\x1b[2K");
+    let _ = io::stdout().flush();
+    result
+}
+
+// `watch`: refreshes, then redraws the summary and assets every `seconds`
+// until q or Ctrl-C. when no ticker at all could be refreshed the last
+// screen stays up with a note under it, rather than one of stale prices
+async fn watch_mode(session: &mut Session, interrupts: &Interrupts, seconds: u64) {
+    let caught = interrupts.catch();
+    let mut drawn: Option<DateTime<Local>> = None;
+    loop {
+        let tickers = distinct_tickers(&session.portfolio.assets).len();
+        let failures = refresh_portfolio(session, interrupts, false).await;
+        if caught.interrupted() {
+            break;
+        }
+        let now = Local::now();
+        match drawn {
+            Some(drawn) if tickers > 0 && failures == tickers => println!(
+                "⚠ No prices could be refreshed at {}; still showing the update from {}.",
+                now.format("%H:%M:%S"),
+                drawn.format("%H:%M:%S")
+            ),
+            _ => {
+                print!("\x1B[2J\x1B[H");
+                println!(
+                    "{} │ every {}s │ q or Ctrl-C to stop",
+                    now.format("%Y-%m-%d %H:%M:%S"),
+                    seconds
+                );
+                let analytics = PortfolioAnalytics::compute(
+                    &session.portfolio,
+                    &session.metadata,
+                    now.date_naive(),
+                );
+                print_summary(&session.portfolio, &analytics);
+                println!("{}", assets_table(&session.portfolio.assets));
+                if let Some(legend) = refresh_failed_legend(&session.portfolio.assets) {
+                    println!("{}", legend);
+                }
+                drawn = Some(now);
+            }
+        }
+        match tokio::task::block_in_place(|| watch_countdown(seconds)) {
+            Ok(true) => {}
+            Ok(false) => break,
+            Err(e) => {
+                println!("Couldn't read the keyboard ({}).", e);
+                break;
+            }
+        }
+    }
+    println!("Left watch mode.");
+}
+
+const DEFAULT_CLEANUP_DAYS: i64 = 365;
+
+// removes assets sold more than DAYS ago after showing them and asking, with
+// a backup written first. returns whether anything was removed
+fn cleanup_sold_assets(
+    portfolio: &mut Portfolio,
+    args: &[&str],
+    current_file: Option<&Path>,
+    dry_run: bool,
+) -> Result<bool, PortfolioError> {
+    let days: i64 = match args.first() {
+        Some(raw) => raw.parse().ok().filter(|days| *days >= 0).ok_or_else(|| {
+            PortfolioError::InvalidInput(format!("'{}' is not a number of days", raw))
+        })?,
+        None => DEFAULT_CLEANUP_DAYS,
+    };
+    let cutoff = Local::now().date_naive() - chrono::Duration::days(days);
+    let indices = sold_before(&portfolio.assets, cutoff);
+    let undated = portfolio
+        .assets_sold()
+        .into_iter()
+        .filter(|asset| asset.sell_date.is_none())
+        .count();
+    if undated > 0 {
+        println!(
+            "{} sold asset{} without a sell date {} skipped.",
+            undated,
+            if undated == 1 { "" } else { "s" },
+            if undated == 1 { "was" } else { "were" }
+        );
+    }
+    if indices.is_empty() {
+        println!("No assets sold more than {} days ago.", days);
+        return Ok(false);
+    }
+
+    let mut table = Table::new();
+    apply_table_display_settings(&mut table);
+    table.set_header(vec!["Ticker", "Sold On", "Quantity", "Realized Gain/Loss"]);
+    let mut total: i64 = 0;
+    for &index in &indices {
+        let asset = &portfolio.assets[index];
+        let gain_loss = realized_gain_loss(asset).unwrap_or(0);
+        total += gain_loss;
+        table.add_row(vec![
+            Cell::new(text_column(&asset.ticker)),
+            Cell::new(
+                asset
+                    .sell_date
+                    .map_or(String::new(), |date| date.to_string()),
+            ),
+            Cell::new(asset.quantity),
+            gain_loss_cell(format_money(gain_loss), gain_loss as f64),
+        ]);
+    }
+    println!("{table}");
+    println!(
+        "{} asset{} sold more than {} days ago, with a total realized gain/loss of {}.",
+        indices.len(),
+        if indices.len() == 1 { "" } else { "s" },
+        days,
+        format_money(total)
+    );
+    let answer = prompt("Remove them? [y/N] ");
+    if !answer.trim().eq_ignore_ascii_case("y") {
+        println!("Nothing removed.");
+        return Ok(false);
+    }
+
+    let backup = backup_path(current_file, Local::now().naive_local());
+    if dry_run {
+        println!("[DRY RUN] Would write a backup to {}", backup.display());
+    } else {
+        dump_portfolio_to_path(portfolio, &backup)?;
+        println!("Backup written to {}.", backup.display());
+    }
+    // highest index first so the earlier indices stay valid
+    for &index in indices.iter().rev() {
+        portfolio.assets.remove(index);
+    }
+    println!(
+        "Removed {} asset{}.",
+        indices.len(),
+        if indices.len() == 1 { "" } else { "s" }
+    );
+    Ok(true)
+}
+
+// refreshes one ticker, e.g. after adding or correcting a position, without
+// waiting on the rest of the portfolio
+async fn refresh_ticker(session: &mut Session, ticker: &str) -> Result<(), PortfolioError> {
+    let Session {
+        quotes,
+        metadata,
+        portfolio,
+        config,
+        ..
+    } = session;
+    let history_points = config.price_history_points;
+    let Some(old_price) = portfolio
+        .assets_by_ticker(ticker)
+        .first()
+        .map(|asset| asset.current_price_cents)
+    else {
+        return Err(PortfolioError::NotFound(format!(
+            "{} is not in the portfolio",
+            ticker.to_uppercase()
+        )));
+    };
+    let quote = match get_ticker_quote(quotes, metadata, ticker).await {
+        Ok(quote) => quote,
+        Err(e) => {
+            portfolio.mark_refresh_failed(ticker, Utc::now());
+            return Err(e);
+        }
+    };
+    let new_price = quote.price_cents;
+    let before: Vec<u32> = portfolio
+        .assets
+        .iter()
+        .map(|asset| asset.current_price_cents)
+        .collect();
+    let lots = portfolio.set_price(ticker, new_price, Utc::now());
+    portfolio.set_currency(ticker, quote.currency.as_deref());
+    portfolio.record_price_history(ticker, new_price, Local::now().date_naive(), history_points);
+    let lot = portfolio.assets_by_ticker(ticker).first().copied();
+    let money = |cents: u32| match lot {
+        Some(lot) => format_asset_money(lot, cents as i64),
+        None => format_money(cents as i64),
+    };
+    println!(
+        "{}: {} → {}{}",
+        ticker.to_uppercase(),
+        money(old_price),
+        money(new_price),
+        if lots > 1 {
+            format!(" ({} lots)", lots)
+        } else {
+            String::new()
+        }
+    );
+    print_target_crossings(&before, portfolio);
+    let breached = portfolio.check_stop_losses(&before, Utc::now());
+    print_stop_breaches(&breached, portfolio);
+    record_portfolio_value(portfolio, Local::now().date_naive());
+    save_metadata(metadata);
+    Ok(())
+}
+
+// simulates going to cash: every held asset is sold at its current price
+// today. returns whether anything was sold
+fn sell_all(portfolio: &mut Portfolio, config: &Config) -> bool {
+    let held = portfolio.assets_held().len();
+    if held == 0 {
+        println!("No held assets to sell.");
+        return false;
+    }
+    let realized_before = portfolio.total_realized_gain_loss();
+    println!(
+        "This sells {} held asset{} at the current prices, worth {} in total.",
+        held,
+        if held == 1 { "" } else { "s" },
+        format_money(portfolio.total_held_market_value())
+    );
+    print_stale_warning(&portfolio.assets, config);
+    let answer = prompt("Sell everything? [y/N] ");
+    if !answer.trim().eq_ignore_ascii_case("y") {
+        println!("Nothing sold.");
+        return false;
+    }
+    let (sold, proceeds) = portfolio.sell_all_held(Local::now().date_naive());
+    println!(
+        "Sold {} asset{} for total proceeds of {} ({} realized).",
+        sold,
+        if sold == 1 { "" } else { "s" },
+        format_money(proceeds),
+        format_money_with_sign(portfolio.total_realized_gain_loss() - realized_before)
+    );
+    true
+}
+
+const IMPORT_MENU: [(&str, Option<ImportFormat>); 7] = [
+    (
+        "CSV file (broker exports are recognised)",
+        Some(ImportFormat::Csv),
+    ),
+    ("Fidelity CSV", Some(ImportFormat::Fidelity)),
+    ("Robinhood CSV", Some(ImportFormat::Robinhood)),
+    ("Schwab CSV", Some(ImportFormat::Schwab)),
+    ("Beancount journal", Some(ImportFormat::Beancount)),
+    ("JSON (another portfolio)", Some(ImportFormat::Json)),
+    // None is manual entry through `new`
+    ("Manual entry", None),
+];
+
+// `import` on its own asks for the format and file; `import FORMAT FILE` is
+// the same without the questions. returns how many assets were added
+async fn run_import(
+    quotes: &QuoteProvider,
+    metadata: &mut MetadataCache,
+    portfolio: &mut Portfolio,
+    args: &[&str],
+    date_order: DateOrder,
+) -> Result<usize, PortfolioError> {
+    let (format, path) = match args {
+        [] => {
+            for (number, (label, _)) in IMPORT_MENU.iter().enumerate() {
+                println!("{}. {}", number + 1, label);
+            }
+            let choice = prompt(&format!("Import from (1-{}): ", IMPORT_MENU.len()));
+            let Some((_, format)) = choice
+                .trim()
+                .parse::<usize>()
+                .ok()
+                .and_then(|number| IMPORT_MENU.get(number.wrapping_sub(1)))
+            else {
+                return Err(PortfolioError::InvalidInput(format!(
+                    "'{}' isn't one of the choices",
+                    choice.trim()
+                )));
+            };
+            let Some(format) = *format else {
+                let asset = add_asset(quotes, metadata, date_order).await?;
+                println!("Added {}", asset);
+                portfolio.assets.push(asset);
+                save_metadata(metadata);
+                return Ok(1);
+            };
+            (format, PathBuf::from(prompt("Enter filename to import: ")))
+        }
+        [name, path] => {
+            let format = ImportFormat::from_name(name).ok_or_else(|| {
+                PortfolioError::InvalidInput(format!(
+                    "unknown format '{}' (expected one of {})",
+                    name, IMPORT_FORMAT_NAMES
+                ))
+            })?;
+            (format, PathBuf::from(path))
+        }
+        _ => {
+            return Err(PortfolioError::InvalidInput(format!(
+                "usage: import [format filename], where format is one of {}",
+                IMPORT_FORMAT_NAMES
+            )))
+        }
+    };
+
+    let imported = import_file(format, &path, date_order, Local::now().date_naive())?;
+    for reason in &imported.skipped {
+        println!("Skipped {}", reason);
+    }
+    let count = imported.assets.len();
+    let now = Utc::now();
+    portfolio
+        .assets
+        .extend(imported.assets.into_iter().map(|asset| Asset {
+            added_at: Some(now),
+            ..asset
+        }));
+    println!(
+        "Imported {} asset{} from {}.",
+        count,
+        if count == 1 { "" } else { "s" },
+        path.display()
+    );
+    if count > 0 && format != ImportFormat::Json {
+        println!("Run 'refresh' to fetch their current prices.");
+    }
+    Ok(count)
+}
+
+const EXPORT_MENU: [(&str, ExportFormat); 6] = [
+    ("JSON", ExportFormat::Json),
+    ("CSV", ExportFormat::Csv),
+    ("Ledger journal", ExportFormat::Ledger),
+    ("HTML report", ExportFormat::Html),
+    ("PDF", ExportFormat::Pdf),
+    ("XLSX", ExportFormat::Xlsx),
+];
+
+// `export` on its own asks for the format and file, then offers to open the
+// result; `export FORMAT FILE` just writes it
+fn run_export(portfolio: &Portfolio, args: &[&str], dry_run: bool) -> Result<(), PortfolioError> {
+    let (format, path, interactive) = match args {
+        [] => {
+            for (number, (label, _)) in EXPORT_MENU.iter().enumerate() {
+                println!("{}. {}", number + 1, label);
+            }
+            let choice = prompt("Export to (1-6): ");
+            let Some((_, format)) = choice
+                .trim()
+                .parse::<usize>()
+                .ok()
+                .and_then(|number| EXPORT_MENU.get(number.wrapping_sub(1)))
+            else {
+                return Err(PortfolioError::InvalidInput(format!(
+                    "'{}' isn't one of the choices",
+                    choice.trim()
+                )));
+            };
+            let default = format!("{}.{}", portfolio.name, format.extension());
+            let filename = prompt(&format!("Enter filename to export to [{}]: ", default));
+            let filename = match filename.trim() {
+                "" => default,
+                filename => filename.to_string(),
+            };
+            (*format, PathBuf::from(filename), true)
+        }
+        [name, path] => {
+            let format = ExportFormat::from_name(name).ok_or_else(|| {
+                PortfolioError::InvalidInput(format!(
+                    "unknown format '{}' (expected one of {})",
+                    name, EXPORT_FORMAT_NAMES
+                ))
+            })?;
+            (format, PathBuf::from(path), false)
+        }
+        _ => {
+            return Err(PortfolioError::InvalidInput(format!(
+                "usage: export [format filename], where format is one of {}",
+                EXPORT_FORMAT_NAMES
+            )))
+        }
+    };
+
+    if dry_run {
+        println!("[DRY RUN] Would write export to {}", path.display());
+        return Ok(());
+    }
+    export_to_path(format, portfolio, &path, Local::now().date_naive())?;
+    println!(
+        "Exported {} to {}.",
+        describe_count(portfolio.assets.len(), "asset"),
+        path.display()
+    );
+    if interactive {
+        let answer = prompt("Open it now? [y/N] ");
+        if answer.trim().eq_ignore_ascii_case("y") {
+            open_file(&path);
+        }
+    }
+    Ok(())
+}
+
+// hands the file to whatever the desktop uses for its type
+fn open_file(path: &Path) {
+    let mut command = if cfg!(target_os = "macos") {
+        std::process::Command::new("open")
+    } else if cfg!(windows) {
+        let mut command = std::process::Command::new("cmd");
+        command.args(["/C", "start", ""]);
+        command
+    } else {
+        std::process::Command::new("xdg-open")
+    };
+    match command.arg(path).status() {
+        Ok(status) if status.success() => {}
+        Ok(status) => println!("Couldn't open {} ({}).", path.display(), status),
+        Err(e) => println!("Couldn't open {} ({}).", path.display(), e),
+    }
+}
+
+// portfolio, settings and metadata cache in one zip, for moving machines
+fn export_all(
+    portfolio: &Portfolio,
+    config: &Config,
+    metadata: &MetadataCache,
+    path: &Path,
+    dry_run: bool,
+) -> Result<(), PortfolioError> {
+    let bytes = write_bundle(portfolio, config, metadata)?;
+    if dry_run {
+        println!(
+            "[DRY RUN] Would write {} to {}",
+            format_bytes(bytes.len()),
+            path.display()
+        );
+        return Ok(());
+    }
+    fs::write(path, bytes)?;
+    println!(
+        "Exported the portfolio, settings and metadata cache to {}.",
+        path.display()
+    );
+    Ok(())
+}
+
+// returns whether anything was converted
+fn convert_totals(portfolio: &mut Portfolio) -> bool {
+    let suspects = portfolio.likely_total_prices();
+    if suspects.is_empty() {
+        println!("No buy prices look like totals.");
+        return false;
+    }
+    let mut table = Table::new();
+    apply_table_display_settings(&mut table);
+    table.set_header(vec!["Ticker", "Quantity", "Stored Buy Price", "Per Share"]);
+    for &index in &suspects {
+        let asset = &portfolio.assets[index];
+        table.add_row(vec![
+            text_column(&asset.ticker),
+            asset.quantity.to_string(),
+            format_money(asset.buy_price_cents as i64),
+            format_money(per_share_price(asset.buy_price_cents, asset.quantity) as i64),
+        ]);
+    }
+    println!("{table}");
+    let answer = prompt("Divide these buy prices by their quantity? [y/N] ");
+    if !answer.trim().eq_ignore_ascii_case("y") {
+        println!("Nothing changed.");
+        return false;
+    }
+    portfolio.convert_totals_to_per_share(&suspects);
+    println!("Converted {}.", describe_count(suspects.len(), "buy price"));
+    true
+}
+
+fn prompt(text: &str) -> String {
+    let mut rustyline = Editor::<()>::new().expect("REASON");
+    let input = tokio::task::block_in_place(|| rustyline.readline(text));
+
+    match input {
+        Ok(line) => line,
+        Err(_) => std::process::exit(3),
+    }
+}
+
+// dimmed hints need escape code support, which dumb terminals and pipes lack
+fn supports_hints() -> bool {
+    let dumb_terminal = matches!(env::var("TERM").as_deref(), Ok("dumb") | Ok(""));
+    io::stdout().is_terminal() && !dumb_terminal
+}
+
+fn filename_or_prompt(filename: Option<&str>, text: &str) -> PathBuf {
+    match filename {
+        Some(filename) => PathBuf::from(filename),
+        None => PathBuf::from(prompt(text)),
+    }
+}
+
+// backs up the portfolio file when backup-schedule says one is due. like the
+// caches, a backup that fails only gets a warning
+fn run_scheduled_backup(config: &Config, file: Option<&Path>, dry_run: bool) {
+    let schedule = match config.backup_schedule() {
+        Ok(Some(schedule)) => schedule,
+        Ok(None) => return,
+        Err(e) => {
+            println!("No scheduled backup: {}", e);
+            return;
+        }
+    };
+    let Some(file) = file.filter(|path| path.exists()) else {
+        return;
+    };
+    let now = Utc::now();
+    if !backup_due(&schedule, last_backup(), now) {
+        return;
+    }
+    let home = dirs_next::home_dir();
+    if dry_run {
+        println!(
+            "[DRY RUN] Would back up {}",
+            display_path(file, home.as_deref())
+        );
+        return;
+    }
+    match back_up(file, now) {
+        Ok(backup) => println!(
+            "Backed up {} to {}.",
+            display_path(file, home.as_deref()),
+            display_path(&backup, home.as_deref())
+        ),
+        Err(e) => {
+            warn!(error = %e, "scheduled backup failed");
+            println!(
+                "Scheduled backup of {} failed: {}",
+                display_path(file, home.as_deref()),
+                e
+            );
+        }
+    }
+}
+
+// `load` without a filename offers the recent files, pruning any that are gone
+fn load_filename(filename: Option<&str>, recent: &mut RecentFiles) -> PathBuf {
+    if let Some(filename) = filename {
+        return PathBuf::from(filename);
+    }
+    if recent.prune_missing() > 0 {
+        save_recent_files(recent);
+    }
+    if recent.paths().is_empty() {
+        return PathBuf::from(prompt("Enter filename to load: "));
+    }
+    let home = dirs_next::home_dir();
+    println!("Recent files:");
+    for (number, path) in recent.paths().iter().enumerate() {
+        println!("{:>3}) {}", number + 1, display_path(path, home.as_deref()));
+    }
+    let answer = prompt("Enter a number, or a filename to load: ");
+    let answer = answer.trim();
+    match answer.parse::<usize>() {
+        Ok(number) if (1..=recent.paths().len()).contains(&number) => {
+            recent.paths()[number - 1].clone()
+        }
+        _ => PathBuf::from(answer),
+    }
+}
+
+// with --dry-run nothing is written, only reported
+fn dump_portfolio(portfolio: &Portfolio, path: &Path, dry_run: bool) -> bool {
+    if dry_run {
+        println!("[DRY RUN] Would write portfolio to {}", path.display());
+        return true;
+    }
+    match dump_portfolio_to_path(portfolio, path) {
+        Ok(()) => true,
+        Err(e) => {
+            println!("Error occurred when dumping ({}). Portfolio not dumped.", e);
+            false
+        }
+    }
+}
+
+// takes the lock on a file that's about to become current_file. Ok(false)
+// when another instance has it open and the user chose to open it read-only
+fn lock_portfolio(
+    portfolio_lock: &mut Option<PortfolioLock>,
+    path: &Path,
+    dry_run: bool,
+) -> Result<bool, PortfolioError> {
+    if dry_run
+        || portfolio_lock
+            .as_ref()
+            .is_some_and(|lock| lock.portfolio() == path)
+    {
+        return Ok(true);
+    }
+    match lock::acquire(path) {
+        Ok(LockAttempt::Acquired(lock)) => {
+            *portfolio_lock = Some(lock);
+            Ok(true)
+        }
+        Ok(LockAttempt::HeldBy(pid)) => {
+            if !confirm(&format!(
+                "another instance (pid {}) has this portfolio open — open read-only? (y/N) ",
+                pid
+            ))? {
+                return Err(PortfolioError::Cancelled);
+            }
+            *portfolio_lock = None;
+            Ok(false)
+        }
+        Err(e) => {
+            println!(
+                "Couldn't lock {} ({}). Another instance could overwrite it.",
+                path.display(),
+                e
+            );
+            *portfolio_lock = None;
+            Ok(true)
+        }
+    }
+}
+
+// a dump makes the file current_file, so it needs the lock too. a lock held
+// by someone else has already been ruled out
+fn lock_dumped_portfolio(portfolio_lock: &mut Option<PortfolioLock>, path: &Path) {
+    if portfolio_lock
+        .as_ref()
+        .is_some_and(|lock| lock.portfolio() == path)
+    {
+        return;
+    }
+    *portfolio_lock = match lock::acquire(path) {
+        Ok(LockAttempt::Acquired(lock)) => Some(lock),
+        _ => None,
+    };
+}
+
+// after a change, write the portfolio back to the file it was loaded from
+// or last dumped to
+fn autosave(session: &Session) {
+    if !session.config.autosave {
+        return;
+    }
+    match session.current_file.as_deref() {
+        Some(path) if session.dry_run => {
+            println!("[DRY RUN] Would autosave portfolio to {}", path.display())
+        }
+        Some(path) => {
+            if let Err(e) = dump_portfolio_to_path(&session.portfolio, path) {
+                println!("Autosave failed ({}).", e);
+            }
+        }
+        None => println!("Autosave is on, but there is no file yet. Use 'dump' to pick one."),
+    }
+}
+
+fn save_config(config: &Config, dry_run: bool) -> bool {
+    if dry_run {
+        println!("[DRY RUN] Would write settings to the config file");
+        return true;
+    }
+    match config.save() {
+        Ok(()) => {
+            println!("Settings saved.");
+            true
+        }
+        Err(e) => {
+            println!("Error when saving settings ({}).", e);
+            false
+        }
+    }
+}
+
+// `set` lists every setting, `set KEY VALUE` changes one for this session
+// and `--save` anywhere in the arguments also writes it to the config file
+fn run_set(config: &mut Config, args: &[&str], dry_run: bool) -> bool {
+    let save = args.contains(&"--save");
+    let args: Vec<&str> = args
+        .iter()
+        .copied()
+        .filter(|arg| *arg != "--save")
+        .collect();
+    match args.as_slice() {
+        [] => {
+            print_settings(config);
+            true
+        }
+        // a user agent can have spaces in it
+        [key, value @ ..] if !value.is_empty() => match config.set(key, &value.join(" ")) {
+            Ok(()) => {
+                config.apply();
+                // echo the value as stored, e.g. "eur" becomes "EUR"
+                if let Some((key, value)) = config
+                    .settings()
+                    .into_iter()
+                    .find(|(known, _)| known == key)
+                {
+                    println!("{} set to {}.", key, value);
+                }
+                // the HTTP client is built once at startup
+                if ["proxy", "api-url", "user-agent"].contains(key) {
+                    println!("This is used from the next start; add --save to keep it.");
+                }
+                !save || save_config(config, dry_run)
+            }
+            Err(e) => {
+                println!("Setting not changed: {}", e);
+                false
+            }
+        },
+        _ => {
+            println!("Usage: set [setting value] [--save]. Enter 'set' to list settings.");
+            false
+        }
+    }
+}
+
+const USAGE: &str =
+    "Usage: portfolio-tracker [--dry-run] [--page-size N] [--full-width] [--quotes-file FILE] [--file FILE] [--report FILE] [--version]";
+
+#[derive(Default)]
+struct Args {
+    dry_run: bool,
+    // overrides the page_size setting for this run
+    page_size: Option<usize>,
+    // fixed prices to use instead of Yahoo Finance
+    quotes_file: Option<PathBuf>,
+    // show text columns in full whatever the column-width setting
+    full_width: bool,
+    // print the markdown report of this portfolio and exit, without prompting
+    report: Option<PathBuf>,
+    // the portfolio to open on start, over PORTFOLIO_TRACKER_FILE and the
+    // default-file setting
+    file: Option<PathBuf>,
+}
+
+impl Args {
+    // settings given on the command line win over the config file, including
+    // one that's imported later
+    fn override_config(&self, config: &mut Config) {
+        if let Some(page_size) = self.page_size {
+            config.page_size = page_size;
+        }
+        if self.full_width {
+            config.max_column_width = 0;
+        }
+    }
+}
+
+// exits on anything it doesn't know, since a typo'd --dry-run would
+// otherwise write files the user expected to be left alone
+fn parse_args() -> Args {
+    let mut parsed = Args::default();
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--dry-run" => parsed.dry_run = true,
+            "--page-size" => match args.next().and_then(|n| n.parse().ok()) {
+                Some(n) => parsed.page_size = Some(n),
+                None => {
+                    eprintln!("--page-size needs a number of rows. {}", USAGE);
+                    std::process::exit(2);
+                }
+            },
+            "--full-width" => parsed.full_width = true,
+            "--quotes-file" => match args.next() {
+                Some(path) => parsed.quotes_file = Some(PathBuf::from(path)),
+                None => {
+                    eprintln!("--quotes-file needs a JSON or TOML file. {}", USAGE);
+                    std::process::exit(2);
+                }
+            },
+            "--file" => match args.next() {
+                Some(path) => parsed.file = Some(PathBuf::from(path)),
+                None => {
+                    eprintln!("--file needs a portfolio file. {}", USAGE);
+                    std::process::exit(2);
+                }
+            },
+            "--report" => match args.next() {
+                Some(path) => parsed.report = Some(PathBuf::from(path)),
+                None => {
+                    eprintln!("--report needs a portfolio file. {}", USAGE);
+                    std::process::exit(2);
+                }
+            },
+            "--help" | "-h" => {
+                println!("{}", USAGE);
+                println!("  --dry-run          run commands without writing any files");
+                println!("  --page-size N      print assets N rows at a time (0 for all at once)");
+                println!("  --full-width       never cut text columns short");
+                println!("  --quotes-file FILE take prices from a JSON or TOML file instead of Yahoo Finance");
+                println!("  --file FILE        open FILE on start, over PORTFOLIO_TRACKER_FILE and the default-file setting");
+                println!("  --report FILE      print a markdown report of FILE and exit, e.g. > review.md");
+                println!("  --version          print version and build information");
+                std::process::exit(0);
+            }
+            "--version" | "-V" => {
+                println!("{}", version_text());
+                std::process::exit(0);
+            }
+            _ => {
+                eprintln!("Unknown argument '{}'. {}", arg, USAGE);
+                std::process::exit(2);
+            }
+        }
+    }
+    parsed
+}
+
+// loads `path` as the session's portfolio, locking it when it's JSON so
+// autosave can write it back. returns whether it loaded
+async fn open_portfolio(
+    session: &mut Session,
+    interrupts: &Interrupts,
+    path: PathBuf,
+    format: Option<FileFormat>,
+) -> bool {
+    let loaded = load_portfolio_detecting(
+        &path,
+        format,
+        session.config.date_order,
+        Local::now().date_naive(),
+    )
+    .and_then(|loaded| {
+        // only JSON files are written back, so only they are locked
+        let writable = if loaded.format == FileFormat::Json {
+            lock_portfolio(&mut session.portfolio_lock, &path, session.dry_run)?
+        } else {
+            session.portfolio_lock = None;
+            false
+        };
+        Ok((loaded, writable))
+    });
+    match loaded {
+        Err(PortfolioError::Cancelled) => {
+            println!("Portfolio not loaded.");
+            false
+        }
+        Err(e) => {
+            println!(
+                "An error occurred when loading portfolio ({}). Portfolio not loaded.",
+                e
+            );
+            false
+        }
+        Ok((loaded, writable)) => {
+            for reason in &loaded.skipped {
+                println!("Skipped {}", reason);
+            }
+            let format = loaded.format;
+            session.portfolio = loaded.portfolio;
+            session.portfolio_replaced = true;
+            if !session.dry_run {
+                session.recent.record(&path);
+                save_recent_files(&session.recent);
+            }
+            if session.portfolio.price_basis < PRICE_BASIS_PER_SHARE {
+                warn_about_total_prices(&session.portfolio);
+                session.portfolio.price_basis = PRICE_BASIS_PER_SHARE;
+            }
+            session.current_file = writable.then(|| path.clone());
+            if format != FileFormat::Json && session.config.autosave {
+                // autosaving over a TOML or CSV file would change its format
+                println!("Autosave only writes JSON. Use 'dump' to pick a file for it.");
+            } else if format == FileFormat::Json && !writable {
+                println!(
+                    "Opened read-only. Changes won't be saved to {} unless you dump them to another file.",
+                    path.display()
+                );
+            }
+            if session.config.refresh_on_load {
+                refresh_portfolio(session, interrupts, true).await;
+                autosave(session);
+            }
+            session.analytics = None;
+            print_loaded(
+                &session.portfolio,
+                current_analytics(
+                    &mut session.analytics,
+                    &session.portfolio,
+                    &session.metadata,
+                ),
+                &path,
+                format.name(),
+                &session.config,
+            );
+            print_duplicate_warnings(&session.portfolio.assets);
+            true
+        }
+    }
+}
+
+// `load [--format FORMAT] [filename]`
+fn parse_load_args<'a>(
+    args: &[&'a str],
+) -> Result<(Option<FileFormat>, Option<&'a str>), PortfolioError> {
+    match args {
+        ["--format", name, rest @ ..] => {
+            let format = FileFormat::from_name(name).ok_or_else(|| {
+                PortfolioError::InvalidInput(format!(
+                    "unknown format '{}' (expected one of {})",
+                    name, FILE_FORMAT_NAMES
+                ))
+            })?;
+            Ok((Some(format), rest.first().copied()))
+        }
+        ["--format"] => Err(PortfolioError::InvalidInput(format!(
+            "--format needs one of {}",
+            FILE_FORMAT_NAMES
+        ))),
+        _ => Ok((None, args.first().copied())),
+    }
+}
+
+// `sort <key> [asc|desc]`
+fn parse_sort_args(args: &[&str]) -> Result<(AssetOrdering, bool), PortfolioError> {
+    let names: Vec<&str> = SORT_KEYS.iter().map(|(name, _, _)| *name).collect();
+    let usage =
+        || PortfolioError::InvalidInput(format!("usage: sort <{}> [asc|desc]", names.join("|")));
+    let (key, direction) = match args {
+        [key] => (key, None),
+        [key, direction] => (key, Some(direction.to_lowercase())),
+        _ => return Err(usage()),
+    };
+    let (_, compare, largest_first) = SORT_KEYS
+        .iter()
+        .find(|(name, _, _)| name.eq_ignore_ascii_case(key))
+        .ok_or_else(usage)?;
+    let descending = match direction.as_deref() {
+        None => *largest_first,
+        Some("asc") => false,
+        Some("desc") => true,
+        Some(_) => return Err(usage()),
+    };
+    Ok((*compare, descending))
+}
+
+// `demo [seed] [--refresh]`
+fn parse_demo_args(args: &[&str]) -> Result<(Option<u64>, bool), PortfolioError> {
+    let refresh = args.contains(&"--refresh");
+    let rest: Vec<&str> = args
+        .iter()
+        .copied()
+        .filter(|arg| *arg != "--refresh")
+        .collect();
+    match rest.as_slice() {
+        [] => Ok((None, refresh)),
+        [seed] => match seed.parse() {
+            Ok(seed) => Ok((Some(seed), refresh)),
+            Err(_) => Err(PortfolioError::InvalidInput(format!(
+                "'{}' isn't a seed, which is a whole number",
+                seed
+            ))),
+        },
+        _ => Err(PortfolioError::InvalidInput(
+            "usage: demo [seed] [--refresh]".to_string(),
+        )),
+    }
+}
+
+// a seed for `demo` when none is given, different each time
+fn random_seed() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_nanos() as u64)
+}
+
+// commands that change neither the portfolio nor the metadata cache, so the
+// analytics worked out for one are still right for the next
+const READ_ONLY_COMMANDS: &[&str] = &[
+    "",
+    "assets",
+    "summary",
+    "copy-summary",
+    "copy-assets",
+    "copy-value",
+    "qr-code",
+    "last",
+    "sort",
+    "stats",
+    "snapshot-diff",
+    "report",
+    "health",
+    "lint",
+    "portfolio-size",
+    "cost-basis-report",
+    "cost",
+    "age",
+    "find-gaps",
+    "range",
+    "price-history",
+    "total-return-with-dividends",
+    "news",
+    "export",
+    "export-all",
+    "help",
+    "version",
+];
+
+fn current_analytics<'a>(
+    cache: &'a mut Option<PortfolioAnalytics>,
+    portfolio: &Portfolio,
+    metadata: &MetadataCache,
+) -> &'a PortfolioAnalytics {
+    cache.get_or_insert_with(|| {
+        PortfolioAnalytics::compute(portfolio, metadata, Local::now().date_naive())
+    })
+}
+
+// what the prompt keeps from one command to the next
+struct Session {
+    portfolio: Portfolio,
+    config: Config,
+    quotes: QuoteProvider,
+    // for what only Yahoo has, like sectors and news, even with a quotes file
+    yahoo_client: YahooClient,
+    metadata: MetadataCache,
+    quote_cache: QuoteCache,
+    recent: RecentFiles,
+    // where autosave writes to: the file last loaded or dumped
+    current_file: Option<PathBuf>,
+    // held while current_file is ours to write
+    portfolio_lock: Option<PortfolioLock>,
+    // worked out when first needed, then kept until a command that isn't
+    // in READ_ONLY_COMMANDS runs
+    analytics: Option<PortfolioAnalytics>,
+    clipboard: Option<Clipboard>,
+    dry_run: bool,
+    // the command line: --file, so the default portfolio file is worked
+    // out again after the default-file setting changes, and the settings
+    // that win over an imported config
+    cli: Args,
+    interrupts: Interrupts,
+    // the batch file being run, if any
+    batch: Option<Batch>,
+    // the command being run as it was entered, for `goal`, which splits it
+    // keeping "quoted names" together
+    line: String,
+    // set once a portfolio is loaded, imported or made by demo, after which a
+    // bare dump no longer falls back to the default file
+    portfolio_replaced: bool,
+}
+
+impl Session {
+    // with a quotes file nothing is fetched, so sectors, earnings and
+    // fundamentals only come from the metadata cache
+    fn offline(&self) -> bool {
+        self.quotes.is_fixture()
+    }
+
+    // the file opened on start and dumped to without a filename, and where
+    // it was set
+    fn default_file(&self) -> Option<(PathBuf, &'static str)> {
+        default_portfolio_file(
+            self.cli.file.as_deref(),
+            |name| env::var(name).ok(),
+            self.config.default_file.as_deref(),
+        )
+    }
+}
+
+// why a command failed
+enum CommandError {
+    // for the registry to print, as "Nothing changed: ..." for an edit.
+    // Cancelled only means nothing was done, so it isn't a failure
+    Error(PortfolioError),
+    // the handler has already printed what went wrong
+    Reported,
+}
+
+impl From<PortfolioError> for CommandError {
+    fn from(e: PortfolioError) -> Self {
+        CommandError::Error(e)
+    }
+}
+
+type CommandResult = Result<(), CommandError>;
+
+// prints why a command failed, for messages that don't fit an error
+fn fail<T>(message: impl fmt::Display) -> Result<T, CommandError> {
+    println!("{}", message);
+    Err(CommandError::Reported)
+}
+
+// for the helpers that print their own errors and return whether they worked
+fn reported_unless(succeeded: bool) -> CommandResult {
+    if succeeded {
+        Ok(())
+    } else {
+        Err(CommandError::Reported)
+    }
+}
+
+// what a registered command does
+type CommandHandler = fn(&mut Session, &[&str]) -> CommandResult;
+
+// what an edit did: Ok(true) when the portfolio changed and wants saving,
+// Ok(false) when there turned out to be nothing to do
+type EditResult = Result<bool, CommandError>;
+type EditHandler = fn(&mut Session, &[&str]) -> EditResult;
+
+// the same for commands that fetch prices or wait, as boxed futures since
+// an async fn can't be a fn pointer
+type CommandFuture<'a, T> = Pin<Box<dyn Future<Output = T> + 'a>>;
+type AsyncCommandHandler =
+    for<'a> fn(&'a mut Session, &'a [&'a str]) -> CommandFuture<'a, CommandResult>;
+type AsyncEditHandler = for<'a> fn(&'a mut Session, &'a [&'a str]) -> CommandFuture<'a, EditResult>;
+
+// edits are autosaved by the registry when they change something, and an
+// error from one is reported as nothing having changed
+enum Handler {
+    Sync(CommandHandler),
+    Async(AsyncCommandHandler),
+    Edit(EditHandler),
+    AsyncEdit(AsyncEditHandler),
+}
+
+// every command but 'exit', which ends the loop in `run`, runs through its
+// handler here. each also needs its COMMANDS entry, which help, completion
+// and hints are built from
+struct CommandRegistry {
+    commands: HashMap<&'static str, Handler>,
+}
+
+impl CommandRegistry {
+    fn new() -> CommandRegistry {
+        CommandRegistry {
+            commands: HashMap::new(),
+        }
+    }
+
+    fn insert(&mut self, name: &'static str, handler: Handler) {
+        assert!(
+            COMMANDS.iter().any(|info| info.name == name),
+            "{} is registered without a COMMANDS entry",
+            name
+        );
+        self.commands.insert(name, handler);
+    }
+
+    fn register(&mut self, name: &'static str, handler: CommandHandler) {
+        self.insert(name, Handler::Sync(handler));
+    }
+
+    fn register_async(&mut self, name: &'static str, handler: AsyncCommandHandler) {
+        self.insert(name, Handler::Async(handler));
+    }
+
+    fn register_edit(&mut self, name: &'static str, handler: EditHandler) {
+        self.insert(name, Handler::Edit(handler));
+    }
+
+    fn register_async_edit(&mut self, name: &'static str, handler: AsyncEditHandler) {
+        self.insert(name, Handler::AsyncEdit(handler));
+    }
+
+    // returns whether the command failed
+    async fn run(&self, name: &str, session: &mut Session, args: &[&str]) -> bool {
+        let Some(handler) = self.commands.get(name) else {
+            println!("Unknown command. Enter 'help' for a list of valid commands");
+            return true;
+        };
+        let (result, edits) = match handler {
+            Handler::Sync(handler) => (handler(session, args).map(|()| false), false),
+            Handler::Async(handler) => (handler(session, args).await.map(|()| false), false),
+            Handler::Edit(handler) => (handler(session, args), true),
+            Handler::AsyncEdit(handler) => (handler(session, args).await, true),
+        };
+        match result {
+            Ok(changed) => {
+                if changed {
+                    autosave(session);
+                }
+                false
+            }
+            Err(CommandError::Reported) => true,
+            Err(CommandError::Error(PortfolioError::Cancelled)) => {
+                println!("Nothing changed.");
+                false
+            }
+            Err(CommandError::Error(e)) if edits => {
+                println!("Nothing changed: {}", e);
+                true
+            }
+            Err(CommandError::Error(e)) => {
+                println!("{}", e);
+                true
+            }
+        }
+    }
+}
+
+// what `new` or `buy` made, added to the portfolio
+fn add_bought_asset(session: &mut Session, asset: Result<Asset, PortfolioError>) -> EditResult {
+    match asset {
+        Ok(x) => {
+            println!("Added {}", x);
+            session.portfolio.assets.push(x);
+            save_metadata(&session.metadata);
+            Ok(true)
+        }
+        Err(PortfolioError::Cancelled) => {
+            println!("Asset not added.");
+            Ok(false)
+        }
+        Err(PortfolioError::InvalidInput(message)) => fail(format!("Asset not added: {}", message)),
+        Err(PortfolioError::NotFound(_)) => {
+            fail("An error occurred when fetching stock price. Ensure ticker is correct.")
+        }
+        Err(e) => fail(format!("Asset not added: {}", e)),
+    }
+}
+
+fn command_registry() -> CommandRegistry {
+    let mut registry = CommandRegistry::new();
+    registry.register("help", |_, _| {
+        print_help();
+        Ok(())
+    });
+    registry.register("version", |_, _| {
+        println!("{}", version_text());
+        Ok(())
+    });
+    registry.register("assets", |session, _| {
+        print_assets(&session.portfolio.assets, session.config.page_size);
+        print_stale_warning(&session.portfolio.assets, &session.config);
+        print_earnings_warnings(
+            &session.portfolio.assets,
+            &session.metadata,
+            &session.config,
+            Local::now().date_naive(),
+        );
+        Ok(())
+    });
+    registry.register("last", |session, args| {
+        let count = match args.first() {
+            None => 5,
+            Some(count) => count.parse().map_err(|_| {
+                PortfolioError::InvalidInput(format!("'{}' isn't a number of assets", count))
+            })?,
+        };
+        let indexes = recently_added(&session.portfolio.assets, count);
+        if indexes.is_empty() {
+            println!("No assets have been added yet.");
+            return Ok(());
+        }
+        let assets: Vec<Asset> = indexes
+            .iter()
+            .map(|&index| session.portfolio.assets[index].clone())
+            .collect();
+        print_assets(&assets, session.config.page_size);
+        Ok(())
+    });
+    registry.register("sort", |session, args| {
+        let (compare, descending) = parse_sort_args(args)?;
+        print_assets(
+            &sorted_assets(&session.portfolio.assets, compare, descending),
+            session.config.page_size,
+        );
+        Ok(())
+    });
+    registry.register("cost", |session, _| {
+        print_cost_summary(&session.portfolio.assets);
+        Ok(())
+    });
+    registry.register("find-gaps", |session, args| {
+        let usage = || PortfolioError::InvalidInput("usage: find-gaps [days]".to_string());
+        let days = match args {
+            [] => session.config.gap_days,
+            [days] => days.parse().map_err(|_| usage())?,
+            _ => return Err(usage().into()),
+        };
+        print_activity_gaps(&session.portfolio.assets, days, Local::now().date_naive());
+        Ok(())
+    });
+    registry.register("age", |session, _| {
+        print_holding_ages(
+            &session.portfolio.assets,
+            Local::now().date_naive(),
+            session.config.long_term_soon_days,
+        );
+        Ok(())
+    });
+    registry.register("lint", |session, _| {
+        print_lint(&session.portfolio.assets);
+        Ok(())
+    });
+    registry.register("portfolio-size", |session, _| {
+        print_portfolio_size(&session.portfolio);
+        Ok(())
+    });
+    registry.register("range", |session, _| {
+        print_ranges(&session.portfolio.assets, &session.metadata);
+        Ok(())
+    });
+    registry.register_edit("stops", |session, args| {
+        let ticker = match args {
+            [] => {
+                print_stops(&session.portfolio.assets);
+                return Ok(false);
+            }
+            ["ack"] => None,
+            ["ack", ticker] => Some(*ticker),
+            _ => return fail("usage: stops [ack [ticker]]"),
+        };
+        match session.portfolio.acknowledge_stop_breaches(ticker) {
+            0 => {
+                println!("No breached stop-losses to acknowledge.");
+                Ok(false)
+            }
+            acknowledged => {
+                println!(
+                    "Acknowledged {}. Remove a stop-loss with 'stop'.",
+                    describe_count(acknowledged, "breached stop")
+                );
+                Ok(true)
+            }
+        }
+    });
+    registry.register_edit("convert-totals", |session, _| {
+        Ok(convert_totals(&mut session.portfolio))
+    });
+    registry.register_edit("sell-all", |session, _| {
+        Ok(sell_all(&mut session.portfolio, &session.config))
+    });
+    registry.register_edit("sell", |session, args| {
+        let sale = sell_asset(&mut session.portfolio, args, session.config.date_order)?;
+        println!("{}", sale);
+        Ok(true)
+    });
+    registry.register_edit("edit", |session, args| {
+        edit_asset(&mut session.portfolio, args, session.config.date_order)?;
+        Ok(true)
+    });
+    registry.register_edit("remove", |session, args| {
+        remove_asset(&mut session.portfolio, args)?;
+        Ok(true)
+    });
+    registry.register_edit("note", |session, args| {
+        note_asset(&mut session.portfolio, args)?;
+        Ok(true)
+    });
+    registry.register_edit("target", |session, args| {
+        set_asset_target(&mut session.portfolio, args)?;
+        Ok(true)
+    });
+    registry.register_edit("stop", |session, args| {
+        set_asset_stop(&mut session.portfolio, args)?;
+        Ok(true)
+    });
+    registry.register_edit("account", |session, args| {
+        set_asset_account(&mut session.portfolio, args)?;
+        Ok(true)
+    });
+    registry.register_edit("tag", |session, args| {
+        tag_asset(&mut session.portfolio, args)?;
+        Ok(true)
+    });
+    registry.register("summary", |session, args| {
+        if args.first() == Some(&"accounts") {
+            print_account_summary(&account_summaries(&session.portfolio.assets));
+            return Ok(());
+        }
+        print_summary(
+            &session.portfolio,
+            current_analytics(
+                &mut session.analytics,
+                &session.portfolio,
+                &session.metadata,
+            ),
+        );
+        print_stale_warning(&session.portfolio.assets, &session.config);
+        print_earnings_warnings(
+            &session.portfolio.assets,
+            &session.metadata,
+            &session.config,
+            Local::now().date_naive(),
+        );
+        Ok(())
+    });
+    registry.register("copy-summary", |session, _| {
+        let text = summary_copy_text(
+            &session.portfolio,
+            current_analytics(
+                &mut session.analytics,
+                &session.portfolio,
+                &session.metadata,
+            ),
+        );
+        copy_to_clipboard(&mut session.clipboard, "the summary", &text);
+        Ok(())
+    });
+    registry.register("copy-assets", |session, _| {
+        copy_to_clipboard(
+            &mut session.clipboard,
+            "the assets table",
+            &assets_copy_text(&session.portfolio.assets),
+        );
+        Ok(())
+    });
+    registry.register("copy-value", |session, _| {
+        let value = format_money(
+            current_analytics(
+                &mut session.analytics,
+                &session.portfolio,
+                &session.metadata,
+            )
+            .total_value,
+        );
+        copy_to_clipboard(
+            &mut session.clipboard,
+            &format!("the value, {},", value),
+            &value,
+        );
+        Ok(())
+    });
+    registry.register("qr-code", |session, args| match args {
+        [] | ["json"] => {
+            let computed = current_analytics(
+                &mut session.analytics,
+                &session.portfolio,
+                &session.metadata,
+            );
+            print_portfolio_qr_code(&session.portfolio, computed, !args.is_empty())?;
+            Ok(())
+        }
+        _ => fail("usage: qr-code [json]"),
+    });
+    registry.register_async("earnings", |session, _| {
+        Box::pin(async move {
+            let today = Local::now().date_naive();
+            if !session.offline() {
+                fetch_stale_earnings(
+                    &session.yahoo_client,
+                    &mut session.metadata,
+                    &session.portfolio.assets,
+                    today,
+                )
+                .await;
+            }
+            print_earnings(&session.portfolio.assets, &session.metadata, today);
+            Ok(())
+        })
+    });
+    registry.register("cost-basis-report", |session, args| {
+        let method = match args.first() {
+            None => session.config.cost_basis_method,
+            Some(name) => match CostBasisMethod::from_name(name) {
+                Some(method) => method,
+                None => {
+                    return fail(format!(
+                        "Unknown cost basis method '{}' (expected one of {}).",
+                        name, COST_BASIS_METHOD_NAMES
+                    ))
+                }
+            },
+        };
+        print_cost_basis_report(&session.portfolio.assets, method);
+        Ok(())
+    });
+    registry.register_async("dca", |session, args| {
+        Box::pin(async move {
+            preview_dca(
+                &session.quotes,
+                &mut session.metadata,
+                &session.portfolio,
+                args,
+            )
+            .await
+            .or_else(fail)
+        })
+    });
+    registry.register("stats", |session, _| {
+        print_stats(
+            &session.portfolio,
+            current_analytics(
+                &mut session.analytics,
+                &session.portfolio,
+                &session.metadata,
+            ),
+            session.config.price_history_points,
+        );
+        Ok(())
+    });
+    registry.register("report", |session, _| {
+        print!(
+            "{}",
+            markdown_report(
+                &session.portfolio,
+                current_analytics(
+                    &mut session.analytics,
+                    &session.portfolio,
+                    &session.metadata
+                ),
+                &session.metadata,
+                session.config.stale_hours,
+                Utc::now(),
+            )
+        );
+        Ok(())
+    });
+    registry.register("health", |session, _| {
+        print_health(&health_score(current_analytics(
+            &mut session.analytics,
+            &session.portfolio,
+            &session.metadata,
+        )));
+        Ok(())
+    });
+    registry.register_async_edit("import", |session, args| {
+        Box::pin(async move {
+            match run_import(
+                &session.quotes,
+                &mut session.metadata,
+                &mut session.portfolio,
+                args,
+                session.config.date_order,
+            )
+            .await
+            {
+                Ok(imported) => Ok(imported > 0),
+                Err(PortfolioError::Cancelled) => {
+                    println!("Nothing imported.");
+                    Ok(false)
+                }
+                Err(e) => fail(format!("Nothing imported: {}", e)),
+            }
+        })
+    });
+    registry.register("export", |session, args| {
+        match run_export(&session.portfolio, args, session.dry_run) {
+            Ok(()) => Ok(()),
+            Err(e @ PortfolioError::Cancelled) => {
+                println!("Nothing exported: {}", e);
+                Ok(())
+            }
+            Err(e) => fail(format!("Nothing exported: {}", e)),
+        }
+    });
+    registry.register_edit("cleanup", |session, args| {
+        cleanup_sold_assets(
+            &mut session.portfolio,
+            args,
+            session.current_file.as_deref(),
+            session.dry_run,
+        )
+        .or_else(|e| fail(format!("Nothing removed: {}", e)))
+    });
+    registry.register_async("fundamentals", |session, _| {
+        Box::pin(async move {
+            if !session.offline() {
+                fetch_stale_fundamentals(
+                    &session.yahoo_client,
+                    &mut session.metadata,
+                    &session.portfolio.assets,
+                    Local::now().date_naive(),
+                )
+                .await;
+            }
+            print_fundamentals(&session.portfolio.assets, &session.metadata);
+            Ok(())
+        })
+    });
+    // from the same cached summary as fundamentals
+    registry.register_async("yield", |session, _| {
+        Box::pin(async move {
+            if !session.offline() {
+                fetch_stale_fundamentals(
+                    &session.yahoo_client,
+                    &mut session.metadata,
+                    &session.portfolio.assets,
+                    Local::now().date_naive(),
+                )
+                .await;
+            }
+            print_yields(&session.portfolio.assets, &session.metadata);
+            Ok(())
+        })
+    });
+    registry.register_async("news", |session, args| {
+        Box::pin(async move {
+            if session.offline() {
+                return fail("News needs Yahoo Finance, which isn't used with --quotes-file.");
+            }
+            match args.first() {
+                Some(ticker) => print_news(&session.yahoo_client, ticker).await,
+                None => print_held_news(&session.yahoo_client, &session.portfolio.assets).await,
+            }
+            Ok(())
+        })
+    });
+    registry.register_async("price-history", |session, args| {
+        Box::pin(async move {
+            print_price_history(&session.quotes, args).await;
+            Ok(())
+        })
+    });
+    registry.register_async("total-return-with-dividends", |session, args| {
+        Box::pin(async move {
+            print_total_return_with_dividends(&session.quotes, args).await;
+            Ok(())
+        })
+    });
+    registry.register_async("sectors", |session, _| {
+        Box::pin(async move {
+            if !session.offline() {
+                fetch_missing_sectors(
+                    &session.yahoo_client,
+                    &mut session.metadata,
+                    &session.portfolio.assets,
+                )
+                .await;
+            }
+            print_sectors(&session.portfolio.assets, &session.metadata);
+            Ok(())
+        })
+    });
+    registry.register_async("chart-sectors", |session, _| {
+        Box::pin(async move {
+            if !session.offline() {
+                fetch_missing_sectors(
+                    &session.yahoo_client,
+                    &mut session.metadata,
+                    &session.portfolio.assets,
+                )
+                .await;
+            }
+            print_sector_chart(&session.portfolio.assets, &session.metadata);
+            Ok(())
+        })
+    });
+    registry.register_async_edit("new", |session, _| {
+        Box::pin(async move {
+            let asset = add_asset(
+                &session.quotes,
+                &mut session.metadata,
+                session.config.date_order,
+            )
+            .await;
+            add_bought_asset(session, asset)
+        })
+    });
+    registry.register_async_edit("buy", |session, args| {
+        Box::pin(async move {
+            let [ticker, quantity] = args else {
+                return fail("usage: buy <ticker> <quantity>");
+            };
+            let asset =
+                buy_at_market(&session.quotes, &mut session.metadata, ticker, quantity).await;
+            add_bought_asset(session, asset)
+        })
+    });
+    registry.register("export-all", |session, args| {
+        let path = filename_or_prompt(args.first().copied(), "Enter zip filename: ");
+        export_all(
+            &session.portfolio,
+            &session.config,
+            &session.metadata,
+            &path,
+            session.dry_run,
+        )
+        .or_else(|e| fail(format!("Nothing exported: {}", e)))
+    });
+    registry.register_async("demo", |session, args| {
+        Box::pin(async move {
+            let replace = |portfolio: &Portfolio| {
+                portfolio.assets.is_empty()
+                    || confirm(&format!(
+                        "Replace the {} in portfolio \"{}\" with demo data? [y/N] ",
+                        describe_count(portfolio.assets.len(), "asset"),
+                        portfolio.name
+                    ))
+                    .unwrap_or(false)
+            };
+            let (seed, refresh) = match parse_demo_args(args) {
+                Err(e) => return fail(format!("No demo created: {}", e)),
+                Ok(_) if !replace(&session.portfolio) => {
+                    println!("Nothing changed.");
+                    return Ok(());
+                }
+                Ok(parsed) => parsed,
+            };
+            let seed = seed.unwrap_or_else(random_seed);
+            session.portfolio = demo_portfolio(seed, Local::now().date_naive(), Utc::now());
+            // never autosave made-up lots over a real portfolio
+            session.current_file = None;
+            session.portfolio_replaced = true;
+            session.portfolio_lock = None;
+            println!(
+                "Created a demo portfolio of {}. Enter 'demo {}' to get the same one again.",
+                describe_count(session.portfolio.assets.len(), "asset"),
+                seed
+            );
+            let failures = if refresh {
+                let interrupts = session.interrupts.clone();
+                refresh_portfolio(session, &interrupts, false).await
+            } else {
+                0
+            };
+            session.analytics = None;
+            print_summary(
+                &session.portfolio,
+                current_analytics(
+                    &mut session.analytics,
+                    &session.portfolio,
+                    &session.metadata,
+                ),
+            );
+            reported_unless(failures == 0)
+        })
+    });
+    registry.register("import-all", |session, args| {
+        let path = filename_or_prompt(args.first().copied(), "Enter zip filename: ");
+        let bundle = match fs::read(&path)
+            .map_err(PortfolioError::from)
+            .and_then(|bytes| read_bundle(&bytes))
+        {
+            Err(e) => return fail(format!("Nothing imported: {}", e)),
+            Ok(bundle) => bundle,
+        };
+        session.portfolio = bundle.portfolio;
+        if session.portfolio.price_basis < PRICE_BASIS_PER_SHARE {
+            warn_about_total_prices(&session.portfolio);
+            session.portfolio.price_basis = PRICE_BASIS_PER_SHARE;
+        }
+        // the portfolio came out of an archive, so there's no file to
+        // autosave to until it's dumped
+        session.current_file = None;
+        session.portfolio_replaced = true;
+        session.portfolio_lock = None;
+        if let Some(imported) = bundle.config {
+            session.config = imported;
+            session.cli.override_config(&mut session.config);
+            session.config.apply();
+            save_config(&session.config, session.dry_run);
+        }
+        if let Some(imported) = bundle.metadata {
+            session.metadata = imported;
+            if !session.dry_run {
+                save_metadata(&session.metadata);
+            }
+        }
+        session.analytics = None;
+        print_loaded(
+            &session.portfolio,
+            current_analytics(
+                &mut session.analytics,
+                &session.portfolio,
+                &session.metadata,
+            ),
+            &path,
+            "export-all zip",
+            &session.config,
+        );
+        print_duplicate_warnings(&session.portfolio.assets);
+        Ok(())
+    });
+    registry.register_async("load", |session, args| {
+        Box::pin(async move {
+            let (format, filename) = match parse_load_args(args) {
+                Err(e) => {
+                    return fail(format!(
+                        "An error occurred when loading portfolio ({}). Portfolio not loaded.",
+                        e
+                    ))
+                }
+                Ok(parsed) => parsed,
+            };
+            let path = load_filename(filename, &mut session.recent);
+            let interrupts = session.interrupts.clone();
+            reported_unless(open_portfolio(session, &interrupts, path, format).await)
+        })
+    });
+    registry.register("dump", |session, args| {
+        // without a filename: the open file, or the default file while
+        // nothing else has been opened
+        let open_file = session.current_file.clone().or_else(|| {
+            (!session.portfolio_replaced)
+                .then(|| session.default_file().map(|(path, _)| path))
+                .flatten()
+        });
+        let path = match (args.first(), open_file) {
+            (None, Some(path)) => path,
+            (filename, _) => {
+                filename_or_prompt(filename.copied(), "Enter filename to dump assets to: ")
+            }
+        };
+        let held = if session.dry_run {
+            None
+        } else {
+            lock::held_by(&path)
+        };
+        if let Some(pid) = held {
+            return fail(format!(
+                "Another instance (pid {}) has {} open. Portfolio not dumped.",
+                pid,
+                path.display()
+            ));
+        }
+        if !dump_portfolio(&session.portfolio, &path, session.dry_run) {
+            return Err(CommandError::Reported);
+        }
+        if !session.dry_run {
+            lock_dumped_portfolio(&mut session.portfolio_lock, &path);
+            session.recent.record(&path);
+            save_recent_files(&session.recent);
+        }
+        session.current_file = Some(path);
+        Ok(())
+    });
+    registry.register("set", |session, args| {
+        reported_unless(run_set(&mut session.config, args, session.dry_run))
+    });
+    registry.register("config", |session, args| match args {
+        ["save"] => reported_unless(save_config(&session.config, session.dry_run)),
+        ["show"] => {
+            print_settings(&session.config);
+            print_connection(&session.config);
+            print_portfolio_file(session.default_file());
+            Ok(())
+        }
+        _ => fail("Usage: config save|show"),
+    });
+    registry.register("batch", |session, args| {
+        if session.batch.is_some() {
+            return fail("A batch file can't start another batch.");
+        }
+        match start_batch(args) {
+            Ok(started) => {
+                session.batch = Some(started);
+                Ok(())
+            }
+            Err(e) => fail(format!("Error when starting batch ({}).", e)),
+        }
+    });
+    registry.register_async_edit("refresh", |session, args| {
+        Box::pin(async move {
+            match args.first() {
+                Some(ticker) => match refresh_ticker(session, ticker).await {
+                    Ok(()) => Ok(true),
+                    Err(e) => fail(format!(
+                        "Error when refreshing {}: {}",
+                        ticker.to_uppercase(),
+                        e
+                    )),
+                },
+                None => {
+                    let interrupts = session.interrupts.clone();
+                    if refresh_portfolio(session, &interrupts, false).await > 0 {
+                        // the prices that did update are kept all the same
+                        autosave(session);
+                        return Err(CommandError::Reported);
+                    }
+                    Ok(true)
+                }
+            }
+        })
+    });
+    registry.register_edit("merge", |session, args| {
+        let [filename] = args else {
+            return fail("usage: merge <filename>");
+        };
+        let loaded = match load_portfolio_detecting(
+            Path::new(filename),
+            None,
+            session.config.date_order,
+            Local::now().date_naive(),
+        ) {
+            Ok(loaded) => loaded,
+            Err(e) => {
+                return fail(format!(
+                    "An error occurred when reading {} ({}). Nothing was merged.",
+                    filename, e
+                ))
+            }
+        };
+        for reason in &loaded.skipped {
+            println!("Skipped {}", reason);
+        }
+        if loaded.portfolio.price_basis < PRICE_BASIS_PER_SHARE {
+            warn_about_total_prices(&loaded.portfolio);
+        }
+        let report = merge_portfolios(
+            &mut session.portfolio,
+            loaded.portfolio,
+            session.config.merge_strategy,
+        );
+        println!(
+            "{}",
+            describe_merge(&report, filename, session.config.merge_strategy)
+        );
+        Ok(true)
+    });
+    registry.register_edit("goal", |session, _| {
+        let words = split_quoted(&session.line);
+        let words: Vec<&str> = words.iter().skip(1).map(String::as_str).collect();
+        let today = Local::now().date_naive();
+        match words.as_slice() {
+            [] | ["show"] => {
+                print_goals(
+                    &session.portfolio.goals,
+                    current_analytics(
+                        &mut session.analytics,
+                        &session.portfolio,
+                        &session.metadata,
+                    ),
+                    session.config.goal_return_percent,
+                    today,
+                );
+                Ok(false)
+            }
+            ["add", name, target, date] => {
+                match new_goal(name, target, date, session.config.date_order, today)
+                    .and_then(|goal| session.portfolio.add_goal(goal))
+                {
+                    Ok(()) => {
+                        println!("Added goal \"{}\". Enter 'goal' to see it.", name);
+                        Ok(true)
+                    }
+                    Err(e) => fail(format!("No goal added: {}", e)),
+                }
+            }
+            ["remove", name] => match session.portfolio.remove_goal(name) {
+                Ok(goal) => {
+                    println!("Removed goal \"{}\".", goal.name);
+                    Ok(true)
+                }
+                Err(e) => fail(format!("Nothing removed: {}", e)),
+            },
+            _ => fail(
+                "usage: goal [add <name> <target> <date> | remove <name>], e.g. goal add \"Beach house\" 250000 2030-06-01",
+            ),
+        }
+    });
+    registry.register("snapshot-diff", |session, args| match args {
+        [] => {
+            print_snapshots(&session.portfolio);
+            Ok(())
+        }
+        [from, to] => {
+            let today = Local::now().date_naive();
+            let diff = parse_date(from, session.config.date_order, today)
+                .and_then(|from| Ok((from, parse_date(to, session.config.date_order, today)?)))
+                .map_err(PortfolioError::InvalidInput)
+                .and_then(|(from, to)| snapshot_diff(&session.portfolio, from, to));
+            match diff {
+                Ok(diff) => {
+                    print_snapshot_diff(&diff);
+                    Ok(())
+                }
+                Err(e) => fail(format!(
+                    "{}. Enter 'snapshot-diff' to list the recorded days.",
+                    e
+                )),
+            }
+        }
+        _ => fail("usage: snapshot-diff [<date> <date>]"),
+    });
+    registry.register_async_edit("watch", |session, args| {
+        Box::pin(async move {
+            if !io::stdout().is_terminal() {
+                return fail(
+                    "watch redraws the screen, so it needs a terminal. 'live' prints updates anywhere.",
+                );
+            }
+            let seconds = match parse_watch_args(args) {
+                Err(e) => return fail(e),
+                Ok(seconds) => seconds,
+            };
+            let interrupts = session.interrupts.clone();
+            watch_mode(session, &interrupts, seconds).await;
+            Ok(true)
+        })
+    });
+    registry.register_async_edit("live", |session, args| {
+        Box::pin(async move {
+            let (seconds, refresh) = match parse_live_args(args) {
+                Err(e) => return fail(e),
+                Ok(parsed) => parsed,
+            };
+            let interrupts = session.interrupts.clone();
+            live_mode(session, &interrupts, seconds, refresh).await;
+            Ok(refresh)
+        })
+    });
+    registry
+}
+
+// commands from a batch file, run in place of prompting until none are left
+struct Batch {
+    path: String,
+    strict: bool,
+    lines: VecDeque<(usize, String)>,
+    // "line N: command" for each command that failed
+    failures: Vec<String>,
+}
+
+fn start_batch(args: &[&str]) -> Result<Batch, PortfolioError> {
+    let strict = args.contains(&"--strict");
+    let args: Vec<&str> = args
+        .iter()
+        .copied()
+        .filter(|arg| *arg != "--strict")
+        .collect();
+    let [path] = args.as_slice() else {
+        return Err(PortfolioError::InvalidInput(
+            "usage: batch <filename> [--strict]".to_string(),
+        ));
+    };
+    let raw = fs::read_to_string(path)?;
+    Ok(Batch {
+        path: path.to_string(),
+        strict,
+        lines: parse_batch(&raw).into(),
+        failures: vec![],
+    })
+}
+
+fn finish_batch(batch: Batch) {
+    if batch.failures.is_empty() {
+        println!("Batch {} finished.", batch.path);
+        return;
+    }
+    println!(
+        "Batch {} finished with {} failed command{}:",
+        batch.path,
+        batch.failures.len(),
+        if batch.failures.len() == 1 { "" } else { "s" }
+    );
+    for failure in &batch.failures {
+        println!("  {}", failure);
+    }
+}
+
+// --report: only the report goes to stdout, so it can be redirected to a
+// file, and anything else to stderr
+fn print_report_and_exit(path: &Path, cli: &Args) -> ! {
+    let mut config = Config::load().unwrap_or_else(|e| {
+        eprintln!("Error in config file ({}). Using default settings.", e);
+        Config::default()
+    });
+    cli.override_config(&mut config);
+    config.apply();
+    let metadata = MetadataCache::load();
+    let now = Utc::now();
+    let loaded = match load_portfolio_detecting(path, None, config.date_order, now.date_naive()) {
+        Ok(loaded) => loaded,
+        Err(e) => {
+            eprintln!("Couldn't load {} ({}).", path.display(), e);
+            std::process::exit(1);
+        }
+    };
+    for reason in &loaded.skipped {
+        eprintln!("Skipped {}", reason);
+    }
+    let portfolio = loaded.portfolio;
+    let analytics = PortfolioAnalytics::compute(&portfolio, &metadata, now.date_naive());
+    print!(
+        "{}",
+        markdown_report(&portfolio, &analytics, &metadata, config.stale_hours, now)
+    );
+    std::process::exit(0);
+}
+
+// parses the command line, then runs commands until 'exit' or the end of input
+pub async fn run() {
+    let cli = parse_args();
+    let dry_run = cli.dry_run;
+    // logging is off unless asked for, e.g. RUST_LOG=portfolio_tracker=debug
+    // to see every request, since failures are already reported to the user
+    tracing_subscriber::fmt()
+        .with_env_filter(
+            EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("off")),
+        )
+        .with_writer(io::stderr)
+        .init();
+    if let Some(path) = &cli.report {
+        print_report_and_exit(path, &cli);
+    }
+    if dry_run {
+        println!("[DRY RUN] No files will be written.");
+    }
+    let mut input: String;
+    let mut editor = Editor::<CommandHelper>::new().expect("failed to set up the prompt");
+    editor.set_helper(Some(CommandHelper::new(supports_hints())));
+    let mut config = Config::load().unwrap_or_else(|e| {
+        println!("Error in config file ({}). Using default settings.", e);
+        Config::default()
+    });
+    cli.override_config(&mut config);
+    config.apply();
+    let http = http_client(config.proxy.as_deref(), config.effective_user_agent());
+    let quotes = match &cli.quotes_file {
+        Some(path) => match QuoteFixture::load(path) {
+            Ok(fixture) => {
+                println!(
+                    "Using {} from {} instead of Yahoo Finance.",
+                    describe_count(fixture.len(), "fixed quote"),
+                    path.display()
+                );
+                QuoteProvider::Fixture(fixture)
+            }
+            Err(e) => {
+                eprintln!("Couldn't read quotes file {}: {}", path.display(), e);
+                std::process::exit(2);
+            }
+        },
+        None => QuoteProvider::Yahoo(YahooClient::new(http.clone(), config.effective_api_url())),
+    };
+    let yahoo_client = YahooClient::new(http, config.effective_api_url());
+    let recent = RecentFiles::load();
+    let mut session = Session {
+        portfolio: Portfolio::default(),
+        config,
+        quotes,
+        yahoo_client,
+        metadata: MetadataCache::load(),
+        quote_cache: QuoteCache::load(),
+        recent,
+        current_file: None,
+        portfolio_lock: None,
+        analytics: None,
+        clipboard: None,
+        dry_run,
+        cli,
+        interrupts: Interrupts::install(),
+        batch: None,
+        line: String::new(),
+        portfolio_replaced: false,
+    };
+    let startup_file = session.default_file();
+    // the file about to be opened, else the one used last. it's backed up
+    // before it's opened, since refresh-on-load can save over it
+    let backup_file = match &startup_file {
+        Some((path, _)) => Some(path.clone()),
+        None => session.recent.paths().first().cloned(),
+    };
+    run_scheduled_backup(&session.config, backup_file.as_deref(), dry_run);
+    if let Some((path, source)) = startup_file {
+        if path.exists() {
+            let interrupts = session.interrupts.clone();
+            open_portfolio(&mut session, &interrupts, path, None).await;
+        } else {
+            println!(
+                "{} (from {}) doesn't exist yet, so the portfolio starts empty. 'dump' saves it there.",
+                path.display(),
+                source
+            );
+        }
+    }
+    let registry = command_registry();
+    // with commands piped in there's no one watching, so a failed command,
+    // like a refresh where a ticker failed, makes the exit status non-zero
+    let interactive = io::stdin().is_terminal();
+    let mut any_failed = false;
+    loop {
+        let batch_line = session
+            .batch
+            .as_mut()
+            .and_then(|batch| batch.lines.pop_front());
+        let line_number = batch_line.as_ref().map(|(number, _)| *number);
+        input = match batch_line {
+            Some((_, line)) => {
+                println!("» {}", line);
+                line
+            }
+            None => {
+                if let Some(finished) = session.batch.take() {
+                    finish_batch(finished);
+                }
+                // rustyline blocks on the terminal, so it waits on tokio's
+                // blocking pool rather than on a runtime worker
+                let (returned, line) = tokio::task::spawn_blocking(move || {
+                    let line = editor.readline("» ");
+                    (editor, line)
+                })
+                .await
+                .expect("the prompt panicked");
+                editor = returned;
+                let line = match line {
+                    Ok(line) => line,
+                    Err(_) => {
+                        if !session.offline() {
+                            save_quote_cache(&session.quote_cache);
+                        }
+                        // exit skips destructors
+                        drop(session.portfolio_lock.take());
+                        std::process::exit(3)
+                    }
+                };
+                if !line.trim().is_empty() {
+                    editor.add_history_entry(line.as_str());
+                }
+                line
+            }
+        };
+
+        let mut words = input.split_whitespace();
+        let command = words.next().unwrap_or("");
+        let args: Vec<&str> = words.collect();
+        match command {
+            "" => continue,
+            "exit" => break,
+            _ => {}
+        }
+        // set by commands that didn't do what was asked, so a batch can
+        // report them or stop
+        session.line.clone_from(&input);
+        let failed = registry.run(command, &mut session, &args).await;
+
+        if !READ_ONLY_COMMANDS.contains(&command) {
+            session.analytics = None;
+        }
+        any_failed |= failed;
+
+        if let (true, Some(batch), Some(number)) = (failed, session.batch.as_mut(), line_number) {
+            batch
+                .failures
+                .push(format!("line {}: {}", number, input.trim()));
+            if batch.strict {
+                println!(
+                    "Stopping batch {} at line {} (--strict).",
+                    batch.path, number
+                );
+                batch.lines.clear();
+            }
+        }
+    }
+    if !session.offline() {
+        save_quote_cache(&session.quote_cache);
+    }
+    if any_failed && !interactive {
+        // exit skips destructors
+        drop(session.portfolio_lock.take());
+        std::process::exit(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::help_text;
+
+    #[test]
+    fn registered_commands_are_in_the_help() {
+        let registry = command_registry();
+        let help = help_text();
+        for name in registry.commands.keys() {
+            assert!(help.contains(name), "{}", name);
+        }
+        for info in COMMANDS {
+            assert!(
+                info.name == "exit" || registry.commands.contains_key(info.name),
+                "{} isn't registered",
+                info.name
+            );
+        }
+    }
+}
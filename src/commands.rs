@@ -0,0 +1,539 @@
+// the commands the prompt understands, used for help text, tab completion
+// and the dimmed hints shown while typing
+use rustyline::completion::Completer;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::{Hint, Hinter};
+use rustyline::validate::Validator;
+use rustyline::{Context, Helper};
+use std::borrow::Cow;
+
+pub struct CommandInfo {
+    pub name: &'static str,
+    // shown after the name in help and hints, blank if the command takes none
+    pub args: &'static str,
+    pub help: &'static str,
+}
+
+pub const COMMANDS: &[CommandInfo] = &[
+    CommandInfo {
+        name: "assets",
+        args: "",
+        help: "prints all assets, both held and sold",
+    },
+    CommandInfo {
+        name: "sort",
+        args: "<ticker|date|gain|value|cost|quantity> [asc|desc]",
+        help: "prints all assets in order of a column, leaving the stored order alone",
+    },
+    CommandInfo {
+        name: "summary",
+        args: "[accounts]",
+        help: "prints a summary of the loaded portfolio, or with 'accounts' one row per account",
+    },
+    CommandInfo {
+        name: "copy-summary",
+        args: "",
+        help: "copies the summary to the clipboard as plain text, for pasting into an email or chat",
+    },
+    CommandInfo {
+        name: "copy-assets",
+        args: "",
+        help: "copies the assets table to the clipboard as plain text",
+    },
+    CommandInfo {
+        name: "copy-value",
+        args: "",
+        help: "copies the current value of the held assets to the clipboard",
+    },
+    CommandInfo {
+        name: "qr-code",
+        args: "[json]",
+        help: "shows the portfolio's value as a QR code to scan with a phone, or with 'json' the whole portfolio",
+    },
+    CommandInfo {
+        name: "last",
+        args: "[count]",
+        help: "shows the most recently added assets, 5 unless a count is given",
+    },
+    CommandInfo {
+        name: "new",
+        args: "",
+        help: "adds a new asset",
+    },
+    CommandInfo {
+        name: "buy",
+        args: "<ticker> <quantity>",
+        help: "adds a held lot bought today at the current price, after confirming the total",
+    },
+    CommandInfo {
+        name: "demo",
+        args: "[seed] [--refresh]",
+        help: "replaces the portfolio with made-up lots of well-known tickers, the same ones each time for a given seed",
+    },
+    CommandInfo {
+        name: "help",
+        args: "",
+        help: "prints this help text",
+    },
+    CommandInfo {
+        name: "version",
+        args: "",
+        help: "prints the version, file format and build date, for bug reports",
+    },
+    CommandInfo {
+        name: "news",
+        args: "[ticker]",
+        help: "prints recent headlines for a ticker, or today's headline for each held ticker",
+    },
+    CommandInfo {
+        name: "earnings",
+        args: "",
+        help: "lists upcoming earnings dates of held tickers",
+    },
+    CommandInfo {
+        name: "fundamentals",
+        args: "",
+        help: "prints the P/E ratio and market cap of held tickers",
+    },
+    CommandInfo {
+        name: "import",
+        args: "[format filename]",
+        help: "adds assets from a CSV, broker export, Beancount journal or another portfolio",
+    },
+    CommandInfo {
+        name: "load",
+        args: "[--format json|toml|csv] [filename]",
+        help: "loads assets from a file, offering recent files when none is given",
+    },
+    CommandInfo {
+        name: "dump",
+        args: "[filename]",
+        help: "saves assets to a file",
+    },
+    CommandInfo {
+        name: "export",
+        args: "[format filename]",
+        help: "writes the portfolio as JSON, CSV, a Ledger journal, an HTML report, PDF or XLSX",
+    },
+    CommandInfo {
+        name: "export-all",
+        args: "[filename.zip]",
+        help: "zips the portfolio, settings and metadata cache, for moving to another machine",
+    },
+    CommandInfo {
+        name: "import-all",
+        args: "[filename.zip]",
+        help:
+            "loads everything from an export-all zip, replacing the current portfolio and settings",
+    },
+    CommandInfo {
+        name: "refresh",
+        args: "[ticker]",
+        help: "updates the current price of all assets, or just of one ticker",
+    },
+    CommandInfo {
+        name: "range",
+        args: "",
+        help: "prints where held assets sit within their 52-week range",
+    },
+    CommandInfo {
+        name: "price-history",
+        args: "<ticker> <period>",
+        help: "prints weekly closing prices of a ticker over a period like 1y or 6mo",
+    },
+    CommandInfo {
+        name: "total-return-with-dividends",
+        args: "<ticker> [period]",
+        help: "compares a ticker's price return with its total return when dividends are reinvested, over 5y unless a period is given",
+    },
+    CommandInfo {
+        name: "sectors",
+        args: "",
+        help: "prints the market value of held assets by sector",
+    },
+    CommandInfo {
+        name: "chart-sectors",
+        args: "",
+        help: "draws a bar chart of sector weights",
+    },
+    CommandInfo {
+        name: "cost-basis-report",
+        args: "[fifo|lifo|average]",
+        help: "prints the cost basis and gains of each ticker and its lots, by the cost-basis setting unless a method is given",
+    },
+    CommandInfo {
+        name: "cost",
+        args: "",
+        help: "prints the shares, total and average cost, and purchase dates of each ticker held, with the total invested",
+    },
+    CommandInfo {
+        name: "dca",
+        args: "<ticker> <shares|$dollars> [price]",
+        help: "previews the average cost after buying more of a ticker, at the price given or the latest quote",
+    },
+    CommandInfo {
+        name: "age",
+        args: "",
+        help: "lists held lots by how long they've been held, marking those about to turn long-term",
+    },
+    CommandInfo {
+        name: "find-gaps",
+        args: "[days]",
+        help: "lists stretches with no buys or sales longer than the gap-days setting, or the days given",
+    },
+    CommandInfo {
+        name: "yield",
+        args: "",
+        help: "prints the dividend yield and yield on cost of each ticker held",
+    },
+    CommandInfo {
+        name: "stats",
+        args: "",
+        help: "prints statistics about the recorded portfolio value history",
+    },
+    CommandInfo {
+        name: "merge",
+        args: "<filename>",
+        help: "adds the assets from another portfolio file, with tickers held in both handled by the merge-strategy setting",
+    },
+    CommandInfo {
+        name: "live",
+        args: "[--no-refresh] <seconds>",
+        help: "refreshes and shows the assets every so many seconds until Ctrl-C, or with --no-refresh only shows them",
+    },
+    CommandInfo {
+        name: "watch",
+        args: "[seconds]",
+        help: "on a terminal, refreshes and redraws the summary and assets every so many seconds (default 60) until q or Ctrl-C",
+    },
+    CommandInfo {
+        name: "snapshot-diff",
+        args: "[<date> <date>]",
+        help: "compares the portfolio's recorded value on two days, by ticker, or lists the recorded days",
+    },
+    CommandInfo {
+        name: "report",
+        args: "",
+        help: "prints a markdown report of the summary, assets, sectors, performance and risks",
+    },
+    CommandInfo {
+        name: "goal",
+        args: "[add <name> <target> <date> | remove <name>]",
+        help: "shows the portfolio's savings goals and what reaching them takes, or adds or removes one",
+    },
+    CommandInfo {
+        name: "health",
+        args: "",
+        help: "grades the portfolio's diversification, concentration, volatility and returns",
+    },
+    CommandInfo {
+        name: "lint",
+        args: "",
+        help: "checks the portfolio for values that look like data entry mistakes",
+    },
+    CommandInfo {
+        name: "portfolio-size",
+        args: "",
+        help: "prints how many assets the portfolio has and how big it is on disk and in memory",
+    },
+    CommandInfo {
+        name: "convert-totals",
+        args: "",
+        help: "changes buy prices that look like a lot's total cost into per-share prices",
+    },
+    CommandInfo {
+        name: "sell-all",
+        args: "",
+        help: "sells every held asset at its current price today, after confirming",
+    },
+    CommandInfo {
+        name: "sell",
+        args: "[ticker]",
+        help: "sells all or part of a held lot",
+    },
+    CommandInfo {
+        name: "edit",
+        args: "[ticker]",
+        help: "changes an asset's prices, quantity or dates",
+    },
+    CommandInfo {
+        name: "remove",
+        args: "[ticker]",
+        help: "deletes an asset",
+    },
+    CommandInfo {
+        name: "account",
+        args: "[ticker]",
+        help: "sets the brokerage account an asset is held in, e.g. IRA",
+    },
+    CommandInfo {
+        name: "note",
+        args: "[ticker]",
+        help: "attaches a note to an asset",
+    },
+    CommandInfo {
+        name: "target",
+        args: "[ticker]",
+        help: "sets the price a held asset is waited on to reach, or clears it",
+    },
+    CommandInfo {
+        name: "stop",
+        args: "[ticker]",
+        help: "sets a stop-loss price or percent below the buy price for a held asset, or removes it",
+    },
+    CommandInfo {
+        name: "stops",
+        args: "[ack [ticker]]",
+        help: "lists the stop-losses, or acknowledges breached ones",
+    },
+    CommandInfo {
+        name: "tag",
+        args: "[ticker]",
+        help: "labels an asset with comma separated tags",
+    },
+    CommandInfo {
+        name: "cleanup",
+        args: "[days]",
+        help: "removes assets sold more than days (default 365) ago, after a backup",
+    },
+    CommandInfo {
+        name: "batch",
+        args: "<filename> [--strict]",
+        help: "runs the commands in a file, one per line; --strict stops at the first failure",
+    },
+    CommandInfo {
+        name: "set",
+        args: "[setting value] [--save]",
+        help: "lists settings, or changes one; --save also writes it to the config file",
+    },
+    CommandInfo {
+        name: "config",
+        args: "save|show",
+        help: "writes the current settings to the config file, or shows them with where requests go",
+    },
+    CommandInfo {
+        name: "exit",
+        args: "",
+        help: "exits the program",
+    },
+];
+
+pub fn help_text() -> String {
+    COMMANDS
+        .iter()
+        .map(|command| match command.args {
+            "" => format!("{} - {}", command.name, command.help),
+            args => format!("{} {} - {}", command.name, args, command.help),
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+pub fn matching_commands(prefix: &str) -> Vec<&'static str> {
+    COMMANDS
+        .iter()
+        .map(|command| command.name)
+        .filter(|name| name.starts_with(prefix))
+        .collect()
+}
+
+// the commands in a batch file with their line numbers, skipping blank lines
+// and lines starting with #. a # later in a line is kept, since filenames
+// can contain one
+pub fn parse_batch(raw: &str) -> Vec<(usize, String)> {
+    raw.lines()
+        .enumerate()
+        .map(|(index, line)| (index + 1, line.trim()))
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+        .map(|(number, line)| (number, line.to_string()))
+        .collect()
+}
+
+#[derive(Debug, PartialEq)]
+pub struct CommandHint {
+    display: String,
+    // None for argument placeholders, which shouldn't be inserted into the line
+    completion: Option<String>,
+}
+
+impl Hint for CommandHint {
+    fn display(&self) -> &str {
+        &self.display
+    }
+
+    fn completion(&self) -> Option<&str> {
+        self.completion.as_deref()
+    }
+}
+
+// the hint for a line with the cursor at its end: the rest of the first
+// command the line is a prefix of, or the arguments of a complete command.
+// anything longer (e.g. a pasted line with arguments) gets no hint
+pub fn command_hint(line: &str) -> Option<CommandHint> {
+    let (name, rest) = match line.split_once(' ') {
+        Some((name, rest)) => (name, Some(rest)),
+        None => (line, None),
+    };
+    if name.is_empty() {
+        return None;
+    }
+    match rest {
+        None => {
+            let command = COMMANDS
+                .iter()
+                .find(|command| command.name.starts_with(name))?;
+            let remainder = &command.name[name.len()..];
+            if remainder.is_empty() {
+                if command.args.is_empty() {
+                    return None;
+                }
+                return Some(CommandHint {
+                    display: format!(" {}", command.args),
+                    completion: None,
+                });
+            }
+            Some(CommandHint {
+                display: remainder.to_string(),
+                completion: Some(remainder.to_string()),
+            })
+        }
+        Some("") => {
+            let command = COMMANDS.iter().find(|command| command.name == name)?;
+            if command.args.is_empty() {
+                return None;
+            }
+            Some(CommandHint {
+                display: command.args.to_string(),
+                completion: None,
+            })
+        }
+        Some(_) => None,
+    }
+}
+
+pub struct CommandHelper {
+    // hints rely on escape codes to dim them, so they can be turned off for
+    // terminals that can't show them
+    hints: bool,
+}
+
+impl CommandHelper {
+    pub fn new(hints: bool) -> CommandHelper {
+        CommandHelper { hints }
+    }
+}
+
+impl Completer for CommandHelper {
+    type Candidate = String;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _ctx: &Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<String>)> {
+        // only the command name itself is completed
+        let typed = &line[..pos];
+        if typed.contains(char::is_whitespace) {
+            return Ok((pos, vec![]));
+        }
+        let candidates = matching_commands(typed)
+            .into_iter()
+            .map(str::to_string)
+            .collect();
+        Ok((0, candidates))
+    }
+}
+
+impl Hinter for CommandHelper {
+    type Hint = CommandHint;
+
+    fn hint(&self, line: &str, pos: usize, _ctx: &Context<'_>) -> Option<CommandHint> {
+        if !self.hints || pos < line.len() {
+            return None;
+        }
+        command_hint(line)
+    }
+}
+
+impl Highlighter for CommandHelper {
+    fn highlight_hint<'h>(&self, hint: &'h str) -> Cow<'h, str> {
+        Cow::Owned(format!("\x1b[2m{}\x1b[0m", hint))
+    }
+}
+
+impl Validator for CommandHelper {}
+
+impl Helper for CommandHelper {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hint(display: &str, completion: Option<&str>) -> Option<CommandHint> {
+        Some(CommandHint {
+            display: display.to_string(),
+            completion: completion.map(str::to_string),
+        })
+    }
+
+    #[test]
+    fn hint_completes_command_prefix() {
+        assert_eq!(command_hint("re"), hint("fresh", Some("fresh")));
+        assert_eq!(command_hint("fund"), hint("amentals", Some("amentals")));
+    }
+
+    #[test]
+    fn hint_shows_arguments_without_completing_them() {
+        assert_eq!(
+            command_hint("load"),
+            hint(" [--format json|toml|csv] [filename]", None)
+        );
+        assert_eq!(
+            command_hint("load "),
+            hint("[--format json|toml|csv] [filename]", None)
+        );
+        assert_eq!(command_hint("assets"), None);
+    }
+
+    #[test]
+    fn no_hint_for_typed_arguments_or_unknown_commands() {
+        assert_eq!(command_hint("load my portfolio.json"), None);
+        assert_eq!(command_hint("xyz"), None);
+        assert_eq!(command_hint(""), None);
+    }
+
+    #[test]
+    fn matching_commands_by_prefix() {
+        assert_eq!(
+            matching_commands("s"),
+            vec![
+                "sort",
+                "summary",
+                "sectors",
+                "stats",
+                "snapshot-diff",
+                "sell-all",
+                "sell",
+                "stop",
+                "stops",
+                "set"
+            ]
+        );
+        assert_eq!(matching_commands("q"), vec!["qr-code"]);
+        assert!(matching_commands("x").is_empty());
+    }
+
+    #[test]
+    fn batch_skips_comments_and_blank_lines() {
+        let raw = "# nightly update\nload main.json\n\n  refresh  \ndump backup#1.json\n";
+        assert_eq!(
+            parse_batch(raw),
+            vec![
+                (2, "load main.json".to_string()),
+                (4, "refresh".to_string()),
+                (5, "dump backup#1.json".to_string()),
+            ]
+        );
+    }
+}
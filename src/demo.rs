@@ -0,0 +1,180 @@
+// a made-up portfolio for `demo`, for showing the tracker off or trying out
+// display changes without real holdings. the same seed always gives the same
+// lots, with dates counted back from today so none are ever in the future
+use crate::portfolio::{Asset, Portfolio};
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+
+// well-known tickers with a recent price per share, used both to pick buy
+// and sell prices and as the current price until the demo is refreshed
+const DEMO_TICKERS: [(&str, u32); 16] = [
+    ("AAPL", 19025),
+    ("MSFT", 41050),
+    ("GOOGL", 16540),
+    ("AMZN", 18075),
+    ("NVDA", 12010),
+    ("TSLA", 24130),
+    ("META", 50020),
+    ("JPM", 19880),
+    ("KO", 6215),
+    ("DIS", 9540),
+    ("NFLX", 62045),
+    ("INTC", 3085),
+    ("PYPL", 6490),
+    ("VTI", 26010),
+    ("COST", 78560),
+    ("PFE", 2890),
+];
+
+// how many of DEMO_TICKERS go into one demo portfolio
+const DEMO_TICKER_COUNT: usize = 12;
+
+// how many of them are bought above today's price, so there's always
+// something in the red
+const DEMO_LOSERS: usize = 2;
+
+// splitmix64: tiny, and unlike a library generator guaranteed to give the
+// same numbers for a seed in every version
+struct DemoRng(u64);
+
+impl DemoRng {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    // a whole number from low to high, both included
+    fn range(&mut self, low: u64, high: u64) -> u64 {
+        low + self.next() % (high - low + 1)
+    }
+
+    // a price scaled by a random percentage from low to high
+    fn scale(&mut self, cents: u32, low_percent: u64, high_percent: u64) -> u32 {
+        let percent = self.range(low_percent, high_percent);
+        ((cents as u64 * percent + 50) / 100).max(1) as u32
+    }
+
+    fn chance(&mut self, percent: u64) -> bool {
+        self.range(1, 100) <= percent
+    }
+}
+
+pub fn demo_portfolio(seed: u64, today: NaiveDate, now: DateTime<Utc>) -> Portfolio {
+    let mut rng = DemoRng(seed);
+    let mut tickers = DEMO_TICKERS.to_vec();
+    // Fisher-Yates, so each seed picks its own dozen
+    for i in (1..tickers.len()).rev() {
+        tickers.swap(i, rng.range(0, i as u64) as usize);
+    }
+    tickers.truncate(DEMO_TICKER_COUNT);
+
+    let mut assets = vec![];
+    // whether each lot may be sold. the losers' first lots are kept so
+    // there's always something in the red
+    let mut sellable = vec![];
+    for (index, (ticker, price)) in tickers.into_iter().enumerate() {
+        let loser = index < DEMO_LOSERS;
+        let (low, high) = if loser { (115, 160) } else { (45, 105) };
+        let lot = |rng: &mut DemoRng, days_ago: u64| {
+            let buy_price = rng.scale(price, low, high);
+            // somewhere between $1,000 and $15,000 a lot
+            let budget = rng.range(1_000, 15_000) * 100;
+            Asset {
+                ticker: ticker.to_string(),
+                buy_price_cents: buy_price,
+                buy_date: Some(today - Duration::days(days_ago as i64)),
+                current_price_cents: price,
+                sell_price_cents: None,
+                sell_date: None,
+                quantity: (budget / buy_price as u64).max(1) as u32,
+                price_updated_at: Some(now),
+                refresh_failed_at: None,
+                currency: None,
+                note: None,
+                tags: vec![],
+                account: None,
+                added_at: Some(now),
+                price_history: vec![],
+                target_price_cents: None,
+                stop_loss: None,
+                stop_breached_at: None,
+                price_from_cache: false,
+            }
+        };
+        let bought = rng.range(90, 1_500);
+        assets.push(lot(&mut rng, bought));
+        sellable.push(!loser);
+        if rng.chance(40) {
+            let bought = rng.range(30, bought);
+            assets.push(lot(&mut rng, bought));
+            sellable.push(true);
+        }
+    }
+
+    // about a quarter of the rest are sold, and at least two so there are
+    // realized gains to show
+    let mut candidates: Vec<usize> = (0..assets.len()).filter(|i| sellable[*i]).collect();
+    let mut needed: usize = 2;
+    while let Some(i) = candidates.pop() {
+        if !(candidates.len() < needed || rng.chance(25)) {
+            continue;
+        }
+        needed = needed.saturating_sub(1);
+        let asset = &mut assets[i];
+        let held_for = (today - asset.buy_date.unwrap_or(today)).num_days().max(2) as u64;
+        asset.sell_date = Some(today - Duration::days(rng.range(1, held_for - 1) as i64));
+        asset.sell_price_cents = Some(rng.scale(asset.buy_price_cents, 80, 150));
+    }
+
+    Portfolio {
+        name: "demo".to_string(),
+        assets,
+        ..Portfolio::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::portfolio::distinct_tickers;
+    use chrono::TimeZone;
+
+    fn generate(seed: u64) -> Portfolio {
+        let now = Utc.with_ymd_and_hms(2024, 6, 1, 12, 0, 0).unwrap();
+        demo_portfolio(seed, now.date_naive(), now)
+    }
+
+    #[test]
+    fn demo_is_the_same_for_a_seed() {
+        let first = serde_json::to_string(&generate(7)).unwrap();
+        assert_eq!(first, serde_json::to_string(&generate(7)).unwrap());
+        assert_ne!(first, serde_json::to_string(&generate(8)).unwrap());
+    }
+
+    #[test]
+    fn demo_has_a_dozen_tickers_with_sales_and_losers() {
+        for seed in 0..50 {
+            let portfolio = generate(seed);
+            let today = NaiveDate::from_ymd_opt(2024, 6, 1).unwrap();
+            assert_eq!(distinct_tickers(&portfolio.assets).len(), DEMO_TICKER_COUNT);
+            assert!(portfolio.assets_sold().len() >= 2);
+            let losers = portfolio
+                .assets
+                .iter()
+                .filter(|a| {
+                    a.sell_price_cents.is_none() && a.current_price_cents < a.buy_price_cents
+                })
+                .count();
+            assert!(losers > 0, "seed {}", seed);
+            for asset in &portfolio.assets {
+                let bought = asset.buy_date.unwrap();
+                assert!(bought < today);
+                if let Some(sold) = asset.sell_date {
+                    assert!(bought < sold && sold < today, "seed {}", seed);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,1788 @@
+// formatting of money and other values, and the tables and reports printed
+// for each command
+use crate::api::proxy_url;
+use crate::commands::help_text;
+use crate::config::{
+    display_settings, redact_url, Config, CostBasisMethod, OutputStyle, SummaryStyle, SETTINGS,
+};
+use crate::dividends::DividendReinvestment;
+use crate::goals::{goal_progress, Goal};
+use crate::health::{Grade, HealthScore};
+use crate::lint::{lint_assets, possible_duplicates};
+use crate::metadata::MetadataCache;
+use crate::portfolio::{
+    activity_gaps, best_and_worst_held, cost_basis, days_held, days_to_long_term, distinct_tickers,
+    held_by_age, held_costs, in_portfolio_currency, is_asset_held, is_asset_sold, market_value,
+    per_share_cost, per_share_price, percent_change, percent_increase, percent_to_target,
+    position_in_range, realized_gain_loss, sector_allocation, stale_held_assets, ticker_cost_basis,
+    ticker_yields, upcoming_earnings, AccountSummary, Asset, Drawdown, HeldCost, Portfolio,
+    PortfolioAnalytics, SnapshotDiff, StopLoss, LONG_TERM_DAYS, PRICE_BASIS_PER_SHARE,
+};
+use chrono::{Local, NaiveDate, Utc};
+use comfy_table::presets::{NOTHING, UTF8_FULL};
+use comfy_table::TableComponent::*;
+use comfy_table::{Attribute, Cell, Color, ColumnConstraint, ContentArrangement, Table};
+use crossterm::style::Stylize;
+use qrcode::render::unicode::Dense1x2;
+use qrcode::QrCode;
+use std::collections::HashSet;
+use std::env;
+use std::io::{self, IsTerminal};
+use std::path::{Path, PathBuf};
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+// e.g. 512 B, 12.3 KB, 4.1 MB
+pub fn format_bytes(bytes: usize) -> String {
+    const UNITS: [(f64, &str); 3] = [
+        (1024.0 * 1024.0 * 1024.0, "GB"),
+        (1024.0 * 1024.0, "MB"),
+        (1024.0, "KB"),
+    ];
+    for (scale, unit) in UNITS {
+        if bytes as f64 >= scale {
+            return format!("{:.1} {}", bytes as f64 / scale, unit);
+        }
+    }
+    format!("{} B", bytes)
+}
+
+// whole dollars scaled to a suffix with three significant digits, e.g. $2.91T or $58.3B
+pub fn format_market_cap(dollars: u64) -> String {
+    const SUFFIXES: [(f64, &str); 4] = [(1e12, "T"), (1e9, "B"), (1e6, "M"), (1e3, "K")];
+    let dollars = dollars as f64;
+    for (scale, suffix) in SUFFIXES {
+        if dollars >= scale {
+            let scaled = dollars / scale;
+            let decimals = if scaled >= 100.0 {
+                0
+            } else if scaled >= 10.0 {
+                1
+            } else {
+                2
+            };
+            return format!("${:.*}{}", decimals, scaled, suffix);
+        }
+    }
+    format!("${}", dollars)
+}
+
+// e.g. "3h ago" for something that happened 3 hours and 10 minutes ago
+pub fn format_relative_time(seconds_ago: i64) -> String {
+    const UNITS: [(i64, &str); 4] = [(86400 * 7, "w"), (86400, "d"), (3600, "h"), (60, "m")];
+    for (seconds, unit) in UNITS {
+        if seconds_ago >= seconds {
+            return format!("{}{} ago", seconds_ago / seconds, unit);
+        }
+    }
+    "just now".to_string()
+}
+
+// cuts text down to at most `width` terminal columns, marking the cut with an
+// ellipsis. wide characters like 株 take two columns and combining accents none
+pub fn truncate_to_width(text: &str, width: usize) -> String {
+    if text.width() <= width {
+        return text.to_string();
+    }
+    let mut truncated = String::new();
+    let mut used = 0;
+    for c in text.chars() {
+        let char_width = c.width().unwrap_or(0);
+        // one column is kept for the ellipsis
+        if used + char_width + 1 > width {
+            break;
+        }
+        truncated.push(c);
+        used += char_width;
+    }
+    truncated.push('…');
+    truncated
+}
+
+// a text column's value cut to the column-width setting, for things like
+// tickers that aren't usually long but can be
+pub fn text_column(text: &str) -> String {
+    match display_settings().max_column_width {
+        0 => text.to_string(),
+        width => truncate_to_width(text, width),
+    }
+}
+
+pub fn format_money(cents: i64) -> String {
+    format_money_with_symbol(cents, display_settings().currency_symbol)
+}
+
+// a signed change to two decimals, e.g. +12.50% or -3.25%, used for every
+// percent change so they all round the same. anything that rounds to zero
+// is 0.00%, never -0.00%
+pub fn format_percent(change: f64) -> String {
+    if (change * 100.0).round() == 0.0 {
+        return "0.00%".to_string();
+    }
+    format!("{:+.2}%", change)
+}
+
+// the same, with a dash when there's nothing to divide by
+pub fn format_optional_percent(change: Option<f64>) -> String {
+    change.map_or_else(|| "-".to_string(), format_percent)
+}
+
+// money in an asset's own currency: with the portfolio's symbol when it's
+// in that, otherwise followed by its currency code, e.g. "2650.00 JPY"
+pub fn format_asset_money(asset: &Asset, cents: i64) -> String {
+    match asset.currency.as_deref() {
+        Some(code) if !in_portfolio_currency(asset) => {
+            format!("{} {}", format_money_with_symbol(cents, ""), code)
+        }
+        _ => format_money(cents),
+    }
+}
+
+// for gains and losses: "+$12.50" and "-$12.50", with no sign for zero
+pub fn format_money_with_sign(cents: i64) -> String {
+    if cents > 0 {
+        format!("+{}", format_money(cents))
+    } else {
+        format_money(cents)
+    }
+}
+
+fn format_money_with_symbol(cents: i64, symbol: &str) -> String {
+    // integer formatting so large amounts don't pick up floating point error
+    let sign = if cents < 0 { "-" } else { "" };
+    let cents = cents.unsigned_abs();
+    format!("{}{}{}.{:02}", sign, symbol, cents / 100, cents % 100)
+}
+
+pub const BAR_WIDTH: usize = 40;
+
+// a bar `width` characters long for 100%, filled to the nearest character
+pub fn percent_bar(percent: f64, width: usize) -> String {
+    let filled = ((percent.clamp(0.0, 100.0) / 100.0 * width as f64).round() as usize).min(width);
+    format!("{}{}", "█".repeat(filled), "░".repeat(width - filled))
+}
+
+pub fn apply_table_display_settings(table: &mut Table) {
+    // on a terminal, cells wrap to keep the table within its width, which is
+    // read each time so a resize between commands is picked up. piped output
+    // is never wrapped
+    if let Some(width) = table_width() {
+        table.set_content_arrangement(ContentArrangement::Dynamic);
+        table.set_width(width.min(u16::MAX as usize) as u16);
+    }
+    if display_settings().output == OutputStyle::Plain {
+        table.load_preset(NOTHING);
+        return;
+    }
+    // this is my preferred style for a table
+    table.load_preset(UTF8_FULL);
+    table.set_style(VerticalLines, '│');
+    table.set_style(HorizontalLines, '─');
+}
+
+// the terminal width, or None when stdout isn't a terminal
+pub fn table_width() -> Option<usize> {
+    io::stdout().is_terminal().then(terminal_width)
+}
+
+// hides columns, least important first, until the rest fit the terminal
+// without wrapping. `priority` lists the column indexes from most to least
+// important; the first is always kept, and wrapping is what's left for when
+// it alone doesn't fit. call once the rows are added, since it goes by them
+pub fn fit_columns(table: &mut Table, priority: &[usize]) {
+    if let Some(width) = table_width() {
+        hide_columns_to_fit(table, priority, width);
+    }
+}
+
+fn hide_columns_to_fit(table: &mut Table, priority: &[usize], width: usize) {
+    let content = table.column_max_content_widths();
+    let mut shown: Vec<usize> = priority.to_vec();
+    while shown.len() > 1 && columns_width(&content, &shown) > width {
+        let hidden = shown.pop().unwrap();
+        if let Some(column) = table.column_mut(hidden) {
+            column.set_constraint(ColumnConstraint::Hidden);
+        }
+    }
+}
+
+// the width of a table showing these columns: the content and a space of
+// padding on each side, and a border between and around them
+fn columns_width(content: &[u16], shown: &[usize]) -> usize {
+    let cells: usize = shown
+        .iter()
+        .map(|column| content.get(*column).map_or(0, |width| *width as usize + 2))
+        .sum();
+    cells + shown.len() + 1
+}
+
+// green for gains and red for losses, unless colour is turned off
+pub fn gain_loss_cell(text: String, change: f64) -> Cell {
+    let cell = Cell::new(text);
+    if !display_settings().color || change == 0.0 {
+        return cell;
+    }
+    cell.fg(if change > 0.0 {
+        Color::Green
+    } else {
+        Color::Red
+    })
+}
+
+// e.g. "⚠ SHOP.TO (CAD) is left out of the totals, which are in USD, as
+// there's no exchange rate to convert it"
+fn foreign_warning(analytics: &PortfolioAnalytics) -> Option<String> {
+    if analytics.foreign.is_empty() {
+        return None;
+    }
+    Some(format!(
+        "⚠ {} {} left out of the totals, which are in {}, as there's no exchange rate to convert {}",
+        analytics.foreign.join(", "),
+        if analytics.foreign.len() == 1 { "is" } else { "are" },
+        display_settings().currency,
+        if analytics.foreign.len() == 1 { "it" } else { "them" }
+    ))
+}
+
+// e.g. "⚠ 2 held tickers (GME, AMC) couldn't be refreshed and are valued at
+// an earlier price"
+fn refresh_failed_warning(analytics: &PortfolioAnalytics) -> Option<String> {
+    if analytics.refresh_failed.is_empty() {
+        return None;
+    }
+    Some(format!(
+        "⚠ {} ({}) couldn't be refreshed and {} valued at an earlier price",
+        describe_count(analytics.refresh_failed.len(), "held ticker"),
+        analytics.refresh_failed.join(", "),
+        if analytics.refresh_failed.len() == 1 {
+            "is"
+        } else {
+            "are"
+        }
+    ))
+}
+
+pub fn print_summary(portfolio: &Portfolio, analytics: &PortfolioAnalytics) {
+    print!(
+        "{}",
+        summary_text(portfolio, analytics, |table| table.to_string())
+    );
+}
+
+// the summary for `copy-summary`
+pub fn summary_copy_text(portfolio: &Portfolio, analytics: &PortfolioAnalytics) -> String {
+    summary_text(portfolio, analytics, copy_text)
+}
+
+// the summary's lines, with `render` turning the table into text
+fn summary_text(
+    portfolio: &Portfolio,
+    analytics: &PortfolioAnalytics,
+    render: impl Fn(Table) -> String,
+) -> String {
+    let mut lines = vec![];
+    // with nothing held every total is $0.00, and there's no age or return
+    // worth adding
+    if analytics.held == 0 {
+        if display_settings().summary_style == SummaryStyle::Compact {
+            lines.push(compact_summary(analytics));
+        } else {
+            lines.push(analytics.headline(&portfolio.name));
+            lines.push(render(summary_table(analytics)));
+            lines.push(positions_line(analytics));
+        }
+        lines.extend(foreign_warning(analytics));
+        return lines.iter().map(|line| format!("{}\n", line)).collect();
+    }
+    if display_settings().summary_style == SummaryStyle::Compact {
+        lines.push(compact_summary(analytics));
+    } else {
+        lines.push(analytics.headline(&portfolio.name));
+        lines.push(render(summary_table(analytics)));
+        lines.push(positions_line(analytics));
+        if let Some(age) = analytics.age {
+            lines.push(format!("Portfolio age: {}", age));
+        }
+        if let Some(change) = analytics.since_inception_percent() {
+            lines.push(format!(
+                "Since inception return: {}",
+                format_percent(change)
+            ));
+        }
+    }
+    lines.extend(foreign_warning(analytics));
+    lines.extend(refresh_failed_warning(analytics));
+    lines.iter().map(|line| format!("{}\n", line)).collect()
+}
+
+fn summary_table(analytics: &PortfolioAnalytics) -> Table {
+    let mut table = Table::new();
+    // TODO: add support for sold assets in a seperate table
+    apply_table_display_settings(&mut table);
+    let mut header = vec![
+        "Net Buy Price",
+        "Market Value",
+        "Unrealized Gains/Losses",
+        "Return %",
+    ];
+    // realized and unrealized together, once something has been sold
+    if analytics.sold > 0 {
+        header.push("Total Return %");
+    }
+    table.set_header(header);
+
+    let net_buy_price = analytics.total_cost;
+    let market_value = analytics.total_value;
+    // gains are what the holdings are worth now over what was paid for them,
+    // and can be negative, so this is signed
+    let unrealized_gains_losses: i64 = analytics.unrealized_pnl;
+    let mut row = vec![
+        Cell::new(format_money(net_buy_price)),
+        Cell::new(format_money(market_value)),
+        gain_loss_cell(
+            format_money_with_sign(unrealized_gains_losses),
+            unrealized_gains_losses as f64,
+        ),
+        // 0.00% on the $0.00 of an empty portfolio, not a dash
+        percent_cell(if analytics.held == 0 {
+            Some(0.0)
+        } else {
+            analytics.return_percent()
+        }),
+    ];
+    if analytics.sold > 0 {
+        row.push(percent_cell(analytics.total_return_percent()));
+    }
+    table.add_row(row);
+    table
+}
+
+// a table as it's copied to the clipboard: borderless like `set output
+// plain`, with every column, nothing wrapped and no colours
+pub fn copy_text(mut table: Table) -> String {
+    table.load_preset(NOTHING);
+    table.force_no_tty();
+    table.set_content_arrangement(ContentArrangement::Disabled);
+    for column in table.column_iter_mut() {
+        column.remove_constraint();
+    }
+    // the preset has no borders, but leaves the padding at the line ends
+    let lines: Vec<String> = table
+        .lines()
+        .map(|line| line.trim_end().to_string())
+        .collect();
+    lines.join("\n")
+}
+
+// the assets table for `copy-assets`
+pub fn assets_copy_text(assets: &[Asset]) -> String {
+    let mut text = copy_text(assets_table(assets));
+    text.push('\n');
+    if let Some(legend) = refresh_failed_legend(assets) {
+        text.push_str(&legend);
+        text.push('\n');
+    }
+    text
+}
+
+fn percent_cell(change: Option<f64>) -> Cell {
+    gain_loss_cell(format_optional_percent(change), change.unwrap_or(0.0))
+}
+
+// `summary accounts`: a row per account and a total
+pub fn print_account_summary(summaries: &[AccountSummary]) {
+    let mut table = Table::new();
+    apply_table_display_settings(&mut table);
+    table.set_header(vec![
+        "Account",
+        "Net Buy Price",
+        "Market Value",
+        "Unrealized Gains/Losses",
+        "Realized Gains/Losses",
+    ]);
+    let row = |name: &str, cost: i64, value: i64, realized: i64| {
+        vec![
+            Cell::new(text_column(name)),
+            Cell::new(format_money(cost)),
+            Cell::new(format_money(value)),
+            gain_loss_cell(format_money_with_sign(value - cost), (value - cost) as f64),
+            gain_loss_cell(format_money_with_sign(realized), realized as f64),
+        ]
+    };
+    for summary in summaries {
+        table.add_row(row(
+            summary.account.as_deref().unwrap_or("(unassigned)"),
+            summary.cost_basis,
+            summary.market_value,
+            summary.realized,
+        ));
+    }
+    table.add_row(row(
+        "Total",
+        summaries.iter().map(|summary| summary.cost_basis).sum(),
+        summaries.iter().map(|summary| summary.market_value).sum(),
+        summaries.iter().map(|summary| summary.realized).sum(),
+    ));
+    println!("{table}");
+}
+
+// e.g. "Positions: 12 held across 9 tickers, 4 sold"
+pub fn positions_line(analytics: &PortfolioAnalytics) -> String {
+    format!(
+        "Positions: {} held across {}, {} sold",
+        analytics.held,
+        describe_count(analytics.held_tickers, "ticker"),
+        analytics.sold
+    )
+}
+
+// e.g. "Total Cost: $50000.00 │ Market Value: $58230.00 │ Gain: +$8230.00 (+16.46%) │ IRR: 12.3%"
+pub fn compact_summary(analytics: &PortfolioAnalytics) -> String {
+    let irr = analytics
+        .irr
+        .map_or_else(|| "n/a".to_string(), |rate| format!("{:.1}%", rate * 100.0));
+    format!(
+        "Total Cost: {} │ Market Value: {} │ Gain: {} ({}) │ IRR: {}",
+        format_money(analytics.total_cost),
+        format_money(analytics.total_value),
+        format_money_with_sign(analytics.unrealized_pnl),
+        format_optional_percent(analytics.return_percent()),
+        irr
+    )
+}
+
+pub fn print_stats(portfolio: &Portfolio, analytics: &PortfolioAnalytics, history_points: usize) {
+    print_price_history_size(portfolio, history_points);
+    let history = &portfolio.value_history;
+    if let (Some(first), Some(last)) = (history.first(), history.last()) {
+        println!(
+            "Value history: {} points from {} to {}",
+            history.len(),
+            first.0,
+            last.0
+        );
+    }
+    match &analytics.drawdown {
+        None => println!(
+            "Not enough value history to compute a drawdown. Refresh on at least two different days."
+        ),
+        Some(drawdown) if drawdown.decline() == 0 => {
+            println!("Max drawdown: none, the portfolio value has never declined")
+        }
+        Some(drawdown) => println!("Max drawdown: {}", describe_drawdown(drawdown)),
+    }
+}
+
+// e.g. "-12.50% ($1250.00) from a peak of $10000.00 on 2024-01-02 to a trough
+// of $8750.00 on 2024-02-01"
+pub fn describe_drawdown(drawdown: &Drawdown) -> String {
+    format!(
+        "-{:.2}% ({}) from a peak of {} on {} to a trough of {} on {}",
+        drawdown.decline_percent(),
+        format_money(drawdown.decline()),
+        format_money(drawdown.peak_value),
+        drawdown.peak_date,
+        format_money(drawdown.trough_value),
+        drawdown.trough_date
+    )
+}
+
+// e.g. "AAPL: Price return 15.2% │ Total return (DRIP): 18.7% │ Dividend
+// contribution: +3.5pp"
+pub fn format_dividend_reinvestment(ticker: &str, result: &DividendReinvestment) -> String {
+    format!(
+        "{}: Price return {:.1}% │ Total return (DRIP): {:.1}% │ Dividend contribution: {:+.1}pp",
+        ticker,
+        result.price_return_percent,
+        result.total_return_percent,
+        result.dividend_contribution()
+    )
+}
+
+pub fn print_dividend_reinvestment(ticker: &str, result: &DividendReinvestment) {
+    println!("{}", format_dividend_reinvestment(ticker, result));
+    println!(
+        "1 share bought at {} on {} grows to {:.4} shares worth {} on {}, with {} reinvested.",
+        format_money(result.start_price as i64),
+        result.start_date,
+        result.shares,
+        format_money((result.shares * result.end_price as f64).round() as i64),
+        result.end_date,
+        describe_count(result.dividends_reinvested, "dividend")
+    );
+}
+
+// the days `snapshot-diff` can compare
+pub fn print_snapshots(portfolio: &Portfolio) {
+    if portfolio.value_history.is_empty() {
+        println!("No values recorded yet. One is recorded each day the portfolio is refreshed.");
+        return;
+    }
+    let mut table = Table::new();
+    apply_table_display_settings(&mut table);
+    table.set_header(vec!["Date", "Market Value"]);
+    for (date, value) in &portfolio.value_history {
+        table.add_row(vec![date.to_string(), format_money(*value)]);
+    }
+    println!("{table}");
+    println!("Enter 'snapshot-diff <date> <date>' to compare two of them.");
+}
+
+pub fn print_snapshot_diff(diff: &SnapshotDiff) {
+    println!(
+        "Value on {}: {} │ on {}: {} │ Change: {} ({})",
+        diff.from.0,
+        format_money(diff.from.1),
+        diff.to.0,
+        format_money(diff.to.1),
+        format_money_with_sign(diff.change()),
+        format_optional_percent(diff.change_percent())
+    );
+    if diff.tickers.is_empty() {
+        println!(
+            "No values by ticker were recorded on those days, so the change can't be broken down."
+        );
+        return;
+    }
+    let mut table = Table::new();
+    apply_table_display_settings(&mut table);
+    table.set_header(vec![
+        "Ticker".to_string(),
+        diff.from.0.to_string(),
+        diff.to.0.to_string(),
+        "Change".to_string(),
+    ]);
+    for (ticker, before, after) in &diff.tickers {
+        let change = after - before;
+        table.add_row(vec![
+            Cell::new(text_column(ticker)),
+            Cell::new(format_money(*before)),
+            Cell::new(format_money(*after)),
+            gain_loss_cell(format_money_with_sign(change), change as f64),
+        ]);
+    }
+    println!("{table}");
+}
+
+// each refresh adds a point per asset, so this is what grows the file over time
+pub fn print_price_history_size(portfolio: &Portfolio, history_points: usize) {
+    let with_history: Vec<&Asset> = portfolio
+        .assets
+        .iter()
+        .filter(|asset| !asset.price_history.is_empty())
+        .collect();
+    if with_history.is_empty() {
+        return;
+    }
+    let points: usize = with_history
+        .iter()
+        .map(|asset| asset.price_history.len())
+        .sum();
+    let bytes: usize = with_history
+        .iter()
+        .map(|asset| {
+            serde_json::to_string(&asset.price_history)
+                .map_or(0, |json| json.len() + r#","price_history":"#.len())
+        })
+        .sum();
+    println!(
+        "Price history: {} across {}, about {} of the file (capped at {} per asset, set with 'set history-points')",
+        describe_count(points, "point"),
+        describe_count(with_history.len(), "asset"),
+        format_bytes(bytes),
+        history_points
+    );
+}
+
+// short-term holdings are yellow, since their gains are taxed as income
+pub fn days_held_cell(days: Option<i64>) -> Cell {
+    let Some(days) = days else {
+        return Cell::new("unknown");
+    };
+    let cell = Cell::new(days);
+    if display_settings().color && days < LONG_TERM_DAYS {
+        cell.fg(Color::Yellow)
+    } else {
+        cell
+    }
+}
+
+// `age`: held lots longest held first, with the ones about to turn long-term
+// marked, since selling those a little later is taxed less
+pub fn print_holding_ages(assets: &[Asset], today: NaiveDate, soon_days: u32) {
+    let held = held_by_age(assets, today);
+    if held.is_empty() {
+        println!("Nothing is held.");
+        return;
+    }
+    let mut table = Table::new();
+    apply_table_display_settings(&mut table);
+    table.set_header(vec![
+        "Ticker",
+        "Quantity",
+        "Buy Date",
+        "Days Held",
+        "Long-Term",
+    ]);
+    let mut soon = 0;
+    for asset in held.iter().map(|index| &assets[*index]) {
+        let long_term = match days_to_long_term(asset, today) {
+            None => Cell::new("unknown"),
+            Some(0) => Cell::new("yes"),
+            Some(days) if days <= soon_days as i64 => {
+                soon += 1;
+                let cell = Cell::new(format!("in {} ◀", describe_count(days as usize, "day")));
+                if display_settings().color {
+                    cell.fg(Color::Yellow)
+                } else {
+                    cell
+                }
+            }
+            Some(days) => Cell::new(format!("in {}", describe_count(days as usize, "day"))),
+        };
+        table.add_row(vec![
+            Cell::new(text_column(&asset.ticker)),
+            Cell::new(asset.quantity),
+            Cell::new(
+                asset
+                    .buy_date
+                    .map_or("unknown".to_string(), |date| date.to_string()),
+            ),
+            days_held_cell(days_held(asset, today)),
+            long_term,
+        ]);
+    }
+    println!("{table}");
+    if soon > 0 {
+        println!(
+            "◀ {} turn{} long-term within {} (set with 'set long-term-soon-days').",
+            describe_count(soon, "lot"),
+            if soon == 1 { "s" } else { "" },
+            describe_count(soon_days as usize, "day")
+        );
+    }
+}
+
+// `find-gaps`: stretches without a buy or sale, where something may have
+// gone unrecorded
+pub fn print_activity_gaps(assets: &[Asset], min_days: u32, today: NaiveDate) {
+    let undated = assets
+        .iter()
+        .filter(|asset| {
+            asset.buy_date.is_none() || (is_asset_sold(asset) && asset.sell_date.is_none())
+        })
+        .count();
+    let gaps = activity_gaps(assets, min_days, today);
+    if gaps.is_empty() {
+        println!(
+            "No gaps in activity longer than {}.",
+            describe_count(min_days as usize, "day")
+        );
+    }
+    for (from, to) in &gaps {
+        println!(
+            "No activity from {} to {} ({})",
+            from,
+            if *to == today {
+                "today".to_string()
+            } else {
+                to.to_string()
+            },
+            describe_count((*to - *from).num_days() as usize, "day")
+        );
+    }
+    if undated > 0 {
+        println!(
+            "{} without a buy or sell date left out.",
+            describe_count(undated, "lot")
+        );
+    }
+}
+
+// the assets table's columns from most to least important, for fit_columns:
+// ticker, percent change, current price, buy price, quantity, cost basis,
+// days held, then sell price. the target column, when there is one, is
+// hidden before any of them
+const ASSET_COLUMN_PRIORITY: [usize; 8] = [0, 3, 2, 1, 5, 6, 7, 4];
+
+// after the current price of a held asset whose last refresh failed
+const REFRESH_FAILED_MARK: &str = "*";
+
+// explains REFRESH_FAILED_MARK under a table of assets that has any
+pub fn refresh_failed_legend(assets: &[Asset]) -> Option<String> {
+    let oldest = assets
+        .iter()
+        .filter(|asset| is_asset_held(asset) && asset.refresh_failed_at.is_some())
+        .map(|asset| asset.price_updated_at)
+        .min()?;
+    Some(format!(
+        "{} the last refresh failed, so this is the price fetched {}",
+        REFRESH_FAILED_MARK,
+        match oldest {
+            Some(at) => format!(
+                "up to {}",
+                format_relative_time((Utc::now() - at).num_seconds())
+            ),
+            None => "before".to_string(),
+        }
+    ))
+}
+
+pub fn assets_table(assets: &[Asset]) -> Table {
+    let mut table = Table::new();
+
+    apply_table_display_settings(&mut table);
+
+    let mut header = vec![
+        "Ticker",
+        "Buy Price",
+        "Current Price",
+        "Percent Change",
+        "Sell Price",
+        "Quantity",
+        "Cost Basis",
+        "Days Held",
+    ];
+    // only once some asset has a target, after every other column
+    let targets = assets
+        .iter()
+        .any(|asset| asset.target_price_cents.is_some());
+    if targets {
+        header.push("To Target");
+    }
+    table.set_header(header);
+
+    let today = Local::now().date_naive();
+    // the best and worst held rows are bold, on top of any gain/loss colour
+    let (best, worst) = if display_settings().color {
+        best_and_worst_held(assets)
+    } else {
+        (None, None)
+    };
+    for (index, asset) in assets.iter().enumerate() {
+        // percent change - calculate on current price if held, calculate on sell price if sold
+        let change = percent_change(asset);
+        let mut row = vec![
+            // ticker
+            Cell::new(text_column(&asset.ticker)),
+            // buy price (formatted as money, in the asset's currency)
+            Cell::new(format_asset_money(asset, asset.buy_price_cents as i64)),
+            // current price (formatted as money) if held, else the current price is irrelevant
+            Cell::new(if is_asset_held(asset) && asset.price_from_cache {
+                format!(
+                    "{} (cached)",
+                    format_asset_money(asset, asset.current_price_cents as i64)
+                )
+            } else if is_asset_held(asset) && asset.refresh_failed_at.is_some() {
+                format!(
+                    "{} {}",
+                    format_asset_money(asset, asset.current_price_cents as i64),
+                    REFRESH_FAILED_MARK
+                )
+            } else if is_asset_held(asset) {
+                format_asset_money(asset, asset.current_price_cents as i64)
+            } else {
+                "N/A (sold)".to_string()
+            }),
+            gain_loss_cell(format_percent(change as f64), change as f64),
+            // sell price - show N/A if not sold
+            Cell::new(if is_asset_sold(asset) {
+                format_asset_money(asset, asset.sell_price_cents.unwrap() as i64)
+            } else {
+                "N/A (currently held)".to_string()
+            }),
+            Cell::new(asset.quantity),
+            // buy price per share times quantity
+            Cell::new(format_asset_money(asset, cost_basis(asset))),
+            days_held_cell(days_held(asset, today)),
+        ];
+        if targets {
+            // green when the price has to rise to reach it, red when to fall
+            let to_target = percent_to_target(asset);
+            row.push(gain_loss_cell(
+                format_optional_percent(to_target),
+                to_target.unwrap_or(0.0),
+            ));
+        }
+        if [best, worst].contains(&Some(index)) {
+            table.add_row(
+                row.into_iter()
+                    .map(|cell| cell.add_attribute(Attribute::Bold)),
+            );
+        } else {
+            table.add_row(row);
+        }
+    }
+    let mut priority = ASSET_COLUMN_PRIORITY.to_vec();
+    if targets {
+        priority.push(ASSET_COLUMN_PRIORITY.len());
+    }
+    fit_columns(&mut table, &priority);
+    table
+}
+
+pub fn print_sectors(assets: &[Asset], metadata: &MetadataCache) {
+    let sectors = sector_allocation(assets, metadata);
+    if sectors.is_empty() {
+        println!("No held assets.");
+        return;
+    }
+    let total: i64 = sectors.iter().map(|sector| sector.market_value).sum();
+
+    let mut table = Table::new();
+    apply_table_display_settings(&mut table);
+    table.set_header(vec!["Sector", "Market Value", "Weight", "Tickers"]);
+    for sector in sectors {
+        let weight = if total == 0 {
+            0.0
+        } else {
+            sector.market_value as f64 / total as f64 * 100.0
+        };
+        table.add_row(vec![
+            text_column(&sector.sector),
+            format_money(sector.market_value),
+            format!("{:.2}%", weight),
+            text_column(&sector.tickers.join(", ")),
+        ]);
+    }
+    println!("{table}");
+}
+
+// each sector's share of held market value as a bar, green or red by the
+// sector's unrealized gain or loss like the summary tables
+pub fn print_sector_chart(assets: &[Asset], metadata: &MetadataCache) {
+    let sectors = sector_allocation(assets, metadata);
+    if sectors.is_empty() {
+        println!("No held assets.");
+        return;
+    }
+    let total: i64 = sectors.iter().map(|sector| sector.market_value).sum();
+    let labels: Vec<String> = sectors
+        .iter()
+        .map(|sector| text_column(&sector.sector))
+        .collect();
+    let label_width = labels.iter().map(|label| label.width()).max().unwrap_or(0);
+    let color = display_settings().color;
+    for (sector, label) in sectors.iter().zip(labels) {
+        let weight = if total == 0 {
+            0.0
+        } else {
+            sector.market_value as f64 / total as f64 * 100.0
+        };
+        let bar = percent_bar(weight, BAR_WIDTH);
+        let change = sector.market_value - sector.buy_value;
+        let bar = if !color || change == 0 {
+            bar
+        } else if change > 0 {
+            bar.green().to_string()
+        } else {
+            bar.red().to_string()
+        };
+        // format! pads by chars, not by columns
+        let padding = " ".repeat(label_width - label.width());
+        println!("{}{}  {} {:>6.2}%", label, padding, bar, weight);
+    }
+}
+
+pub fn print_ranges(assets: &[Asset], metadata: &MetadataCache) {
+    let mut table = Table::new();
+    apply_table_display_settings(&mut table);
+    table.set_header(vec![
+        "Ticker",
+        "Current Price",
+        "52-Week Low",
+        "52-Week High",
+        "Position in Range",
+    ]);
+
+    let mut shown: HashSet<String> = HashSet::new();
+    for asset in assets.iter().filter(|asset| is_asset_held(asset)) {
+        if !shown.insert(asset.ticker.to_uppercase()) {
+            continue;
+        }
+        let range = metadata
+            .get(&asset.ticker)
+            .and_then(|entry| entry.fifty_two_week_range_cents);
+        let (low, high, position) = match range {
+            Some((low, high)) => (
+                format_money(low as i64),
+                format_money(high as i64),
+                position_in_range(asset.current_price_cents, low, high)
+                    .map(|percent| format!("{:.0}% of range", percent))
+                    .unwrap_or_else(|| "-".to_string()),
+            ),
+            None => ("-".to_string(), "-".to_string(), "-".to_string()),
+        };
+        table.add_row(vec![
+            text_column(&asset.ticker),
+            format_money(asset.current_price_cents as i64),
+            low,
+            high,
+            position,
+        ]);
+    }
+    println!("{table}");
+}
+
+// a gain cell, or "-" when there's nothing to have gained on
+fn optional_gain_cell(gain: Option<i64>) -> Cell {
+    match gain {
+        Some(gain) => gain_loss_cell(format_money_with_sign(gain), gain as f64),
+        None => Cell::new("-"),
+    }
+}
+
+// one row per ticker with sales matched to purchases by `method`, then the
+// individual lots of any ticker bought more than once
+pub fn print_cost_basis_report(assets: &[Asset], method: CostBasisMethod) {
+    if assets.is_empty() {
+        println!("No assets.");
+        return;
+    }
+    let tickers = ticker_cost_basis(assets, method);
+
+    let mut table = Table::new();
+    apply_table_display_settings(&mut table);
+    table.set_header(vec![
+        "Ticker",
+        "Total Shares",
+        "Total Cost",
+        "Per-Share Cost Basis",
+        "Current Value",
+        "Unrealized Gain",
+        "Realized Gain",
+        "Method",
+    ]);
+    for ticker in &tickers {
+        let held = ticker.shares_held > 0;
+        table.add_row(vec![
+            Cell::new(text_column(&ticker.ticker)),
+            Cell::new(ticker.shares_bought),
+            Cell::new(format_money(ticker.total_cost)),
+            // of the shares still held, or of all of them once everything's sold
+            Cell::new(if held {
+                format_money(per_share_cost(ticker.held_cost, ticker.shares_held))
+            } else {
+                format_money(per_share_cost(ticker.total_cost, ticker.shares_bought))
+            }),
+            Cell::new(if held {
+                format_money(ticker.held_value)
+            } else {
+                "-".to_string()
+            }),
+            optional_gain_cell(held.then(|| ticker.held_value - ticker.held_cost)),
+            optional_gain_cell((ticker.shares_sold > 0).then_some(ticker.realized_gain)),
+            Cell::new(method.label()),
+        ]);
+    }
+    println!("{table}");
+
+    for ticker in tickers.iter().filter(|ticker| ticker.lots.len() > 1) {
+        println!("{} lots:", ticker.ticker);
+        println!("{}", lots_table(assets, &ticker.lots));
+    }
+}
+
+// `cost`: what's been put into each ticker still held, and in total
+pub fn print_cost_summary(assets: &[Asset]) {
+    let costs = held_costs(assets);
+    if costs.is_empty() {
+        println!("Nothing is held.");
+        return;
+    }
+    let mut table = Table::new();
+    apply_table_display_settings(&mut table);
+    table.set_header(vec![
+        "Ticker",
+        "Shares",
+        "Total Cost",
+        "Average Cost",
+        "Bought",
+    ]);
+    for cost in &costs {
+        let bought = match (cost.first_bought, cost.last_bought) {
+            (Some(first), Some(last)) if first != last => format!("{} to {}", first, last),
+            (Some(first), _) => first.to_string(),
+            _ => "unknown".to_string(),
+        };
+        table.add_row(vec![
+            Cell::new(text_column(&cost.ticker)),
+            Cell::new(cost.shares),
+            Cell::new(format_money(cost.cost)),
+            Cell::new(format_money(cost.average_cost())),
+            Cell::new(bought),
+        ]);
+    }
+    table.add_row(vec![
+        Cell::new("Total"),
+        Cell::new(""),
+        Cell::new(format_money(costs.iter().map(|cost| cost.cost).sum())),
+        Cell::new(""),
+        Cell::new(""),
+    ]);
+    println!("{table}");
+}
+
+// `dca`: the held position next to what it would be after the buy. with no
+// held position there's only the new one to show
+pub fn print_dca_preview(before: Option<&HeldCost>, after: &HeldCost, price_cents: u32) {
+    let bought = after.shares - before.map_or(0, |before| before.shares);
+    println!(
+        "Buying {} of {} at {} for {}:",
+        describe_count(bought as usize, "share"),
+        after.ticker,
+        format_money(price_cents as i64),
+        format_money(bought as i64 * price_cents as i64)
+    );
+    let mut table = Table::new();
+    apply_table_display_settings(&mut table);
+    let rows = |cost: &HeldCost| {
+        [
+            cost.shares.to_string(),
+            format_money(cost.cost),
+            format_money(cost.average_cost()),
+        ]
+    };
+    let after_rows = rows(after);
+    match before {
+        Some(before) => {
+            table.set_header(vec!["", "Now", "After Buying"]);
+            for ((label, now), then) in ["Shares", "Total Cost", "Average Cost"]
+                .into_iter()
+                .zip(rows(before))
+                .zip(after_rows)
+            {
+                table.add_row(vec![label.to_string(), now, then]);
+            }
+        }
+        None => {
+            println!(
+                "{} isn't held, so this would be a new position.",
+                after.ticker
+            );
+            table.set_header(vec!["", "After Buying"]);
+            for (label, then) in ["Shares", "Total Cost", "Average Cost"]
+                .into_iter()
+                .zip(after_rows)
+            {
+                table.add_row(vec![label.to_string(), then]);
+            }
+        }
+    }
+    println!("{table}");
+    let break_even = after.average_cost();
+    let change = percent_increase(price_cents, break_even.clamp(0, u32::MAX as i64) as u32);
+    println!(
+        "Break-even price after buying: {}{}",
+        format_money(break_even),
+        if format_percent(change as f64) == "0.00%" {
+            String::new()
+        } else {
+            format!(
+                ", {:.2}% {} the price paid",
+                change.abs(),
+                if change > 0.0 { "above" } else { "below" }
+            )
+        }
+    );
+}
+
+// each lot's own basis and gain, whatever the method
+fn lots_table(assets: &[Asset], lots: &[usize]) -> Table {
+    let mut table = Table::new();
+    apply_table_display_settings(&mut table);
+    table.set_header(vec![
+        "Buy Date",
+        "Shares",
+        "Buy Price",
+        "Cost Basis",
+        "Sell Date",
+        "Sell Price",
+        "Gain",
+    ]);
+    let date_cell = |date: Option<NaiveDate>| match date {
+        Some(date) => Cell::new(date),
+        None => Cell::new("unknown"),
+    };
+    for asset in lots.iter().map(|index| &assets[*index]) {
+        let gain = realized_gain_loss(asset).unwrap_or(market_value(asset) - cost_basis(asset));
+        table.add_row(vec![
+            date_cell(asset.buy_date),
+            Cell::new(asset.quantity),
+            Cell::new(format_money(asset.buy_price_cents as i64)),
+            Cell::new(format_money(cost_basis(asset))),
+            if is_asset_sold(asset) {
+                date_cell(asset.sell_date)
+            } else {
+                Cell::new("held")
+            },
+            Cell::new(match asset.sell_price_cents {
+                Some(price) => format_money(price as i64),
+                None => "-".to_string(),
+            }),
+            gain_loss_cell(format_money_with_sign(gain), gain as f64),
+        ]);
+    }
+    table
+}
+
+pub fn print_fundamentals(assets: &[Asset], metadata: &MetadataCache) {
+    let mut table = Table::new();
+    apply_table_display_settings(&mut table);
+    table.set_header(vec!["Ticker", "P/E Ratio", "Market Cap"]);
+
+    let mut shown: HashSet<String> = HashSet::new();
+    for asset in assets.iter().filter(|asset| is_asset_held(asset)) {
+        if !shown.insert(asset.ticker.to_uppercase()) {
+            continue;
+        }
+        let fundamentals = metadata
+            .get(&asset.ticker)
+            .and_then(|entry| entry.fundamentals.as_ref());
+        table.add_row(vec![
+            text_column(&asset.ticker),
+            fundamentals
+                .and_then(|fundamentals| fundamentals.pe_ratio)
+                .map(|pe| format!("{:.2}", pe))
+                .unwrap_or_else(|| "n/a".to_string()),
+            fundamentals
+                .and_then(|fundamentals| fundamentals.market_cap)
+                .map(format_market_cap)
+                .unwrap_or_else(|| "n/a".to_string()),
+        ]);
+    }
+    println!("{table}");
+}
+
+// `yield`: trailing dividends over the current price and over the average
+// price paid, n/a for tickers that pay nothing
+pub fn print_yields(assets: &[Asset], metadata: &MetadataCache) {
+    let yields = ticker_yields(assets, metadata);
+    if yields.is_empty() {
+        println!("Nothing is held.");
+        return;
+    }
+    let mut table = Table::new();
+    apply_table_display_settings(&mut table);
+    table.set_header(vec![
+        "Ticker",
+        "Dividends (12 Months)",
+        "Current Price",
+        "Yield",
+        "Average Cost",
+        "Yield on Cost",
+    ]);
+    let percent =
+        |percent: Option<f64>| percent.map_or("n/a".to_string(), |p| format!("{:.2}%", p));
+    for ticker in &yields {
+        table.add_row(vec![
+            text_column(&ticker.ticker),
+            ticker.dividend_cents.map_or("n/a".to_string(), |cents| {
+                format_money(cents.round() as i64)
+            }),
+            format_money(ticker.price_cents as i64),
+            percent(ticker.yield_percent()),
+            format_money(ticker.average_cost),
+            percent(ticker.yield_on_cost_percent()),
+        ]);
+    }
+    println!("{table}");
+}
+
+// "$135.00", or "10% below buy ($135.00)" for a percent stop
+pub fn describe_stop_loss(stop: StopLoss, buy_price_cents: u32) -> String {
+    let price = format_money(stop.price_cents(buy_price_cents) as i64);
+    match stop {
+        StopLoss::Price(_) => price,
+        StopLoss::Percent(percent) => format!("{}% below buy ({})", percent, price),
+    }
+}
+
+// `stops`: the held lots with a stop-loss, breached ones flagged until
+// they're acknowledged
+pub fn print_stops(assets: &[Asset]) {
+    let stopped: Vec<&Asset> = assets
+        .iter()
+        .filter(|asset| asset.stop_loss.is_some() && is_asset_held(asset))
+        .collect();
+    if stopped.is_empty() {
+        println!("No held assets have a stop-loss. Set one with 'stop [ticker]'.");
+        return;
+    }
+    let mut table = Table::new();
+    apply_table_display_settings(&mut table);
+    table.set_header(vec![
+        "Ticker",
+        "Quantity",
+        "Buy Price",
+        "Stop",
+        "Current Price",
+        "To Stop",
+        "Status",
+    ]);
+    let mut breached = 0;
+    for asset in stopped {
+        let Some(stop) = asset.stop_loss else {
+            continue;
+        };
+        let stop_price = stop.price_cents(asset.buy_price_cents);
+        let status = match asset.stop_breached_at {
+            None => Cell::new("active"),
+            Some(at) => {
+                breached += 1;
+                let cell = Cell::new(format!(
+                    "BREACHED {}",
+                    at.with_timezone(&Local).date_naive()
+                ));
+                if display_settings().color {
+                    cell.fg(Color::Red)
+                } else {
+                    cell
+                }
+            }
+        };
+        table.add_row(vec![
+            Cell::new(text_column(&asset.ticker)),
+            Cell::new(asset.quantity),
+            Cell::new(format_money(asset.buy_price_cents as i64)),
+            Cell::new(describe_stop_loss(stop, asset.buy_price_cents)),
+            Cell::new(format_money(asset.current_price_cents as i64)),
+            Cell::new(if asset.current_price_cents > 0 {
+                format_percent(percent_increase(asset.current_price_cents, stop_price) as f64)
+            } else {
+                "-".to_string()
+            }),
+            status,
+        ]);
+    }
+    println!("{table}");
+    if breached > 0 {
+        println!(
+            "{} breached. Acknowledge with 'stops ack [ticker]'.",
+            describe_count(breached, "stop")
+        );
+    }
+}
+
+pub fn describe_days_until(days: i64) -> String {
+    match days {
+        0 => "today".to_string(),
+        1 => "tomorrow".to_string(),
+        _ => format!("in {} days", days),
+    }
+}
+
+// notes any holdings reporting earnings within the configured window
+pub fn print_earnings_warnings(
+    assets: &[Asset],
+    metadata: &MetadataCache,
+    config: &Config,
+    today: NaiveDate,
+) {
+    for (ticker, date) in upcoming_earnings(assets, metadata, today) {
+        let days = (date - today).num_days();
+        if days <= config.earnings_warning_days {
+            println!(
+                "⚠ {} reports earnings {}",
+                ticker,
+                describe_days_until(days)
+            );
+        }
+    }
+}
+
+pub fn print_earnings(assets: &[Asset], metadata: &MetadataCache, today: NaiveDate) {
+    let mut table = Table::new();
+    apply_table_display_settings(&mut table);
+    table.set_header(vec!["Ticker", "Earnings Date", "Reports"]);
+    for (ticker, date) in upcoming_earnings(assets, metadata, today) {
+        table.add_row(vec![
+            text_column(&ticker),
+            date.to_string(),
+            describe_days_until((date - today).num_days()),
+        ]);
+    }
+    println!("{table}");
+}
+
+pub fn terminal_width() -> usize {
+    crossterm::terminal::size()
+        .map(|(columns, _)| columns as usize)
+        .unwrap_or(80)
+}
+
+pub fn print_lint(assets: &[Asset]) {
+    let warnings = lint_assets(assets, Local::now().date_naive());
+    if warnings.is_empty() {
+        println!("No problems found.");
+        return;
+    }
+    let mut table = Table::new();
+    apply_table_display_settings(&mut table);
+    table.set_header(vec!["Ticker", "Problem", "Suggested Fix"]);
+    for warning in &warnings {
+        table.add_row(vec![&warning.ticker, &warning.problem, &warning.suggestion]);
+    }
+    println!("{table}");
+}
+
+// each goal measured against the held market value. savings assume
+// `annual_return_percent`, and the years to go the portfolio's own IRR
+pub fn print_goals(
+    goals: &[Goal],
+    analytics: &PortfolioAnalytics,
+    annual_return_percent: f64,
+    today: NaiveDate,
+) {
+    if goals.is_empty() {
+        println!("No goals yet. Add one with 'goal add <name> <target> <date>'.");
+        return;
+    }
+    let mut table = Table::new();
+    apply_table_display_settings(&mut table);
+    table.set_header(vec![
+        "Goal",
+        "Target",
+        "By",
+        "Progress",
+        "Gap",
+        "Monthly Savings Needed",
+        "Years at Current Growth",
+    ]);
+    for goal in goals {
+        let progress = goal_progress(
+            goal,
+            analytics.total_value,
+            annual_return_percent,
+            analytics.irr,
+            today,
+        );
+        table.add_row(vec![
+            Cell::new(text_column(&goal.name)),
+            Cell::new(format_money(goal.target_value_cents as i64)),
+            Cell::new(goal.target_date),
+            Cell::new(format!("{:.1}%", progress.progress_percent)),
+            Cell::new(format_money(progress.gap)),
+            Cell::new(match progress.monthly_savings {
+                _ if progress.gap == 0 => "reached".to_string(),
+                Some(cents) => format_money(cents),
+                None => "date passed".to_string(),
+            }),
+            Cell::new(match progress.years_to_goal {
+                _ if progress.gap == 0 => "reached".to_string(),
+                Some(years) => format!("{:.1}", years),
+                None => "never".to_string(),
+            }),
+        ]);
+    }
+    println!("{table}");
+    println!(
+        "Savings assume a {} annual return (set goal-return to change it); the years use {}.",
+        format_percent(annual_return_percent).trim_start_matches('+'),
+        match analytics.irr {
+            Some(irr) => format!("the portfolio's IRR of {}", format_percent(irr * 100.0)),
+            None => "the portfolio's IRR, which needs buy dates".to_string(),
+        }
+    );
+}
+
+// green for good grades, yellow for middling and red for poor ones
+fn grade_cell(grade: Option<Grade>) -> Cell {
+    let Some(grade) = grade else {
+        return Cell::new("-");
+    };
+    let cell = Cell::new(grade.letter());
+    if !display_settings().color {
+        return cell;
+    }
+    cell.fg(match grade {
+        Grade::A | Grade::B => Color::Green,
+        Grade::C => Color::Yellow,
+        Grade::D | Grade::F => Color::Red,
+    })
+}
+
+// half-height blocks for the modules, two rows to a line, so the code is
+// about square in a terminal. light modules are the blocks, which reads the
+// right way round on a dark background. the border sits outside the quiet
+// zone, and the table isn't wrapped since that would break the code up
+pub fn qr_code_table(code: &QrCode) -> Table {
+    let image = code
+        .render::<Dense1x2>()
+        .dark_color(Dense1x2::Light)
+        .light_color(Dense1x2::Dark)
+        .build();
+    let mut table = Table::new();
+    apply_table_display_settings(&mut table);
+    table.set_content_arrangement(ContentArrangement::Disabled);
+    table.add_row(vec![image]);
+    table
+}
+
+pub fn print_qr_code(code: &QrCode) {
+    let table = qr_code_table(code);
+    println!("{table}");
+    let width = table
+        .column_max_content_widths()
+        .first()
+        .copied()
+        .unwrap_or(0) as usize;
+    if table_width().is_some_and(|terminal| width + 4 > terminal) {
+        println!("⚠ The code is wider than the terminal; widen it to scan the code.");
+    }
+}
+
+pub fn print_health(score: &HealthScore) {
+    let mut table = Table::new();
+    apply_table_display_settings(&mut table);
+    table.set_header(vec!["Dimension", "Grade", "Why"]);
+    for dimension in score.dimensions.iter().chain([&score.overall]) {
+        table.add_row(vec![
+            Cell::new(dimension.dimension),
+            grade_cell(dimension.grade),
+            Cell::new(&dimension.explanation),
+        ]);
+    }
+    println!("{table}");
+}
+
+pub fn print_portfolio_size(portfolio: &Portfolio) {
+    let mut table = Table::new();
+    apply_table_display_settings(&mut table);
+    table.set_header(vec!["Measure", "Size"]);
+    let json_size = portfolio.to_json_str().map_or_else(
+        |e| format!("unknown ({})", e),
+        |json| match json.len() {
+            len if len < 1024 => format_bytes(len),
+            len => format!("{} ({} bytes)", format_bytes(len), len),
+        },
+    );
+    table.add_row(vec![
+        "Assets".to_string(),
+        portfolio.assets.len().to_string(),
+    ]);
+    table.add_row(vec![
+        "Distinct tickers".to_string(),
+        distinct_tickers(&portfolio.assets).len().to_string(),
+    ]);
+    table.add_row(vec![
+        "Value history points".to_string(),
+        portfolio.value_history.len().to_string(),
+    ]);
+    // neither is part of the data model yet
+    table.add_row(vec!["Dividend records", "not tracked"]);
+    table.add_row(vec!["Transaction log entries", "not tracked"]);
+    table.add_row(vec!["Saved JSON size".to_string(), json_size]);
+    table.add_row(vec![
+        "Estimated memory".to_string(),
+        format_bytes(portfolio.estimated_memory_bytes()),
+    ]);
+    println!("{table}");
+}
+
+// files from before prices were settled as per share may have totals in
+// them, which would make every figure off by the quantity
+pub fn warn_about_total_prices(portfolio: &Portfolio) {
+    let suspects = portfolio.likely_total_prices();
+    if suspects.is_empty() {
+        return;
+    }
+    println!(
+        "⚠ This file is from before buy prices were per share, and {} look{} like the total paid:",
+        describe_count(suspects.len(), "asset"),
+        if suspects.len() == 1 { "s" } else { "" }
+    );
+    for &index in &suspects {
+        let asset = &portfolio.assets[index];
+        println!(
+            "  {}: {} for {} shares, i.e. {} per share",
+            asset.ticker,
+            format_money(asset.buy_price_cents as i64),
+            asset.quantity,
+            format_money(per_share_price(asset.buy_price_cents, asset.quantity) as i64)
+        );
+    }
+    println!("Enter 'convert-totals' to change them to per-share prices.");
+}
+
+// e.g. "1 asset" or "3 assets"
+pub fn describe_count(count: usize, noun: &str) -> String {
+    format!("{} {}{}", count, noun, if count == 1 { "" } else { "s" })
+}
+
+// for bug reports and for knowing whether a shared portfolio file will load
+pub fn version_text() -> String {
+    format!(
+        "portfolio-tracker {}\n\
+         portfolio file format {} (older files are converted when loaded)\n\
+         yahoo_finance_api {}\n\
+         serde_json {}\n\
+         built {}",
+        env!("CARGO_PKG_VERSION"),
+        PRICE_BASIS_PER_SHARE,
+        env!("YAHOO_FINANCE_API_VERSION"),
+        env!("SERDE_JSON_VERSION"),
+        env!("BUILD_DATE")
+    )
+}
+
+pub fn print_help() {
+    println!("{}", help_text());
+}
+
+// printed on every load whatever the settings, since nothing else points
+// them out unprompted
+pub fn print_duplicate_warnings(assets: &[Asset]) {
+    let groups = possible_duplicates(assets);
+    for group in &groups {
+        let asset = &assets[group[0]];
+        let mut entries: Vec<String> = group.iter().map(|index| (index + 1).to_string()).collect();
+        let last = entries.pop().unwrap_or_default();
+        println!(
+            "WARNING: Possible duplicate asset: {} {} @ {} (entries {} and {})",
+            asset.ticker,
+            describe_count(asset.quantity as usize, "share"),
+            format_money(asset.buy_price_cents as i64),
+            entries.join(", "),
+            last
+        );
+    }
+    if !groups.is_empty() {
+        println!("Run 'lint' to review them or 'remove' to delete the extra entries.");
+    }
+}
+
+// `format` is what the file was read as, e.g. "TOML"
+pub fn print_loaded(
+    portfolio: &Portfolio,
+    analytics: &PortfolioAnalytics,
+    path: &Path,
+    format: &str,
+    config: &Config,
+) {
+    if !config.summary_on_load {
+        return;
+    }
+    let count = portfolio.assets.len();
+    println!(
+        "Loaded {} asset{} from {} ({})",
+        count,
+        if count == 1 { "" } else { "s" },
+        path.display(),
+        format
+    );
+    print_summary(portfolio, analytics);
+    print_stale_warning(&portfolio.assets, config);
+}
+
+pub fn print_stale_warning(assets: &[Asset], config: &Config) {
+    let stale = stale_held_assets(assets, Utc::now(), config.stale_hours);
+    if stale > 0 {
+        println!(
+            "⚠ {} held asset{} ha{} prices older than {} hours. Run 'refresh' to update.",
+            stale,
+            if stale == 1 { "" } else { "s" },
+            if stale == 1 { "s" } else { "ve" },
+            config.stale_hours
+        );
+    }
+}
+
+pub fn print_settings(config: &Config) {
+    let mut table = Table::new();
+    apply_table_display_settings(&mut table);
+    table.set_header(vec!["Setting", "Value", "Valid Values"]);
+    for ((key, value), (_, valid)) in config.settings().into_iter().zip(SETTINGS) {
+        table.add_row(vec![key.to_string(), value, valid.to_string()]);
+    }
+    println!("{table}");
+}
+
+// where requests actually go, taking the environment into account
+pub fn print_connection(config: &Config) {
+    println!("Requests go to {}", redact_url(config.effective_api_url()));
+    println!("User agent: {}", config.effective_user_agent());
+    match proxy_url(config.proxy.as_deref(), |name| env::var(name).ok()) {
+        Some((url, source)) => println!("Proxy: {} (from {})", redact_url(&url), source),
+        None => println!("Proxy: none"),
+    }
+}
+
+// the file opened on start, from --file, the environment or the settings
+pub fn print_portfolio_file(file: Option<(PathBuf, &str)>) {
+    match file {
+        Some((path, source)) => println!("Portfolio file: {} (from {})", path.display(), source),
+        None => println!("Portfolio file: none"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn format_market_cap_suffixes() {
+        assert_eq!(format_market_cap(2_910_000_000_000), "$2.91T");
+        assert_eq!(format_market_cap(58_300_000_000), "$58.3B");
+        assert_eq!(format_market_cap(512_400_000), "$512M");
+        assert_eq!(format_market_cap(999), "$999");
+    }
+
+    #[test]
+    fn copied_tables_are_plain_text() {
+        let mut table = Table::new();
+        apply_table_display_settings(&mut table);
+        table.set_header(vec!["Ticker", "Return %"]);
+        table.add_row(vec![
+            Cell::new("AAPL"),
+            gain_loss_cell("+10.00%".to_string(), 10.0).add_attribute(Attribute::Bold),
+        ]);
+        table
+            .column_mut(1)
+            .unwrap()
+            .set_constraint(ColumnConstraint::Hidden);
+        assert_eq!(copy_text(table), " Ticker  Return %\n AAPL    +10.00%");
+    }
+
+    #[test]
+    fn empty_summary_is_all_zeroes() {
+        let portfolio = Portfolio::default();
+        let today = NaiveDate::from_ymd_opt(2024, 1, 2).unwrap();
+        let analytics = PortfolioAnalytics::compute(&portfolio, &MetadataCache::default(), today);
+        let text = summary_copy_text(&portfolio, &analytics);
+        let row: Vec<&str> = text.lines().nth(2).unwrap().split_whitespace().collect();
+        assert_eq!(row, ["$0.00", "$0.00", "$0.00", "0.00%"], "{}", text);
+        assert!(!text.contains("Portfolio age"));
+    }
+
+    #[test]
+    fn qr_codes_are_square_blocks() {
+        let code = QrCode::new("Portfolio \"main\" │ Value: $12,345.67").unwrap();
+        let image = qr_code_table(&code).to_string();
+        let lines: Vec<&str> = image.lines().collect();
+        // the modules and a quiet zone of 4 on each side, half as many lines
+        // as there are modules down, plus the border
+        let modules = code.width() + 8;
+        assert_eq!(lines.len(), modules.div_ceil(2) + 2);
+        let widths: HashSet<usize> = lines.iter().map(|line| line.chars().count()).collect();
+        assert_eq!(widths, HashSet::from([modules + 4]));
+    }
+
+    #[test]
+    fn dividend_reinvestment_line() {
+        let date = NaiveDate::from_ymd_opt(2024, 1, 2).unwrap();
+        let result = DividendReinvestment {
+            start_date: date,
+            start_price: 10_000,
+            end_date: date,
+            end_price: 11_520,
+            price_return_percent: 15.2,
+            total_return_percent: 18.7,
+            shares: 1.03,
+            dividends_reinvested: 4,
+        };
+        assert_eq!(
+            format_dividend_reinvestment("AAPL", &result),
+            "AAPL: Price return 15.2% │ Total return (DRIP): 18.7% │ Dividend contribution: +3.5pp"
+        );
+    }
+
+    #[test]
+    fn format_relative_time_units() {
+        assert_eq!(format_relative_time(30), "just now");
+        assert_eq!(format_relative_time(3 * 3600 + 600), "3h ago");
+        assert_eq!(format_relative_time(2 * 86400), "2d ago");
+        assert_eq!(format_relative_time(15 * 86400), "2w ago");
+    }
+
+    #[test]
+    fn truncate_to_width_adds_ellipsis() {
+        assert_eq!(truncate_to_width("short", 10), "short");
+        assert_eq!(truncate_to_width("a long headline", 7), "a long…");
+        assert_eq!(truncate_to_width("héllo wörld", 6), "héllo…");
+        assert_eq!(
+            truncate_to_width("International Business Machines Corporation", 20),
+            "International Busin…"
+        );
+    }
+
+    #[test]
+    fn truncate_to_width_counts_terminal_columns() {
+        // each of these takes two columns
+        assert_eq!(truncate_to_width("株式会社", 8), "株式会社");
+        assert_eq!(truncate_to_width("株式会社", 7), "株式会…");
+        assert_eq!(truncate_to_width("株式会社", 6), "株式…");
+        // a combining accent takes none
+        assert_eq!(truncate_to_width("cafe\u{301}s", 5), "cafe\u{301}s");
+    }
+
+    #[test]
+    fn format_money_dollars() {
+        assert_eq!(format_money(10000), "$100.00");
+    }
+
+    #[test]
+    fn format_money_single_cent() {
+        assert_eq!(format_money(1), "$0.01");
+    }
+
+    #[test]
+    fn format_money_other_currency() {
+        assert_eq!(format_money_with_symbol(-5050, "€"), "-€50.50");
+    }
+
+    #[test]
+    fn format_money_with_sign_marks_gains() {
+        assert_eq!(format_money_with_sign(1250), "+$12.50");
+        assert_eq!(format_money_with_sign(-1250), "-$12.50");
+        assert_eq!(format_money_with_sign(0), "$0.00");
+    }
+
+    #[test]
+    fn format_money_negative() {
+        assert_eq!(format_money(-5050), "-$50.50");
+    }
+
+    #[test]
+    fn percent_bars_round_to_the_nearest_character() {
+        assert_eq!(percent_bar(50.0, 4), "██░░");
+        assert_eq!(percent_bar(12.4, 4), "░░░░");
+        assert_eq!(percent_bar(100.0, 4), "████");
+        assert_eq!(percent_bar(130.0, 4), "████");
+    }
+
+    #[test]
+    fn format_bytes_scales() {
+        assert_eq!(format_bytes(512), "512 B");
+        assert_eq!(format_bytes(12_595), "12.3 KB");
+        assert_eq!(format_bytes(3 * 1024 * 1024), "3.0 MB");
+    }
+
+    #[test]
+    fn percents_are_signed_without_negative_zero() {
+        assert_eq!(format_percent(12.5), "+12.50%");
+        assert_eq!(format_percent(-3.256), "-3.26%");
+        assert_eq!(format_percent(-0.001), "0.00%");
+        assert_eq!(format_optional_percent(None), "-");
+    }
+
+    #[test]
+    fn narrow_tables_hide_the_least_important_columns() {
+        let mut table = Table::new();
+        table.set_header(vec!["Ticker", "Change", "Notes"]);
+        table.add_row(vec!["AAPL", "+1.00%", "bought on a whim"]);
+        let hidden = |table: &Table| -> Vec<bool> {
+            table
+                .column_iter()
+                .map(|column| column.is_hidden())
+                .collect()
+        };
+
+        hide_columns_to_fit(&mut table, &[0, 2, 1], 80);
+        assert_eq!(hidden(&table), vec![false, false, false]);
+        // the notes column is second in priority, so change goes first
+        hide_columns_to_fit(&mut table, &[0, 2, 1], 30);
+        assert_eq!(hidden(&table), vec![false, true, false]);
+        hide_columns_to_fit(&mut table, &[0, 2, 1], 5);
+        assert_eq!(hidden(&table), vec![false, true, true]);
+    }
+}
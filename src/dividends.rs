@@ -0,0 +1,111 @@
+// total return with dividends reinvested for `total-return-with-dividends`:
+// one share bought at the first close, with every dividend buying more shares
+// at the close on its ex-dividend date, compared with holding the share alone
+use crate::quotes::DividendHistory;
+use chrono::NaiveDate;
+
+#[derive(Debug, PartialEq)]
+pub struct DividendReinvestment {
+    pub start_date: NaiveDate,
+    pub start_price: u32,
+    pub end_date: NaiveDate,
+    pub end_price: u32,
+    pub price_return_percent: f64,
+    pub total_return_percent: f64,
+    // what the one share has grown to
+    pub shares: f64,
+    pub dividends_reinvested: usize,
+}
+
+impl DividendReinvestment {
+    // percentage points the dividends added to the return
+    pub fn dividend_contribution(&self) -> f64 {
+        self.total_return_percent - self.price_return_percent
+    }
+}
+
+// None without at least two closes to measure a return between. dividends
+// from before the first close or after the last are left out
+pub fn reinvest_dividends(history: &DividendHistory) -> Option<DividendReinvestment> {
+    let closes: Vec<&(NaiveDate, u32)> = history
+        .closes
+        .iter()
+        .filter(|(_, close)| *close > 0)
+        .collect();
+    let (&&(start_date, start_price), &&(end_date, end_price)) = (closes.first()?, closes.last()?);
+    if start_date == end_date {
+        return None;
+    }
+
+    let mut shares = 1.0;
+    let mut dividends_reinvested = 0;
+    for (date, cents) in &history.dividends {
+        if *date <= start_date || *date > end_date {
+            continue;
+        }
+        // the close on the ex-dividend date, or the last one before it when
+        // there's no bar for that day
+        let index = closes.partition_point(|(close_date, _)| close_date <= date);
+        let Some((_, price)) = index.checked_sub(1).map(|index| closes[index]) else {
+            continue;
+        };
+        shares += shares * cents / *price as f64;
+        dividends_reinvested += 1;
+    }
+
+    let start = start_price as f64;
+    Some(DividendReinvestment {
+        start_date,
+        start_price,
+        end_date,
+        end_price,
+        price_return_percent: (end_price as f64 - start) / start * 100.0,
+        total_return_percent: (shares * end_price as f64 - start) / start * 100.0,
+        shares,
+        dividends_reinvested,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, month, day).unwrap()
+    }
+
+    #[test]
+    fn dividends_buy_more_shares() {
+        let history = DividendHistory {
+            closes: vec![
+                (date(1, 2), 10_000),
+                (date(3, 1), 10_000),
+                (date(6, 3), 11_000),
+            ],
+            // the last is paid on a day without a bar, so it's reinvested
+            // at the close before. the first predates the history
+            dividends: vec![
+                (date(1, 1), 500.0),
+                (date(3, 1), 100.0),
+                (date(5, 20), 100.0),
+            ],
+        };
+        let result = reinvest_dividends(&history).unwrap();
+        assert_eq!(result.dividends_reinvested, 2);
+        // 1 share, then 1.01 at $100, then 1% more again at $100
+        assert!((result.shares - 1.0201).abs() < 1e-9, "{}", result.shares);
+        assert!((result.price_return_percent - 10.0).abs() < 1e-9);
+        assert!((result.total_return_percent - 12.211).abs() < 1e-9);
+        assert!((result.dividend_contribution() - 2.211).abs() < 1e-9);
+    }
+
+    #[test]
+    fn no_return_without_two_closes() {
+        let history = DividendHistory {
+            closes: vec![(date(1, 2), 10_000)],
+            dividends: vec![],
+        };
+        assert_eq!(reinvest_dividends(&history), None);
+        assert_eq!(reinvest_dividends(&DividendHistory::default()), None);
+    }
+}
@@ -0,0 +1,101 @@
+use std::error::Error;
+use std::fmt;
+use yahoo_finance_api as yf;
+
+// the error type returned by anything in the crate that can fail
+#[derive(Debug)]
+pub enum PortfolioError {
+    // a request to the quote API failed or returned something unusable
+    ApiError(String),
+    // a file or response couldn't be parsed
+    ParseError(String),
+    IoError(std::io::Error),
+    // e.g. a gzip stream that's been truncated
+    Corrupted(String),
+    // e.g. an unknown ticker
+    NotFound(String),
+    // the user entered something that doesn't make sense
+    InvalidInput(String),
+    // the user backed out of a multi-step prompt
+    Cancelled,
+}
+
+impl fmt::Display for PortfolioError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PortfolioError::ApiError(message) => write!(f, "API error: {}", message),
+            PortfolioError::ParseError(message) => write!(f, "parse error: {}", message),
+            PortfolioError::IoError(e) => write!(f, "I/O error: {}", e),
+            PortfolioError::Corrupted(message) => write!(f, "file appears corrupted: {}", message),
+            PortfolioError::NotFound(message) => write!(f, "not found: {}", message),
+            PortfolioError::InvalidInput(message) => write!(f, "invalid input: {}", message),
+            PortfolioError::Cancelled => write!(f, "cancelled"),
+        }
+    }
+}
+
+impl Error for PortfolioError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            PortfolioError::IoError(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for PortfolioError {
+    fn from(e: std::io::Error) -> Self {
+        PortfolioError::IoError(e)
+    }
+}
+
+impl From<serde_json::Error> for PortfolioError {
+    fn from(e: serde_json::Error) -> Self {
+        PortfolioError::ParseError(e.to_string())
+    }
+}
+
+impl From<toml::de::Error> for PortfolioError {
+    fn from(e: toml::de::Error) -> Self {
+        PortfolioError::ParseError(e.to_string())
+    }
+}
+
+impl From<toml::ser::Error> for PortfolioError {
+    fn from(e: toml::ser::Error) -> Self {
+        PortfolioError::ParseError(e.to_string())
+    }
+}
+
+// without the ticker, which api::yahoo_error adds where it's known
+impl From<yf::YahooError> for PortfolioError {
+    fn from(e: yf::YahooError) -> Self {
+        match e {
+            yf::YahooError::FetchFailed(status) if status.starts_with("404") => {
+                PortfolioError::NotFound("no such symbol".to_string())
+            }
+            yf::YahooError::FetchFailed(status) => {
+                PortfolioError::ApiError(format!("Yahoo returned {}", status))
+            }
+            yf::YahooError::EmptyDataSet => PortfolioError::NotFound("no price data".to_string()),
+            e => PortfolioError::ApiError(e.to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn errors_convert_with_their_messages() {
+        let e: PortfolioError = serde_json::from_str::<u32>("\"x\"").unwrap_err().into();
+        assert!(matches!(&e, PortfolioError::ParseError(message) if message.contains("line 1")));
+        let e: PortfolioError = std::io::Error::from(std::io::ErrorKind::NotFound).into();
+        assert!(e.source().is_some());
+        let e: PortfolioError = yf::YahooError::FetchFailed("404 Not Found".to_string()).into();
+        assert!(matches!(e, PortfolioError::NotFound(_)));
+        let e: PortfolioError = yf::YahooError::FetchFailed("503".to_string()).into();
+        assert_eq!(e.to_string(), "API error: Yahoo returned 503");
+    }
+}
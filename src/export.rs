@@ -0,0 +1,628 @@
+// writing the portfolio out for other tools. each format is rendered to
+// bytes first so the renderers can be tested without touching the disk
+use crate::config::Config;
+use crate::display::{format_money, format_money_with_sign, format_percent};
+use crate::error::PortfolioError;
+use crate::metadata::MetadataCache;
+use crate::portfolio::{
+    cost_basis, is_asset_held, market_value, percent_increase, realized_gain_loss, Asset, Portfolio,
+};
+use chrono::NaiveDate;
+use std::fs;
+use std::io::{Read, Write};
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExportFormat {
+    Json,
+    // the same columns `import csv` reads, so exports can be imported again
+    Csv,
+    Ledger,
+    Html,
+    Pdf,
+    Xlsx,
+}
+
+impl ExportFormat {
+    pub fn from_name(name: &str) -> Option<ExportFormat> {
+        match name.to_lowercase().as_str() {
+            "json" => Some(ExportFormat::Json),
+            "csv" => Some(ExportFormat::Csv),
+            "ledger" => Some(ExportFormat::Ledger),
+            "html" => Some(ExportFormat::Html),
+            "pdf" => Some(ExportFormat::Pdf),
+            "xlsx" => Some(ExportFormat::Xlsx),
+            _ => None,
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Json => "json",
+            ExportFormat::Csv => "csv",
+            ExportFormat::Ledger => "ledger",
+            ExportFormat::Html => "html",
+            ExportFormat::Pdf => "pdf",
+            ExportFormat::Xlsx => "xlsx",
+        }
+    }
+}
+
+pub const EXPORT_FORMAT_NAMES: &str = "json, csv, ledger, html, pdf, xlsx";
+
+pub fn render(
+    format: ExportFormat,
+    portfolio: &Portfolio,
+    today: NaiveDate,
+) -> Result<Vec<u8>, PortfolioError> {
+    match format {
+        ExportFormat::Json => Ok(portfolio.to_json_str()?.into_bytes()),
+        ExportFormat::Csv => Ok(to_csv(portfolio).into_bytes()),
+        ExportFormat::Ledger => Ok(to_ledger(portfolio, today).into_bytes()),
+        ExportFormat::Html => Ok(to_html(portfolio, today).into_bytes()),
+        ExportFormat::Pdf => Ok(to_pdf(portfolio, today)),
+        ExportFormat::Xlsx => to_xlsx(portfolio),
+    }
+}
+
+pub fn export_to_path(
+    format: ExportFormat,
+    portfolio: &Portfolio,
+    path: &Path,
+    today: NaiveDate,
+) -> Result<(), PortfolioError> {
+    let bytes = render(format, portfolio, today)?;
+    fs::write(path, bytes)?;
+    Ok(())
+}
+
+// "1234.56", without a currency symbol, for formats other programs read
+fn decimal(cents: i64) -> String {
+    let sign = if cents < 0 { "-" } else { "" };
+    let cents = cents.unsigned_abs();
+    format!("{}{}.{:02}", sign, cents / 100, cents % 100)
+}
+
+fn optional_date(date: Option<NaiveDate>) -> String {
+    date.map_or_else(String::new, |date| date.to_string())
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+pub fn to_csv(portfolio: &Portfolio) -> String {
+    let mut csv = String::from("ticker,buy_price,quantity,buy_date,sell_price,sell_date\n");
+    for asset in &portfolio.assets {
+        csv.push_str(&format!(
+            "{},{},{},{},{},{}\n",
+            csv_field(&asset.ticker),
+            decimal(asset.buy_price_cents as i64),
+            asset.quantity,
+            optional_date(asset.buy_date),
+            asset
+                .sell_price_cents
+                .map_or_else(String::new, |price| decimal(price as i64)),
+            optional_date(asset.sell_date),
+        ));
+    }
+    csv
+}
+
+// every lot as a purchase, and every sold lot as a sale, in ledger-cli's
+// format. an unknown date becomes the export date with a comment saying so
+pub fn to_ledger(portfolio: &Portfolio, today: NaiveDate) -> String {
+    let mut journal = String::new();
+    for asset in &portfolio.assets {
+        let header = |date: Option<NaiveDate>, description: &str| match date {
+            Some(date) => format!(
+                "{} {} {}\n",
+                date.format("%Y/%m/%d"),
+                description,
+                asset.ticker
+            ),
+            None => format!(
+                "{} {} {} ; date unknown\n",
+                today.format("%Y/%m/%d"),
+                description,
+                asset.ticker
+            ),
+        };
+        journal.push_str(&header(asset.buy_date, "Buy"));
+        journal.push_str(&format!(
+            "    Assets:Brokerage    {} \"{}\" @ ${}\n    Assets:Cash\n\n",
+            asset.quantity,
+            asset.ticker,
+            decimal(asset.buy_price_cents as i64)
+        ));
+        if let Some(sell_price) = asset.sell_price_cents {
+            journal.push_str(&header(asset.sell_date, "Sell"));
+            journal.push_str(&format!(
+                "    Assets:Brokerage    -{} \"{}\" @ ${}\n    Assets:Cash\n\n",
+                asset.quantity,
+                asset.ticker,
+                decimal(sell_price as i64)
+            ));
+        }
+    }
+    journal
+}
+
+// the table the report formats (HTML, PDF, XLSX) share
+const REPORT_HEADER: [&str; 8] = [
+    "Ticker",
+    "Quantity",
+    "Buy Price",
+    "Current/Sell Price",
+    "Cost Basis",
+    "Value",
+    "Gain/Loss",
+    "Change",
+];
+
+fn report_row(asset: &Asset) -> [String; 8] {
+    let price = asset.sell_price_cents.unwrap_or(asset.current_price_cents);
+    let value = price as i64 * asset.quantity as i64;
+    let gain_loss = realized_gain_loss(asset).unwrap_or(value - cost_basis(asset));
+    [
+        asset.ticker.clone(),
+        asset.quantity.to_string(),
+        format_money(asset.buy_price_cents as i64),
+        format!(
+            "{}{}",
+            format_money(price as i64),
+            if is_asset_held(asset) { "" } else { " (sold)" }
+        ),
+        format_money(cost_basis(asset)),
+        format_money(value),
+        format_money_with_sign(gain_loss),
+        format_percent(percent_increase(asset.buy_price_cents, price) as f64),
+    ]
+}
+
+fn html_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+pub fn to_html(portfolio: &Portfolio, today: NaiveDate) -> String {
+    let title = html_escape(format!("Portfolio {}", portfolio.name).trim_end());
+    let mut html = format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{title}</title>\n\
+         <style>body {{ font-family: sans-serif; }} table {{ border-collapse: collapse; }} \
+         th, td {{ border: 1px solid #ccc; padding: 4px 8px; text-align: right; }} \
+         th:first-child, td:first-child {{ text-align: left; }}</style>\n</head>\n<body>\n\
+         <h1>{title}</h1>\n<p>{}</p>\n<p>Generated on {}</p>\n<table>\n<tr>",
+        html_escape(&portfolio.to_string()),
+        today
+    );
+    for header in REPORT_HEADER {
+        html.push_str(&format!("<th>{}</th>", header));
+    }
+    html.push_str("</tr>\n");
+    for asset in &portfolio.assets {
+        html.push_str("<tr>");
+        for cell in report_row(asset) {
+            html.push_str(&format!("<td>{}</td>", html_escape(&cell)));
+        }
+        html.push_str("</tr>\n");
+    }
+    html.push_str("</table>\n</body>\n</html>\n");
+    html
+}
+
+// a plain text PDF: monospaced lines on as many A4 pages as they need. this
+// avoids pulling in a PDF library for what is a printable table
+pub fn to_pdf(portfolio: &Portfolio, today: NaiveDate) -> Vec<u8> {
+    const LINES_PER_PAGE: usize = 60;
+    let widths: Vec<usize> = (0..REPORT_HEADER.len())
+        .map(|column| {
+            portfolio
+                .assets
+                .iter()
+                .map(|asset| report_row(asset)[column].chars().count())
+                .chain([REPORT_HEADER[column].len()])
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+    let line = |cells: Vec<String>| {
+        cells
+            .iter()
+            .zip(&widths)
+            .map(|(cell, width)| format!("{:<width$}", cell, width = width))
+            .collect::<Vec<String>>()
+            .join("  ")
+    };
+    let mut lines = vec![
+        portfolio.to_string(),
+        format!("Generated on {}", today),
+        String::new(),
+        line(
+            REPORT_HEADER
+                .iter()
+                .map(|header| header.to_string())
+                .collect(),
+        ),
+    ];
+    lines.extend(
+        portfolio
+            .assets
+            .iter()
+            .map(|asset| line(report_row(asset).to_vec())),
+    );
+
+    // objects: 1 catalog, 2 page tree, 3 font, then a page and its contents
+    // for every page
+    let pages: Vec<&[String]> = lines.chunks(LINES_PER_PAGE).collect();
+    let mut objects: Vec<String> = vec![
+        "<< /Type /Catalog /Pages 2 0 R >>".to_string(),
+        format!(
+            "<< /Type /Pages /Kids [{}] /Count {} >>",
+            (0..pages.len())
+                .map(|page| format!("{} 0 R", 4 + page * 2))
+                .collect::<Vec<String>>()
+                .join(" "),
+            pages.len()
+        ),
+        "<< /Type /Font /Subtype /Type1 /BaseFont /Courier >>".to_string(),
+    ];
+    for (page, page_lines) in pages.iter().enumerate() {
+        let mut stream = String::from("BT /F1 8 Tf 10 TL 30 810 Td\n");
+        for text in page_lines.iter() {
+            stream.push_str(&format!("({}) '\n", pdf_text(text)));
+        }
+        stream.push_str("ET");
+        objects.push(format!(
+            "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 595 842] \
+             /Resources << /Font << /F1 3 0 R >> >> /Contents {} 0 R >>",
+            5 + page * 2
+        ));
+        objects.push(format!(
+            "<< /Length {} >>\nstream\n{}\nendstream",
+            stream.len(),
+            stream
+        ));
+    }
+
+    let mut pdf = b"%PDF-1.4\n".to_vec();
+    let mut offsets = vec![];
+    for (index, object) in objects.iter().enumerate() {
+        offsets.push(pdf.len());
+        pdf.extend(format!("{} 0 obj\n{}\nendobj\n", index + 1, object).into_bytes());
+    }
+    let xref = pdf.len();
+    pdf.extend(format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1).into_bytes());
+    for offset in offsets {
+        pdf.extend(format!("{:010} 00000 n \n", offset).into_bytes());
+    }
+    pdf.extend(
+        format!(
+            "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n",
+            objects.len() + 1,
+            xref
+        )
+        .into_bytes(),
+    );
+    pdf
+}
+
+// the standard fonts only cover Latin-1, so anything else (e.g. the │ in the
+// summary line) is replaced
+fn pdf_text(text: &str) -> String {
+    text.chars()
+        .map(|c| match c {
+            '│' => '|',
+            '(' | ')' | '\\' => c,
+            c if c.is_ascii() => c,
+            _ => '?',
+        })
+        .collect::<String>()
+        .replace('\\', "\\\\")
+        .replace('(', "\\(")
+        .replace(')', "\\)")
+}
+
+fn xml_escape(text: &str) -> String {
+    html_escape(text).replace('\'', "&apos;")
+}
+
+// a minimal workbook with one sheet. money columns are numbers in dollars
+// so they can be summed, everything else is an inline string
+pub fn to_xlsx(portfolio: &Portfolio) -> Result<Vec<u8>, PortfolioError> {
+    let header = [
+        "Ticker",
+        "Quantity",
+        "Buy Price",
+        "Current Price",
+        "Sell Price",
+        "Buy Date",
+        "Sell Date",
+        "Cost Basis",
+        "Market Value",
+    ];
+    let text = |value: &str| {
+        format!(
+            "<c t=\"inlineStr\"><is><t>{}</t></is></c>",
+            xml_escape(value)
+        )
+    };
+    let number = |cents: i64| format!("<c><v>{}</v></c>", decimal(cents));
+    let mut rows = vec![header.iter().map(|title| text(title)).collect::<String>()];
+    for asset in &portfolio.assets {
+        rows.push(
+            [
+                text(&asset.ticker),
+                format!("<c><v>{}</v></c>", asset.quantity),
+                number(asset.buy_price_cents as i64),
+                number(asset.current_price_cents as i64),
+                asset
+                    .sell_price_cents
+                    .map_or_else(|| text(""), |price| number(price as i64)),
+                text(&optional_date(asset.buy_date)),
+                text(&optional_date(asset.sell_date)),
+                number(cost_basis(asset)),
+                number(market_value(asset)),
+            ]
+            .concat(),
+        );
+    }
+    let sheet = format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n\
+         <worksheet xmlns=\"http://schemas.openxmlformats.org/spreadsheetml/2006/main\"><sheetData>{}</sheetData></worksheet>",
+        rows.iter()
+            .enumerate()
+            .map(|(index, row)| format!("<row r=\"{}\">{}</row>", index + 1, row))
+            .collect::<String>()
+    );
+    let files = [
+        (
+            "[Content_Types].xml",
+            "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n\
+             <Types xmlns=\"http://schemas.openxmlformats.org/package/2006/content-types\">\
+             <Default Extension=\"rels\" ContentType=\"application/vnd.openxmlformats-package.relationships+xml\"/>\
+             <Default Extension=\"xml\" ContentType=\"application/xml\"/>\
+             <Override PartName=\"/xl/workbook.xml\" ContentType=\"application/vnd.openxmlformats-officedocument.spreadsheetml.sheet.main+xml\"/>\
+             <Override PartName=\"/xl/worksheets/sheet1.xml\" ContentType=\"application/vnd.openxmlformats-officedocument.spreadsheetml.worksheet+xml\"/>\
+             </Types>"
+                .to_string(),
+        ),
+        (
+            "_rels/.rels",
+            "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n\
+             <Relationships xmlns=\"http://schemas.openxmlformats.org/package/2006/relationships\">\
+             <Relationship Id=\"rId1\" Type=\"http://schemas.openxmlformats.org/officeDocument/2006/relationships/officeDocument\" Target=\"xl/workbook.xml\"/>\
+             </Relationships>"
+                .to_string(),
+        ),
+        (
+            "xl/workbook.xml",
+            "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n\
+             <workbook xmlns=\"http://schemas.openxmlformats.org/spreadsheetml/2006/main\" \
+             xmlns:r=\"http://schemas.openxmlformats.org/officeDocument/2006/relationships\">\
+             <sheets><sheet name=\"Portfolio\" sheetId=\"1\" r:id=\"rId1\"/></sheets></workbook>"
+                .to_string(),
+        ),
+        (
+            "xl/_rels/workbook.xml.rels",
+            "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n\
+             <Relationships xmlns=\"http://schemas.openxmlformats.org/package/2006/relationships\">\
+             <Relationship Id=\"rId1\" Type=\"http://schemas.openxmlformats.org/officeDocument/2006/relationships/worksheet\" Target=\"worksheets/sheet1.xml\"/>\
+             </Relationships>"
+                .to_string(),
+        ),
+        ("xl/worksheets/sheet1.xml", sheet),
+    ];
+
+    zip_files(&files)
+}
+
+// everything needed to carry the tracker to another machine, as written by
+// `export-all`. the value history travels inside the portfolio, and
+// transactions and dividends aren't recorded separately, so there are no
+// files for them
+pub struct Bundle {
+    pub portfolio: Portfolio,
+    // None if the archive has no config.toml
+    pub config: Option<Config>,
+    // None if the archive has no metadata.json
+    pub metadata: Option<MetadataCache>,
+}
+
+const BUNDLE_PORTFOLIO: &str = "portfolio.json";
+const BUNDLE_CONFIG: &str = "config.toml";
+const BUNDLE_METADATA: &str = "metadata.json";
+
+pub fn write_bundle(
+    portfolio: &Portfolio,
+    config: &Config,
+    metadata: &MetadataCache,
+) -> Result<Vec<u8>, PortfolioError> {
+    let files = [
+        (BUNDLE_PORTFOLIO, portfolio.to_json_str()?),
+        (BUNDLE_CONFIG, toml::to_string(config)?),
+        (BUNDLE_METADATA, serde_json::to_string(metadata)?),
+    ];
+    zip_files(&files)
+}
+
+pub fn read_bundle(bytes: &[u8]) -> Result<Bundle, PortfolioError> {
+    let mut archive = zip::ZipArchive::new(std::io::Cursor::new(bytes)).map_err(zip_error)?;
+    let mut read = |name: &str| -> Result<Option<String>, PortfolioError> {
+        let mut file = match archive.by_name(name) {
+            Ok(file) => file,
+            Err(zip::result::ZipError::FileNotFound) => return Ok(None),
+            Err(e) => return Err(zip_error(e)),
+        };
+        let mut contents = String::new();
+        file.read_to_string(&mut contents)?;
+        Ok(Some(contents))
+    };
+    let portfolio = read(BUNDLE_PORTFOLIO)?
+        .ok_or_else(|| PortfolioError::NotFound(format!("{} in the archive", BUNDLE_PORTFOLIO)))?;
+    let portfolio = Portfolio::from_json_str(&portfolio).map_err(|e| match e {
+        PortfolioError::ParseError(message) => {
+            PortfolioError::ParseError(format!("{}: {}", BUNDLE_PORTFOLIO, message))
+        }
+        e => e,
+    })?;
+    let config = read(BUNDLE_CONFIG)?
+        .map(|raw| Config::parse(&raw))
+        .transpose()?;
+    let metadata = read(BUNDLE_METADATA)?
+        .map(|raw| {
+            serde_json::from_str(&raw)
+                .map_err(|e| PortfolioError::ParseError(format!("{}: {}", BUNDLE_METADATA, e)))
+        })
+        .transpose()?;
+    Ok(Bundle {
+        portfolio,
+        config,
+        metadata,
+    })
+}
+
+fn zip_error(e: zip::result::ZipError) -> PortfolioError {
+    match e {
+        zip::result::ZipError::Io(e) => PortfolioError::IoError(e),
+        e => PortfolioError::ParseError(e.to_string()),
+    }
+}
+
+fn zip_files(files: &[(&str, String)]) -> Result<Vec<u8>, PortfolioError> {
+    let mut archive = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+    let options = zip::write::SimpleFileOptions::default();
+    for (name, contents) in files {
+        archive.start_file(*name, options).map_err(zip_error)?;
+        archive.write_all(contents.as_bytes())?;
+    }
+    Ok(archive.finish().map_err(zip_error)?.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::DateOrder;
+    use crate::import::import_csv;
+    use crate::portfolio::AssetBuilder;
+
+    fn date(m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, m, d).unwrap()
+    }
+
+    fn sample() -> Portfolio {
+        let asset = |ticker: &str, buy, sell: Option<u32>| {
+            let builder = AssetBuilder::new(ticker, buy)
+                .buy_date(date(1, 5))
+                .current_price(18942)
+                .quantity(10);
+            match sell {
+                Some(sell) => builder.sell_price(sell).sell_date(date(6, 30)),
+                None => builder,
+            }
+            .build()
+            .unwrap()
+        };
+        Portfolio {
+            name: "main".to_string(),
+            assets: vec![
+                asset("AAPL", 15000, None),
+                asset("R&D <Co>", 25000, Some(30000)),
+            ],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn csv_round_trips_through_import() {
+        let portfolio = sample();
+        let imported = import_csv(&to_csv(&portfolio), DateOrder::Mdy, date(10, 1)).unwrap();
+        assert!(imported.skipped.is_empty());
+        assert_eq!(imported.assets.len(), 2);
+        for (original, imported) in portfolio.assets.iter().zip(&imported.assets) {
+            assert_eq!(original.buy_price_cents, imported.buy_price_cents);
+            assert_eq!(original.quantity, imported.quantity);
+            assert_eq!(original.buy_date, imported.buy_date);
+            assert_eq!(original.sell_price_cents, imported.sell_price_cents);
+            assert_eq!(original.sell_date, imported.sell_date);
+        }
+    }
+
+    #[test]
+    fn ledger_has_buys_and_sales() {
+        let mut portfolio = sample();
+        portfolio.assets[0].buy_date = None;
+        let journal = to_ledger(&portfolio, date(10, 1));
+        assert!(journal.starts_with(
+            "2024/10/01 Buy AAPL ; date unknown\n    Assets:Brokerage    10 \"AAPL\" @ $150.00\n"
+        ));
+        assert!(journal.contains(
+            "2024/06/30 Sell R&D <Co>\n    Assets:Brokerage    -10 \"R&D <Co>\" @ $300.00\n"
+        ));
+    }
+
+    #[test]
+    fn html_escapes_tickers() {
+        let html = to_html(&sample(), date(10, 1));
+        assert!(html.contains("<td>R&amp;D &lt;Co&gt;</td>"));
+        assert!(html.contains("<td>+$394.20</td>"));
+    }
+
+    #[test]
+    fn pdf_is_well_formed() {
+        let pdf = String::from_utf8(to_pdf(&sample(), date(10, 1))).unwrap();
+        assert!(pdf.starts_with("%PDF-1.4\n"));
+        assert!(pdf.ends_with("%%EOF\n"));
+        assert!(pdf.contains("/Count 1"));
+        assert!(pdf.contains("(Portfolio \"main\" | 2 assets"));
+        // the xref offset points at the xref table
+        let start: usize = pdf.lines().rev().nth(1).unwrap().parse().unwrap();
+        assert!(pdf[start..].starts_with("xref"));
+    }
+
+    #[test]
+    fn xlsx_is_a_zip_with_a_sheet() {
+        let bytes = to_xlsx(&sample()).unwrap();
+        let mut archive = zip::ZipArchive::new(std::io::Cursor::new(bytes)).unwrap();
+        let mut sheet = String::new();
+        std::io::Read::read_to_string(
+            &mut archive.by_name("xl/worksheets/sheet1.xml").unwrap(),
+            &mut sheet,
+        )
+        .unwrap();
+        assert!(sheet.contains("<t>AAPL</t>"));
+        assert!(sheet.contains("<c><v>150.00</v></c>"));
+        assert!(sheet.contains("R&amp;D &lt;Co&gt;"));
+    }
+
+    #[test]
+    fn bundles_round_trip() {
+        let mut config = Config::default();
+        config.set("currency", "EUR").unwrap();
+        let bytes = write_bundle(&sample(), &config, &MetadataCache::default()).unwrap();
+        let bundle = read_bundle(&bytes).unwrap();
+        assert_eq!(bundle.portfolio.assets.len(), 2);
+        assert_eq!(bundle.portfolio.assets[1].ticker, "R&D <Co>");
+        assert_eq!(bundle.config, Some(config));
+        assert!(bundle.metadata.is_some());
+    }
+
+    #[test]
+    fn bundles_need_a_portfolio() {
+        let bytes = zip_files(&[(BUNDLE_CONFIG, String::new())]).unwrap();
+        assert!(matches!(
+            read_bundle(&bytes),
+            Err(PortfolioError::NotFound(_))
+        ));
+        assert!(matches!(
+            read_bundle(b"not a zip"),
+            Err(PortfolioError::ParseError(_))
+        ));
+    }
+}
@@ -0,0 +1,156 @@
+// savings goals for `goal`: a value to reach by a date, and what it would
+// take to get there from what the held assets are worth now
+use chrono::{Datelike, NaiveDate};
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Goal {
+    pub name: String,
+    pub target_value_cents: u64,
+    pub target_date: NaiveDate,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct GoalProgress {
+    pub progress_percent: f64,
+    // how much is still missing, 0 once the goal is reached
+    pub gap: i64,
+    // saved at the end of every month from now to the target date, with
+    // returns compounded monthly. None once the target date has passed
+    // without the goal being met
+    pub monthly_savings: Option<i64>,
+    // at the portfolio's own growth rate and with nothing added. None when
+    // it isn't growing, so it never gets there
+    pub years_to_goal: Option<f64>,
+}
+
+// whole months from today to the date, not counting a month that hasn't
+// been completed
+pub fn months_until(today: NaiveDate, date: NaiveDate) -> i64 {
+    let months =
+        (date.year() - today.year()) as i64 * 12 + date.month() as i64 - today.month() as i64;
+    if date.day() < today.day() {
+        months - 1
+    } else {
+        months
+    }
+}
+
+// `annual_return_percent` is the return assumed for the savings, and
+// `growth_rate` the annual rate the portfolio has grown at so far, as a
+// fraction like the internal rate of return
+pub fn goal_progress(
+    goal: &Goal,
+    value: i64,
+    annual_return_percent: f64,
+    growth_rate: Option<f64>,
+    today: NaiveDate,
+) -> GoalProgress {
+    let target = goal.target_value_cents as i64;
+    let value = value.max(0);
+    let progress_percent = if target == 0 {
+        100.0
+    } else {
+        value as f64 / target as f64 * 100.0
+    };
+    let gap = (target - value).max(0);
+
+    let months = months_until(today, goal.target_date);
+    let monthly_savings = if gap == 0 {
+        Some(0)
+    } else if months <= 0 {
+        None
+    } else {
+        let rate = (1.0 + annual_return_percent / 100.0).powf(1.0 / 12.0) - 1.0;
+        let growth = (1.0 + rate).powi(months as i32);
+        let needed = target as f64 - value as f64 * growth;
+        let payment = if needed <= 0.0 {
+            0.0
+        } else if rate.abs() < 1e-12 {
+            needed / months as f64
+        } else {
+            needed * rate / (growth - 1.0)
+        };
+        Some(payment.ceil() as i64)
+    };
+
+    let years_to_goal = match growth_rate {
+        _ if gap == 0 => Some(0.0),
+        Some(rate) if rate > 0.0 && value > 0 => {
+            Some((target as f64 / value as f64).ln() / (1.0 + rate).ln())
+        }
+        _ => None,
+    };
+
+    GoalProgress {
+        progress_percent,
+        gap,
+        monthly_savings,
+        years_to_goal,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    fn goal(target_dollars: u64, target_date: NaiveDate) -> Goal {
+        Goal {
+            name: "Retirement".to_string(),
+            target_value_cents: target_dollars * 100,
+            target_date,
+        }
+    }
+
+    #[test]
+    fn months_until_counts_whole_months() {
+        assert_eq!(months_until(date(2024, 1, 15), date(2024, 3, 15)), 2);
+        assert_eq!(months_until(date(2024, 1, 15), date(2024, 3, 14)), 1);
+        assert_eq!(months_until(date(2024, 1, 15), date(2023, 12, 15)), -1);
+    }
+
+    #[test]
+    fn progress_without_returns_spreads_the_gap_evenly() {
+        let today = date(2024, 1, 1);
+        let progress = goal_progress(&goal(12_000, date(2025, 1, 1)), 0, 0.0, None, today);
+        assert_eq!(progress.progress_percent, 0.0);
+        assert_eq!(progress.gap, 1_200_000);
+        assert_eq!(progress.monthly_savings, Some(100_000));
+        assert_eq!(progress.years_to_goal, None);
+    }
+
+    #[test]
+    fn progress_with_returns_needs_less_saved() {
+        let today = date(2024, 1, 1);
+        let goal = goal(1_000_000, date(2044, 1, 1));
+        let progress = goal_progress(&goal, 10_000_000, 7.0, Some(0.07), today);
+        assert_eq!(progress.progress_percent, 10.0);
+        assert_eq!(progress.gap, 90_000_000);
+        // $100k growing for 20 years at 7% reaches about $387k, so the savings
+        // make up the other $613k
+        let monthly = progress.monthly_savings.unwrap();
+        assert!((120_000..122_000).contains(&monthly), "{}", monthly);
+        // ln(10) / ln(1.07)
+        let years = progress.years_to_goal.unwrap();
+        assert!((years - 34.03).abs() < 0.01, "{}", years);
+    }
+
+    #[test]
+    fn reached_and_missed_goals() {
+        let today = date(2024, 1, 1);
+        let reached = goal_progress(&goal(100, date(2030, 1, 1)), 20_000, 7.0, None, today);
+        assert_eq!(reached.progress_percent, 200.0);
+        assert_eq!(reached.gap, 0);
+        assert_eq!(reached.monthly_savings, Some(0));
+        assert_eq!(reached.years_to_goal, Some(0.0));
+
+        let missed = goal_progress(&goal(100, date(2023, 6, 1)), 5_000, 7.0, Some(-0.1), today);
+        assert_eq!(missed.gap, 5_000);
+        assert_eq!(missed.monthly_savings, None);
+        assert_eq!(missed.years_to_goal, None);
+    }
+}
@@ -0,0 +1,336 @@
+// letter grades for `health`: how spread out, how concentrated, how volatile
+// and how well returning the held assets are. the thresholds are rules of
+// thumb for a personal portfolio, not advice
+use crate::portfolio::{PortfolioAnalytics, UNKNOWN_SECTOR};
+use crate::report::share_percent;
+
+// no index prices are fetched, so returns are compared with the long-run
+// average annual return of the US stock market
+pub const BENCHMARK_ANNUAL_RETURN: f64 = 0.07;
+
+// best first, so the worse of two grades is the max
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Grade {
+    A,
+    B,
+    C,
+    D,
+    F,
+}
+
+const GRADES: [Grade; 5] = [Grade::A, Grade::B, Grade::C, Grade::D, Grade::F];
+
+impl Grade {
+    pub fn letter(self) -> &'static str {
+        match self {
+            Grade::A => "A",
+            Grade::B => "B",
+            Grade::C => "C",
+            Grade::D => "D",
+            Grade::F => "F",
+        }
+    }
+
+    // 4 for an A down to 0 for an F, like a grade point average
+    fn points(self) -> f64 {
+        4.0 - GRADES.iter().position(|grade| *grade == self).unwrap_or(4) as f64
+    }
+
+    fn from_points(points: f64) -> Grade {
+        let index = (4.0 - points.round()).clamp(0.0, 4.0) as usize;
+        GRADES[index]
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct DimensionGrade {
+    pub dimension: &'static str,
+    // None when there isn't the data to grade it
+    pub grade: Option<Grade>,
+    // why it isn't an A, or why it couldn't be graded. empty for an A
+    pub explanation: String,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct HealthScore {
+    pub dimensions: Vec<DimensionGrade>,
+    pub overall: DimensionGrade,
+}
+
+// A if the value reaches the first threshold, B the second and so on, F if
+// it reaches none of them
+fn grade_at_least(value: f64, thresholds: [f64; 4]) -> Grade {
+    thresholds
+        .iter()
+        .position(|threshold| value >= *threshold)
+        .map_or(Grade::F, |index| GRADES[index])
+}
+
+// the same for values where lower is better
+fn grade_at_most(value: f64, thresholds: [f64; 4]) -> Grade {
+    grade_at_least(-value, thresholds.map(|threshold| -threshold))
+}
+
+fn graded(dimension: &'static str, grade: Grade, explanation: String) -> DimensionGrade {
+    DimensionGrade {
+        dimension,
+        grade: Some(grade),
+        explanation: if grade == Grade::A {
+            String::new()
+        } else {
+            explanation
+        },
+    }
+}
+
+fn ungraded(dimension: &'static str, explanation: &str) -> DimensionGrade {
+    DimensionGrade {
+        dimension,
+        grade: None,
+        explanation: explanation.to_string(),
+    }
+}
+
+// by held tickers, and also by sectors once any are known
+fn diversification(analytics: &PortfolioAnalytics) -> DimensionGrade {
+    const DIMENSION: &str = "Diversification";
+    if analytics.held_tickers == 0 {
+        return ungraded(DIMENSION, "Nothing is held.");
+    }
+    let tickers = analytics.held_tickers;
+    let by_tickers = grade_at_least(tickers as f64, [15.0, 10.0, 6.0, 3.0]);
+    let sectors = analytics
+        .by_sector
+        .keys()
+        .filter(|sector| *sector != UNKNOWN_SECTOR)
+        .count();
+    let by_sectors = grade_at_least(sectors as f64, [6.0, 4.0, 3.0, 2.0]);
+    if sectors > 0 && by_sectors > by_tickers {
+        return graded(
+            DIMENSION,
+            by_sectors,
+            format!(
+                "The held assets span only {} sector{}, so a downturn in one hits much of the portfolio.",
+                sectors,
+                if sectors == 1 { "" } else { "s" }
+            ),
+        );
+    }
+    graded(
+        DIMENSION,
+        by_tickers,
+        format!(
+            "Only {} ticker{} held, so each company's fortunes weigh heavily.",
+            tickers,
+            if tickers == 1 { " is" } else { "s are" }
+        ),
+    )
+}
+
+fn concentration(analytics: &PortfolioAnalytics) -> DimensionGrade {
+    const DIMENSION: &str = "Concentration Risk";
+    let Some((ticker, value)) = &analytics.largest_position else {
+        return ungraded(DIMENSION, "Nothing is held.");
+    };
+    let share = share_percent(*value, analytics.total_value);
+    graded(
+        DIMENSION,
+        grade_at_most(share, [10.0, 20.0, 30.0, 50.0]),
+        format!(
+            "{} is {:.1}% of the held value, so its moves dominate the portfolio.",
+            ticker, share
+        ),
+    )
+}
+
+// the deepest fall in the recorded value history stands in for volatility
+fn volatility(analytics: &PortfolioAnalytics) -> DimensionGrade {
+    const DIMENSION: &str = "Volatility";
+    let Some(drawdown) = &analytics.drawdown else {
+        return ungraded(
+            DIMENSION,
+            "Not enough value history; refresh on at least two different days.",
+        );
+    };
+    let decline = drawdown.decline_percent();
+    graded(
+        DIMENSION,
+        grade_at_most(decline, [5.0, 10.0, 20.0, 30.0]),
+        format!(
+            "The portfolio value has fallen as much as {:.1}% from a peak.",
+            decline
+        ),
+    )
+}
+
+fn return_vs_benchmark(analytics: &PortfolioAnalytics) -> DimensionGrade {
+    const DIMENSION: &str = "Return vs Benchmark";
+    let Some(irr) = analytics.irr else {
+        return ungraded(
+            DIMENSION,
+            "Needs buy dates on the assets to work out an annual return.",
+        );
+    };
+    let difference = (irr - BENCHMARK_ANNUAL_RETURN) * 100.0;
+    graded(
+        DIMENSION,
+        grade_at_least(difference, [2.0, 0.0, -3.0, -7.0]),
+        format!(
+            "The annual return of {:.1}% is {:.1} points {} the {:.0}% market benchmark.",
+            irr * 100.0,
+            difference.abs(),
+            if difference >= 0.0 { "above" } else { "below" },
+            BENCHMARK_ANNUAL_RETURN * 100.0
+        ),
+    )
+}
+
+// the overall grade averages the dimensions that could be graded
+pub fn health_score(analytics: &PortfolioAnalytics) -> HealthScore {
+    let dimensions = vec![
+        diversification(analytics),
+        concentration(analytics),
+        volatility(analytics),
+        return_vs_benchmark(analytics),
+    ];
+    let grades: Vec<&DimensionGrade> = dimensions
+        .iter()
+        .filter(|dimension| dimension.grade.is_some())
+        .collect();
+    // max_by_key keeps the last of equals, so reversed it finds the first
+    let overall = match grades.iter().rev().max_by_key(|dimension| dimension.grade) {
+        None => ungraded("Overall", "There isn't enough data to grade anything yet."),
+        Some(weakest) => {
+            let points = grades
+                .iter()
+                .filter_map(|dimension| dimension.grade.map(Grade::points))
+                .sum::<f64>()
+                / grades.len() as f64;
+            graded(
+                "Overall",
+                Grade::from_points(points),
+                format!(
+                    "The average of {} graded dimension{}, held back most by {}.",
+                    grades.len(),
+                    if grades.len() == 1 { "" } else { "s" },
+                    weakest.dimension
+                ),
+            )
+        }
+    };
+    HealthScore {
+        dimensions,
+        overall,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::portfolio::Drawdown;
+    use chrono::NaiveDate;
+    use std::collections::HashMap;
+
+    fn analytics(held_tickers: usize, largest: i64, total: i64) -> PortfolioAnalytics {
+        PortfolioAnalytics {
+            held: held_tickers,
+            sold: 0,
+            held_tickers,
+            largest_position: Some(("AAPL".to_string(), largest)),
+            total_value: total,
+            total_cost: total,
+            sold_cost: 0,
+            unrealized_pnl: 0,
+            realized_pnl: 0,
+            by_sector: HashMap::new(),
+            irr: None,
+            drawdown: None,
+            foreign: vec![],
+            refresh_failed: vec![],
+            age: None,
+        }
+    }
+
+    fn drawdown(peak: i64, trough: i64) -> Drawdown {
+        let date = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        Drawdown {
+            peak_date: date,
+            peak_value: peak,
+            trough_date: date,
+            trough_value: trough,
+        }
+    }
+
+    #[test]
+    fn thresholds_pick_grades() {
+        assert_eq!(grade_at_least(15.0, [15.0, 10.0, 6.0, 3.0]), Grade::A);
+        assert_eq!(grade_at_least(7.0, [15.0, 10.0, 6.0, 3.0]), Grade::C);
+        assert_eq!(grade_at_least(2.0, [15.0, 10.0, 6.0, 3.0]), Grade::F);
+        assert_eq!(grade_at_most(10.0, [10.0, 20.0, 30.0, 50.0]), Grade::A);
+        assert_eq!(grade_at_most(25.0, [10.0, 20.0, 30.0, 50.0]), Grade::C);
+        assert_eq!(grade_at_most(80.0, [10.0, 20.0, 30.0, 50.0]), Grade::F);
+        assert_eq!(Grade::from_points(2.6), Grade::B);
+    }
+
+    #[test]
+    fn healthy_portfolio_gets_straight_as_without_explanations() {
+        let mut analytics = analytics(20, 500, 10_000);
+        for sector in [
+            "Technology",
+            "Energy",
+            "Utilities",
+            "Healthcare",
+            "Industrials",
+            "Financial Services",
+        ] {
+            analytics.by_sector.insert(sector.to_string(), 1_000);
+        }
+        analytics.irr = Some(0.12);
+        analytics.drawdown = Some(drawdown(10_000, 9_800));
+        let score = health_score(&analytics);
+        assert!(
+            score
+                .dimensions
+                .iter()
+                .all(|dimension| dimension.grade == Some(Grade::A)
+                    && dimension.explanation.is_empty())
+        );
+        assert_eq!(score.overall.grade, Some(Grade::A));
+        assert!(score.overall.explanation.is_empty());
+    }
+
+    #[test]
+    fn weak_dimensions_are_explained() {
+        let mut analytics = analytics(2, 6_000, 10_000);
+        analytics.by_sector.insert("Technology".to_string(), 10_000);
+        analytics.irr = Some(0.05);
+        let score = health_score(&analytics);
+        let grades: Vec<Option<Grade>> = score.dimensions.iter().map(|d| d.grade).collect();
+        assert_eq!(
+            grades,
+            vec![Some(Grade::F), Some(Grade::F), None, Some(Grade::C)]
+        );
+        assert!(score.dimensions[0]
+            .explanation
+            .contains("Only 2 tickers are held"));
+        assert!(score.dimensions[1].explanation.contains("AAPL is 60.0%"));
+        assert!(score.dimensions[2].explanation.contains("value history"));
+        assert!(score.dimensions[3].explanation.contains("2.0 points below"));
+        // (0 + 0 + 2) / 3 rounds to a D
+        assert_eq!(score.overall.grade, Some(Grade::D));
+        assert!(score.overall.explanation.contains("3 graded dimensions"));
+        assert!(score.overall.explanation.contains("Diversification"));
+    }
+
+    #[test]
+    fn empty_portfolio_is_not_graded() {
+        let mut analytics = analytics(0, 0, 0);
+        analytics.largest_position = None;
+        let score = health_score(&analytics);
+        assert!(score
+            .dimensions
+            .iter()
+            .all(|dimension| dimension.grade.is_none()));
+        assert_eq!(score.overall.grade, None);
+    }
+}
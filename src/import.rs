@@ -0,0 +1,808 @@
+// reading assets out of files written by other tools. every importer works
+// on the file's contents so it can be tested without touching the disk
+use crate::config::DateOrder;
+use crate::error::PortfolioError;
+use crate::input::{parse_buy_date, parse_date, parse_price};
+use crate::persistence::load_portfolio_from_path;
+use crate::portfolio::Asset;
+use chrono::NaiveDate;
+use std::fs;
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ImportFormat {
+    // ticker,buy_price,quantity with optional buy_date, sell_price, sell_date
+    Csv,
+    // the positions export from Fidelity
+    Fidelity,
+    // the account activity report from Robinhood; each buy becomes a lot
+    Robinhood,
+    // the positions export from Schwab
+    Schwab,
+    // postings held at a cost, e.g. `Assets:Broker  10 AAPL {150.00 USD}`
+    Beancount,
+    // another portfolio file saved by this program
+    Json,
+}
+
+impl ImportFormat {
+    // the name used by `import <format> <file>`
+    pub fn from_name(name: &str) -> Option<ImportFormat> {
+        match name.to_lowercase().as_str() {
+            "csv" => Some(ImportFormat::Csv),
+            "fidelity" => Some(ImportFormat::Fidelity),
+            "robinhood" => Some(ImportFormat::Robinhood),
+            "schwab" => Some(ImportFormat::Schwab),
+            "beancount" => Some(ImportFormat::Beancount),
+            "json" => Some(ImportFormat::Json),
+            _ => None,
+        }
+    }
+}
+
+pub const IMPORT_FORMAT_NAMES: &str = "csv, fidelity, robinhood, schwab, beancount, json";
+
+// brokers whose CSV exports are recognised by their headers
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BrokerFormat {
+    Robinhood,
+    Fidelity,
+    Schwab,
+}
+
+// in the order detect_csv_format tries them, since Fidelity's columns
+// include a "Cost Basis Total" that shouldn't be taken for Schwab's
+const BROKER_FORMATS: [BrokerFormat; 3] = [
+    BrokerFormat::Robinhood,
+    BrokerFormat::Fidelity,
+    BrokerFormat::Schwab,
+];
+
+// which columns of a broker's CSV hold each field. headers are matched
+// ignoring case, and prices are read as brokers write them, so "150" is $150
+#[derive(Debug, Clone, PartialEq)]
+pub struct CsvImportConfig {
+    pub ticker_col: String,
+    pub buy_price_col: String,
+    pub quantity_col: String,
+    pub date_col: Option<String>,
+    pub sell_price_col: Option<String>,
+    // for transaction histories: only rows with a buy here are imported
+    pub action_col: Option<String>,
+    // the buy price column is the total paid for the lot rather than the
+    // price per share
+    pub buy_price_is_total: bool,
+}
+
+impl CsvImportConfig {
+    pub fn from_format(format: BrokerFormat) -> CsvImportConfig {
+        let column = |name: &str| name.to_string();
+        match format {
+            BrokerFormat::Robinhood => CsvImportConfig {
+                ticker_col: column("Instrument"),
+                buy_price_col: column("Price"),
+                quantity_col: column("Quantity"),
+                date_col: Some(column("Activity Date")),
+                sell_price_col: None,
+                action_col: Some(column("Trans Code")),
+                buy_price_is_total: false,
+            },
+            BrokerFormat::Fidelity => CsvImportConfig {
+                ticker_col: column("Symbol"),
+                buy_price_col: column("Average Cost Basis"),
+                quantity_col: column("Quantity"),
+                date_col: None,
+                sell_price_col: None,
+                action_col: None,
+                buy_price_is_total: false,
+            },
+            BrokerFormat::Schwab => CsvImportConfig {
+                ticker_col: column("Symbol"),
+                buy_price_col: column("Cost Basis"),
+                quantity_col: column("Quantity"),
+                date_col: None,
+                sell_price_col: None,
+                action_col: None,
+                buy_price_is_total: true,
+            },
+        }
+    }
+
+    // every column the mapping reads, which a file needs all of to match it
+    fn columns(&self) -> Vec<&str> {
+        let mut columns = vec![
+            self.ticker_col.as_str(),
+            self.buy_price_col.as_str(),
+            self.quantity_col.as_str(),
+        ];
+        columns.extend(
+            [&self.date_col, &self.sell_price_col, &self.action_col]
+                .into_iter()
+                .flatten()
+                .map(String::as_str),
+        );
+        columns
+    }
+}
+
+// the broker whose export has these headers, if any
+pub fn detect_csv_format(headers: &[String]) -> Option<BrokerFormat> {
+    BROKER_FORMATS.into_iter().find(|format| {
+        CsvImportConfig::from_format(*format)
+            .columns()
+            .into_iter()
+            .all(|column| {
+                headers
+                    .iter()
+                    .any(|header| header.eq_ignore_ascii_case(column))
+            })
+    })
+}
+
+#[derive(Debug, Default)]
+pub struct Imported {
+    pub assets: Vec<Asset>,
+    // why each row that looked like data wasn't imported, e.g. "line 4: ..."
+    pub skipped: Vec<String>,
+}
+
+// numeric dates in a plain CSV are read in the same order as at the prompt
+pub fn import_file(
+    format: ImportFormat,
+    path: &Path,
+    date_order: DateOrder,
+    today: NaiveDate,
+) -> Result<Imported, PortfolioError> {
+    if format == ImportFormat::Json {
+        let portfolio = load_portfolio_from_path(path)?;
+        return Ok(Imported {
+            assets: portfolio.assets,
+            skipped: vec![],
+        });
+    }
+    let raw = fs::read_to_string(path)?;
+    match format {
+        ImportFormat::Csv => match detect_broker(&raw) {
+            Some(BrokerFormat::Fidelity) => import_fidelity(&raw),
+            Some(BrokerFormat::Robinhood) => import_robinhood(&raw),
+            Some(BrokerFormat::Schwab) => import_schwab(&raw),
+            None => import_csv(&raw, date_order, today),
+        },
+        ImportFormat::Fidelity => import_fidelity(&raw),
+        ImportFormat::Robinhood => import_robinhood(&raw),
+        ImportFormat::Schwab => import_schwab(&raw),
+        ImportFormat::Beancount => Ok(import_beancount(&raw)),
+        ImportFormat::Json => unreachable!("handled above"),
+    }
+}
+
+// imported assets have no current price until the next refresh, so the buy
+// price stands in for it and the price is reported as stale
+fn imported_asset(ticker: &str, buy_price_cents: u32, quantity: u32) -> Asset {
+    Asset {
+        ticker: ticker.to_uppercase(),
+        buy_price_cents,
+        buy_date: None,
+        current_price_cents: buy_price_cents,
+        sell_price_cents: None,
+        sell_date: None,
+        quantity,
+        price_updated_at: None,
+        refresh_failed_at: None,
+        currency: None,
+        note: None,
+        tags: vec![],
+        account: None,
+        added_at: None,
+        price_history: vec![],
+        target_price_cents: None,
+        stop_loss: None,
+        stop_breached_at: None,
+        price_from_cache: false,
+    }
+}
+
+// brokers give prices to four or more decimal places, so these are rounded to
+// the nearest cent rather than rejected like a typed price would be
+pub fn parse_decimal_cents(raw: &str) -> Option<u32> {
+    let cleaned: String = raw
+        .trim()
+        .trim_start_matches('$')
+        .chars()
+        .filter(|c| *c != ',')
+        .collect();
+    let (whole, fraction) = cleaned.split_once('.').unwrap_or((&cleaned, ""));
+    if (whole.is_empty() && fraction.is_empty())
+        || !whole.chars().all(|c| c.is_ascii_digit())
+        || !fraction.chars().all(|c| c.is_ascii_digit())
+    {
+        return None;
+    }
+    let whole: u64 = if whole.is_empty() {
+        0
+    } else {
+        whole.parse().ok()?
+    };
+    let mut digits = fraction.chars().map(|c| c as u64 - '0' as u64);
+    let cents = digits.next().unwrap_or(0) * 10 + digits.next().unwrap_or(0);
+    let round_up = digits.next().unwrap_or(0) >= 5;
+    (whole * 100 + cents + round_up as u64).try_into().ok()
+}
+
+// whole shares only; fractional shares can't be represented yet
+fn parse_whole_quantity(raw: &str) -> Result<u32, String> {
+    let raw = raw.trim().replace(',', "");
+    // brokers write whole quantities as e.g. "10.000"
+    let whole = match raw.split_once('.') {
+        Some((whole, fraction)) if fraction.chars().all(|c| c == '0') => whole,
+        Some(_) if raw.parse::<f64>().is_ok() => {
+            return Err(format!("fractional quantity {}", raw))
+        }
+        _ => &raw,
+    };
+    match whole.parse::<u32>() {
+        Ok(0) => Err(format!("quantity {} is zero", raw)),
+        Ok(quantity) => Ok(quantity),
+        Err(_) => Err(format!("'{}' isn't a quantity", raw)),
+    }
+}
+
+fn csv_reader(raw: &str) -> csv::Reader<&[u8]> {
+    csv::ReaderBuilder::new()
+        .flexible(true)
+        .trim(csv::Trim::All)
+        .from_reader(raw.as_bytes())
+}
+
+fn column(headers: &csv::StringRecord, names: &[&str]) -> Option<usize> {
+    headers
+        .iter()
+        .position(|header| names.iter().any(|name| header.eq_ignore_ascii_case(name)))
+}
+
+fn required_column(headers: &csv::StringRecord, names: &[&str]) -> Result<usize, PortfolioError> {
+    column(headers, names).ok_or_else(|| {
+        PortfolioError::ParseError(format!("no '{}' column in the header", names[0]))
+    })
+}
+
+fn csv_error(e: csv::Error) -> PortfolioError {
+    PortfolioError::ParseError(e.to_string())
+}
+
+fn optional_date(raw: Option<&str>, format: &str) -> Result<Option<NaiveDate>, String> {
+    match raw.map(str::trim) {
+        None | Some("") => Ok(None),
+        Some(raw) => NaiveDate::parse_from_str(raw, format)
+            .map(Some)
+            .map_err(|_| format!("'{}' isn't a date", raw)),
+    }
+}
+
+// the header names prices are written like they would be typed at the
+// prompt, so a bare whole number is in cents
+pub fn import_csv(
+    raw: &str,
+    date_order: DateOrder,
+    today: NaiveDate,
+) -> Result<Imported, PortfolioError> {
+    let mut reader = csv_reader(raw);
+    let headers = reader.headers().map_err(csv_error)?.clone();
+    let ticker = required_column(&headers, &["ticker"])?;
+    let buy_price = required_column(&headers, &["buy_price"])?;
+    let quantity = required_column(&headers, &["quantity"])?;
+    let buy_date = column(&headers, &["buy_date"]);
+    let sell_price = column(&headers, &["sell_price"]);
+    let sell_date = column(&headers, &["sell_date"]);
+
+    let mut imported = Imported::default();
+    for (index, record) in reader.records().enumerate() {
+        let record = record.map_err(csv_error)?;
+        let field = |column: Option<usize>| column.and_then(|column| record.get(column));
+        let Some(symbol) = field(Some(ticker)).filter(|symbol| !symbol.is_empty()) else {
+            continue;
+        };
+        let row = || -> Result<Asset, String> {
+            let mut asset = imported_asset(
+                symbol,
+                parse_price(field(Some(buy_price)).unwrap_or(""))?,
+                parse_whole_quantity(field(Some(quantity)).unwrap_or(""))?,
+            );
+            asset.buy_date = field(buy_date)
+                .filter(|raw| !raw.is_empty())
+                .map(|raw| parse_buy_date(raw, date_order, today))
+                .transpose()?;
+            asset.sell_price_cents = match field(sell_price).filter(|raw| !raw.is_empty()) {
+                Some(raw) => Some(parse_price(raw)?),
+                None => None,
+            };
+            asset.sell_date = field(sell_date)
+                .filter(|raw| !raw.is_empty())
+                .map(|raw| parse_date(raw, date_order, today))
+                .transpose()?;
+            Ok(asset)
+        };
+        // +2: one for the header, one because lines count from 1
+        match row() {
+            Ok(asset) => imported.assets.push(asset),
+            Err(reason) => {
+                imported
+                    .skipped
+                    .push(format!("line {}: {} ({})", index + 2, symbol, reason))
+            }
+        }
+    }
+    Ok(imported)
+}
+
+fn detect_broker(raw: &str) -> Option<BrokerFormat> {
+    let headers = csv_reader(raw).headers().ok()?.clone();
+    let headers: Vec<String> = headers.iter().map(str::to_string).collect();
+    detect_csv_format(&headers)
+}
+
+// buys as Robinhood and Schwab write them in transaction histories
+fn is_buy(action: &str) -> bool {
+    ["buy", "bought", "buy to open", "reinvest shares"]
+        .iter()
+        .any(|buy| action.trim().eq_ignore_ascii_case(buy))
+}
+
+fn is_sell(action: &str) -> bool {
+    ["sell", "sold", "sell to close"]
+        .iter()
+        .any(|sell| action.trim().eq_ignore_ascii_case(sell))
+}
+
+// a broker's dates, which are month first in every export handled here
+fn broker_date(raw: &str) -> Result<Option<NaiveDate>, String> {
+    // Schwab writes e.g. "03/15/2024 as of 03/14/2024" for corrected trades
+    let raw = raw.split(" as of ").next().unwrap_or(raw);
+    optional_date(Some(raw), "%m/%d/%Y")
+        .or_else(|e| optional_date(Some(raw), "%Y-%m-%d").map_err(|_| e))
+}
+
+// any broker's CSV, read through a column mapping. rows without a ticker or
+// a quantity, like cash and account totals, and rows too short to hold the
+// columns, like a trailing disclaimer, are left out
+pub fn import_csv_with_config(
+    raw: &str,
+    config: &CsvImportConfig,
+) -> Result<Imported, PortfolioError> {
+    let mut reader = csv_reader(raw);
+    let headers = reader.headers().map_err(csv_error)?.clone();
+    let ticker = required_column(&headers, &[&config.ticker_col])?;
+    let buy_price = required_column(&headers, &[&config.buy_price_col])?;
+    let quantity = required_column(&headers, &[&config.quantity_col])?;
+    let optional = |name: &Option<String>| {
+        name.as_deref()
+            .map(|name| required_column(&headers, &[name]))
+            .transpose()
+    };
+    let date = optional(&config.date_col)?;
+    let sell_price = optional(&config.sell_price_col)?;
+    let action = optional(&config.action_col)?;
+
+    let mut imported = Imported::default();
+    for (index, record) in reader.records().enumerate() {
+        let record = record.map_err(csv_error)?;
+        let Some(symbol) = record.get(ticker).filter(|symbol| !symbol.is_empty()) else {
+            continue;
+        };
+        if record.len() <= quantity.max(buy_price)
+            || matches!(record.get(quantity), Some("" | "--"))
+        {
+            continue;
+        }
+        if let Some(action) = action.map(|column| record.get(column).unwrap_or("")) {
+            if is_sell(action) {
+                imported
+                    .skipped
+                    .push(format!("line {}: {} (sell)", index + 2, symbol));
+            }
+            if !is_buy(action) {
+                continue;
+            }
+        }
+        let row = || -> Result<Asset, String> {
+            let shares = parse_whole_quantity(record.get(quantity).unwrap_or(""))?;
+            let raw_price = record.get(buy_price).unwrap_or("");
+            let price = parse_decimal_cents(raw_price)
+                .ok_or_else(|| format!("'{}' isn't a price", raw_price))?;
+            let price = if config.buy_price_is_total {
+                ((price as u64 + shares as u64 / 2) / shares as u64) as u32
+            } else {
+                price
+            };
+            let mut asset = imported_asset(symbol, price, shares);
+            asset.buy_date = match date.and_then(|column| record.get(column)) {
+                Some(raw) => broker_date(raw)?,
+                None => None,
+            };
+            asset.sell_price_cents = match sell_price
+                .and_then(|column| record.get(column))
+                .filter(|raw| !raw.is_empty())
+            {
+                Some(raw) => Some(
+                    parse_decimal_cents(raw).ok_or_else(|| format!("'{}' isn't a price", raw))?,
+                ),
+                None => None,
+            };
+            Ok(asset)
+        };
+        match row() {
+            Ok(asset) => imported.assets.push(asset),
+            Err(reason) => {
+                imported
+                    .skipped
+                    .push(format!("line {}: {} ({})", index + 2, symbol, reason))
+            }
+        }
+    }
+    Ok(imported)
+}
+
+// Schwab's positions export, where the cost basis is what the whole
+// position cost
+pub fn import_schwab(raw: &str) -> Result<Imported, PortfolioError> {
+    import_csv_with_config(raw, &CsvImportConfig::from_format(BrokerFormat::Schwab))
+}
+
+// Fidelity's positions export, with one row per holding and a block of
+// disclaimers after the data. cash (money market) positions are skipped
+pub fn import_fidelity(raw: &str) -> Result<Imported, PortfolioError> {
+    let mut reader = csv_reader(raw);
+    let headers = reader.headers().map_err(csv_error)?.clone();
+    let symbol = required_column(&headers, &["Symbol"])?;
+    let quantity = required_column(&headers, &["Quantity"])?;
+    let cost = required_column(&headers, &["Average Cost Basis", "Cost Basis Per Share"])?;
+    let last_price = column(&headers, &["Last Price"]);
+
+    let mut imported = Imported::default();
+    for (index, record) in reader.records().enumerate() {
+        let record = record.map_err(csv_error)?;
+        let Some(ticker) = record.get(symbol).filter(|ticker| !ticker.is_empty()) else {
+            continue;
+        };
+        // the disclaimer block has a single long field per line
+        if record.len() <= quantity {
+            continue;
+        }
+        if ticker.ends_with("**") {
+            imported
+                .skipped
+                .push(format!("line {}: {} (cash position)", index + 2, ticker));
+            continue;
+        }
+        let row = || -> Result<Asset, String> {
+            let raw_cost = record.get(cost).unwrap_or("");
+            let buy_price = parse_decimal_cents(raw_cost)
+                .ok_or_else(|| format!("'{}' isn't a cost basis", raw_cost))?;
+            let mut asset = imported_asset(
+                ticker,
+                buy_price,
+                parse_whole_quantity(record.get(quantity).unwrap_or(""))?,
+            );
+            if let Some(price) = last_price
+                .and_then(|column| record.get(column))
+                .and_then(parse_decimal_cents)
+            {
+                asset.current_price_cents = price;
+            }
+            Ok(asset)
+        };
+        match row() {
+            Ok(asset) => imported.assets.push(asset),
+            Err(reason) => {
+                imported
+                    .skipped
+                    .push(format!("line {}: {} ({})", index + 2, ticker, reason))
+            }
+        }
+    }
+    Ok(imported)
+}
+
+// Robinhood's activity report lists every transaction; each Buy becomes its
+// own lot. sells can't be matched to lots, so they're reported as skipped
+pub fn import_robinhood(raw: &str) -> Result<Imported, PortfolioError> {
+    let mut reader = csv_reader(raw);
+    let headers = reader.headers().map_err(csv_error)?.clone();
+    let date = required_column(&headers, &["Activity Date"])?;
+    let instrument = required_column(&headers, &["Instrument"])?;
+    let code = required_column(&headers, &["Trans Code"])?;
+    let quantity = required_column(&headers, &["Quantity"])?;
+    let price = required_column(&headers, &["Price"])?;
+
+    let mut imported = Imported::default();
+    for (index, record) in reader.records().enumerate() {
+        let record = record.map_err(csv_error)?;
+        let Some(ticker) = record.get(instrument).filter(|ticker| !ticker.is_empty()) else {
+            continue;
+        };
+        let trans_code = record.get(code).unwrap_or("");
+        if trans_code != "Buy" {
+            if trans_code == "Sell" {
+                imported
+                    .skipped
+                    .push(format!("line {}: {} (sell)", index + 2, ticker));
+            }
+            continue;
+        }
+        let row = || -> Result<Asset, String> {
+            let raw_price = record.get(price).unwrap_or("");
+            let buy_price = parse_decimal_cents(raw_price)
+                .ok_or_else(|| format!("'{}' isn't a price", raw_price))?;
+            let mut asset = imported_asset(
+                ticker,
+                buy_price,
+                parse_whole_quantity(record.get(quantity).unwrap_or(""))?,
+            );
+            asset.buy_date = optional_date(record.get(date), "%m/%d/%Y")?;
+            Ok(asset)
+        };
+        match row() {
+            Ok(asset) => imported.assets.push(asset),
+            Err(reason) => {
+                imported
+                    .skipped
+                    .push(format!("line {}: {} ({})", index + 2, ticker, reason))
+            }
+        }
+    }
+    Ok(imported)
+}
+
+// postings with a cost basis, dated by the transaction they belong to:
+//
+//   2024-01-05 * "Buy Apple"
+//     Assets:Broker:AAPL   10 AAPL {150.00 USD}
+//     Assets:Broker:Cash  -1500.00 USD
+//
+// reductions (negative quantities) are sales and are skipped
+pub fn import_beancount(raw: &str) -> Imported {
+    let mut imported = Imported::default();
+    let mut date: Option<NaiveDate> = None;
+    for (index, line) in raw.lines().enumerate() {
+        let line = line.split(';').next().unwrap_or("");
+        if !line.starts_with(char::is_whitespace) {
+            date = line
+                .split_whitespace()
+                .next()
+                .and_then(|first| NaiveDate::parse_from_str(first, "%Y-%m-%d").ok());
+            continue;
+        }
+        let (Some(open), Some(close)) = (line.find('{'), line.find('}')) else {
+            continue;
+        };
+        let mut amount = line[..open].split_whitespace().skip(1);
+        let (Some(raw_quantity), Some(ticker)) = (amount.next(), amount.next()) else {
+            continue;
+        };
+        let row = || -> Result<Asset, String> {
+            if raw_quantity.starts_with('-') {
+                return Err("sale".to_string());
+            }
+            let cost = line[open + 1..close]
+                .split_whitespace()
+                .next()
+                .unwrap_or("");
+            let buy_price =
+                parse_decimal_cents(cost).ok_or_else(|| format!("'{}' isn't a cost", cost))?;
+            let mut asset = imported_asset(ticker, buy_price, parse_whole_quantity(raw_quantity)?);
+            asset.buy_date = date;
+            Ok(asset)
+        };
+        match row() {
+            Ok(asset) => imported.assets.push(asset),
+            Err(reason) => {
+                imported
+                    .skipped
+                    .push(format!("line {}: {} ({})", index + 1, ticker, reason))
+            }
+        }
+    }
+    imported
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    fn summary(imported: &Imported) -> Vec<(String, u32, u32)> {
+        imported
+            .assets
+            .iter()
+            .map(|asset| (asset.ticker.clone(), asset.buy_price_cents, asset.quantity))
+            .collect()
+    }
+
+    #[test]
+    fn decimal_cents_round_to_the_nearest_cent() {
+        assert_eq!(parse_decimal_cents("$1,234.5678"), Some(123457));
+        assert_eq!(parse_decimal_cents("150.2345"), Some(15023));
+        assert_eq!(parse_decimal_cents("42"), Some(4200));
+        assert_eq!(parse_decimal_cents("--"), None);
+    }
+
+    #[test]
+    fn quantities_must_be_whole() {
+        assert_eq!(parse_whole_quantity("10.000"), Ok(10));
+        assert_eq!(parse_whole_quantity("1,000"), Ok(1000));
+        assert_eq!(
+            parse_whole_quantity("0.5"),
+            Err("fractional quantity 0.5".to_string())
+        );
+    }
+
+    #[test]
+    fn generic_csv() {
+        let raw = "ticker,buy_price,quantity,buy_date,sell_price\n\
+                   aapl,$150.00,10,2023-01-05,\n\
+                   msft,25000,5,,30000\n\
+                   tsla,twelve,1,,\n";
+        let imported = import_csv(raw, DateOrder::Mdy, date(2024, 6, 10)).unwrap();
+        assert_eq!(
+            summary(&imported),
+            vec![
+                ("AAPL".to_string(), 15000, 10),
+                ("MSFT".to_string(), 25000, 5)
+            ]
+        );
+        assert_eq!(imported.assets[0].buy_date, Some(date(2023, 1, 5)));
+        assert_eq!(imported.assets[1].sell_price_cents, Some(30000));
+        assert_eq!(imported.skipped.len(), 1);
+        assert!(imported.skipped[0].starts_with("line 4: tsla"));
+    }
+
+    #[test]
+    fn generic_csv_needs_its_columns() {
+        assert!(import_csv("symbol,price\nAAPL,1\n", DateOrder::Mdy, date(2024, 6, 10)).is_err());
+    }
+
+    #[test]
+    fn generic_csv_dates_use_the_date_order() {
+        let raw = "ticker,buy_price,quantity,buy_date,sell_price,sell_date\n\
+                   aapl,150,10,5.1.2023,,\n\
+                   msft,250,5,2023-02-30,,\n\
+                   nvda,400,2,1.7.2024,,\n\
+                   tsla,200,1,2.3.2023,210,today\n";
+        let imported = import_csv(raw, DateOrder::Dmy, date(2024, 6, 10)).unwrap();
+        assert_eq!(imported.assets.len(), 2);
+        assert_eq!(imported.assets[0].buy_date, Some(date(2023, 1, 5)));
+        assert_eq!(imported.assets[1].buy_date, Some(date(2023, 3, 2)));
+        assert_eq!(imported.assets[1].sell_date, Some(date(2024, 6, 10)));
+        assert_eq!(
+            imported.skipped,
+            vec![
+                "line 3: msft ('2023-02-30' isn't a real date — February 2023 has 28 days)",
+                "line 4: nvda (2024-07-01 is in the future — a buy date can be today (2024-06-10) at the latest)"
+            ]
+        );
+    }
+
+    #[test]
+    fn fidelity_positions() {
+        let raw = "Account Number,Account Name,Symbol,Description,Quantity,Last Price,Current Value,Average Cost Basis\n\
+                   X123,Individual,SPAXX**,HELD IN MONEY MARKET,,,$812.20,\n\
+                   X123,Individual,AAPL,APPLE INC,10,$189.42,$1894.20,$150.2345\n\
+                   X123,Individual,VTI,VANGUARD TOTAL,2.5,$240.00,$600.00,$200.00\n\
+                   \n\
+                   \"The data and information in this spreadsheet is provided to you solely for your use.\"\n";
+        let imported = import_fidelity(raw).unwrap();
+        assert_eq!(summary(&imported), vec![("AAPL".to_string(), 15023, 10)]);
+        assert_eq!(imported.assets[0].current_price_cents, 18942);
+        assert_eq!(imported.skipped.len(), 2);
+    }
+
+    #[test]
+    fn robinhood_activity() {
+        let raw = "Activity Date,Process Date,Settle Date,Instrument,Description,Trans Code,Quantity,Price,Amount\n\
+                   3/15/2024,3/15/2024,3/19/2024,AAPL,Apple,Buy,10,$172.50,($1725.00)\n\
+                   3/20/2024,3/20/2024,3/22/2024,AAPL,Apple,Sell,5,$180.00,$900.00\n\
+                   3/21/2024,3/21/2024,3/21/2024,,Deposit,ACH,,,$500.00\n";
+        let imported = import_robinhood(raw).unwrap();
+        assert_eq!(summary(&imported), vec![("AAPL".to_string(), 17250, 10)]);
+        assert_eq!(imported.assets[0].buy_date, Some(date(2024, 3, 15)));
+        assert_eq!(imported.skipped, vec!["line 3: AAPL (sell)".to_string()]);
+    }
+
+    #[test]
+    fn mapped_csv_reads_total_costs_and_actions() {
+        let raw = "Date,Action,Symbol,Quantity,Amount,Sold At\n\
+                   03/15/2024,Buy,AAPL,10,\"$1,725.00\",\n\
+                   03/16/2024 as of 03/15/2024,Bought,MSFT,3,\"$1,200.01\",$410\n\
+                   03/20/2024,Sell,AAPL,5,$900.00,\n\
+                   03/21/2024,Qual Div Reinvest,AAPL,,$4.20,\n";
+        let config = CsvImportConfig {
+            ticker_col: "symbol".to_string(),
+            buy_price_col: "Amount".to_string(),
+            quantity_col: "Quantity".to_string(),
+            date_col: Some("Date".to_string()),
+            sell_price_col: Some("Sold At".to_string()),
+            action_col: Some("Action".to_string()),
+            buy_price_is_total: true,
+        };
+        let imported = import_csv_with_config(raw, &config).unwrap();
+        assert_eq!(
+            summary(&imported),
+            vec![
+                ("AAPL".to_string(), 17250, 10),
+                ("MSFT".to_string(), 40000, 3)
+            ]
+        );
+        assert_eq!(imported.assets[1].buy_date, Some(date(2024, 3, 16)));
+        assert_eq!(imported.assets[1].sell_price_cents, Some(41000));
+        assert_eq!(imported.skipped, vec!["line 4: AAPL (sell)".to_string()]);
+
+        let missing = CsvImportConfig {
+            date_col: Some("Trade Date".to_string()),
+            ..config
+        };
+        let e = import_csv_with_config(raw, &missing).unwrap_err();
+        assert!(e.to_string().contains("no 'Trade Date' column"));
+    }
+
+    #[test]
+    fn schwab_positions() {
+        let raw = "Symbol,Description,Quantity,Price,Market Value,Cost Basis,Gain/Loss %\n\
+                   AAPL,APPLE INC,10,$189.42,$1894.20,$1502.35,26.08%\n\
+                   Cash & Cash Investments,--,--,--,$812.20,--,--\n\
+                   Account Total,--,--,--,$2706.40,--,--\n";
+        let imported = import_schwab(raw).unwrap();
+        assert_eq!(summary(&imported), vec![("AAPL".to_string(), 15024, 10)]);
+        assert!(imported.skipped.is_empty());
+    }
+
+    #[test]
+    fn broker_formats_are_detected_by_their_headers() {
+        let headers = |line: &str| -> Vec<String> { line.split(',').map(str::to_string).collect() };
+        assert_eq!(
+            detect_csv_format(&headers(
+                "Activity Date,Instrument,Trans Code,Quantity,Price"
+            )),
+            Some(BrokerFormat::Robinhood)
+        );
+        assert_eq!(
+            detect_csv_format(&headers(
+                "Symbol,Quantity,Last Price,Average Cost Basis,Cost Basis Total"
+            )),
+            Some(BrokerFormat::Fidelity)
+        );
+        assert_eq!(
+            detect_csv_format(&headers("symbol,description,quantity,price,cost basis")),
+            Some(BrokerFormat::Schwab)
+        );
+        assert_eq!(
+            detect_csv_format(&headers("ticker,buy_price,quantity")),
+            None
+        );
+    }
+
+    #[test]
+    fn beancount_postings() {
+        let raw = "2024-01-05 * \"Buy Apple\"\n  \
+                   Assets:Broker:AAPL   10 AAPL {150.00 USD} ; first lot\n  \
+                   Assets:Broker:Cash  -1500.00 USD\n\
+                   \n\
+                   2024-02-01 * \"Sell Apple\"\n  \
+                   Assets:Broker:AAPL  -5 AAPL {150.00 USD} @ 170.00 USD\n";
+        let imported = import_beancount(raw);
+        assert_eq!(summary(&imported), vec![("AAPL".to_string(), 15000, 10)]);
+        assert_eq!(imported.assets[0].buy_date, Some(date(2024, 1, 5)));
+        assert_eq!(imported.skipped, vec!["line 6: AAPL (sale)".to_string()]);
+    }
+
+    #[test]
+    fn format_names() {
+        assert_eq!(
+            ImportFormat::from_name("Fidelity"),
+            Some(ImportFormat::Fidelity)
+        );
+        assert_eq!(ImportFormat::from_name("xlsx"), None);
+    }
+}
@@ -0,0 +1,536 @@
+// parsing for values typed at prompts. the errors are messages to show the
+// user before asking again, so they say what was wrong and what to type
+use crate::config::DateOrder;
+use crate::portfolio::{BuyAmount, StopLoss};
+use chrono::{Datelike, Month, Months, NaiveDate};
+
+// the formats parse_price accepts, for prompts to show
+pub const PRICE_FORMATS: &str = "$1,234.56, 1234.56, or whole cents like 123456";
+
+// a price in dollars ("$1,234.56", "1234.5") or, written as a bare whole
+// number, in cents ("123456"), so existing habits and batch files keep
+// working. dollars are split at the point rather than parsed as a float so
+// the conversion is exact
+pub fn parse_price(raw: &str) -> Result<u32, String> {
+    let raw = raw.trim();
+    let invalid = || {
+        format!(
+            "'{}' isn't a price — enter dollars like 1234.56 or whole cents like 123456",
+            raw
+        )
+    };
+    let is_dollars = raw.contains(['$', '.', ',']);
+    if !is_dollars {
+        return raw.parse().map_err(|_| invalid());
+    }
+    dollars_to_cents(raw, invalid)?
+        .try_into()
+        .map_err(|_| format!("'{}' is too large a price", raw))
+}
+
+// "$1,234.56", "1234.5" or "1234" in dollars, converted to cents
+fn dollars_to_cents(raw: &str, invalid: impl Fn() -> String) -> Result<u64, String> {
+    let amount = raw.strip_prefix('$').unwrap_or(raw);
+    let (whole, fraction) = amount.split_once('.').unwrap_or((amount, ""));
+    let digits_missing = whole.is_empty() && fraction.is_empty();
+    if digits_missing || !valid_thousands(whole) || !fraction.chars().all(|c| c.is_ascii_digit()) {
+        return Err(invalid());
+    }
+    if fraction.len() > 2 {
+        return Err(format!("'{}' has fractions of a cent", raw));
+    }
+    let whole: u64 = match whole.replace(',', "").as_str() {
+        "" => 0,
+        digits => digits.parse().map_err(|_| invalid())?,
+    };
+    let fraction: u64 = format!("{:0<2}", fraction).parse().map_err(|_| invalid())?;
+    whole
+        .checked_mul(100)
+        .and_then(|cents| cents.checked_add(fraction))
+        .ok_or_else(invalid)
+}
+
+// a goal's target value. unlike prices, bare numbers are dollars, since
+// goals are typed as round amounts like 1000000
+pub fn parse_goal_target(raw: &str) -> Result<u64, String> {
+    let raw = raw.trim();
+    let invalid = || {
+        format!(
+            "'{}' isn't an amount — enter dollars like 1000000 or $250,000",
+            raw
+        )
+    };
+    match dollars_to_cents(raw, invalid)? {
+        0 => Err("The target must be more than $0".to_string()),
+        cents => Ok(cents),
+    }
+}
+
+// digits, optionally grouped in threes by commas, e.g. "1,234,567"
+fn valid_thousands(whole: &str) -> bool {
+    if !whole.contains(',') {
+        return whole.chars().all(|c| c.is_ascii_digit());
+    }
+    let mut groups = whole.split(',');
+    let first = groups.next().unwrap_or("");
+    (1..=3).contains(&first.len())
+        && first.chars().all(|c| c.is_ascii_digit())
+        && groups.all(|group| group.len() == 3 && group.chars().all(|c| c.is_ascii_digit()))
+}
+
+// 'held', or the price it was sold at
+pub fn parse_sell_price(raw: &str) -> Result<Option<u32>, String> {
+    let raw = raw.trim();
+    if raw.eq_ignore_ascii_case("held") {
+        return Ok(None);
+    }
+    parse_price(raw)
+        .map(Some)
+        .map_err(|e| format!("{}, or 'held'", e))
+}
+
+// what to buy for `dca`: dollars like "$500", or a number of shares
+pub fn parse_buy_amount(raw: &str) -> Result<BuyAmount, String> {
+    let raw = raw.trim();
+    if !raw.starts_with('$') {
+        return parse_quantity(raw)
+            .map(BuyAmount::Shares)
+            .map_err(|e| format!("{}, or dollars like $500", e));
+    }
+    let invalid = || format!("'{}' isn't an amount — enter dollars like $500", raw);
+    match dollars_to_cents(raw, invalid)? {
+        0 => Err("The amount must be more than $0".to_string()),
+        cents => Ok(BuyAmount::Cents(cents)),
+    }
+}
+
+// a price per share, or a percent below the buy price like "10%". empty
+// removes the stop
+pub fn parse_stop_loss(raw: &str) -> Result<Option<StopLoss>, String> {
+    let raw = raw.trim();
+    if raw.is_empty() {
+        return Ok(None);
+    }
+    let Some(percent) = raw.strip_suffix('%') else {
+        return parse_price(raw)
+            .map(|cents| Some(StopLoss::Price(cents)))
+            .map_err(|e| format!("{}, or a percent below the buy price like 10%", e));
+    };
+    match percent.trim().parse::<f64>() {
+        Ok(percent) if percent > 0.0 && percent < 100.0 => Ok(Some(StopLoss::Percent(percent))),
+        _ => Err(format!(
+            "'{}' isn't a percent below the buy price — enter one between 0 and 100 like 10%",
+            raw
+        )),
+    }
+}
+
+pub fn parse_quantity(raw: &str) -> Result<u32, String> {
+    let raw = raw.trim();
+    match raw.parse::<u32>() {
+        Ok(0) => Err("The quantity must be at least 1".to_string()),
+        Ok(quantity) => Ok(quantity),
+        Err(_) => Err(format!(
+            "'{}' isn't a whole number — enter a number of shares like 10",
+            raw
+        )),
+    }
+}
+
+// a date as it's written at the prompt or in an imported file: ISO
+// (2024-03-15), numeric in the configured order (3/15/2024 or 15.3.2024), or
+// 'today' / 'yesterday'. every date is naive, time zones never come into it
+pub fn parse_date(raw: &str, order: DateOrder, today: NaiveDate) -> Result<NaiveDate, String> {
+    let raw = raw.trim();
+    match raw.to_lowercase().as_str() {
+        "today" => return Ok(today),
+        "yesterday" => return today.pred_opt().ok_or_else(|| invalid_date(raw, order)),
+        _ => {}
+    }
+    let parts: Vec<&str> = raw.split(['-', '/', '.']).collect();
+    let numeric = parts
+        .iter()
+        .all(|part| !part.is_empty() && part.chars().all(|c| c.is_ascii_digit()));
+    if parts.len() != 3 || !numeric {
+        return Err(invalid_date(raw, order));
+    }
+    let (year, month, day) = if raw.contains('-') {
+        (parts[0], parts[1], parts[2])
+    } else {
+        match order {
+            DateOrder::Mdy => (parts[2], parts[0], parts[1]),
+            DateOrder::Dmy => (parts[2], parts[1], parts[0]),
+        }
+    };
+    if year.len() != 4 || month.len() > 2 || day.len() > 2 {
+        return Err(invalid_date(raw, order));
+    }
+    let (year, month, day): (i32, u32, u32) = (
+        year.parse().map_err(|_| invalid_date(raw, order))?,
+        month.parse().map_err(|_| invalid_date(raw, order))?,
+        day.parse().map_err(|_| invalid_date(raw, order))?,
+    );
+    let Some(month_name) = u8::try_from(month)
+        .ok()
+        .and_then(|month| Month::try_from(month).ok())
+    else {
+        let hint = match (raw.contains('-'), order) {
+            (true, _) => "",
+            (false, DateOrder::Mdy) => {
+                " (dates are read month first; 'set date-order dmy' reads them day first)"
+            }
+            (false, DateOrder::Dmy) => {
+                " (dates are read day first; 'set date-order mdy' reads them month first)"
+            }
+        };
+        return Err(format!("'{}' has no month {}{}", raw, month, hint));
+    };
+    NaiveDate::from_ymd_opt(year, month, day).ok_or_else(|| {
+        let days = NaiveDate::from_ymd_opt(year, month, 1)
+            .and_then(|first| first.checked_add_months(Months::new(1)))
+            .and_then(|next| next.pred_opt())
+            .map_or(31, |last| last.day());
+        format!(
+            "'{}' isn't a real date — {} {} has {} days",
+            raw,
+            month_name.name(),
+            year,
+            days
+        )
+    })
+}
+
+fn invalid_date(raw: &str, order: DateOrder) -> String {
+    format!(
+        "'{}' isn't a date — enter it like 2024-03-15, {} or 'today'",
+        raw,
+        match order {
+            DateOrder::Mdy => "3/15/2024",
+            DateOrder::Dmy => "15.3.2024",
+        }
+    )
+}
+
+// a buy date can't be later than today
+pub fn parse_buy_date(raw: &str, order: DateOrder, today: NaiveDate) -> Result<NaiveDate, String> {
+    let date = parse_date(raw, order, today)?;
+    if date > today {
+        return Err(format!(
+            "{} is in the future — a buy date can be today ({}) at the latest",
+            date, today
+        ));
+    }
+    Ok(date)
+}
+
+// 'unknown', or a date parsed by `parse`
+pub fn parse_optional_date(
+    raw: &str,
+    parse: impl Fn(&str) -> Result<NaiveDate, String>,
+) -> Result<Option<NaiveDate>, String> {
+    if raw.trim().eq_ignore_ascii_case("unknown") {
+        return Ok(None);
+    }
+    parse(raw)
+        .map(Some)
+        .map_err(|e| format!("{}, or 'unknown'", e))
+}
+
+// a sell date can't be before the lot was bought
+pub fn parse_sell_date(
+    raw: &str,
+    order: DateOrder,
+    today: NaiveDate,
+    buy_date: Option<NaiveDate>,
+) -> Result<NaiveDate, String> {
+    let date = parse_date(raw, order, today)?;
+    match buy_date {
+        Some(buy_date) if date < buy_date => {
+            Err(format!("{} is before the buy date ({})", date, buy_date))
+        }
+        _ => Ok(date),
+    }
+}
+
+// a row number from a numbered list of `count` rows, returned as a position
+// counting from 0
+pub fn parse_choice(raw: &str, count: usize) -> Result<usize, String> {
+    let raw = raw.trim();
+    match raw.parse::<usize>() {
+        Ok(number) if (1..=count).contains(&number) => Ok(number - 1),
+        _ => Err(format!(
+            "'{}' isn't in the list — enter a number from 1 to {}, or 'cancel'",
+            raw, count
+        )),
+    }
+}
+
+// the ranges Yahoo's chart endpoint accepts
+pub const HISTORY_PERIODS: [&str; 9] = ["1mo", "3mo", "6mo", "1y", "2y", "5y", "10y", "ytd", "max"];
+
+// a period like 1y or 6mo. 6m is taken to mean months, since Yahoo has no
+// minute-long range
+pub fn parse_history_period(raw: &str) -> Result<&'static str, String> {
+    let raw = raw.trim().to_lowercase();
+    let months = raw.strip_suffix('m').map(|count| format!("{}mo", count));
+    HISTORY_PERIODS
+        .into_iter()
+        .find(|period| *period == raw || Some(*period) == months.as_deref())
+        .ok_or_else(|| {
+            format!(
+                "'{}' isn't a period — use one of {}",
+                raw,
+                HISTORY_PERIODS.join(", ")
+            )
+        })
+}
+
+// splits a command line into words like split_whitespace, except that
+// "double quoted" text stays one word, so names can have spaces
+pub fn split_quoted(line: &str) -> Vec<String> {
+    let mut words = vec![];
+    let mut word = String::new();
+    let mut quoted = false;
+    // set by a quote, so "" is kept as an empty word
+    let mut started = false;
+    for c in line.chars() {
+        match c {
+            '"' => {
+                quoted = !quoted;
+                started = true;
+            }
+            c if c.is_whitespace() && !quoted => {
+                if started || !word.is_empty() {
+                    words.push(std::mem::take(&mut word));
+                }
+                started = false;
+            }
+            c => word.push(c),
+        }
+    }
+    if started || !word.is_empty() {
+        words.push(word);
+    }
+    words
+}
+
+// comma separated tags, trimmed and lowercased, without blanks or repeats
+pub fn parse_tags(raw: &str) -> Vec<String> {
+    let mut tags: Vec<String> = vec![];
+    for tag in raw.split(',').map(|tag| tag.trim().to_lowercase()) {
+        if !tag.is_empty() && !tags.contains(&tag) {
+            tags.push(tag);
+        }
+    }
+    tags
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bare_whole_numbers_are_cents() {
+        assert_eq!(parse_price(" 123456 "), Ok(123456));
+        assert_eq!(
+            parse_price("twelve"),
+            Err(
+                "'twelve' isn't a price — enter dollars like 1234.56 or whole cents like 123456"
+                    .to_string()
+            )
+        );
+        assert!(parse_price("-5").is_err());
+    }
+
+    #[test]
+    fn dollars_convert_exactly() {
+        assert_eq!(parse_price("1234.56"), Ok(123456));
+        assert_eq!(parse_price("$1,234.56"), Ok(123456));
+        assert_eq!(parse_price("$1,200"), Ok(120000));
+        assert_eq!(parse_price("0.29"), Ok(29));
+        assert_eq!(parse_price("19.9"), Ok(1990));
+        assert_eq!(parse_price(".50"), Ok(50));
+        assert_eq!(parse_price("$42"), Ok(4200));
+    }
+
+    #[test]
+    fn malformed_dollars_are_rejected() {
+        assert!(parse_price("1,23.45").is_err());
+        assert!(parse_price("1.2.3").is_err());
+        assert!(parse_price("$").is_err());
+        assert_eq!(
+            parse_price("1.234"),
+            Err("'1.234' has fractions of a cent".to_string())
+        );
+        assert!(parse_price("$99,999,999.99").is_err());
+    }
+
+    #[test]
+    fn sell_price_accepts_held() {
+        assert_eq!(parse_sell_price("held"), Ok(None));
+        assert_eq!(parse_sell_price("15000"), Ok(Some(15000)));
+        assert_eq!(parse_sell_price("$150"), Ok(Some(15000)));
+        assert!(parse_sell_price("sold").unwrap_err().contains("'held'"));
+    }
+
+    #[test]
+    fn quantity_must_be_positive() {
+        assert_eq!(parse_quantity("10"), Ok(10));
+        assert!(parse_quantity("0").is_err());
+        assert!(parse_quantity("2.5").is_err());
+    }
+
+    fn day(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    #[test]
+    fn dates_parse_in_every_format() {
+        let today = day(2024, 6, 10);
+        let expected = Ok(day(2024, 3, 15));
+        assert_eq!(parse_date("2024-03-15", DateOrder::Mdy, today), expected);
+        assert_eq!(parse_date(" 2024-3-15 ", DateOrder::Dmy, today), expected);
+        assert_eq!(parse_date("3/15/2024", DateOrder::Mdy, today), expected);
+        assert_eq!(parse_date("15.3.2024", DateOrder::Dmy, today), expected);
+        assert_eq!(parse_date("15/03/2024", DateOrder::Dmy, today), expected);
+        assert_eq!(parse_date("Today", DateOrder::Mdy, today), Ok(today));
+        assert_eq!(
+            parse_date("yesterday", DateOrder::Mdy, today),
+            Ok(day(2024, 6, 9))
+        );
+    }
+
+    #[test]
+    fn impossible_dates_say_why() {
+        let today = day(2024, 6, 10);
+        assert_eq!(
+            parse_date("2023-02-29", DateOrder::Mdy, today),
+            Err("'2023-02-29' isn't a real date — February 2023 has 28 days".to_string())
+        );
+        assert_eq!(
+            parse_date("2/30/2024", DateOrder::Mdy, today),
+            Err("'2/30/2024' isn't a real date — February 2024 has 29 days".to_string())
+        );
+        assert!(parse_date("15.3.2024", DateOrder::Mdy, today)
+            .unwrap_err()
+            .contains("has no month 15 (dates are read month first"));
+        assert_eq!(
+            parse_date("3/15/24", DateOrder::Mdy, today),
+            Err(
+                "'3/15/24' isn't a date — enter it like 2024-03-15, 3/15/2024 or 'today'"
+                    .to_string()
+            )
+        );
+        assert!(parse_date("2024-03", DateOrder::Mdy, today).is_err());
+        assert!(parse_date("next week", DateOrder::Mdy, today).is_err());
+    }
+
+    #[test]
+    fn buy_dates_cannot_be_in_the_future() {
+        let today = day(2024, 6, 10);
+        assert_eq!(parse_buy_date("today", DateOrder::Mdy, today), Ok(today));
+        assert_eq!(
+            parse_buy_date("2024-06-11", DateOrder::Mdy, today),
+            Err(
+                "2024-06-11 is in the future — a buy date can be today (2024-06-10) at the latest"
+                    .to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn unknown_dates_are_none() {
+        let parse = |raw: &str| parse_date(raw, DateOrder::Mdy, day(2024, 6, 10));
+        assert_eq!(parse_optional_date("Unknown", parse), Ok(None));
+        assert_eq!(
+            parse_optional_date("2024-01-02", parse),
+            Ok(Some(day(2024, 1, 2)))
+        );
+        assert!(parse_optional_date("soon", parse)
+            .unwrap_err()
+            .ends_with(", or 'unknown'"));
+    }
+
+    #[test]
+    fn sell_dates_cannot_be_before_the_buy_date() {
+        let today = day(2024, 6, 10);
+        let bought = Some(day(2024, 3, 15));
+        assert_eq!(
+            parse_sell_date("3/14/2024", DateOrder::Mdy, today, bought),
+            Err("2024-03-14 is before the buy date (2024-03-15)".to_string())
+        );
+        assert_eq!(
+            parse_sell_date("today", DateOrder::Mdy, today, bought),
+            Ok(today)
+        );
+        assert_eq!(
+            parse_sell_date("2020-01-01", DateOrder::Mdy, today, None),
+            Ok(day(2020, 1, 1))
+        );
+    }
+
+    #[test]
+    fn choices_are_numbered_from_one() {
+        assert_eq!(parse_choice(" 3 ", 3), Ok(2));
+        assert_eq!(parse_choice("1", 3), Ok(0));
+        assert_eq!(
+            parse_choice("4", 3),
+            Err("'4' isn't in the list — enter a number from 1 to 3, or 'cancel'".to_string())
+        );
+        assert!(parse_choice("0", 3).is_err());
+        assert!(parse_choice("first", 3).is_err());
+    }
+
+    #[test]
+    fn tags_are_normalized() {
+        assert_eq!(
+            parse_tags(" Tech, long-term,,tech "),
+            vec!["tech".to_string(), "long-term".to_string()]
+        );
+        assert!(parse_tags(" , ").is_empty());
+    }
+
+    #[test]
+    fn history_periods() {
+        assert_eq!(parse_history_period("1Y"), Ok("1y"));
+        assert_eq!(parse_history_period("6m"), Ok("6mo"));
+        assert_eq!(parse_history_period("ytd"), Ok("ytd"));
+        assert!(parse_history_period("3w").is_err());
+    }
+
+    #[test]
+    fn goal_targets_are_dollars() {
+        assert_eq!(parse_goal_target("1000000"), Ok(100_000_000));
+        assert_eq!(parse_goal_target("$250,000.50"), Ok(25_000_050));
+        assert!(parse_goal_target("0").is_err());
+        assert!(parse_goal_target("a lot").is_err());
+        assert!(parse_goal_target("99999999999999999999").is_err());
+    }
+
+    #[test]
+    fn buy_amounts_are_dollars_or_shares() {
+        assert_eq!(parse_buy_amount("$1,500"), Ok(BuyAmount::Cents(150000)));
+        assert_eq!(parse_buy_amount("12"), Ok(BuyAmount::Shares(12)));
+        assert!(parse_buy_amount("$0").is_err());
+        assert!(parse_buy_amount("1.5").is_err());
+    }
+
+    #[test]
+    fn stop_losses_are_prices_or_percents() {
+        assert_eq!(parse_stop_loss("$135"), Ok(Some(StopLoss::Price(13500))));
+        assert_eq!(parse_stop_loss("12.5 %"), Ok(Some(StopLoss::Percent(12.5))));
+        assert_eq!(parse_stop_loss(" "), Ok(None));
+        assert!(parse_stop_loss("100%").is_err());
+        assert!(parse_stop_loss("-5%").is_err());
+        assert!(parse_stop_loss("soon").is_err());
+    }
+
+    #[test]
+    fn quoted_words_stay_together() {
+        assert_eq!(
+            split_quoted(r#"goal add "Beach  house" 1000000 2045-01-01"#),
+            vec!["goal", "add", "Beach  house", "1000000", "2045-01-01"]
+        );
+        assert_eq!(split_quoted(r#"  a "" b "#), vec!["a", "", "b"]);
+    }
+}
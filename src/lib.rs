@@ -0,0 +1,22 @@
+pub mod api;
+pub mod backup;
+pub mod cli;
+pub mod commands;
+pub mod config;
+pub mod demo;
+pub mod display;
+pub mod dividends;
+pub mod error;
+pub mod export;
+pub mod goals;
+pub mod health;
+pub mod import;
+pub mod input;
+pub mod lint;
+pub mod lock;
+pub mod metadata;
+pub mod persistence;
+pub mod portfolio;
+pub mod quotes;
+pub mod recent;
+pub mod report;
@@ -0,0 +1,206 @@
+// data quality checks for `lint`. none of these are necessarily wrong, they
+// are just unusual enough that they are more likely to be typos than real
+use crate::portfolio::Asset;
+use chrono::NaiveDate;
+
+// more shares than this of a single asset is more likely a typo than a real position
+pub const MAX_PLAUSIBLE_QUANTITY: u32 = 1_000_000;
+const MAX_HOLDING_YEARS: i64 = 50;
+
+#[derive(Debug, PartialEq)]
+pub struct LintWarning {
+    pub ticker: String,
+    pub problem: String,
+    pub suggestion: String,
+}
+
+fn warning(asset: &Asset, problem: String, suggestion: &str) -> LintWarning {
+    LintWarning {
+        ticker: asset.ticker.clone(),
+        problem,
+        suggestion: suggestion.to_string(),
+    }
+}
+
+pub fn lint_assets(assets: &[Asset], today: NaiveDate) -> Vec<LintWarning> {
+    let mut warnings = vec![];
+    for (index, asset) in assets.iter().enumerate() {
+        if asset.quantity > MAX_PLAUSIBLE_QUANTITY {
+            warnings.push(warning(
+                asset,
+                format!("quantity of {} is unusually high", asset.quantity),
+                "check the quantity wasn't entered in cents or with extra digits",
+            ));
+        }
+        if asset.buy_price_cents == 0 {
+            warnings.push(warning(
+                asset,
+                "buy price is $0.00".to_string(),
+                "enter the price paid per share, in cents",
+            ));
+        }
+        // below 10% of the buy price, i.e. a loss of more than 90%
+        if let Some(sell_price) = asset.sell_price_cents {
+            if (sell_price as u64) * 10 < asset.buy_price_cents as u64 {
+                warnings.push(warning(
+                    asset,
+                    "sell price is more than 90% below the buy price".to_string(),
+                    "check the sell price is in cents and not in dollars",
+                ));
+            }
+        }
+        if let Some(buy_date) = asset.buy_date {
+            if (today - buy_date).num_days() > MAX_HOLDING_YEARS * 365 {
+                warnings.push(warning(
+                    asset,
+                    format!("held since {}, over {} years", buy_date, MAX_HOLDING_YEARS),
+                    "set the real buy date; this looks like a default date",
+                ));
+            }
+        }
+        // the same purchase entered twice with different dates; only reported
+        // once per pair, against the later entry
+        for earlier in &assets[..index] {
+            let same_purchase = earlier.ticker.eq_ignore_ascii_case(&asset.ticker)
+                && earlier.buy_price_cents == asset.buy_price_cents
+                && earlier.quantity == asset.quantity;
+            if same_purchase && earlier.buy_date != asset.buy_date {
+                warnings.push(warning(
+                    asset,
+                    format!(
+                        "appears twice with the same price and quantity but buy dates {} and {}",
+                        describe_date(earlier.buy_date),
+                        describe_date(asset.buy_date)
+                    ),
+                    "if this is one purchase, remove the duplicate; otherwise correct the dates",
+                ));
+            }
+        }
+    }
+    warnings
+}
+
+// groups of assets with the same ticker, buy price and quantity, as indexes
+// in the order stored. these are checked on every load, since they're most
+// often a `new` entered twice
+pub fn possible_duplicates(assets: &[Asset]) -> Vec<Vec<usize>> {
+    let mut groups: Vec<Vec<usize>> = vec![];
+    for (index, asset) in assets.iter().enumerate() {
+        let same = |other: &Asset| {
+            other.ticker.eq_ignore_ascii_case(&asset.ticker)
+                && other.buy_price_cents == asset.buy_price_cents
+                && other.quantity == asset.quantity
+        };
+        match groups.iter_mut().find(|group| same(&assets[group[0]])) {
+            Some(group) => group.push(index),
+            None => groups.push(vec![index]),
+        }
+    }
+    groups.retain(|group| group.len() > 1);
+    groups
+}
+
+fn describe_date(date: Option<NaiveDate>) -> String {
+    date.map_or_else(|| "unknown".to_string(), |date| date.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    fn asset(ticker: &str, buy_price_cents: u32, quantity: u32) -> Asset {
+        Asset {
+            ticker: ticker.to_string(),
+            buy_price_cents,
+            buy_date: Some(date(2020, 1, 1)),
+            current_price_cents: buy_price_cents,
+            sell_price_cents: None,
+            sell_date: None,
+            quantity,
+            price_updated_at: None,
+            refresh_failed_at: None,
+            currency: None,
+            note: None,
+            tags: vec![],
+            account: None,
+            added_at: None,
+            price_history: vec![],
+            target_price_cents: None,
+            stop_loss: None,
+            stop_breached_at: None,
+            price_from_cache: false,
+        }
+    }
+
+    fn problems(assets: &[Asset]) -> Vec<String> {
+        lint_assets(assets, date(2024, 1, 1))
+            .into_iter()
+            .map(|warning| format!("{}: {}", warning.ticker, warning.problem))
+            .collect()
+    }
+
+    #[test]
+    fn clean_assets_have_no_warnings() {
+        assert!(problems(&[asset("AAPL", 15000, 10), asset("MSFT", 25000, 5)]).is_empty());
+    }
+
+    #[test]
+    fn warns_about_suspicious_values() {
+        let mut sold = asset("GME", 40000, 1);
+        sold.sell_price_cents = Some(3999);
+        let mut ancient = asset("KO", 100, 1);
+        ancient.buy_date = Some(date(1970, 1, 1));
+        let warnings = problems(&[
+            asset("AAPL", 15000, 5_000_000),
+            asset("FREE", 0, 1),
+            sold,
+            ancient,
+        ]);
+        assert_eq!(
+            warnings,
+            vec![
+                "AAPL: quantity of 5000000 is unusually high",
+                "FREE: buy price is $0.00",
+                "GME: sell price is more than 90% below the buy price",
+                "KO: held since 1970-01-01, over 50 years",
+            ]
+        );
+    }
+
+    #[test]
+    fn a_90_percent_loss_is_not_flagged() {
+        let mut sold = asset("GME", 40000, 1);
+        sold.sell_price_cents = Some(4000);
+        assert!(problems(&[sold]).is_empty());
+    }
+
+    #[test]
+    fn warns_once_about_duplicates_with_different_dates() {
+        let mut duplicate = asset("aapl", 15000, 10);
+        duplicate.buy_date = Some(date(2021, 6, 1));
+        let warnings = problems(&[
+            asset("AAPL", 15000, 10),
+            duplicate,
+            asset("AAPL", 15000, 10),
+        ]);
+        assert_eq!(warnings.len(), 2);
+        assert!(warnings[0].contains("buy dates 2020-01-01 and 2021-06-01"));
+    }
+
+    #[test]
+    fn identical_lots_are_possible_duplicates() {
+        let assets = vec![
+            asset("AAPL", 15000, 100),
+            asset("MSFT", 15000, 100),
+            asset("aapl", 15000, 100),
+            asset("AAPL", 15000, 10),
+            asset("AAPL", 15000, 100),
+        ];
+        assert_eq!(possible_duplicates(&assets), vec![vec![0, 2, 4]]);
+        assert!(possible_duplicates(&assets[..2]).is_empty());
+    }
+}
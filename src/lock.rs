@@ -0,0 +1,158 @@
+// an advisory lock so two running trackers don't overwrite each other's
+// changes to the same portfolio file. the lock is a file next to the
+// portfolio, e.g. main.json.lock, holding the pid of the instance that has
+// it open
+
+use crate::error::PortfolioError;
+use std::fs;
+use std::io::{self, ErrorKind, Write};
+use std::path::{Path, PathBuf};
+
+// the lock file is removed when this is dropped
+#[derive(Debug)]
+pub struct PortfolioLock {
+    portfolio: PathBuf,
+    path: PathBuf,
+}
+
+impl PortfolioLock {
+    pub fn portfolio(&self) -> &Path {
+        &self.portfolio
+    }
+}
+
+impl Drop for PortfolioLock {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+#[derive(Debug)]
+pub enum LockAttempt {
+    Acquired(PortfolioLock),
+    // another instance, with this pid, has the portfolio open
+    HeldBy(u32),
+}
+
+pub fn lock_path(portfolio: &Path) -> PathBuf {
+    let mut name = portfolio.file_name().unwrap_or_default().to_os_string();
+    name.push(".lock");
+    portfolio.with_file_name(name)
+}
+
+// the pid of another running instance holding the lock, if any. a lock left
+// behind by a process that has since exited doesn't count
+pub fn held_by(portfolio: &Path) -> Option<u32> {
+    let pid = fs::read_to_string(lock_path(portfolio))
+        .ok()?
+        .trim()
+        .parse::<u32>()
+        .ok()?;
+    (pid != std::process::id() && process_is_running(pid)).then_some(pid)
+}
+
+pub fn acquire(portfolio: &Path) -> Result<LockAttempt, PortfolioError> {
+    let path = lock_path(portfolio);
+    // the second attempt is after clearing a stale lock
+    for _ in 0..2 {
+        match fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&path)
+        {
+            Ok(mut file) => {
+                write!(file, "{}", std::process::id())?;
+                return Ok(LockAttempt::Acquired(PortfolioLock {
+                    portfolio: portfolio.to_path_buf(),
+                    path,
+                }));
+            }
+            Err(e) if e.kind() == ErrorKind::AlreadyExists => {
+                if let Some(pid) = held_by(portfolio) {
+                    return Ok(LockAttempt::HeldBy(pid));
+                }
+                match fs::remove_file(&path) {
+                    Err(e) if e.kind() != ErrorKind::NotFound => {
+                        return Err(PortfolioError::IoError(e))
+                    }
+                    _ => {}
+                }
+            }
+            Err(e) => return Err(PortfolioError::IoError(e)),
+        }
+    }
+    Err(PortfolioError::IoError(io::Error::new(
+        ErrorKind::AlreadyExists,
+        format!("{} keeps reappearing", path.display()),
+    )))
+}
+
+#[cfg(unix)]
+fn process_is_running(pid: u32) -> bool {
+    // signal 0 only checks that the process exists. EPERM means it does but
+    // belongs to someone else
+    let Ok(pid) = libc::pid_t::try_from(pid) else {
+        return false;
+    };
+    let signalled = unsafe { libc::kill(pid, 0) } == 0;
+    signalled || io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+}
+
+// without a cheap way to ask, assume the holder is still running; opening
+// read-only always works
+#[cfg(not(unix))]
+fn process_is_running(_pid: u32) -> bool {
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lock_is_released_on_drop() {
+        let dir = tempfile::tempdir().unwrap();
+        let portfolio = dir.path().join("main.json");
+        let lock = match acquire(&portfolio).unwrap() {
+            LockAttempt::Acquired(lock) => lock,
+            LockAttempt::HeldBy(pid) => panic!("held by {}", pid),
+        };
+        assert_eq!(lock_path(&portfolio), dir.path().join("main.json.lock"));
+        assert!(lock_path(&portfolio).exists());
+        drop(lock);
+        assert!(!lock_path(&portfolio).exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn lock_of_a_running_process_is_held() {
+        let dir = tempfile::tempdir().unwrap();
+        let portfolio = dir.path().join("main.json");
+        let mut child = std::process::Command::new("sleep")
+            .arg("5")
+            .spawn()
+            .unwrap();
+        fs::write(lock_path(&portfolio), child.id().to_string()).unwrap();
+        let attempt = acquire(&portfolio).unwrap();
+        child.kill().unwrap();
+        child.wait().unwrap();
+        assert!(matches!(attempt, LockAttempt::HeldBy(pid) if pid == child.id()));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn stale_lock_is_taken_over() {
+        let dir = tempfile::tempdir().unwrap();
+        let portfolio = dir.path().join("main.json");
+        let mut child = std::process::Command::new("true").spawn().unwrap();
+        child.wait().unwrap();
+        fs::write(lock_path(&portfolio), child.id().to_string()).unwrap();
+        assert_eq!(held_by(&portfolio), None);
+        let attempt = acquire(&portfolio).unwrap();
+        assert!(matches!(attempt, LockAttempt::Acquired(_)));
+        assert_eq!(
+            fs::read_to_string(lock_path(&portfolio)).unwrap(),
+            std::process::id().to_string()
+        );
+    }
+}
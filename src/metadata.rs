@@ -0,0 +1,101 @@
+// per-ticker data that changes rarely (sector etc.), cached on disk so it
+// only has to be fetched once
+use crate::error::PortfolioError;
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use tracing::{debug, warn};
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+pub struct TickerMetadata {
+    #[serde(default)]
+    pub sector: Option<String>,
+    // (low, high), updated whenever the price is fetched
+    #[serde(default)]
+    pub fifty_two_week_range_cents: Option<(u32, u32)>,
+    #[serde(default)]
+    pub fundamentals: Option<Fundamentals>,
+    #[serde(default)]
+    pub earnings: Option<EarningsDate>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct EarningsDate {
+    pub fetched_on: NaiveDate,
+    // None if no upcoming earnings date was scheduled when this was fetched
+    pub date: Option<NaiveDate>,
+}
+
+impl EarningsDate {
+    // a date that has already passed means the next one has probably been
+    // announced since, and tickers with nothing scheduled are rechecked daily
+    pub fn needs_refetch(&self, today: NaiveDate) -> bool {
+        match self.date {
+            Some(date) => date < today,
+            None => self.fetched_on != today,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Fundamentals {
+    // fundamentals are refetched once a day, so this is all the freshness we track
+    pub fetched_on: NaiveDate,
+    pub pe_ratio: Option<f64>,
+    pub market_cap: Option<u64>,
+    // dividends per share over the last twelve months, in cents. None for
+    // non-payers, and in caches from before it was fetched
+    #[serde(default)]
+    pub dividend_cents: Option<f64>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct MetadataCache {
+    #[serde(default)]
+    tickers: HashMap<String, TickerMetadata>,
+}
+
+fn cache_path() -> Option<PathBuf> {
+    dirs_next::cache_dir().map(|dir| dir.join("portfolio-tracker").join("metadata.json"))
+}
+
+impl MetadataCache {
+    // a missing or unreadable cache just means everything gets fetched again
+    pub fn load() -> MetadataCache {
+        cache_path()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .and_then(|raw| serde_json::from_str(&raw).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> Result<(), PortfolioError> {
+        let path = cache_path()
+            .ok_or_else(|| PortfolioError::NotFound("platform cache directory".to_string()))?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let json = serde_json::to_string(self)?;
+        debug!(path = %path.display(), tickers = self.tickers.len(), "saving metadata cache");
+        fs::write(path, json)?;
+        Ok(())
+    }
+
+    // tickers are stored uppercase so "aapl" and "AAPL" share an entry
+    pub fn get(&self, ticker: &str) -> Option<&TickerMetadata> {
+        self.tickers.get(&ticker.to_uppercase())
+    }
+
+    pub fn entry(&mut self, ticker: &str) -> &mut TickerMetadata {
+        self.tickers.entry(ticker.to_uppercase()).or_default()
+    }
+}
+
+pub fn save_metadata(metadata: &MetadataCache) {
+    // the cache is only an optimization, so failing to save it isn't worth
+    // interrupting the user over
+    if let Err(e) = metadata.save() {
+        warn!(error = %e, "failed to save metadata cache");
+    }
+}
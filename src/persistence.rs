@@ -0,0 +1,257 @@
+// reading and writing portfolio files
+use crate::config::DateOrder;
+use crate::error::PortfolioError;
+use crate::import::import_csv;
+use crate::portfolio::Portfolio;
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use std::fs;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use tracing::{error, info};
+
+pub fn load_portfolio_from_path(path: &Path) -> Result<Portfolio, PortfolioError> {
+    let result = read_portfolio(path);
+    match &result {
+        Ok(portfolio) => info!(
+            path = %path.display(),
+            assets = portfolio.assets.len(),
+            "loaded portfolio"
+        ),
+        Err(e) => error!(path = %path.display(), error = %e, "failed to load portfolio"),
+    }
+    result
+}
+
+fn read_portfolio(path: &Path) -> Result<Portfolio, PortfolioError> {
+    let raw_portfolio = read_portfolio_file(path)?;
+    let mut portfolio = Portfolio::from_json_str(&raw_portfolio)?;
+    if portfolio.name.is_empty() {
+        portfolio.name = portfolio_name_from_path(path);
+    }
+    portfolio.file_created_date = file_created_date(path);
+    Ok(portfolio)
+}
+
+// None where the filesystem doesn't record creation times. files are
+// overwritten in place when saved, so this stays when the file was first made
+fn file_created_date(path: &Path) -> Option<NaiveDate> {
+    let created = fs::metadata(path)
+        .and_then(|metadata| metadata.created())
+        .ok()?;
+    Some(DateTime::<Local>::from(created).date_naive())
+}
+
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+// the contents of a portfolio file, decompressed first if it's gzipped
+// whatever its extension
+fn read_portfolio_file(path: &Path) -> Result<String, PortfolioError> {
+    let bytes = fs::read(path)?;
+    if !bytes.starts_with(&GZIP_MAGIC) {
+        return String::from_utf8(bytes).map_err(|e| {
+            PortfolioError::IoError(std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+        });
+    }
+    let mut raw = String::new();
+    GzDecoder::new(bytes.as_slice())
+        .read_to_string(&mut raw)
+        .map_err(|e| PortfolioError::Corrupted(format!("can't decompress it ({})", e)))?;
+    Ok(raw)
+}
+
+fn is_gzip_path(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("gz"))
+}
+
+// main.json and main.json.gz are both called "main"
+fn portfolio_name_from_path(path: &Path) -> String {
+    let path = if is_gzip_path(path) {
+        Path::new(path.file_stem().unwrap_or_default())
+    } else {
+        path
+    };
+    path.file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FileFormat {
+    Json,
+    Toml,
+    // the columns `import csv` reads
+    Csv,
+}
+
+pub const FILE_FORMAT_NAMES: &str = "json, toml, csv";
+
+impl FileFormat {
+    // in the order they're tried when the format isn't given
+    const ALL: [FileFormat; 3] = [FileFormat::Json, FileFormat::Toml, FileFormat::Csv];
+
+    // the name used by `load --format`
+    pub fn from_name(name: &str) -> Option<FileFormat> {
+        FileFormat::ALL
+            .into_iter()
+            .find(|format| format.name().eq_ignore_ascii_case(name))
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            FileFormat::Json => "JSON",
+            FileFormat::Toml => "TOML",
+            FileFormat::Csv => "CSV",
+        }
+    }
+}
+
+pub struct LoadedPortfolio {
+    pub portfolio: Portfolio,
+    pub format: FileFormat,
+    // rows of a CSV file that couldn't be read, and why
+    pub skipped: Vec<String>,
+}
+
+// loads a portfolio in whichever format the contents turn out to be, since
+// backups and copies often don't keep a meaningful extension. with a format
+// given only that one is tried
+pub fn load_portfolio_detecting(
+    path: &Path,
+    format: Option<FileFormat>,
+    date_order: DateOrder,
+    today: NaiveDate,
+) -> Result<LoadedPortfolio, PortfolioError> {
+    let raw = read_portfolio_file(path)?;
+    let formats = match format {
+        Some(format) => vec![format],
+        None => FileFormat::ALL.to_vec(),
+    };
+    let mut failures = vec![];
+    for format in formats.iter().copied() {
+        let parsed = match format {
+            FileFormat::Json => Portfolio::from_json_str(&raw)
+                .map(|portfolio| (portfolio, vec![]))
+                .map_err(|e| match e {
+                    PortfolioError::ParseError(message) => message,
+                    e => e.to_string(),
+                }),
+            FileFormat::Toml => toml::from_str::<Portfolio>(&raw)
+                .map(|portfolio| (portfolio, vec![]))
+                .map_err(|e| e.message().to_string()),
+            FileFormat::Csv => import_csv(&raw, date_order, today)
+                .map(|imported| {
+                    let portfolio = Portfolio {
+                        assets: imported.assets,
+                        ..Default::default()
+                    };
+                    (portfolio, imported.skipped)
+                })
+                .map_err(|e| match e {
+                    PortfolioError::ParseError(message) => message,
+                    e => e.to_string(),
+                }),
+        };
+        match parsed {
+            Ok((mut portfolio, skipped)) => {
+                if portfolio.name.is_empty() {
+                    portfolio.name = portfolio_name_from_path(path);
+                }
+                portfolio.file_created_date = file_created_date(path);
+                info!(
+                    path = %path.display(),
+                    format = format.name(),
+                    assets = portfolio.assets.len(),
+                    "loaded portfolio"
+                );
+                return Ok(LoadedPortfolio {
+                    portfolio,
+                    format,
+                    skipped,
+                });
+            }
+            Err(e) => failures.push(format!("{}: {}", format.name(), e)),
+        }
+    }
+    let e = PortfolioError::ParseError(match formats.as_slice() {
+        [_] => failures.join(""),
+        _ => format!(
+            "not a portfolio in any known format ({})",
+            failures.join("; ")
+        ),
+    });
+    error!(path = %path.display(), error = %e, "failed to load portfolio");
+    Err(e)
+}
+
+// a timestamped copy next to the portfolio file, e.g. main.backup-20240101-120000.json,
+// or in the working directory if the portfolio hasn't been saved yet
+pub fn backup_path(file: Option<&Path>, at: NaiveDateTime) -> PathBuf {
+    let stamp = at.format("%Y%m%d-%H%M%S");
+    let Some(file) = file else {
+        return PathBuf::from(format!("portfolio.backup-{}.json", stamp));
+    };
+    let stem = file
+        .file_stem()
+        .map_or("portfolio".into(), |stem| stem.to_string_lossy());
+    let name = match file.extension() {
+        Some(extension) => format!("{}.backup-{}.{}", stem, stamp, extension.to_string_lossy()),
+        None => format!("{}.backup-{}", stem, stamp),
+    };
+    file.with_file_name(name)
+}
+
+fn write_portfolio(portfolio: &Portfolio, path: &Path) -> Result<(), PortfolioError> {
+    let json = portfolio.to_json_str()?;
+    let bytes = if is_gzip_path(path) {
+        gzip(json.as_bytes())?
+    } else {
+        json.into_bytes()
+    };
+    fs::write(path, bytes)?;
+    Ok(())
+}
+
+// written gzipped when the path ends in .gz
+pub fn dump_portfolio_to_path(portfolio: &Portfolio, path: &Path) -> Result<(), PortfolioError> {
+    let result = write_portfolio(portfolio, path);
+    match &result {
+        Ok(()) => info!(
+            path = %path.display(),
+            assets = portfolio.assets.len(),
+            "dumped portfolio"
+        ),
+        Err(e) => error!(path = %path.display(), error = %e, "failed to dump portfolio"),
+    }
+    result
+}
+
+fn gzip(bytes: &[u8]) -> std::io::Result<Vec<u8>> {
+    let mut encoder = GzEncoder::new(vec![], Compression::default());
+    encoder.write_all(bytes)?;
+    encoder.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backup_path_sits_next_to_the_file() {
+        let at = NaiveDate::from_ymd_opt(2024, 1, 2)
+            .unwrap()
+            .and_hms_opt(9, 30, 5)
+            .unwrap();
+        assert_eq!(
+            backup_path(Some(Path::new("data/main.json")), at),
+            Path::new("data/main.backup-20240102-093005.json")
+        );
+        assert_eq!(
+            backup_path(None, at),
+            Path::new("portfolio.backup-20240102-093005.json")
+        );
+    }
+}
@@ -0,0 +1,3092 @@
+// the portfolio and its assets, and everything worked out from them. nothing
+// here prints or touches the disk
+use crate::config::{display_settings, CostBasisMethod, MergeStrategy};
+use crate::display::{format_asset_money, format_money, format_percent};
+use crate::error::PortfolioError;
+use crate::goals::Goal;
+use crate::metadata::MetadataCache;
+use chrono::{DateTime, Datelike, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::fmt;
+
+// prices are stored per share. files from before this was settled have no
+// price_basis and may hold a lot's total cost as its buy price instead
+pub const PRICE_BASIS_PER_SHARE: u32 = 1;
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Portfolio {
+    // files saved before portfolios had names get named after the file on load
+    #[serde(default)]
+    pub name: String,
+    pub assets: Vec<Asset>,
+    // market value of the held assets, recorded at most once per day on refresh;
+    // older files don't have this, so it defaults to empty
+    #[serde(default)]
+    pub value_history: Vec<(NaiveDate, i64)>,
+    // what each held ticker was worth on the days in value_history, recorded
+    // with them so `snapshot-diff` can break a change down even after lots
+    // are removed. older files don't have this
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub asset_values: BTreeMap<NaiveDate, BTreeMap<String, i64>>,
+    // 0 for files written before prices were settled as per share
+    #[serde(default)]
+    pub price_basis: u32,
+    // set with `goal add`, left out of the file when there are none
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub goals: Vec<Goal>,
+    // when tracking started, if it's earlier than any buy date. only ever set
+    // by editing the file
+    #[serde(
+        default,
+        rename = "portfolio_created_date",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub created_date: Option<NaiveDate>,
+    // when the file it was loaded from was created, if the platform says.
+    // never saved
+    #[serde(skip)]
+    pub file_created_date: Option<NaiveDate>,
+}
+
+impl Default for Portfolio {
+    fn default() -> Self {
+        Portfolio {
+            name: String::new(),
+            assets: vec![],
+            value_history: vec![],
+            asset_values: BTreeMap::new(),
+            price_basis: PRICE_BASIS_PER_SHARE,
+            goals: vec![],
+            created_date: None,
+            file_created_date: None,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Asset {
+    pub ticker: String,
+    pub buy_price_cents: u32,
+    // unknown for assets added before buy dates were recorded
+    #[serde(default)]
+    pub buy_date: Option<NaiveDate>,
+    // technically we don't care about the current price if
+    // it is sold, but it is still a valid property to have,
+    // so we include it here, although it isn't displayed
+    pub current_price_cents: u32,
+    // if sell price is None, it isn't sold
+    pub sell_price_cents: Option<u32>,
+    // unknown for sold assets added before sell dates were recorded
+    #[serde(default)]
+    pub sell_date: Option<NaiveDate>,
+    pub quantity: u32,
+    // when current_price_cents was last fetched; unknown for older files
+    #[serde(default)]
+    pub price_updated_at: Option<DateTime<Utc>>,
+    // when the last attempt to refresh its price failed, leaving the price
+    // from price_updated_at in place. cleared by the next one that succeeds
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub refresh_failed_at: Option<DateTime<Utc>>,
+    // the currency its prices are in, from its quotes, e.g. "CAD" for
+    // SHOP.TO. unknown for assets not fetched since currencies were recorded,
+    // which are taken to be in the portfolio's currency
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub currency: Option<String>,
+    // free text set with `note`, left out of the file when empty
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+    // labels set with `tag`, left out of the file when empty
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    // the brokerage account it's held in, e.g. "IRA", set with `account`
+    // and left out of the file when there's none
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub account: Option<String>,
+    // the price per share being waited for, set with `target` or `edit`.
+    // refresh says when the price crosses it. left out of the file when
+    // there's none
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub target_price_cents: Option<u32>,
+    // set with `stop` on held assets and checked on every refresh. left out
+    // of the file when there's none
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stop_loss: Option<StopLoss>,
+    // when a refresh first found the price at or below the stop. it stays
+    // set, even if the price recovers, until `stops ack` or the stop is removed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stop_breached_at: Option<DateTime<Utc>>,
+    // when it was entered or imported; unknown for older files, where the
+    // order in the file is the order they were added
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub added_at: Option<DateTime<Utc>>,
+    // (date, price) from each day it was refreshed, oldest first and at most
+    // one per day. left out of the file when empty, and ignored by versions
+    // that don't know about it
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub price_history: Vec<(NaiveDate, u32)>,
+    // whether current_price_cents came from the quote cache rather than a
+    // fetch, so tables can say so. only for this session, never saved
+    #[serde(skip)]
+    pub price_from_cache: bool,
+}
+
+// saved as {"price": 13500} or {"percent": 10.0}
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum StopLoss {
+    // cents per share
+    Price(u32),
+    // below the buy price, never the last close, so it doesn't creep down
+    // with the price
+    Percent(f64),
+}
+
+impl StopLoss {
+    pub fn price_cents(&self, buy_price_cents: u32) -> u32 {
+        match *self {
+            StopLoss::Price(cents) => cents,
+            StopLoss::Percent(percent) => {
+                (buy_price_cents as f64 * (1.0 - percent / 100.0)).round() as u32
+            }
+        }
+    }
+}
+
+// e.g. `Portfolio "main" │ 3 assets (2 held, 1 sold) │ Value: $5000.00 │ Return: +12.50%`
+impl fmt::Display for Portfolio {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let held = self.assets_held().len();
+        write!(
+            f,
+            "{}",
+            headline(
+                &self.name,
+                held,
+                self.assets.len() - held,
+                self.total_held_market_value(),
+                self.total_held_cost_basis()
+            )
+        )
+    }
+}
+
+fn headline(name: &str, held: usize, sold: usize, market_value: i64, cost_basis: i64) -> String {
+    let name = if name.is_empty() { "untitled" } else { name };
+    let change = if cost_basis == 0 {
+        "n/a".to_string()
+    } else {
+        let change = (market_value - cost_basis) as f64 / cost_basis as f64 * 100.0;
+        format_percent(change)
+    };
+    format!(
+        "Portfolio \"{}\" │ {} assets ({} held, {} sold) │ Value: {} │ Return: {}",
+        name,
+        held + sold,
+        held,
+        sold,
+        format_money(market_value),
+        change
+    )
+}
+
+// e.g. "AAPL: 100 shares @ $150.00 (current: $189.42, +26.28%)". sold assets
+// show the sell price instead of the current one
+impl fmt::Display for Asset {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (label, price) = match self.sell_price_cents {
+            Some(sell_price) => ("sold", sell_price),
+            None => ("current", self.current_price_cents),
+        };
+        write!(
+            f,
+            "{}: {} shares @ {} ({}: {}, {})",
+            self.ticker,
+            self.quantity,
+            format_asset_money(self, self.buy_price_cents as i64),
+            label,
+            format_asset_money(self, price as i64),
+            format_percent(percent_increase(self.buy_price_cents, price) as f64)
+        )
+    }
+}
+
+// builds an asset in code rather than at the prompt, e.g.
+// `AssetBuilder::new("AAPL", 15000).quantity(100).sell_price(20000).build()`.
+// the current price starts out as the buy price and the quantity as 1
+#[derive(Debug, Clone)]
+pub struct AssetBuilder {
+    asset: Asset,
+}
+
+impl AssetBuilder {
+    pub fn new(ticker: &str, buy_price_cents: u32) -> AssetBuilder {
+        AssetBuilder {
+            asset: Asset {
+                ticker: ticker.to_string(),
+                buy_price_cents,
+                buy_date: None,
+                current_price_cents: buy_price_cents,
+                sell_price_cents: None,
+                sell_date: None,
+                quantity: 1,
+                price_updated_at: None,
+                refresh_failed_at: None,
+                currency: None,
+                note: None,
+                tags: vec![],
+                account: None,
+                added_at: None,
+                price_history: vec![],
+                target_price_cents: None,
+                stop_loss: None,
+                stop_breached_at: None,
+                price_from_cache: false,
+            },
+        }
+    }
+
+    pub fn quantity(mut self, quantity: u32) -> AssetBuilder {
+        self.asset.quantity = quantity;
+        self
+    }
+
+    pub fn buy_date(mut self, date: NaiveDate) -> AssetBuilder {
+        self.asset.buy_date = Some(date);
+        self
+    }
+
+    pub fn current_price(mut self, cents: u32) -> AssetBuilder {
+        self.asset.current_price_cents = cents;
+        self
+    }
+
+    pub fn sell_price(mut self, cents: u32) -> AssetBuilder {
+        self.asset.sell_price_cents = Some(cents);
+        self
+    }
+
+    pub fn sell_date(mut self, date: NaiveDate) -> AssetBuilder {
+        self.asset.sell_date = Some(date);
+        self
+    }
+
+    pub fn note(mut self, note: &str) -> AssetBuilder {
+        self.asset.note = Some(note.to_string());
+        self
+    }
+
+    pub fn tags(mut self, tags: &[&str]) -> AssetBuilder {
+        self.asset.tags = tags.iter().map(|tag| tag.to_string()).collect();
+        self
+    }
+
+    pub fn account(mut self, account: &str) -> AssetBuilder {
+        self.asset.account = Some(account.to_string());
+        self
+    }
+
+    pub fn added_at(mut self, at: DateTime<Utc>) -> AssetBuilder {
+        self.asset.added_at = Some(at);
+        self
+    }
+
+    pub fn build(self) -> Result<Asset, PortfolioError> {
+        let asset = self.asset;
+        let invalid = |problem: &str| {
+            Err(PortfolioError::InvalidInput(format!(
+                "{} {}",
+                asset.ticker, problem
+            )))
+        };
+        if asset.ticker.trim().is_empty() {
+            return Err(PortfolioError::InvalidInput(
+                "an asset needs a ticker".to_string(),
+            ));
+        }
+        if asset.quantity == 0 {
+            return invalid("has a quantity of 0");
+        }
+        if asset.buy_price_cents == 0 {
+            return invalid("has a buy price of $0.00");
+        }
+        if asset.sell_date.is_some() && asset.sell_price_cents.is_none() {
+            return invalid("has a sell date but no sell price");
+        }
+        if let (Some(buy), Some(sell)) = (asset.buy_date, asset.sell_date) {
+            if sell < buy {
+                return invalid("was sold before it was bought");
+            }
+        }
+        Ok(asset)
+    }
+}
+
+pub fn is_asset_sold(asset: &Asset) -> bool {
+    // if there is no sell price, then it isn't sold (i.e., it is currently held)
+    asset.sell_price_cents.is_some()
+}
+
+pub fn is_asset_held(asset: &Asset) -> bool {
+    !is_asset_sold(asset)
+}
+
+// whether the asset is priced in the currency set with `set currency`. there
+// are no exchange rates, so the others are left out of every total
+pub fn in_portfolio_currency(asset: &Asset) -> bool {
+    asset
+        .currency
+        .as_deref()
+        .is_none_or(|code| code.eq_ignore_ascii_case(display_settings().currency))
+}
+
+pub fn percent_increase(old: u32, new: u32) -> f32 {
+    // nothing to compare against, e.g. an empty portfolio's totals, is no
+    // change rather than NaN or infinity
+    if old == 0 {
+        return 0.0;
+    }
+    // ensure floating point math. f64 holds every u32 exactly, whereas in f32
+    // two nearby large prices can round to the same value and hide a change
+    ((new as f64 - old as f64) / old as f64 * 100_f64) as f32
+}
+
+// the percent change shown in the assets table: to the current price while
+// held, to the sell price once sold
+pub fn percent_change(asset: &Asset) -> f32 {
+    percent_increase(
+        asset.buy_price_cents,
+        asset.sell_price_cents.unwrap_or(asset.current_price_cents),
+    )
+}
+
+// indexes of the held assets with the highest and lowest percent change, the
+// first one found on a tie. with a single held asset it's only the best
+pub fn best_and_worst_held(assets: &[Asset]) -> (Option<usize>, Option<usize>) {
+    let mut best: Option<usize> = None;
+    let mut worst: Option<usize> = None;
+    let change = |i: usize| percent_change(&assets[i]);
+    for (i, asset) in assets.iter().enumerate() {
+        if !is_asset_held(asset) {
+            continue;
+        }
+        if best.is_none_or(|best| change(i) > change(best)) {
+            best = Some(i);
+        }
+        if worst.is_none_or(|worst| change(i) < change(worst)) {
+            worst = Some(i);
+        }
+    }
+    if best == worst {
+        return (best, None);
+    }
+    (best, worst)
+}
+
+// where a price sits between a low and a high, as a percentage of the range
+pub fn position_in_range(price: u32, low: u32, high: u32) -> Option<f64> {
+    if high <= low {
+        return None;
+    }
+    Some((price as f64 - low as f64) / (high as f64 - low as f64) * 100.0)
+}
+
+impl Portfolio {
+    // the portfolio file format. fields it doesn't know are ignored, so files
+    // written by newer versions still load
+    pub fn from_json_str(json: &str) -> Result<Portfolio, PortfolioError> {
+        Ok(serde_json::from_str(json)?)
+    }
+
+    pub fn to_json_str(&self) -> Result<String, PortfolioError> {
+        Ok(serde_json::to_string(self)?)
+    }
+
+    // every lot of a ticker, held or sold, matched in any case
+    pub fn assets_by_ticker(&self, ticker: &str) -> Vec<&Asset> {
+        self.assets
+            .iter()
+            .filter(|asset| asset.ticker.eq_ignore_ascii_case(ticker))
+            .collect()
+    }
+
+    pub fn assets_held(&self) -> Vec<&Asset> {
+        self.assets
+            .iter()
+            .filter(|asset| is_asset_held(asset))
+            .collect()
+    }
+
+    pub fn assets_sold(&self) -> Vec<&Asset> {
+        self.assets
+            .iter()
+            .filter(|asset| is_asset_sold(asset))
+            .collect()
+    }
+
+    // the earliest buy date or created date, or without either when the file
+    // was created
+    pub fn inception_date(&self) -> Option<NaiveDate> {
+        self.assets
+            .iter()
+            .filter_map(|asset| asset.buy_date)
+            .chain(self.created_date)
+            .min()
+            .or(self.file_created_date)
+    }
+
+    // what the currently held assets are worth at their current prices
+    pub fn total_held_market_value(&self) -> i64 {
+        self.assets_held()
+            .into_iter()
+            .filter(|asset| in_portfolio_currency(asset))
+            .map(market_value)
+            .sum()
+    }
+
+    // what was paid for the currently held assets
+    pub fn total_held_cost_basis(&self) -> i64 {
+        self.assets_held()
+            .into_iter()
+            .filter(|asset| in_portfolio_currency(asset))
+            .map(cost_basis)
+            .sum()
+    }
+
+    // assets whose buy price looks like the total paid for the lot rather than
+    // the price per share: divided by the quantity it is close to the current
+    // price, while undivided it is several times higher
+    pub fn likely_total_prices(&self) -> Vec<usize> {
+        self.assets
+            .iter()
+            .enumerate()
+            .filter(|(_, asset)| asset.quantity > 1 && asset.current_price_cents > 0)
+            .filter(|(_, asset)| {
+                let current = asset.current_price_cents as f64;
+                let as_total = asset.buy_price_cents as f64 / current;
+                let per_share = as_total / asset.quantity as f64;
+                as_total > 5.0 && (1.0 / 3.0..=3.0).contains(&per_share)
+            })
+            .map(|(index, _)| index)
+            .collect()
+    }
+
+    // turns total-cost buy prices into per-share prices, rounding to the cent
+    pub fn convert_totals_to_per_share(&mut self, indices: &[usize]) {
+        for &index in indices {
+            let asset = &mut self.assets[index];
+            asset.buy_price_cents = per_share_price(asset.buy_price_cents, asset.quantity);
+        }
+    }
+
+    // goal names are matched ignoring case, so two goals can't differ only by it
+    pub fn add_goal(&mut self, goal: Goal) -> Result<(), PortfolioError> {
+        if self
+            .goals
+            .iter()
+            .any(|existing| existing.name.eq_ignore_ascii_case(&goal.name))
+        {
+            return Err(PortfolioError::InvalidInput(format!(
+                "there is already a goal named \"{}\"",
+                goal.name
+            )));
+        }
+        self.goals.push(goal);
+        Ok(())
+    }
+
+    pub fn remove_goal(&mut self, name: &str) -> Result<Goal, PortfolioError> {
+        let index = self
+            .goals
+            .iter()
+            .position(|goal| goal.name.eq_ignore_ascii_case(name))
+            .ok_or_else(|| PortfolioError::NotFound(format!("no goal named \"{}\"", name)))?;
+        Ok(self.goals.remove(index))
+    }
+
+    // updates every lot of a ticker to a freshly fetched price, returning how
+    // many lots there were
+    pub fn set_price(&mut self, ticker: &str, price_cents: u32, at: DateTime<Utc>) -> usize {
+        let mut lots = 0;
+        for asset in &mut self.assets {
+            if asset.ticker.eq_ignore_ascii_case(ticker) {
+                asset.current_price_cents = price_cents;
+                asset.price_updated_at = Some(at);
+                asset.refresh_failed_at = None;
+                asset.price_from_cache = false;
+                lots += 1;
+            }
+        }
+        lots
+    }
+
+    // records the currency a ticker's quotes came in. an unknown one leaves
+    // whatever was recorded before
+    pub fn set_currency(&mut self, ticker: &str, currency: Option<&str>) {
+        let Some(currency) = currency else {
+            return;
+        };
+        for asset in &mut self.assets {
+            if asset.ticker.eq_ignore_ascii_case(ticker) {
+                asset.currency = Some(currency.to_string());
+            }
+        }
+    }
+
+    // flags the held lots whose price fell to or through their stop since
+    // `before`, the prices each lot had then, returning their indexes. a lot
+    // that stays below an acknowledged stop isn't flagged again
+    pub fn check_stop_losses(&mut self, before: &[u32], at: DateTime<Utc>) -> Vec<usize> {
+        let mut breached = vec![];
+        for (index, (asset, before)) in self.assets.iter_mut().zip(before).enumerate() {
+            let Some(stop) = asset.stop_loss.filter(|_| is_asset_held(asset)) else {
+                continue;
+            };
+            let stop = stop.price_cents(asset.buy_price_cents);
+            let price = asset.current_price_cents;
+            if asset.stop_breached_at.is_none()
+                && price > 0
+                && price <= stop
+                && (*before == 0 || *before > stop)
+            {
+                asset.stop_breached_at = Some(at);
+                breached.push(index);
+            }
+        }
+        breached
+    }
+
+    // clears the breach flags of a ticker's lots, or of every lot, returning
+    // how many there were. the stops stay in place
+    pub fn acknowledge_stop_breaches(&mut self, ticker: Option<&str>) -> usize {
+        let mut acknowledged = 0;
+        for asset in &mut self.assets {
+            let matches = ticker.is_none_or(|ticker| asset.ticker.eq_ignore_ascii_case(ticker));
+            if matches && asset.stop_breached_at.take().is_some() {
+                acknowledged += 1;
+            }
+        }
+        acknowledged
+    }
+
+    // keeps the ticker's stored price, noting it couldn't be refreshed
+    pub fn mark_refresh_failed(&mut self, ticker: &str, at: DateTime<Utc>) {
+        for asset in &mut self.assets {
+            if asset.ticker.eq_ignore_ascii_case(ticker) {
+                asset.refresh_failed_at = Some(at);
+            }
+        }
+    }
+
+    // like set_price, but marks the lots as priced from the quote cache
+    pub fn set_cached_price(&mut self, ticker: &str, price_cents: u32, at: DateTime<Utc>) {
+        self.set_price(ticker, price_cents, at);
+        for asset in &mut self.assets {
+            if asset.ticker.eq_ignore_ascii_case(ticker) {
+                asset.price_from_cache = true;
+            }
+        }
+    }
+
+    // adds today's price to the history of every lot of the ticker
+    pub fn record_price_history(
+        &mut self,
+        ticker: &str,
+        price_cents: u32,
+        date: NaiveDate,
+        limit: usize,
+    ) {
+        for asset in &mut self.assets {
+            if asset.ticker.eq_ignore_ascii_case(ticker) {
+                record_asset_price(asset, price_cents, date, limit);
+            }
+        }
+    }
+
+    // the in-memory size of the portfolio, including what its strings and
+    // vectors have allocated. an estimate, since allocators add overhead
+    pub fn estimated_memory_bytes(&self) -> usize {
+        std::mem::size_of_val(self)
+            + self.name.capacity()
+            + self.assets.capacity() * std::mem::size_of::<Asset>()
+            + self
+                .assets
+                .iter()
+                .map(|asset| {
+                    asset.ticker.capacity()
+                        + asset.price_history.capacity() * std::mem::size_of::<(NaiveDate, u32)>()
+                })
+                .sum::<usize>()
+            + self.value_history.capacity() * std::mem::size_of::<(NaiveDate, i64)>()
+            + self
+                .asset_values
+                .values()
+                .map(|values| values.len() * std::mem::size_of::<(String, i64)>())
+                .sum::<usize>()
+    }
+
+    // marks every held asset as sold today at its current price, returning how
+    // many were sold and the total proceeds
+    pub fn sell_all_held(&mut self, today: NaiveDate) -> (usize, i64) {
+        let mut sold = 0;
+        let mut proceeds: i64 = 0;
+        for asset in self.assets.iter_mut().filter(|asset| is_asset_held(asset)) {
+            asset.sell_price_cents = Some(asset.current_price_cents);
+            asset.sell_date = Some(today);
+            sold += 1;
+            proceeds += market_value(asset);
+        }
+        (sold, proceeds)
+    }
+
+    // gains (or losses, if negative) locked in by selling assets
+    pub fn total_realized_gain_loss(&self) -> i64 {
+        self.assets
+            .iter()
+            .filter(|asset| in_portfolio_currency(asset))
+            .filter_map(realized_gain_loss)
+            .sum()
+    }
+
+    // sells `quantity` shares of a held lot. selling part of a lot splits it:
+    // the sold shares become a new lot straight after it and the rest stay
+    // held. returns the index of the sold lot, or an error for an empty lot
+    // or more shares than it has
+    pub fn sell_lot(
+        &mut self,
+        index: usize,
+        quantity: u32,
+        price_cents: u32,
+        date: Option<NaiveDate>,
+    ) -> Result<usize, PortfolioError> {
+        let lot = &mut self.assets[index];
+        if lot.quantity == 0 {
+            return Err(PortfolioError::InvalidInput(format!(
+                "the {} lot has no shares to sell",
+                lot.ticker
+            )));
+        }
+        if quantity == 0 || quantity > lot.quantity {
+            return Err(PortfolioError::InvalidInput(format!(
+                "can't sell {} of the {} shares in the lot",
+                quantity, lot.quantity
+            )));
+        }
+        let sold_index = if quantity < lot.quantity {
+            let mut sold = lot.clone();
+            sold.quantity = quantity;
+            lot.quantity -= quantity;
+            self.assets.insert(index + 1, sold);
+            index + 1
+        } else {
+            index
+        };
+        let sold = &mut self.assets[sold_index];
+        sold.sell_price_cents = Some(price_cents);
+        sold.sell_date = date;
+        Ok(sold_index)
+    }
+}
+
+// the money paid for and received from every lot, with the held lots valued
+// at their current price today. None if a date is missing, since an annual
+// return can't be worked out without knowing how long money was invested.
+// lots in another currency are left out, as they are from every total
+pub fn cash_flows(assets: &[Asset], today: NaiveDate) -> Option<Vec<(NaiveDate, i64)>> {
+    let mut flows = vec![];
+    for asset in assets.iter().filter(|asset| in_portfolio_currency(asset)) {
+        flows.push((asset.buy_date?, -cost_basis(asset)));
+        match asset.sell_price_cents {
+            Some(price) => flows.push((asset.sell_date?, price as i64 * asset.quantity as i64)),
+            None => flows.push((today, market_value(asset))),
+        }
+    }
+    Some(flows)
+}
+
+// the annual rate at which the flows' present value is zero (the internal
+// rate of return, as XIRR works it out), found by bisection. None without
+// both money in and money out, or if no rate between -99.99% and 100000%
+// balances them
+pub fn internal_rate_of_return(flows: &[(NaiveDate, i64)]) -> Option<f64> {
+    if !flows.iter().any(|(_, flow)| *flow < 0) || !flows.iter().any(|(_, flow)| *flow > 0) {
+        return None;
+    }
+    let start = flows.iter().map(|(date, _)| *date).min()?;
+    let present_value = |rate: f64| -> f64 {
+        flows
+            .iter()
+            .map(|(date, flow)| {
+                let years = (*date - start).num_days() as f64 / 365.0;
+                *flow as f64 / (1.0 + rate).powf(years)
+            })
+            .sum()
+    };
+    let (mut low, mut high) = (-0.9999, 1000.0);
+    if present_value(low).signum() == present_value(high).signum() {
+        return None;
+    }
+    for _ in 0..200 {
+        let middle = (low + high) / 2.0;
+        if present_value(middle).signum() == present_value(low).signum() {
+            low = middle;
+        } else {
+            high = middle;
+        }
+    }
+    Some((low + high) / 2.0)
+}
+
+// held for less than this many days, a gain is taxed as short-term
+pub const LONG_TERM_DAYS: i64 = 365;
+
+// how long a lot has been held, or was held until it was sold. None if the
+// buy date, or for a sold lot the sell date, isn't known
+pub fn days_held(asset: &Asset, today: NaiveDate) -> Option<i64> {
+    let until = match asset.sell_price_cents {
+        Some(_) => asset.sell_date?,
+        None => today,
+    };
+    Some((until - asset.buy_date?).num_days())
+}
+
+// how far the current price has to move to reach a held lot's target, e.g.
+// +10.0 when it's 10% below. None without a target or once sold
+pub fn percent_to_target(asset: &Asset) -> Option<f64> {
+    let target = asset.target_price_cents.filter(|_| is_asset_held(asset))?;
+    (asset.current_price_cents > 0)
+        .then(|| percent_increase(asset.current_price_cents, target) as f64)
+}
+
+// indexes of the held lots whose price reached or passed their target since
+// `before`, the prices each lot had then, from either side
+pub fn targets_crossed(before: &[u32], assets: &[Asset]) -> Vec<usize> {
+    assets
+        .iter()
+        .zip(before)
+        .enumerate()
+        .filter(|(_, (asset, _))| is_asset_held(asset))
+        .filter(|(_, (asset, &old))| {
+            let (Some(target), new) = (asset.target_price_cents, asset.current_price_cents) else {
+                return false;
+            };
+            (old < target && new >= target) || (old > target && new <= target)
+        })
+        .map(|(index, _)| index)
+        .collect()
+}
+
+// how many days until a held lot's gain is long-term, 0 once it is. None for
+// sold lots and lots without a buy date
+pub fn days_to_long_term(asset: &Asset, today: NaiveDate) -> Option<i64> {
+    if is_asset_sold(asset) {
+        return None;
+    }
+    Some((LONG_TERM_DAYS - days_held(asset, today)?).max(0))
+}
+
+// indexes of the held assets, held longest first, then the ones without a
+// buy date in the order they're stored
+pub fn held_by_age(assets: &[Asset], today: NaiveDate) -> Vec<usize> {
+    let mut indexes: Vec<usize> = (0..assets.len())
+        .filter(|&index| is_asset_held(&assets[index]))
+        .collect();
+    indexes.sort_by_key(|&index| {
+        let days = days_held(&assets[index], today);
+        (days.is_none(), std::cmp::Reverse(days))
+    });
+    indexes
+}
+
+// stretches longer than `min_days` between one buy or sale and the next, or
+// between the last one and today, as (from, to). lots without dates can't be
+// placed, so they're left out
+pub fn activity_gaps(
+    assets: &[Asset],
+    min_days: u32,
+    today: NaiveDate,
+) -> Vec<(NaiveDate, NaiveDate)> {
+    let mut dates: Vec<NaiveDate> = assets
+        .iter()
+        .flat_map(|asset| [asset.buy_date, asset.sell_date])
+        .flatten()
+        .filter(|date| *date <= today)
+        .collect();
+    dates.sort();
+    dates.dedup();
+    if let Some(&last) = dates.last() {
+        if last < today {
+            dates.push(today);
+        }
+    }
+    dates
+        .windows(2)
+        .map(|pair| (pair[0], pair[1]))
+        .filter(|(from, to)| (*to - *from).num_days() > min_days as i64)
+        .collect()
+}
+
+// indexes of the `count` most recently added assets, oldest first. assets
+// without an added_at come from before it was recorded, so they count as
+// older than every asset with one, in the order they're stored
+pub fn recently_added(assets: &[Asset], count: usize) -> Vec<usize> {
+    let mut indexes: Vec<usize> = (0..assets.len()).collect();
+    indexes.sort_by_key(|&index| (assets[index].added_at, index));
+    indexes.split_off(indexes.len().saturating_sub(count))
+}
+
+// indexes of the assets a picker offers, in the order it numbers them: by
+// ticker, then oldest lot first (undated lots last), then as stored.
+// `ticker` narrows it to one ticker and `include` to e.g. held assets
+pub fn picker_order(
+    assets: &[Asset],
+    ticker: Option<&str>,
+    include: impl Fn(&Asset) -> bool,
+) -> Vec<usize> {
+    let mut indexes: Vec<usize> = (0..assets.len())
+        .filter(|&index| include(&assets[index]))
+        .filter(|&index| {
+            ticker.is_none_or(|ticker| assets[index].ticker.eq_ignore_ascii_case(ticker))
+        })
+        .collect();
+    indexes.sort_by_key(|&index| {
+        let asset = &assets[index];
+        (
+            asset.ticker.to_uppercase(),
+            asset.buy_date.is_none(),
+            asset.buy_date,
+            index,
+        )
+    });
+    indexes
+}
+
+// comparisons for `sort`, which can be passed straight to sort_by. each sorts
+// smallest first; SORT_KEYS says which way round they're usually wanted
+pub fn by_ticker(a: &Asset, b: &Asset) -> Ordering {
+    a.ticker.to_uppercase().cmp(&b.ticker.to_uppercase())
+}
+
+// undated lots last
+pub fn by_buy_date(a: &Asset, b: &Asset) -> Ordering {
+    (a.buy_date.is_none(), a.buy_date).cmp(&(b.buy_date.is_none(), b.buy_date))
+}
+
+pub fn by_gain(a: &Asset, b: &Asset) -> Ordering {
+    percent_change(a).total_cmp(&percent_change(b))
+}
+
+// market value while held, what it sold for once sold
+pub fn by_value(a: &Asset, b: &Asset) -> Ordering {
+    let value = |asset: &Asset| match asset.sell_price_cents {
+        Some(sell_price) => sell_price as i64 * asset.quantity as i64,
+        None => market_value(asset),
+    };
+    value(a).cmp(&value(b))
+}
+
+pub fn by_cost_basis(a: &Asset, b: &Asset) -> Ordering {
+    cost_basis(a).cmp(&cost_basis(b))
+}
+
+pub fn by_quantity(a: &Asset, b: &Asset) -> Ordering {
+    a.quantity.cmp(&b.quantity)
+}
+
+pub type AssetOrdering = fn(&Asset, &Asset) -> Ordering;
+
+// (name, comparison, largest first unless asked otherwise)
+pub const SORT_KEYS: [(&str, AssetOrdering, bool); 6] = [
+    ("ticker", by_ticker, false),
+    ("date", by_buy_date, false),
+    ("gain", by_gain, true),
+    ("value", by_value, true),
+    ("cost", by_cost_basis, true),
+    ("quantity", by_quantity, true),
+];
+
+// the assets in order of `compare`, ties kept as stored
+pub fn sorted_assets(assets: &[Asset], compare: AssetOrdering, descending: bool) -> Vec<Asset> {
+    let mut sorted = assets.to_vec();
+    if descending {
+        sorted.sort_by(|a, b| compare(b, a));
+    } else {
+        sorted.sort_by(compare);
+    }
+    sorted
+}
+
+// a lot's total split across its shares, rounded to the nearest cent
+pub fn per_share_price(total_cents: u32, quantity: u32) -> u32 {
+    let quantity = quantity.max(1);
+    ((total_cents as u64 + quantity as u64 / 2) / quantity as u64) as u32
+}
+
+// a cost spread over shares, rounded to the nearest cent. 0 for no shares
+pub fn per_share_cost(cost: i64, shares: u64) -> i64 {
+    if shares == 0 {
+        return 0;
+    }
+    (cost + shares as i64 / 2) / shares as i64
+}
+
+// buy and current prices are per share, so these scale by the quantity
+pub fn cost_basis(asset: &Asset) -> i64 {
+    asset.buy_price_cents as i64 * asset.quantity as i64
+}
+
+pub fn market_value(asset: &Asset) -> i64 {
+    asset.current_price_cents as i64 * asset.quantity as i64
+}
+
+// None if the asset is still held
+pub fn realized_gain_loss(asset: &Asset) -> Option<i64> {
+    let sell_price = asset.sell_price_cents? as i64;
+    Some(sell_price * asset.quantity as i64 - cost_basis(asset))
+}
+
+// indices of assets sold before the cutoff date. sold assets without a
+// sell date are left out, since there's no telling how old they are
+pub fn sold_before(assets: &[Asset], cutoff: NaiveDate) -> Vec<usize> {
+    assets
+        .iter()
+        .enumerate()
+        .filter(|(_, asset)| is_asset_sold(asset))
+        .filter(|(_, asset)| asset.sell_date.is_some_and(|date| date < cutoff))
+        .map(|(index, _)| index)
+        .collect()
+}
+
+pub const UNKNOWN_SECTOR: &str = "Unknown";
+
+#[derive(Debug, PartialEq)]
+pub struct SectorAllocation {
+    pub sector: String,
+    pub market_value: i64,
+    // what the held shares cost, to colour the sector by its gain or loss
+    pub buy_value: i64,
+    pub tickers: Vec<String>,
+}
+
+// groups the market value of held assets by sector, largest first. tickers
+// without a cached sector are grouped under UNKNOWN_SECTOR, which always
+// sorts last.
+pub fn sector_allocation(assets: &[Asset], metadata: &MetadataCache) -> Vec<SectorAllocation> {
+    let mut sectors: Vec<SectorAllocation> = vec![];
+    for asset in assets.iter().filter(|asset| is_asset_held(asset)) {
+        let sector = metadata
+            .get(&asset.ticker)
+            .and_then(|entry| entry.sector.clone())
+            .unwrap_or_else(|| UNKNOWN_SECTOR.to_string());
+        let market_value = asset.current_price_cents as i64 * asset.quantity as i64;
+        let buy_value = asset.buy_price_cents as i64 * asset.quantity as i64;
+        match sectors
+            .iter_mut()
+            .find(|existing| existing.sector == sector)
+        {
+            Some(existing) => {
+                existing.market_value += market_value;
+                existing.buy_value += buy_value;
+                if !existing.tickers.contains(&asset.ticker) {
+                    existing.tickers.push(asset.ticker.clone());
+                }
+            }
+            None => sectors.push(SectorAllocation {
+                sector,
+                market_value,
+                buy_value,
+                tickers: vec![asset.ticker.clone()],
+            }),
+        }
+    }
+    sectors.sort_by(|a, b| {
+        (a.sector == UNKNOWN_SECTOR)
+            .cmp(&(b.sector == UNKNOWN_SECTOR))
+            .then(b.market_value.cmp(&a.market_value))
+    });
+    sectors
+}
+
+// (ticker, date) of upcoming earnings for each held ticker with a cached
+// date, soonest first
+pub fn upcoming_earnings(
+    assets: &[Asset],
+    metadata: &MetadataCache,
+    today: NaiveDate,
+) -> Vec<(String, NaiveDate)> {
+    let mut upcoming: Vec<(String, NaiveDate)> = vec![];
+    for asset in assets.iter().filter(|asset| is_asset_held(asset)) {
+        let date = metadata
+            .get(&asset.ticker)
+            .and_then(|entry| entry.earnings.as_ref())
+            .and_then(|earnings| earnings.date)
+            .filter(|date| *date >= today);
+        let already_listed = upcoming
+            .iter()
+            .any(|(ticker, _)| ticker.eq_ignore_ascii_case(&asset.ticker));
+        if let (Some(date), false) = (date, already_listed) {
+            upcoming.push((asset.ticker.clone(), date));
+        }
+    }
+    upcoming.sort_by_key(|(_, date)| *date);
+    upcoming
+}
+
+// each ticker once, uppercased, in order of first appearance, with how many
+// lots (assets) share it
+pub fn distinct_tickers(assets: &[Asset]) -> Vec<(String, usize)> {
+    let mut tickers: Vec<(String, usize)> = vec![];
+    for asset in assets {
+        let ticker = asset.ticker.to_uppercase();
+        match tickers.iter_mut().find(|(known, _)| *known == ticker) {
+            Some((_, lots)) => *lots += 1,
+            None => tickers.push((ticker, 1)),
+        }
+    }
+    tickers
+}
+
+// what's been put into a ticker's held lots, for `cost`
+#[derive(Debug, PartialEq)]
+pub struct HeldCost {
+    pub ticker: String,
+    pub shares: u64,
+    pub cost: i64,
+    // the range of the lots' buy dates, None when none of them has one
+    pub first_bought: Option<NaiveDate>,
+    pub last_bought: Option<NaiveDate>,
+}
+
+impl HeldCost {
+    pub fn average_cost(&self) -> i64 {
+        per_share_cost(self.cost, self.shares)
+    }
+}
+
+// held lots by ticker, largest cost first. sold lots are left out, their
+// basis is part of the realized gains
+pub fn held_costs(assets: &[Asset]) -> Vec<HeldCost> {
+    let held: Vec<Asset> = assets
+        .iter()
+        .filter(|asset| is_asset_held(asset))
+        .cloned()
+        .collect();
+    let mut costs: Vec<HeldCost> = distinct_tickers(&held)
+        .into_iter()
+        .map(|(ticker, _)| {
+            let lots: Vec<&Asset> = held
+                .iter()
+                .filter(|asset| asset.ticker.eq_ignore_ascii_case(&ticker))
+                .collect();
+            HeldCost {
+                ticker,
+                shares: lots.iter().map(|asset| asset.quantity as u64).sum(),
+                cost: lots.iter().map(|asset| cost_basis(asset)).sum(),
+                first_bought: lots.iter().filter_map(|asset| asset.buy_date).min(),
+                last_bought: lots.iter().filter_map(|asset| asset.buy_date).max(),
+            }
+        })
+        .collect();
+    costs.sort_by_key(|cost| std::cmp::Reverse(cost.cost));
+    costs
+}
+
+// how much a hypothetical buy is for: a number of shares, or a sum of money
+// spent on as many whole shares as it covers
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BuyAmount {
+    Shares(u32),
+    Cents(u64),
+}
+
+impl BuyAmount {
+    pub fn shares_at(&self, price_cents: u32) -> u64 {
+        match *self {
+            BuyAmount::Shares(shares) => shares as u64,
+            BuyAmount::Cents(cents) => cents / price_cents.max(1) as u64,
+        }
+    }
+}
+
+// `dca`: a ticker's held position as it is, None when it isn't held, and as
+// it would be after buying more at a price today. nothing is changed
+pub fn dca_preview(
+    assets: &[Asset],
+    ticker: &str,
+    shares: u64,
+    price_cents: u32,
+    today: NaiveDate,
+) -> (Option<HeldCost>, HeldCost) {
+    let before = held_costs(assets)
+        .into_iter()
+        .find(|cost| cost.ticker.eq_ignore_ascii_case(ticker));
+    let after = match &before {
+        Some(before) => HeldCost {
+            ticker: before.ticker.clone(),
+            shares: before.shares + shares,
+            cost: before.cost + shares as i64 * price_cents as i64,
+            first_bought: before.first_bought.or(Some(today)),
+            last_bought: Some(today),
+        },
+        None => HeldCost {
+            ticker: ticker.to_uppercase(),
+            shares,
+            cost: shares as i64 * price_cents as i64,
+            first_bought: Some(today),
+            last_bought: Some(today),
+        },
+    };
+    (before, after)
+}
+
+// `yield`: a held ticker's trailing dividends over its price and over what
+// was paid per share. both use the one cached dividend figure, so they can be
+// compared
+#[derive(Debug, PartialEq)]
+pub struct TickerYield {
+    pub ticker: String,
+    // None for non-payers and tickers whose fundamentals aren't cached
+    pub dividend_cents: Option<f64>,
+    pub price_cents: u32,
+    pub average_cost: i64,
+}
+
+impl TickerYield {
+    pub fn yield_percent(&self) -> Option<f64> {
+        percent_of(self.dividend_cents?, self.price_cents as i64)
+    }
+
+    pub fn yield_on_cost_percent(&self) -> Option<f64> {
+        percent_of(self.dividend_cents?, self.average_cost)
+    }
+}
+
+fn percent_of(dividend_cents: f64, price_cents: i64) -> Option<f64> {
+    (price_cents > 0).then(|| dividend_cents / price_cents as f64 * 100.0)
+}
+
+// held tickers in the order of held_costs, priced at the most recently
+// fetched price of their lots
+pub fn ticker_yields(assets: &[Asset], metadata: &MetadataCache) -> Vec<TickerYield> {
+    held_costs(assets)
+        .into_iter()
+        .map(|cost| {
+            let price_cents = assets
+                .iter()
+                .filter(|asset| {
+                    is_asset_held(asset) && asset.ticker.eq_ignore_ascii_case(&cost.ticker)
+                })
+                .max_by_key(|asset| asset.price_updated_at)
+                .map_or(0, |asset| asset.current_price_cents);
+            TickerYield {
+                dividend_cents: metadata
+                    .get(&cost.ticker)
+                    .and_then(|entry| entry.fundamentals.as_ref())
+                    .and_then(|fundamentals| fundamentals.dividend_cents),
+                price_cents,
+                average_cost: cost.average_cost(),
+                ticker: cost.ticker,
+            }
+        })
+        .collect()
+}
+
+// the cost basis of one ticker's lots taken together, with sales matched
+// against purchases by `method` rather than lot by lot
+#[derive(Debug, PartialEq)]
+pub struct TickerCostBasis {
+    pub ticker: String,
+    // indices of the ticker's lots, in the order they appear in the portfolio
+    pub lots: Vec<usize>,
+    pub shares_bought: u64,
+    pub total_cost: i64,
+    pub shares_held: u64,
+    // what the shares still held cost, by the method
+    pub held_cost: i64,
+    // the held shares at the most recently fetched price
+    pub held_value: i64,
+    pub shares_sold: u64,
+    pub realized_gain: i64,
+}
+
+// purchases and sales of each ticker are replayed in date order: a sale takes
+// its shares out of what had been bought by then. purchases without a date
+// count as the oldest and sales without one as the newest
+pub fn ticker_cost_basis(assets: &[Asset], method: CostBasisMethod) -> Vec<TickerCostBasis> {
+    distinct_tickers(assets)
+        .into_iter()
+        .map(|(ticker, _)| {
+            let lots: Vec<usize> = (0..assets.len())
+                .filter(|index| assets[*index].ticker.eq_ignore_ascii_case(&ticker))
+                .collect();
+            lot_cost_basis(ticker, lots, assets, method)
+        })
+        .collect()
+}
+
+fn lot_cost_basis(
+    ticker: String,
+    lots: Vec<usize>,
+    assets: &[Asset],
+    method: CostBasisMethod,
+) -> TickerCostBasis {
+    // (date, is a sale, shares, price per share)
+    let mut events: Vec<(NaiveDate, bool, u32, u32)> = vec![];
+    for asset in lots.iter().map(|index| &assets[*index]) {
+        let bought = asset.buy_date.unwrap_or(NaiveDate::MIN);
+        events.push((bought, false, asset.quantity, asset.buy_price_cents));
+        if let Some(sell_price) = asset.sell_price_cents {
+            let sold = asset.sell_date.unwrap_or(NaiveDate::MAX);
+            events.push((sold, true, asset.quantity, sell_price));
+        }
+    }
+    // purchases before sales on the same day; stable, so otherwise in file order
+    events.sort_by_key(|(date, is_sale, _, _)| (*date, *is_sale));
+
+    // (shares, what they cost) of each purchase not sold yet, oldest first
+    let mut open: VecDeque<(u32, i64)> = VecDeque::new();
+    let mut shares_bought = 0;
+    let mut total_cost = 0;
+    let mut shares_sold = 0;
+    let mut realized_gain = 0;
+    for (_, is_sale, shares, price) in events {
+        if !is_sale {
+            shares_bought += shares as u64;
+            let cost = shares as i64 * price as i64;
+            total_cost += cost;
+            open.push_back((shares, cost));
+            continue;
+        }
+        shares_sold += shares as u64;
+        let cost = match method {
+            CostBasisMethod::Fifo => take_shares(&mut open, shares, false),
+            CostBasisMethod::Lifo => take_shares(&mut open, shares, true),
+            CostBasisMethod::Average => take_average(&mut open, shares),
+        };
+        realized_gain += shares as i64 * price as i64 - cost;
+    }
+
+    let shares_held: u64 = open.iter().map(|(shares, _)| *shares as u64).sum();
+    let held_cost = open.iter().map(|(_, cost)| cost).sum();
+    let current_price = lots
+        .iter()
+        .map(|index| &assets[*index])
+        .max_by_key(|asset| asset.price_updated_at)
+        .map_or(0, |asset| asset.current_price_cents);
+    TickerCostBasis {
+        ticker,
+        lots,
+        shares_bought,
+        total_cost,
+        shares_held,
+        held_cost,
+        held_value: shares_held as i64 * current_price as i64,
+        shares_sold,
+        realized_gain,
+    }
+}
+
+// removes shares from the oldest purchases, or the newest, returning what
+// they cost. a purchase that's only partly sold keeps the rest of its cost
+fn take_shares(open: &mut VecDeque<(u32, i64)>, mut shares: u32, newest_first: bool) -> i64 {
+    let mut cost = 0;
+    while shares > 0 {
+        let next = if newest_first {
+            open.back_mut()
+        } else {
+            open.front_mut()
+        };
+        let Some((available, available_cost)) = next else {
+            break;
+        };
+        let taken = shares.min(*available);
+        let taken_cost = share_of_cost(*available_cost, taken, *available);
+        cost += taken_cost;
+        *available -= taken;
+        *available_cost -= taken_cost;
+        shares -= taken;
+        if *available == 0 {
+            if newest_first {
+                open.pop_back();
+            } else {
+                open.pop_front();
+            }
+        }
+    }
+    cost
+}
+
+// removes shares at the average cost of everything held, which leaves the
+// rest as a single purchase at that average
+fn take_average(open: &mut VecDeque<(u32, i64)>, shares: u32) -> i64 {
+    let held: u32 = open.iter().map(|(shares, _)| *shares).sum();
+    let held_cost: i64 = open.iter().map(|(_, cost)| cost).sum();
+    let shares = shares.min(held);
+    let cost = share_of_cost(held_cost, shares, held);
+    open.clear();
+    if held > shares {
+        open.push_back((held - shares, held_cost - cost));
+    }
+    cost
+}
+
+// the cost of `shares` out of `of` shares that cost `cost` altogether,
+// rounded to the nearest cent
+fn share_of_cost(cost: i64, shares: u32, of: u32) -> i64 {
+    if of == 0 {
+        return 0;
+    }
+    (cost * shares as i64 + of as i64 / 2) / of as i64
+}
+
+// held assets whose price is older than max_age_hours, or was never fetched
+pub fn stale_held_assets(assets: &[Asset], now: DateTime<Utc>, max_age_hours: u32) -> usize {
+    assets
+        .iter()
+        .filter(|asset| is_asset_held(asset))
+        .filter(|asset| match asset.price_updated_at {
+            Some(updated) => now - updated > chrono::Duration::hours(max_age_hours as i64),
+            None => true,
+        })
+        .count()
+}
+
+// records today's held market value and what each ticker makes up of it,
+// replacing an earlier point from the same day
+pub fn record_portfolio_value(portfolio: &mut Portfolio, date: NaiveDate) {
+    let total = portfolio.total_held_market_value();
+    match portfolio.value_history.last_mut() {
+        Some(last) if last.0 == date => last.1 = total,
+        _ => portfolio.value_history.push((date, total)),
+    }
+    let mut values = BTreeMap::new();
+    for asset in portfolio.assets_held() {
+        if in_portfolio_currency(asset) {
+            *values.entry(asset.ticker.to_uppercase()).or_default() += market_value(asset);
+        }
+    }
+    portfolio.asset_values.insert(date, values);
+}
+
+// records the price for the day, replacing an earlier point from the same
+// day, then drops the oldest points beyond `limit`
+pub fn record_asset_price(asset: &mut Asset, price_cents: u32, date: NaiveDate, limit: usize) {
+    match asset.price_history.last_mut() {
+        Some(last) if last.0 == date => last.1 = price_cents,
+        _ => asset.price_history.push((date, price_cents)),
+    }
+    let excess = asset.price_history.len().saturating_sub(limit);
+    asset.price_history.drain(..excess);
+}
+
+// two points of the value history compared, for `snapshot-diff`
+#[derive(Debug, PartialEq)]
+pub struct SnapshotDiff {
+    pub from: (NaiveDate, i64),
+    pub to: (NaiveDate, i64),
+    // (ticker, value on the first date, value on the second), largest gain
+    // first. a ticker held on only one of them is worth 0 on the other
+    pub tickers: Vec<(String, i64, i64)>,
+}
+
+impl SnapshotDiff {
+    pub fn change(&self) -> i64 {
+        self.to.1 - self.from.1
+    }
+
+    // None if the first value was zero
+    pub fn change_percent(&self) -> Option<f64> {
+        if self.from.1 == 0 {
+            return None;
+        }
+        Some(self.change() as f64 / self.from.1 as f64 * 100.0)
+    }
+}
+
+// both dates have to be in the value history, which gets a point each day
+// the portfolio is refreshed. the tickers are only compared when both days
+// have their values recorded, which files from older versions don't
+pub fn snapshot_diff(
+    portfolio: &Portfolio,
+    from: NaiveDate,
+    to: NaiveDate,
+) -> Result<SnapshotDiff, PortfolioError> {
+    let value_on = |date: NaiveDate| {
+        portfolio
+            .value_history
+            .iter()
+            .find(|(day, _)| *day == date)
+            .map(|(_, value)| *value)
+            .ok_or_else(|| PortfolioError::NotFound(format!("no recorded value on {}", date)))
+    };
+    let (from_value, to_value) = (value_on(from)?, value_on(to)?);
+    let (before, after) = match (
+        portfolio.asset_values.get(&from),
+        portfolio.asset_values.get(&to),
+    ) {
+        (Some(before), Some(after)) => (before, after),
+        _ => {
+            return Ok(SnapshotDiff {
+                from: (from, from_value),
+                to: (to, to_value),
+                tickers: vec![],
+            })
+        }
+    };
+    let mut tickers: Vec<(String, i64, i64)> = before
+        .keys()
+        .chain(after.keys().filter(|ticker| !before.contains_key(*ticker)))
+        .map(|ticker| {
+            (
+                ticker.clone(),
+                before.get(ticker).copied().unwrap_or(0),
+                after.get(ticker).copied().unwrap_or(0),
+            )
+        })
+        .collect();
+    tickers.sort_by(|a, b| (b.2 - b.1).cmp(&(a.2 - a.1)).then(a.0.cmp(&b.0)));
+    Ok(SnapshotDiff {
+        from: (from, from_value),
+        to: (to, to_value),
+        tickers,
+    })
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Drawdown {
+    pub peak_date: NaiveDate,
+    pub peak_value: i64,
+    pub trough_date: NaiveDate,
+    pub trough_value: i64,
+}
+
+impl Drawdown {
+    pub fn decline(&self) -> i64 {
+        self.peak_value - self.trough_value
+    }
+
+    pub fn decline_percent(&self) -> f64 {
+        if self.peak_value == 0 {
+            return 0.0;
+        }
+        self.decline() as f64 / self.peak_value as f64 * 100.0
+    }
+}
+
+// finds the largest peak-to-trough decline in a date-ordered value history.
+// returns None when there are fewer than two points, since a single value
+// can't decline from anything. a series that never declines has a drawdown
+// of zero at its first point.
+pub fn max_drawdown(history: &[(NaiveDate, i64)]) -> Option<Drawdown> {
+    if history.len() < 2 {
+        return None;
+    }
+    let (first_date, first_value) = history[0];
+    let mut peak = (first_date, first_value);
+    let mut worst = Drawdown {
+        peak_date: first_date,
+        peak_value: first_value,
+        trough_date: first_date,
+        trough_value: first_value,
+    };
+    for &(date, value) in &history[1..] {
+        if value > peak.1 {
+            peak = (date, value);
+        } else if peak.1 - value > worst.decline() {
+            worst = Drawdown {
+                peak_date: peak.0,
+                peak_value: peak.1,
+                trough_date: date,
+                trough_value: value,
+            };
+        }
+    }
+    Some(worst)
+}
+
+// what merge_portfolios did, for telling the user
+#[derive(Debug, Default, PartialEq)]
+pub struct MergeReport {
+    // lots now in the portfolio that weren't before, including replacements
+    pub added: usize,
+    // held tickers that were in both, uppercased, each once
+    pub duplicates: Vec<String>,
+}
+
+// adds the incoming portfolio's assets to the base. an incoming held lot of a
+// ticker the base already held is a duplicate, and the strategy says what
+// happens to it; everything else, including every sold lot, is added as it
+// is. the incoming value history is dropped, since it's of another portfolio
+pub fn merge_portfolios(
+    base: &mut Portfolio,
+    incoming: Portfolio,
+    strategy: MergeStrategy,
+) -> MergeReport {
+    let held_before: HashSet<String> = base
+        .assets_held()
+        .into_iter()
+        .map(|asset| asset.ticker.to_uppercase())
+        .collect();
+    let mut report = MergeReport::default();
+    let is_duplicate =
+        |asset: &Asset| is_asset_held(asset) && held_before.contains(&asset.ticker.to_uppercase());
+    for asset in incoming.assets.iter().filter(|asset| is_duplicate(asset)) {
+        let ticker = asset.ticker.to_uppercase();
+        if !report.duplicates.contains(&ticker) {
+            report.duplicates.push(ticker);
+        }
+    }
+    if strategy == MergeStrategy::KeepIncoming {
+        base.assets.retain(|asset| {
+            !(is_asset_held(asset) && report.duplicates.contains(&asset.ticker.to_uppercase()))
+        });
+    }
+    for asset in incoming.assets {
+        if !is_duplicate(&asset) {
+            base.assets.push(asset);
+            report.added += 1;
+            continue;
+        }
+        match strategy {
+            MergeStrategy::KeepBoth | MergeStrategy::KeepIncoming => {
+                base.assets.push(asset);
+                report.added += 1;
+            }
+            MergeStrategy::KeepExisting => {}
+            MergeStrategy::SumQuantity => {
+                let existing = base
+                    .assets
+                    .iter_mut()
+                    .find(|lot| {
+                        is_asset_held(lot) && lot.ticker.eq_ignore_ascii_case(&asset.ticker)
+                    })
+                    .expect("a duplicate has a held lot to add to");
+                if !add_lot(existing, &asset) {
+                    // too many shares for one lot
+                    base.assets.push(asset);
+                    report.added += 1;
+                }
+            }
+        }
+    }
+    report
+}
+
+// adds another lot's shares to a held one at the weighted average price, and
+// its price if that's newer. false, changing nothing, if the shares don't fit
+fn add_lot(lot: &mut Asset, other: &Asset) -> bool {
+    let Some(quantity) = lot.quantity.checked_add(other.quantity) else {
+        return false;
+    };
+    if quantity > 0 {
+        let paid = lot.buy_price_cents as u64 * lot.quantity as u64
+            + other.buy_price_cents as u64 * other.quantity as u64;
+        lot.buy_price_cents = ((paid + quantity as u64 / 2) / quantity as u64) as u32;
+    }
+    lot.quantity = quantity;
+    if other.price_updated_at > lot.price_updated_at {
+        lot.current_price_cents = other.current_price_cents;
+        lot.price_updated_at = other.price_updated_at;
+    }
+    for tag in &other.tags {
+        if !lot.tags.contains(tag) {
+            lot.tags.push(tag.clone());
+        }
+    }
+    true
+}
+
+// held and realized totals for one account, for `summary accounts`
+#[derive(Debug, PartialEq)]
+pub struct AccountSummary {
+    // None for assets without an account
+    pub account: Option<String>,
+    pub cost_basis: i64,
+    pub market_value: i64,
+    pub realized: i64,
+}
+
+impl AccountSummary {
+    pub fn unrealized(&self) -> i64 {
+        self.market_value - self.cost_basis
+    }
+}
+
+// one summary per account, alphabetically with the unassigned assets last.
+// held and sold assets are counted as in the summary: cost and value from
+// the held ones, realized gains from the sold ones
+pub fn account_summaries(assets: &[Asset]) -> Vec<AccountSummary> {
+    let mut accounts: Vec<AccountSummary> = vec![];
+    for asset in assets.iter().filter(|asset| in_portfolio_currency(asset)) {
+        let index = match accounts
+            .iter()
+            .position(|summary| summary.account == asset.account)
+        {
+            Some(index) => index,
+            None => {
+                accounts.push(AccountSummary {
+                    account: asset.account.clone(),
+                    cost_basis: 0,
+                    market_value: 0,
+                    realized: 0,
+                });
+                accounts.len() - 1
+            }
+        };
+        let summary = &mut accounts[index];
+        match realized_gain_loss(asset) {
+            Some(gain) => summary.realized += gain,
+            None => {
+                summary.cost_basis += cost_basis(asset);
+                summary.market_value += market_value(asset);
+            }
+        }
+    }
+    // Some sorts after None, so the unassigned row is moved to the end
+    accounts
+        .sort_by(|a, b| (a.account.is_none(), &a.account).cmp(&(b.account.is_none(), &b.account)));
+    accounts
+}
+
+// the totals commands print again and again, worked out together so each
+// command doesn't go over every asset itself. the prompt keeps one around
+// until a command changes the portfolio or the metadata it depends on
+#[derive(Debug, PartialEq)]
+pub struct PortfolioAnalytics {
+    pub held: usize,
+    pub sold: usize,
+    // distinct tickers among the held assets, ignoring case
+    pub held_tickers: usize,
+    // the held ticker worth the most and its market value, None when
+    // nothing is held
+    pub largest_position: Option<(String, i64)>,
+    // market value of the held assets
+    pub total_value: i64,
+    // what was paid for the held assets
+    pub total_cost: i64,
+    // and for the sold ones
+    pub sold_cost: i64,
+    pub unrealized_pnl: i64,
+    pub realized_pnl: i64,
+    // held market value by sector, with tickers that have no cached sector
+    // under UNKNOWN_SECTOR
+    pub by_sector: HashMap<String, i64>,
+    // None when there aren't enough dated cash flows to work it out
+    pub irr: Option<f64>,
+    pub drawdown: Option<Drawdown>,
+    // tickers priced in another currency, e.g. "SHOP.TO (CAD)", which are
+    // counted as held or sold but left out of every money total
+    pub foreign: Vec<String>,
+    // held tickers whose last refresh failed, so they show an older price
+    pub refresh_failed: Vec<String>,
+    // how long the portfolio has been tracked, None without any date to go by
+    pub age: Option<PortfolioAge>,
+}
+
+// whole years and months, or days for a portfolio under a month old
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PortfolioAge {
+    pub years: u32,
+    pub months: u32,
+    pub days: u32,
+}
+
+impl PortfolioAge {
+    // a start in the future counts as no age at all
+    pub fn between(start: NaiveDate, today: NaiveDate) -> PortfolioAge {
+        if start >= today {
+            return PortfolioAge {
+                years: 0,
+                months: 0,
+                days: 0,
+            };
+        }
+        let mut months =
+            (today.year() - start.year()) * 12 + today.month() as i32 - start.month() as i32;
+        if today.day() < start.day() {
+            months -= 1;
+        }
+        let months = months.max(0) as u32;
+        PortfolioAge {
+            years: months / 12,
+            months: months % 12,
+            days: if months == 0 {
+                (today - start).num_days() as u32
+            } else {
+                0
+            },
+        }
+    }
+}
+
+// e.g. "2 years, 3 months", "1 year" or "12 days"
+impl fmt::Display for PortfolioAge {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let plural = |count: u32, unit: &str| {
+            format!("{} {}{}", count, unit, if count == 1 { "" } else { "s" })
+        };
+        let mut parts = vec![];
+        if self.years > 0 {
+            parts.push(plural(self.years, "year"));
+        }
+        if self.months > 0 {
+            parts.push(plural(self.months, "month"));
+        }
+        if parts.is_empty() {
+            parts.push(plural(self.days, "day"));
+        }
+        write!(f, "{}", parts.join(", "))
+    }
+}
+
+impl PortfolioAnalytics {
+    pub fn compute(
+        portfolio: &Portfolio,
+        metadata: &MetadataCache,
+        today: NaiveDate,
+    ) -> PortfolioAnalytics {
+        let mut analytics = PortfolioAnalytics {
+            held: 0,
+            sold: 0,
+            held_tickers: 0,
+            largest_position: None,
+            total_value: 0,
+            total_cost: 0,
+            sold_cost: 0,
+            unrealized_pnl: 0,
+            realized_pnl: 0,
+            by_sector: HashMap::new(),
+            irr: cash_flows(&portfolio.assets, today)
+                .and_then(|flows| internal_rate_of_return(&flows)),
+            drawdown: max_drawdown(&portfolio.value_history),
+            foreign: vec![],
+            refresh_failed: vec![],
+            age: portfolio
+                .inception_date()
+                .map(|start| PortfolioAge::between(start, today)),
+        };
+        let mut tickers = HashSet::new();
+        let mut values: HashMap<String, i64> = HashMap::new();
+        for asset in &portfolio.assets {
+            let foreign = !in_portfolio_currency(asset);
+            if foreign {
+                let label = format!(
+                    "{} ({})",
+                    asset.ticker.to_uppercase(),
+                    asset.currency.as_deref().unwrap_or_default()
+                );
+                if !analytics.foreign.contains(&label) {
+                    analytics.foreign.push(label);
+                }
+            }
+            if let Some(gain) = realized_gain_loss(asset) {
+                analytics.sold += 1;
+                if !foreign {
+                    analytics.realized_pnl += gain;
+                    analytics.sold_cost += cost_basis(asset);
+                }
+                continue;
+            }
+            analytics.held += 1;
+            let ticker = asset.ticker.to_uppercase();
+            if asset.refresh_failed_at.is_some() && !analytics.refresh_failed.contains(&ticker) {
+                analytics.refresh_failed.push(ticker.clone());
+            }
+            tickers.insert(ticker);
+            if foreign {
+                continue;
+            }
+            let value = market_value(asset);
+            *values.entry(asset.ticker.to_uppercase()).or_default() += value;
+            analytics.total_value += value;
+            analytics.total_cost += cost_basis(asset);
+            let sector = metadata
+                .get(&asset.ticker)
+                .and_then(|entry| entry.sector.as_deref())
+                .unwrap_or(UNKNOWN_SECTOR);
+            *analytics.by_sector.entry(sector.to_string()).or_default() += value;
+        }
+        analytics.held_tickers = tickers.len();
+        analytics.largest_position = values
+            .into_iter()
+            .max_by(|a, b| a.1.cmp(&b.1).then(b.0.cmp(&a.0)));
+        analytics.unrealized_pnl = analytics.total_value - analytics.total_cost;
+        analytics
+    }
+
+    // the same line Portfolio displays as
+    pub fn headline(&self, name: &str) -> String {
+        headline(
+            name,
+            self.held,
+            self.sold,
+            self.total_value,
+            self.total_cost,
+        )
+    }
+
+    // the unrealized gain as a percentage of what was paid, None with
+    // nothing held
+    pub fn return_percent(&self) -> Option<f64> {
+        if self.total_cost == 0 {
+            return None;
+        }
+        Some(self.unrealized_pnl as f64 / self.total_cost as f64 * 100.0)
+    }
+
+    // realized and unrealized gains over everything ever paid, None until
+    // something has been sold
+    pub fn total_return_percent(&self) -> Option<f64> {
+        if self.sold == 0 {
+            return None;
+        }
+        self.since_inception_percent()
+    }
+
+    // the same whether or not anything's been sold, None when nothing has
+    // been paid for anything
+    pub fn since_inception_percent(&self) -> Option<f64> {
+        let paid = self.total_cost + self.sold_cost;
+        if paid == 0 {
+            return None;
+        }
+        Some((self.unrealized_pnl + self.realized_pnl) as f64 / paid as f64 * 100.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use proptest::prelude::*;
+
+    fn asset(
+        buy_price_cents: u32,
+        current_price_cents: u32,
+        sell_price_cents: Option<u32>,
+    ) -> Asset {
+        Asset {
+            ticker: "AAPL".to_string(),
+            buy_price_cents,
+            buy_date: None,
+            current_price_cents,
+            sell_price_cents,
+            sell_date: None,
+            quantity: 1,
+            price_updated_at: None,
+            refresh_failed_at: None,
+            currency: None,
+            note: None,
+            tags: vec![],
+            account: None,
+            added_at: None,
+            price_history: vec![],
+            target_price_cents: None,
+            stop_loss: None,
+            stop_breached_at: None,
+            price_from_cache: false,
+        }
+    }
+
+    #[test]
+    fn builder_fills_in_defaults() {
+        let day = NaiveDate::from_ymd_opt(2023, 1, 1).unwrap();
+        let asset = AssetBuilder::new("AAPL", 15000)
+            .quantity(100)
+            .buy_date(day)
+            .sell_price(20000)
+            .build()
+            .unwrap();
+        assert_eq!(asset.ticker, "AAPL");
+        assert_eq!(asset.quantity, 100);
+        assert_eq!(asset.buy_date, Some(day));
+        assert_eq!(asset.current_price_cents, 15000);
+        assert_eq!(asset.sell_price_cents, Some(20000));
+        assert!(asset.tags.is_empty());
+    }
+
+    #[test]
+    fn price_history_keeps_one_point_a_day_up_to_the_limit() {
+        let day = |d| NaiveDate::from_ymd_opt(2024, 1, d).unwrap();
+        let mut asset = asset(100, 100, None);
+        record_asset_price(&mut asset, 110, day(1), 3);
+        record_asset_price(&mut asset, 120, day(1), 3);
+        assert_eq!(asset.price_history, vec![(day(1), 120)]);
+        for d in 2..=5 {
+            record_asset_price(&mut asset, 100 + d, day(d), 3);
+        }
+        assert_eq!(
+            asset.price_history,
+            vec![(day(3), 103), (day(4), 104), (day(5), 105)]
+        );
+        record_asset_price(&mut asset, 106, day(6), 0);
+        assert!(asset.price_history.is_empty());
+    }
+
+    #[test]
+    fn price_history_is_recorded_for_every_lot() {
+        let day = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let mut portfolio = portfolio(vec![asset(100, 100, None), asset(100, 100, None)]);
+        portfolio.assets[1].ticker = "msft".to_string();
+        portfolio.record_price_history("aapl", 150, day, 10);
+        assert_eq!(portfolio.assets[0].price_history, vec![(day, 150)]);
+        assert!(portfolio.assets[1].price_history.is_empty());
+    }
+
+    #[test]
+    fn builder_rejects_impossible_assets() {
+        let day = |d| NaiveDate::from_ymd_opt(2023, 1, d).unwrap();
+        let rejected =
+            |builder: AssetBuilder| matches!(builder.build(), Err(PortfolioError::InvalidInput(_)));
+        assert!(rejected(AssetBuilder::new("AAPL", 15000).quantity(0)));
+        assert!(rejected(AssetBuilder::new("AAPL", 0)));
+        assert!(rejected(AssetBuilder::new(" ", 15000)));
+        assert!(rejected(AssetBuilder::new("AAPL", 15000).sell_date(day(2))));
+        assert!(rejected(
+            AssetBuilder::new("AAPL", 15000)
+                .buy_date(day(5))
+                .sell_price(16000)
+                .sell_date(day(2))
+        ));
+    }
+
+    #[test]
+    fn percent_increase_gain() {
+        assert_eq!(percent_increase(100, 150), 50.0);
+        assert_eq!(percent_increase(0, 0), 0.0);
+        assert_eq!(percent_increase(0, 150), 0.0);
+    }
+
+    #[test]
+    fn percent_increase_loss() {
+        assert!((percent_increase(150, 100) - -33.333_332).abs() < 0.0001);
+    }
+
+    #[test]
+    fn display_portfolio() {
+        let mut gain = asset(10000, 12500, None);
+        gain.quantity = 4;
+        let mut portfolio = portfolio(vec![gain, asset(100, 100, Some(150))]);
+        portfolio.name = "main".to_string();
+        assert_eq!(
+            portfolio.to_string(),
+            "Portfolio \"main\" │ 2 assets (1 held, 1 sold) │ Value: $500.00 │ Return: +25.00%"
+        );
+    }
+
+    #[test]
+    fn display_empty_portfolio() {
+        assert_eq!(
+            Portfolio::default().to_string(),
+            "Portfolio \"untitled\" │ 0 assets (0 held, 0 sold) │ Value: $0.00 │ Return: n/a"
+        );
+    }
+
+    #[test]
+    fn display_held_asset() {
+        let mut held = asset(15000, 18942, None);
+        held.quantity = 100;
+        assert_eq!(
+            held.to_string(),
+            "AAPL: 100 shares @ $150.00 (current: $189.42, +26.28%)"
+        );
+    }
+
+    #[test]
+    fn display_sold_asset() {
+        assert_eq!(
+            asset(20000, 18942, Some(15000)).to_string(),
+            "AAPL: 1 shares @ $200.00 (sold: $150.00, -25.00%)"
+        );
+    }
+
+    #[test]
+    fn position_in_range_percent() {
+        assert_eq!(position_in_range(150, 100, 200), Some(50.0));
+        assert_eq!(position_in_range(200, 100, 200), Some(100.0));
+        assert_eq!(position_in_range(100, 100, 100), None);
+    }
+    proptest! {
+        #[test]
+        fn percent_increase_unchanged_is_zero(x in 1u32..) {
+            prop_assert_eq!(percent_increase(x, x), 0.0);
+        }
+
+        #[test]
+        fn percent_increase_doubled_is_one_hundred(x in 1u32..=u32::MAX / 2) {
+            prop_assert!((percent_increase(x, 2 * x) - 100.0).abs() < 0.001);
+        }
+
+        #[test]
+        fn percent_increase_negative_when_price_falls(old in 1u32.., new in 0u32..) {
+            prop_assume!(new < old);
+            prop_assert!(percent_increase(old, new) < 0.0);
+        }
+
+        #[test]
+        fn percent_increase_monotone_in_new(old in 1u32.., a in 0u32.., b in 0u32..) {
+            let (low, high) = if a <= b { (a, b) } else { (b, a) };
+            prop_assert!(percent_increase(old, low) <= percent_increase(old, high));
+        }
+    }
+
+    #[test]
+    fn asset_without_sell_price_is_held() {
+        let held = asset(100, 100, None);
+        assert!(!is_asset_sold(&held));
+        assert!(is_asset_held(&held));
+    }
+
+    #[test]
+    fn asset_with_sell_price_is_sold() {
+        let sold = asset(100, 100, Some(120));
+        assert!(is_asset_sold(&sold));
+        assert!(!is_asset_held(&sold));
+    }
+
+    fn portfolio(assets: Vec<Asset>) -> Portfolio {
+        Portfolio {
+            assets,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn held_totals_with_unrealized_loss_does_not_underflow() {
+        // market value below the buy price used to underflow the u32 subtraction
+        let portfolio = portfolio(vec![asset(15000, 10000, None), asset(100, 999, Some(200))]);
+        let net_buy_price = portfolio.total_held_cost_basis();
+        let market_value = portfolio.total_held_market_value();
+        assert_eq!(net_buy_price, 15000);
+        assert_eq!(market_value, 10000);
+        assert_eq!(format_money(market_value - net_buy_price), "-$50.00");
+    }
+
+    #[test]
+    fn held_totals_multiply_by_quantity() {
+        let mut held = asset(1000, 1500, None);
+        held.quantity = 10;
+        let portfolio = portfolio(vec![held]);
+        assert_eq!(portfolio.total_held_cost_basis(), 10000);
+        assert_eq!(portfolio.total_held_market_value(), 15000);
+    }
+
+    #[test]
+    fn realized_gain_loss_only_counts_sold_assets() {
+        let mut gain = asset(1000, 9999, Some(1500));
+        gain.quantity = 4;
+        let loss = asset(1000, 9999, Some(800));
+        let portfolio = portfolio(vec![gain, loss, asset(1000, 5000, None)]);
+        assert_eq!(portfolio.total_realized_gain_loss(), 2000 - 200);
+    }
+
+    #[test]
+    fn sector_allocation_groups_by_sector() {
+        let mut metadata = MetadataCache::default();
+        metadata.entry("AAPL").sector = Some("Technology".to_string());
+        metadata.entry("MSFT").sector = Some("Technology".to_string());
+        metadata.entry("VTI").sector = Some("Fund".to_string());
+        let mut assets = vec![
+            asset(100, 100, None),
+            asset(100, 200, None),
+            asset(100, 500, None),
+            asset(100, 1000, Some(100)),
+            asset(100, 50, None),
+        ];
+        assets[1].ticker = "msft".to_string();
+        assets[2].ticker = "VTI".to_string();
+        assets[3].ticker = "VTI".to_string();
+        assets[4].ticker = "XYZ".to_string();
+
+        let sectors = sector_allocation(&assets, &metadata);
+        assert_eq!(
+            sectors,
+            vec![
+                SectorAllocation {
+                    sector: "Fund".to_string(),
+                    market_value: 500,
+                    buy_value: 100,
+                    tickers: vec!["VTI".to_string()],
+                },
+                SectorAllocation {
+                    sector: "Technology".to_string(),
+                    market_value: 300,
+                    buy_value: 200,
+                    tickers: vec!["AAPL".to_string(), "msft".to_string()],
+                },
+                SectorAllocation {
+                    sector: UNKNOWN_SECTOR.to_string(),
+                    market_value: 50,
+                    buy_value: 100,
+                    tickers: vec!["XYZ".to_string()],
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn best_and_worst_ignore_sold_assets() {
+        let assets = vec![
+            asset(100, 120, None),
+            asset(100, 9999, Some(500)),
+            asset(100, 90, None),
+            asset(100, 150, None),
+            asset(100, 90, None),
+        ];
+        assert_eq!(best_and_worst_held(&assets), (Some(3), Some(2)));
+        assert_eq!(best_and_worst_held(&assets[..2]), (Some(0), None));
+        assert_eq!(best_and_worst_held(&assets[1..2]), (None, None));
+    }
+
+    fn merged(strategy: MergeStrategy) -> (Vec<(String, u32, u32)>, MergeReport) {
+        let mut base = portfolio(vec![asset(100, 150, None), asset(100, 9999, Some(120))]);
+        base.assets[1].ticker = "MSFT".to_string();
+        let mut incoming = portfolio(vec![
+            asset(200, 160, None),
+            asset(300, 300, None),
+            asset(100, 9999, Some(110)),
+        ]);
+        incoming.assets[0].ticker = "aapl".to_string();
+        incoming.assets[0].quantity = 3;
+        incoming.assets[1].ticker = "VTI".to_string();
+        incoming.assets[2].ticker = "AAPL".to_string();
+        let report = merge_portfolios(&mut base, incoming, strategy);
+        let lots = base
+            .assets
+            .iter()
+            .map(|asset| (asset.ticker.clone(), asset.quantity, asset.buy_price_cents))
+            .collect();
+        (lots, report)
+    }
+
+    #[test]
+    fn merge_strategies_handle_held_duplicates() {
+        let lot = |ticker: &str, quantity, price| (ticker.to_string(), quantity, price);
+        let (lots, report) = merged(MergeStrategy::KeepBoth);
+        assert_eq!(report.duplicates, vec!["AAPL".to_string()]);
+        assert_eq!(report.added, 3);
+        assert_eq!(lots.len(), 5);
+
+        // the sold AAPL lot isn't a duplicate, so it's always added
+        let (lots, report) = merged(MergeStrategy::KeepExisting);
+        assert_eq!(report.added, 2);
+        assert_eq!(
+            lots,
+            vec![
+                lot("AAPL", 1, 100),
+                lot("MSFT", 1, 100),
+                lot("VTI", 1, 300),
+                lot("AAPL", 1, 100)
+            ]
+        );
+
+        let (lots, _) = merged(MergeStrategy::KeepIncoming);
+        assert_eq!(
+            lots,
+            vec![
+                lot("MSFT", 1, 100),
+                lot("aapl", 3, 200),
+                lot("VTI", 1, 300),
+                lot("AAPL", 1, 100)
+            ]
+        );
+
+        // (100 + 3 * 200) / 4
+        let (lots, report) = merged(MergeStrategy::SumQuantity);
+        assert_eq!(report.added, 2);
+        assert_eq!(lots[0], lot("AAPL", 4, 175));
+        assert_eq!(lots.len(), 4);
+    }
+
+    #[test]
+    fn snapshot_diff_compares_recorded_days() {
+        let mut held = asset(100, 100, None);
+        held.quantity = 2;
+        let mut sold = asset(100, 95, None);
+        sold.ticker = "MSFT".to_string();
+        let mut portfolio = portfolio(vec![held, sold]);
+        record_portfolio_value(&mut portfolio, date(2));
+        // MSFT is sold and then removed, VTI bought, and AAPL goes up
+        portfolio.assets.remove(1);
+        let mut later = asset(100, 40, None);
+        later.ticker = "VTI".to_string();
+        portfolio.assets.push(later);
+        portfolio.assets[0].current_price_cents = 150;
+        record_portfolio_value(&mut portfolio, date(5));
+        assert_eq!(
+            portfolio.value_history,
+            vec![(date(2), 295), (date(5), 340)]
+        );
+
+        let diff = snapshot_diff(&portfolio, date(2), date(5)).unwrap();
+        assert_eq!(diff.change(), 45);
+        assert_eq!(
+            diff.tickers,
+            vec![
+                ("AAPL".to_string(), 200, 300),
+                ("VTI".to_string(), 0, 40),
+                ("MSFT".to_string(), 95, 0),
+            ]
+        );
+        assert!(snapshot_diff(&portfolio, date(2), date(3)).is_err());
+
+        // a day recorded before the values were kept has only the totals
+        portfolio.asset_values.remove(&date(2));
+        let diff = snapshot_diff(&portfolio, date(2), date(5)).unwrap();
+        assert_eq!(diff.change(), 45);
+        assert!(diff.tickers.is_empty());
+    }
+
+    #[test]
+    fn account_summaries_are_alphabetical_with_unassigned_last() {
+        let mut assets = vec![
+            asset(100, 150, None),
+            asset(100, 120, None),
+            asset(100, 9999, Some(130)),
+            asset(200, 100, None),
+        ];
+        assets[0].account = Some("Taxable".to_string());
+        assets[1].account = Some("IRA".to_string());
+        assets[2].account = Some("Taxable".to_string());
+        let summaries = account_summaries(&assets);
+        let rows: Vec<(Option<&str>, i64, i64, i64)> = summaries
+            .iter()
+            .map(|summary| {
+                (
+                    summary.account.as_deref(),
+                    summary.cost_basis,
+                    summary.market_value,
+                    summary.realized,
+                )
+            })
+            .collect();
+        assert_eq!(
+            rows,
+            vec![
+                (Some("IRA"), 100, 120, 0),
+                (Some("Taxable"), 100, 150, 30),
+                (None, 200, 100, 0),
+            ]
+        );
+        assert_eq!(summaries[2].unrealized(), -100);
+    }
+
+    #[test]
+    fn other_currencies_are_left_out_of_totals() {
+        let mut assets = vec![
+            asset(100, 150, None),
+            asset(200, 300, None),
+            asset(100, 9999, Some(130)),
+        ];
+        assets[0].currency = Some("usd".to_string());
+        assets[1].ticker = "SHOP.TO".to_string();
+        assets[1].currency = Some("CAD".to_string());
+        assets[2].ticker = "VOD.L".to_string();
+        assets[2].currency = Some("GBP".to_string());
+        let portfolio = portfolio(assets);
+        assert_eq!(portfolio.total_held_market_value(), 150);
+        assert_eq!(portfolio.total_realized_gain_loss(), 0);
+
+        let analytics =
+            PortfolioAnalytics::compute(&portfolio, &MetadataCache::default(), date(10));
+        assert_eq!(
+            (analytics.held, analytics.sold, analytics.held_tickers),
+            (2, 1, 2)
+        );
+        assert_eq!((analytics.total_value, analytics.total_cost), (150, 100));
+        assert_eq!(analytics.realized_pnl, 0);
+        assert_eq!(analytics.foreign, vec!["SHOP.TO (CAD)", "VOD.L (GBP)"]);
+        assert_eq!(account_summaries(&portfolio.assets)[0].market_value, 150);
+    }
+
+    #[test]
+    fn analytics_totals_held_and_sold_assets() {
+        let mut metadata = MetadataCache::default();
+        metadata.entry("AAPL").sector = Some("Technology".to_string());
+        let mut assets = vec![
+            asset(100, 150, None),
+            asset(200, 150, None),
+            asset(100, 9999, Some(130)),
+        ];
+        assets[1].ticker = "XYZ".to_string();
+        assets[1].quantity = 2;
+        assets.push(asset(100, 100, None));
+        assets[3].ticker = "aapl".to_string();
+        assets[3].quantity = 0;
+        let portfolio = portfolio(assets);
+
+        let analytics = PortfolioAnalytics::compute(&portfolio, &metadata, date(10));
+        assert_eq!((analytics.held, analytics.sold), (3, 1));
+        assert_eq!(analytics.held_tickers, 2);
+        assert_eq!(analytics.largest_position, Some(("XYZ".to_string(), 300)));
+        assert_eq!(analytics.total_value, 450);
+        assert_eq!(analytics.total_cost, 500);
+        assert_eq!(analytics.unrealized_pnl, -50);
+        assert_eq!(analytics.realized_pnl, 30);
+        assert_eq!(analytics.return_percent(), Some(-10.0));
+        // (-50 + 30) / (500 + 100)
+        let total = analytics.total_return_percent().unwrap();
+        assert!((total - -3.333).abs() < 0.001, "{}", total);
+        assert_eq!(analytics.by_sector["Technology"], 150);
+        assert_eq!(analytics.by_sector[UNKNOWN_SECTOR], 300);
+        assert_eq!(analytics.headline(&portfolio.name), portfolio.to_string());
+    }
+
+    fn date(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, 1, day).unwrap()
+    }
+
+    #[test]
+    fn upcoming_earnings_sorted_and_filtered() {
+        use crate::metadata::EarningsDate;
+        let mut metadata = MetadataCache::default();
+        let earnings = |day| {
+            Some(EarningsDate {
+                fetched_on: date(1),
+                date: Some(date(day)),
+            })
+        };
+        metadata.entry("AAPL").earnings = earnings(20);
+        metadata.entry("NVDA").earnings = earnings(12);
+        metadata.entry("OLD").earnings = earnings(2);
+        metadata.entry("SOLD").earnings = earnings(11);
+        let mut assets = vec![
+            asset(100, 100, None),
+            asset(100, 100, None),
+            asset(100, 100, None),
+            asset(100, 100, None),
+            asset(100, 100, Some(120)),
+            asset(100, 100, None),
+        ];
+        for (asset, ticker) in assets
+            .iter_mut()
+            .zip(["AAPL", "NVDA", "nvda", "OLD", "SOLD", "NONE"])
+        {
+            asset.ticker = ticker.to_string();
+        }
+        assert_eq!(
+            upcoming_earnings(&assets, &metadata, date(10)),
+            vec![
+                ("NVDA".to_string(), date(12)),
+                ("AAPL".to_string(), date(20))
+            ]
+        );
+    }
+
+    #[test]
+    fn max_drawdown_needs_two_points() {
+        assert_eq!(max_drawdown(&[]), None);
+        assert_eq!(max_drawdown(&[(date(1), 100)]), None);
+    }
+
+    #[test]
+    fn max_drawdown_monotonic_increase_is_zero() {
+        let drawdown = max_drawdown(&[(date(1), 100), (date(2), 110), (date(3), 120)]).unwrap();
+        assert_eq!(drawdown.decline(), 0);
+    }
+
+    #[test]
+    fn max_drawdown_monotonic_decrease() {
+        let drawdown = max_drawdown(&[(date(1), 200), (date(2), 150), (date(3), 100)]).unwrap();
+        assert_eq!(drawdown.peak_date, date(1));
+        assert_eq!(drawdown.trough_date, date(3));
+        assert_eq!(drawdown.decline_percent(), 50.0);
+    }
+
+    #[test]
+    fn max_drawdown_single_dip() {
+        let history = [
+            (date(1), 100),
+            (date(2), 120),
+            (date(3), 90),
+            (date(4), 110),
+        ];
+        let drawdown = max_drawdown(&history).unwrap();
+        assert_eq!(drawdown.peak_date, date(2));
+        assert_eq!(drawdown.trough_date, date(3));
+        assert_eq!(drawdown.decline(), 30);
+    }
+
+    #[test]
+    fn max_drawdown_keeps_larger_dip_after_new_high() {
+        // recovers to a new high, then has a smaller dip that must not replace the first
+        let history = [
+            (date(1), 100),
+            (date(2), 60),
+            (date(3), 150),
+            (date(4), 130),
+        ];
+        let drawdown = max_drawdown(&history).unwrap();
+        assert_eq!(drawdown.peak_date, date(1));
+        assert_eq!(drawdown.trough_date, date(2));
+        assert_eq!(drawdown.decline(), 40);
+    }
+
+    #[test]
+    fn max_drawdown_measures_from_new_high() {
+        let history = [
+            (date(1), 100),
+            (date(2), 90),
+            (date(3), 200),
+            (date(4), 120),
+        ];
+        let drawdown = max_drawdown(&history).unwrap();
+        assert_eq!(drawdown.peak_date, date(3));
+        assert_eq!(drawdown.trough_date, date(4));
+        assert_eq!(drawdown.decline(), 80);
+    }
+
+    #[test]
+    fn record_portfolio_value_replaces_same_day() {
+        let mut portfolio = portfolio(vec![asset(100, 150, None)]);
+        record_portfolio_value(&mut portfolio, date(1));
+        portfolio.assets[0].current_price_cents = 175;
+        record_portfolio_value(&mut portfolio, date(1));
+        record_portfolio_value(&mut portfolio, date(2));
+        assert_eq!(
+            portfolio.value_history,
+            vec![(date(1), 175), (date(2), 175)]
+        );
+    }
+
+    #[test]
+    fn stale_held_assets_counts_old_and_unknown_prices() {
+        let now = Utc.with_ymd_and_hms(2024, 3, 1, 12, 0, 0).unwrap();
+        let mut fresh = asset(100, 150, None);
+        fresh.price_updated_at = Some(now - chrono::Duration::hours(2));
+        let mut old = asset(100, 150, None);
+        old.price_updated_at = Some(now - chrono::Duration::hours(30));
+        let unknown = asset(100, 150, None);
+        let sold = asset(100, 150, Some(200));
+        assert_eq!(stale_held_assets(&[fresh, old, unknown, sold], now, 24), 2);
+    }
+
+    #[test]
+    fn distinct_tickers_counts_lots() {
+        let mut assets = vec![asset(100, 150, None), asset(100, 150, None)];
+        assets[1].ticker = "aapl".to_string();
+        let mut other = asset(100, 150, None);
+        other.ticker = "VTI".to_string();
+        assets.push(other);
+        assert_eq!(
+            distinct_tickers(&assets),
+            vec![("AAPL".to_string(), 2), ("VTI".to_string(), 1)]
+        );
+    }
+
+    #[test]
+    fn sold_before_skips_held_recent_and_undated() {
+        let mut old = asset(100, 150, Some(200));
+        old.sell_date = Some(date(1));
+        let mut recent = asset(100, 150, Some(200));
+        recent.sell_date = Some(date(20));
+        let undated = asset(100, 150, Some(200));
+        let held = asset(100, 150, None);
+        assert_eq!(
+            sold_before(&[held, old, recent, undated], date(10)),
+            vec![1]
+        );
+    }
+
+    #[test]
+    fn failed_refreshes_are_marked_until_one_succeeds() {
+        let mut portfolio = portfolio(vec![asset(100, 150, None), asset(100, 150, Some(170))]);
+        let at = Utc.with_ymd_and_hms(2024, 3, 1, 12, 0, 0).unwrap();
+        portfolio.mark_refresh_failed("aapl", at);
+        assert!(portfolio
+            .assets
+            .iter()
+            .all(|asset| asset.refresh_failed_at == Some(at) && asset.current_price_cents == 150));
+        let metadata = MetadataCache::default();
+        let today = NaiveDate::from_ymd_opt(2024, 3, 2).unwrap();
+        let analytics = PortfolioAnalytics::compute(&portfolio, &metadata, today);
+        assert_eq!(analytics.refresh_failed, vec!["AAPL"]);
+
+        let json = serde_json::to_string(&portfolio).unwrap();
+        let loaded: Portfolio = serde_json::from_str(&json).unwrap();
+        assert_eq!(loaded.assets[0].refresh_failed_at, Some(at));
+
+        portfolio.set_price("AAPL", 160, at);
+        assert!(portfolio
+            .assets
+            .iter()
+            .all(|asset| asset.refresh_failed_at.is_none()));
+        let analytics = PortfolioAnalytics::compute(&portfolio, &metadata, today);
+        assert!(analytics.refresh_failed.is_empty());
+    }
+
+    #[test]
+    fn set_price_updates_every_lot() {
+        let mut portfolio = portfolio(vec![asset(100, 150, None), asset(120, 150, None)]);
+        portfolio.assets[1].ticker = "aapl".to_string();
+        let at = Utc.with_ymd_and_hms(2024, 3, 1, 12, 0, 0).unwrap();
+        assert_eq!(portfolio.set_price("AAPL", 175, at), 2);
+        assert!(portfolio
+            .assets
+            .iter()
+            .all(|asset| asset.current_price_cents == 175 && asset.price_updated_at == Some(at)));
+        assert_eq!(portfolio.set_price("MSFT", 175, at), 0);
+    }
+
+    #[test]
+    fn memory_estimate_counts_assets() {
+        let empty = Portfolio::default();
+        let full = portfolio(vec![asset(100, 150, None), asset(100, 150, None)]);
+        assert_eq!(
+            empty.estimated_memory_bytes(),
+            std::mem::size_of::<Portfolio>()
+        );
+        assert!(
+            full.estimated_memory_bytes()
+                >= empty.estimated_memory_bytes() + 2 * std::mem::size_of::<Asset>()
+        );
+    }
+
+    #[test]
+    fn sell_all_held_sells_at_current_prices() {
+        let mut held = asset(100, 150, None);
+        held.quantity = 4;
+        let mut portfolio = portfolio(vec![held, asset(100, 120, Some(90))]);
+        assert_eq!(portfolio.sell_all_held(date(5)), (1, 600));
+        assert_eq!(portfolio.assets[0].sell_price_cents, Some(150));
+        assert_eq!(portfolio.assets[0].sell_date, Some(date(5)));
+        // already sold assets keep their original sale
+        assert_eq!(portfolio.assets[1].sell_price_cents, Some(90));
+        assert_eq!(portfolio.total_realized_gain_loss(), 200 - 10);
+    }
+
+    #[test]
+    fn prices_are_per_share() {
+        let mut lot = asset(15000, 18942, None);
+        lot.quantity = 10;
+        assert_eq!(cost_basis(&lot), 150000);
+        assert_eq!(market_value(&lot), 189420);
+        // the percent change doesn't depend on the quantity
+        assert!(
+            (percent_increase(lot.buy_price_cents, lot.current_price_cents) - 26.28).abs() < 0.01
+        );
+        let portfolio = portfolio(vec![lot]);
+        assert_eq!(portfolio.total_held_cost_basis(), 150000);
+        assert_eq!(portfolio.total_held_market_value(), 189420);
+    }
+
+    #[test]
+    fn detects_and_converts_total_buy_prices() {
+        // 10 shares bought for $1500 in total, entered as the buy price
+        let mut total = asset(150000, 18942, None);
+        total.quantity = 10;
+        let mut per_share = asset(15000, 18942, None);
+        per_share.quantity = 10;
+        // a single share can't be told apart
+        let single = asset(150000, 18942, None);
+        let mut portfolio = portfolio(vec![total, per_share, single]);
+        assert_eq!(portfolio.likely_total_prices(), vec![0]);
+        portfolio.convert_totals_to_per_share(&[0]);
+        assert_eq!(portfolio.assets[0].buy_price_cents, 15000);
+        assert!(portfolio.likely_total_prices().is_empty());
+    }
+
+    #[test]
+    fn files_without_a_price_basis_are_legacy() {
+        let legacy: Portfolio = serde_json::from_str(r#"{"assets":[]}"#).unwrap();
+        assert_eq!(legacy.price_basis, 0);
+        assert_eq!(Portfolio::default().price_basis, PRICE_BASIS_PER_SHARE);
+    }
+
+    #[test]
+    fn picker_order_sorts_and_filters() {
+        let lot = |ticker: &str, day: Option<u32>, sell: Option<u32>| {
+            let mut lot = asset(100, 150, sell);
+            lot.ticker = ticker.to_string();
+            lot.buy_date = day.map(date);
+            lot
+        };
+        let assets = vec![
+            lot("VTI", Some(3), None),
+            lot("aapl", None, None),
+            lot("AAPL", Some(9), None),
+            lot("AAPL", Some(2), Some(200)),
+        ];
+        assert_eq!(picker_order(&assets, None, |_| true), vec![3, 2, 1, 0]);
+        assert_eq!(
+            picker_order(&assets, Some("aapl"), is_asset_held),
+            vec![2, 1]
+        );
+        assert!(picker_order(&assets, Some("MSFT"), |_| true).is_empty());
+    }
+
+    #[test]
+    fn selling_part_of_a_lot_splits_it() {
+        let mut lot = asset(100, 150, None);
+        lot.quantity = 10;
+        let mut portfolio = portfolio(vec![lot, asset(200, 250, None)]);
+        assert_eq!(portfolio.sell_lot(0, 4, 175, Some(date(5))).unwrap(), 1);
+        assert_eq!(portfolio.assets.len(), 3);
+        assert_eq!(portfolio.assets[0].quantity, 6);
+        assert!(is_asset_held(&portfolio.assets[0]));
+        assert_eq!(portfolio.assets[1].quantity, 4);
+        assert_eq!(portfolio.assets[1].sell_price_cents, Some(175));
+        assert_eq!(portfolio.assets[1].sell_date, Some(date(5)));
+
+        assert!(portfolio.sell_lot(0, 7, 180, None).is_err());
+        assert_eq!(portfolio.sell_lot(0, 6, 180, None).unwrap(), 0);
+        assert_eq!(portfolio.assets.len(), 3);
+        assert_eq!(portfolio.assets[0].sell_price_cents, Some(180));
+    }
+
+    #[test]
+    fn empty_lots_cant_be_sold() {
+        let mut lot = asset(100, 150, None);
+        lot.quantity = 0;
+        let mut portfolio = portfolio(vec![lot]);
+        assert!(portfolio.sell_lot(0, 0, 175, None).is_err());
+        assert!(portfolio.sell_lot(0, 1, 175, None).is_err());
+        assert!(is_asset_held(&portfolio.assets[0]));
+        assert_eq!(portfolio.assets.len(), 1);
+    }
+
+    #[test]
+    fn doubling_in_a_year_is_a_100_percent_return() {
+        let start = NaiveDate::from_ymd_opt(2023, 1, 1).unwrap();
+        let end = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let rate = internal_rate_of_return(&[(start, -1000), (end, 2000)]).unwrap();
+        assert!((rate - 1.0).abs() < 0.01);
+        assert_eq!(internal_rate_of_return(&[(start, -1000)]), None);
+    }
+
+    #[test]
+    fn cash_flows_need_every_date() {
+        let mut held = asset(100, 150, None);
+        held.buy_date = Some(date(1));
+        let mut sold = asset(100, 150, Some(120));
+        sold.buy_date = Some(date(2));
+        sold.sell_date = Some(date(3));
+        assert_eq!(
+            cash_flows(&[held.clone(), sold.clone()], date(10)),
+            Some(vec![
+                (date(1), -100),
+                (date(10), 150),
+                (date(2), -100),
+                (date(3), 120)
+            ])
+        );
+        sold.sell_date = None;
+        assert_eq!(cash_flows(&[held, sold], date(10)), None);
+    }
+
+    #[test]
+    fn recently_added_uses_timestamps_then_position() {
+        let at = |hour: u32| Some(Utc.with_ymd_and_hms(2024, 1, 1, hour, 0, 0).unwrap());
+        let mut assets = vec![
+            asset(100, 150, None),
+            asset(100, 150, None),
+            asset(100, 150, None),
+            asset(100, 150, None),
+        ];
+        assets[0].added_at = at(9);
+        assets[2].added_at = at(8);
+        assert_eq!(recently_added(&assets, 3), vec![3, 2, 0]);
+        assert_eq!(recently_added(&assets, 10), vec![1, 3, 2, 0]);
+        assert!(recently_added(&assets, 0).is_empty());
+    }
+
+    #[test]
+    fn portfolio_age_is_in_whole_months() {
+        let ymd = |year, month, day| NaiveDate::from_ymd_opt(year, month, day).unwrap();
+        let age = |start, today| PortfolioAge::between(start, today).to_string();
+        assert_eq!(age(ymd(2022, 3, 15), ymd(2024, 6, 15)), "2 years, 3 months");
+        assert_eq!(age(ymd(2022, 3, 15), ymd(2024, 6, 14)), "2 years, 2 months");
+        assert_eq!(age(ymd(2023, 6, 1), ymd(2024, 6, 1)), "1 year");
+        assert_eq!(age(ymd(2024, 5, 20), ymd(2024, 6, 1)), "12 days");
+        assert_eq!(age(ymd(2024, 6, 2), ymd(2024, 6, 1)), "0 days");
+    }
+
+    #[test]
+    fn inception_is_the_earliest_date_known() {
+        let mut portfolio = portfolio(vec![asset(100, 150, None), asset(100, 150, None)]);
+        assert_eq!(portfolio.inception_date(), None);
+        portfolio.file_created_date = Some(date(20));
+        assert_eq!(portfolio.inception_date(), Some(date(20)));
+        portfolio.assets[1].buy_date = Some(date(10));
+        assert_eq!(portfolio.inception_date(), Some(date(10)));
+        portfolio.created_date = Some(date(5));
+        assert_eq!(portfolio.inception_date(), Some(date(5)));
+    }
+
+    #[test]
+    fn days_held_runs_to_the_sell_date() {
+        let mut held = asset(100, 150, None);
+        assert_eq!(days_held(&held, date(10)), None);
+        held.buy_date = Some(date(1));
+        assert_eq!(days_held(&held, date(10)), Some(9));
+        let mut sold = asset(100, 150, Some(120));
+        sold.buy_date = Some(date(1));
+        assert_eq!(days_held(&sold, date(10)), None);
+        sold.sell_date = Some(date(4));
+        assert_eq!(days_held(&sold, date(10)), Some(3));
+    }
+
+    #[test]
+    fn gaps_are_found_between_buys_and_sales() {
+        let ymd = |month, day| NaiveDate::from_ymd_opt(2024, month, day).unwrap();
+        let mut assets = vec![
+            asset(100, 150, None),
+            asset(100, 150, Some(120)),
+            asset(100, 150, None),
+            asset(100, 150, None),
+        ];
+        assets[0].buy_date = Some(ymd(1, 1));
+        assets[1].buy_date = Some(ymd(1, 15));
+        assets[1].sell_date = Some(ymd(6, 1));
+        assets[2].buy_date = Some(ymd(7, 1));
+        assert_eq!(
+            activity_gaps(&assets, 90, ymd(12, 1)),
+            vec![(ymd(1, 15), ymd(6, 1)), (ymd(7, 1), ymd(12, 1))]
+        );
+        assert_eq!(activity_gaps(&assets, 30, ymd(7, 20)).len(), 1);
+        assert!(activity_gaps(&[], 90, ymd(12, 1)).is_empty());
+    }
+
+    #[test]
+    fn targets_are_crossed_from_either_side() {
+        let mut assets = vec![
+            asset(100, 210, None),
+            asset(100, 140, None),
+            asset(100, 190, None),
+            asset(100, 210, Some(300)),
+            asset(100, 210, None),
+        ];
+        for asset in &mut assets[..4] {
+            asset.target_price_cents = Some(200);
+        }
+        assets[1].target_price_cents = Some(150);
+        assert_eq!(
+            targets_crossed(&[190, 160, 180, 190, 190], &assets),
+            vec![0, 1]
+        );
+        assert!(targets_crossed(&[200, 150, 180, 190, 190], &assets).is_empty());
+        assert!((percent_to_target(&assets[2]).unwrap() - 5.263).abs() < 0.001);
+        assert_eq!(percent_to_target(&assets[3]), None);
+        assert_eq!(percent_to_target(&assets[4]), None);
+    }
+
+    #[test]
+    fn buying_more_lowers_or_raises_the_average() {
+        let today = NaiveDate::from_ymd_opt(2024, 6, 1).unwrap();
+        let mut lots = vec![asset(10000, 8000, None), asset(14000, 8000, None)];
+        lots.push(asset(1000, 8000, Some(9000)));
+        // two held shares averaging $120, and the sold lot left out
+        let (before, after) = dca_preview(&lots, "aapl", 2, 6000, today);
+        assert_eq!(before.map(|before| before.average_cost()), Some(12000));
+        assert_eq!((after.shares, after.cost), (4, 36000));
+        assert_eq!(after.average_cost(), 9000);
+        assert_eq!(after.last_bought, Some(today));
+        let (before, after) = dca_preview(&lots, "MSFT", 3, 41000, today);
+        assert_eq!(before, None);
+        assert_eq!(
+            (after.ticker.as_str(), after.average_cost()),
+            ("MSFT", 41000)
+        );
+        assert_eq!(BuyAmount::Cents(50000).shares_at(20100), 2);
+        assert_eq!(BuyAmount::Shares(5).shares_at(20100), 5);
+    }
+
+    #[test]
+    fn assets_are_found_by_ticker_and_status() {
+        let mut portfolio = portfolio(vec![
+            asset(10000, 12000, None),
+            asset(10000, 12000, Some(11000)),
+            asset(5000, 6000, None),
+        ]);
+        portfolio.assets[2].ticker = "MSFT".to_string();
+        let prices = |assets: Vec<&Asset>| -> Vec<u32> {
+            assets.iter().map(|asset| asset.buy_price_cents).collect()
+        };
+        assert_eq!(portfolio.assets_by_ticker("aapl").len(), 2);
+        assert_eq!(prices(portfolio.assets_by_ticker("Msft")), vec![5000]);
+        assert!(portfolio.assets_by_ticker("GME").is_empty());
+        assert_eq!(prices(portfolio.assets_held()), vec![10000, 5000]);
+        assert_eq!(portfolio.assets_sold().len(), 1);
+        assert!(std::ptr::eq(
+            portfolio.assets_sold()[0],
+            &portfolio.assets[1]
+        ));
+    }
+
+    #[test]
+    fn stop_losses_stay_breached_until_acknowledged() {
+        let at = Utc.with_ymd_and_hms(2024, 6, 1, 12, 0, 0).unwrap();
+        let mut portfolio = portfolio(vec![
+            asset(10000, 9000, None),
+            asset(10000, 9100, None),
+            asset(10000, 8000, Some(12000)),
+        ]);
+        // 10% below the buy price, not the current one
+        portfolio.assets[0].stop_loss = Some(StopLoss::Percent(10.0));
+        portfolio.assets[1].stop_loss = Some(StopLoss::Price(9000));
+        portfolio.assets[2].stop_loss = Some(StopLoss::Price(9000));
+        assert_eq!(
+            portfolio.check_stop_losses(&[9500, 9500, 9500], at),
+            vec![0]
+        );
+        // recovering doesn't clear it, and it isn't reported twice
+        assert!(portfolio
+            .check_stop_losses(&[8500, 8500, 8500], at)
+            .is_empty());
+        assert_eq!(portfolio.assets[0].stop_breached_at, Some(at));
+        assert_eq!(portfolio.acknowledge_stop_breaches(Some("aapl")), 1);
+        assert_eq!(portfolio.assets[0].stop_breached_at, None);
+        // once acknowledged, only falling through the stop again counts
+        assert!(portfolio
+            .check_stop_losses(&[8900, 8900, 8900], at)
+            .is_empty());
+        assert_eq!(
+            portfolio.check_stop_losses(&[9100, 9100, 9100], at),
+            vec![0]
+        );
+        assert_eq!(portfolio.assets[0].stop_loss, Some(StopLoss::Percent(10.0)));
+        assert_eq!(StopLoss::Percent(12.5).price_cents(15000), 13125);
+    }
+
+    #[test]
+    fn held_lots_are_ordered_by_age() {
+        let today = NaiveDate::from_ymd_opt(2025, 1, 20).unwrap();
+        let mut assets = vec![
+            asset(100, 150, None),
+            asset(100, 150, None),
+            asset(100, 150, Some(120)),
+            asset(100, 150, None),
+        ];
+        assets[1].buy_date = Some(date(1));
+        assets[2].buy_date = Some(date(1));
+        assets[3].buy_date = Some(NaiveDate::from_ymd_opt(2023, 6, 1).unwrap());
+        assert_eq!(held_by_age(&assets, today), vec![3, 1, 0]);
+        assert_eq!(days_to_long_term(&assets[0], today), None);
+        // 19 days in, then over a year by 2025
+        assert_eq!(days_to_long_term(&assets[1], date(20)), Some(346));
+        assert_eq!(days_to_long_term(&assets[1], today), Some(0));
+        assert_eq!(days_to_long_term(&assets[2], date(20)), None);
+    }
+
+    // 10 shares at $100, 10 more at $200 sold at $300 on day 3 and another
+    // 10 at $400 bought after the sale
+    fn lots_around_a_sale() -> Vec<Asset> {
+        vec![
+            AssetBuilder::new("AAPL", 10000)
+                .quantity(10)
+                .buy_date(date(1))
+                .current_price(25000)
+                .build()
+                .unwrap(),
+            AssetBuilder::new("AAPL", 20000)
+                .quantity(10)
+                .buy_date(date(2))
+                .sell_price(30000)
+                .sell_date(date(3))
+                .build()
+                .unwrap(),
+            AssetBuilder::new("aapl", 40000)
+                .quantity(10)
+                .buy_date(date(4))
+                .current_price(25000)
+                .build()
+                .unwrap(),
+        ]
+    }
+
+    #[test]
+    fn cost_basis_matches_sales_by_method() {
+        let assets = lots_around_a_sale();
+        // FIFO sells the $100 shares, LIFO the $200 ones, since the $400
+        // ones weren't bought yet
+        for (method, realized, held_cost) in [
+            (CostBasisMethod::Fifo, 200000, 600000),
+            (CostBasisMethod::Lifo, 100000, 500000),
+            (CostBasisMethod::Average, 150000, 550000),
+        ] {
+            let [ticker] = ticker_cost_basis(&assets, method).try_into().unwrap();
+            assert_eq!(ticker.ticker, "AAPL");
+            assert_eq!(ticker.lots, vec![0, 1, 2]);
+            assert_eq!(ticker.shares_bought, 30);
+            assert_eq!(ticker.total_cost, 700000);
+            assert_eq!(ticker.shares_sold, 10);
+            assert_eq!(ticker.realized_gain, realized, "{:?}", method);
+            assert_eq!(ticker.shares_held, 20);
+            assert_eq!(ticker.held_cost, held_cost, "{:?}", method);
+            assert_eq!(ticker.held_value, 500000);
+        }
+    }
+
+    #[test]
+    fn cost_basis_partly_sells_a_purchase() {
+        let mut assets = lots_around_a_sale();
+        assets[1].quantity = 15;
+        assets[1].buy_date = Some(date(1));
+        // FIFO takes 10 shares at $100 and 5 of the 15 at $200
+        let [ticker] = ticker_cost_basis(&assets[..2], CostBasisMethod::Fifo)
+            .try_into()
+            .unwrap();
+        assert_eq!(ticker.realized_gain, 15 * 30000 - (100000 + 5 * 20000));
+        assert_eq!(ticker.shares_held, 10);
+        assert_eq!(ticker.held_cost, 10 * 20000);
+    }
+
+    #[test]
+    fn cost_basis_averages_to_the_cent() {
+        let assets = vec![
+            AssetBuilder::new("VTI", 100).quantity(2).build().unwrap(),
+            AssetBuilder::new("VTI", 101)
+                .quantity(1)
+                .sell_price(200)
+                .build()
+                .unwrap(),
+        ];
+        let [ticker] = ticker_cost_basis(&assets, CostBasisMethod::Average)
+            .try_into()
+            .unwrap();
+        // 301 cents over 3 shares: 100 for the one sold, 201 left
+        assert_eq!(ticker.realized_gain, 100);
+        assert_eq!(ticker.held_cost, 201);
+    }
+
+    #[test]
+    fn held_costs_average_held_lots_to_the_cent() {
+        let date = |day| NaiveDate::from_ymd_opt(2024, 3, day).unwrap();
+        let assets = vec![
+            AssetBuilder::new("VTI", 10000)
+                .quantity(2)
+                .buy_date(date(5))
+                .build()
+                .unwrap(),
+            AssetBuilder::new("AAPL", 15000)
+                .quantity(1)
+                .build()
+                .unwrap(),
+            AssetBuilder::new("vti", 10001)
+                .quantity(1)
+                .buy_date(date(1))
+                .build()
+                .unwrap(),
+            AssetBuilder::new("VTI", 50000)
+                .quantity(4)
+                .buy_date(date(20))
+                .sell_price(60000)
+                .build()
+                .unwrap(),
+            AssetBuilder::new("MSFT", 30000)
+                .sell_price(40000)
+                .build()
+                .unwrap(),
+        ];
+        let costs = held_costs(&assets);
+        assert_eq!(
+            costs,
+            vec![
+                HeldCost {
+                    ticker: "VTI".to_string(),
+                    shares: 3,
+                    cost: 30001,
+                    first_bought: Some(date(1)),
+                    last_bought: Some(date(5)),
+                },
+                HeldCost {
+                    ticker: "AAPL".to_string(),
+                    shares: 1,
+                    cost: 15000,
+                    first_bought: None,
+                    last_bought: None,
+                },
+            ]
+        );
+        // 30001 cents over 3 shares
+        assert_eq!(costs[0].average_cost(), 10000);
+    }
+
+    #[test]
+    fn yields_share_the_dividend_figure() {
+        let mut metadata = MetadataCache::default();
+        metadata.entry("KO").fundamentals = Some(crate::metadata::Fundamentals {
+            fetched_on: NaiveDate::from_ymd_opt(2024, 1, 1).unwrap(),
+            pe_ratio: None,
+            market_cap: None,
+            dividend_cents: Some(194.0),
+        });
+        let assets = vec![
+            AssetBuilder::new("KO", 4850)
+                .quantity(10)
+                .current_price(6466)
+                .build()
+                .unwrap(),
+            AssetBuilder::new("TSLA", 20000)
+                .current_price(25000)
+                .build()
+                .unwrap(),
+        ];
+        let yields = ticker_yields(&assets, &metadata);
+        let ko = &yields[0];
+        assert_eq!(ko.ticker, "KO");
+        assert!((ko.yield_percent().unwrap() - 3.0).abs() < 0.01);
+        assert!((ko.yield_on_cost_percent().unwrap() - 4.0).abs() < 0.01);
+        assert_eq!(yields[1].ticker, "TSLA");
+        assert_eq!(yields[1].yield_percent(), None);
+        assert_eq!(yields[1].yield_on_cost_percent(), None);
+    }
+
+    #[test]
+    fn sort_keys_order_assets() {
+        let mut assets = vec![
+            asset(100, 150, None),
+            asset(100, 90, Some(200)),
+            asset(100, 120, None),
+        ];
+        assets[0].ticker = "vti".to_string();
+        assets[0].buy_date = Some(date(3));
+        assets[2].buy_date = Some(date(1));
+        let prices = |sorted: Vec<Asset>| -> Vec<u32> {
+            sorted
+                .iter()
+                .map(|asset| asset.current_price_cents)
+                .collect()
+        };
+        // the sold one gained 100% on its sell price
+        assert_eq!(
+            prices(sorted_assets(&assets, by_gain, true)),
+            vec![90, 150, 120]
+        );
+        // undated last
+        assert_eq!(
+            prices(sorted_assets(&assets, by_buy_date, false)),
+            vec![120, 150, 90]
+        );
+        // ties keep the stored order either way round
+        assert_eq!(
+            prices(sorted_assets(&assets, by_ticker, false)),
+            vec![90, 120, 150]
+        );
+        assert_eq!(
+            prices(sorted_assets(&assets, by_ticker, true)),
+            vec![150, 90, 120]
+        );
+        assert_eq!(
+            prices(sorted_assets(&assets, by_quantity, true)),
+            vec![150, 90, 120]
+        );
+    }
+
+    #[test]
+    fn goals_are_added_and_removed_by_name_ignoring_case() {
+        let mut portfolio = portfolio(vec![]);
+        let goal = |name: &str| Goal {
+            name: name.to_string(),
+            target_value_cents: 100_000,
+            target_date: date(31),
+        };
+        portfolio.add_goal(goal("House")).unwrap();
+        assert!(portfolio.add_goal(goal("house")).is_err());
+        portfolio.add_goal(goal("Retirement")).unwrap();
+        assert!(portfolio.remove_goal("car").is_err());
+        assert_eq!(portfolio.remove_goal("HOUSE").unwrap().name, "House");
+        assert_eq!(portfolio.goals, vec![goal("Retirement")]);
+    }
+}
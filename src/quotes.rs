@@ -0,0 +1,473 @@
+// where prices come from: Yahoo Finance, or a fixture file of fixed prices
+// given with --quotes-file, for demos and tests that shouldn't depend on the
+// network. a fixture maps tickers to a price and optionally the previous
+// close and currency, e.g. {"AAPL": 190.25, "MSFT": {"price": 410.0,
+// "previous_close": 405.5}, "SHOP.TO": {"price": 95.5, "currency": "CAD"}}.
+// quotes fetched from Yahoo are also kept in a cache file between sessions
+use crate::api::{
+    closing_prices, dividend_payments, parse_quote, quote_currency, TickerQuote, YahooClient,
+    QUOTE_BATCH_SIZE, QUOTE_RANGE,
+};
+use crate::error::PortfolioError;
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use futures::future::join_all;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+use tracing::{debug, warn};
+use yahoo_finance_api as yf;
+
+// how many tickers are fetched at once when it's one request each. more
+// would be faster, but Yahoo starts refusing requests that arrive in a burst
+const QUOTE_CONCURRENCY: usize = 8;
+
+pub enum QuoteProvider {
+    Yahoo(YahooClient),
+    Fixture(QuoteFixture),
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub struct QuoteFixture {
+    // keyed by uppercased ticker
+    quotes: HashMap<String, FixtureQuote>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(untagged)]
+enum FixtureEntry {
+    Price(f64),
+    Quote(FixtureQuote),
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+struct FixtureQuote {
+    price: f64,
+    #[serde(default)]
+    previous_close: Option<f64>,
+    // e.g. "CAD", for trying out tickers priced in another currency
+    #[serde(default)]
+    currency: Option<String>,
+}
+
+impl QuoteFixture {
+    // TOML if the file ends in .toml, JSON otherwise
+    pub fn load(path: &Path) -> Result<QuoteFixture, PortfolioError> {
+        let raw = fs::read_to_string(path)?;
+        let is_toml = path
+            .extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("toml"));
+        if is_toml {
+            QuoteFixture::from_toml(&raw)
+        } else {
+            QuoteFixture::from_json(&raw)
+        }
+    }
+
+    pub fn from_json(raw: &str) -> Result<QuoteFixture, PortfolioError> {
+        let entries: HashMap<String, FixtureEntry> = serde_json::from_str(raw)
+            .map_err(|e| PortfolioError::ParseError(format!("quotes file: {}", e)))?;
+        QuoteFixture::from_entries(entries)
+    }
+
+    pub fn from_toml(raw: &str) -> Result<QuoteFixture, PortfolioError> {
+        let entries: HashMap<String, FixtureEntry> = toml::from_str(raw)
+            .map_err(|e| PortfolioError::ParseError(format!("quotes file: {}", e)))?;
+        QuoteFixture::from_entries(entries)
+    }
+
+    fn from_entries(
+        entries: HashMap<String, FixtureEntry>,
+    ) -> Result<QuoteFixture, PortfolioError> {
+        let mut quotes = HashMap::new();
+        for (ticker, entry) in entries {
+            let quote = match entry {
+                FixtureEntry::Price(price) => FixtureQuote {
+                    price,
+                    previous_close: None,
+                    currency: None,
+                },
+                FixtureEntry::Quote(quote) => quote,
+            };
+            let prices = [Some(quote.price), quote.previous_close];
+            if prices
+                .iter()
+                .flatten()
+                .any(|price| !price.is_finite() || *price <= 0.0)
+            {
+                return Err(PortfolioError::ParseError(format!(
+                    "quotes file: {} needs prices above zero",
+                    ticker
+                )));
+            }
+            quotes.insert(ticker.to_uppercase(), quote);
+        }
+        Ok(QuoteFixture { quotes })
+    }
+
+    pub fn len(&self) -> usize {
+        self.quotes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.quotes.is_empty()
+    }
+
+    // the same error Yahoo gives for a ticker it doesn't list, so callers
+    // report it as an unknown symbol
+    pub fn quote(&self, ticker: &str) -> Result<TickerQuote, yf::YahooError> {
+        let quote = self
+            .quotes
+            .get(&ticker.to_uppercase())
+            .ok_or_else(|| yf::YahooError::FetchFailed("404 Not Found".to_string()))?;
+        Ok(TickerQuote {
+            price_cents: to_cents(quote.price),
+            previous_close_cents: quote.previous_close.map(to_cents),
+            fifty_two_week_range_cents: None,
+            currency: quote.currency.as_ref().map(|code| code.to_uppercase()),
+        })
+    }
+}
+
+fn to_cents(price: f64) -> u32 {
+    (price * 100.0).round() as u32
+}
+
+// the oldest quotes are dropped beyond this many, so tickers that were
+// looked up once don't stay in the file forever
+pub const MAX_CACHED_QUOTES: usize = 1000;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct CachedQuote {
+    pub price_cents: u32,
+    #[serde(default)]
+    pub previous_close_cents: Option<u32>,
+    #[serde(default)]
+    pub currency: Option<String>,
+    pub fetched_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq)]
+pub struct QuoteCache {
+    // keyed by uppercased ticker
+    #[serde(default)]
+    quotes: HashMap<String, CachedQuote>,
+}
+
+fn quote_cache_path() -> Option<PathBuf> {
+    dirs_next::cache_dir().map(|dir| dir.join("portfolio-tracker").join("quotes.json"))
+}
+
+impl QuoteCache {
+    // a missing or corrupt cache just means everything gets fetched again
+    pub fn load() -> QuoteCache {
+        quote_cache_path()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .map_or_else(QuoteCache::default, |raw| QuoteCache::from_json(&raw))
+    }
+
+    pub fn from_json(raw: &str) -> QuoteCache {
+        serde_json::from_str(raw).unwrap_or_else(|e| {
+            warn!(error = %e, "ignoring corrupt quote cache");
+            QuoteCache::default()
+        })
+    }
+
+    pub fn save(&self) -> Result<(), PortfolioError> {
+        let path = quote_cache_path()
+            .ok_or_else(|| PortfolioError::NotFound("platform cache directory".to_string()))?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let json = serde_json::to_string(self)?;
+        debug!(path = %path.display(), quotes = self.quotes.len(), "saving quote cache");
+        fs::write(path, json)?;
+        Ok(())
+    }
+
+    pub fn len(&self) -> usize {
+        self.quotes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.quotes.is_empty()
+    }
+
+    pub fn insert(&mut self, ticker: &str, quote: &TickerQuote, fetched_at: DateTime<Utc>) {
+        self.quotes.insert(
+            ticker.to_uppercase(),
+            CachedQuote {
+                price_cents: quote.price_cents,
+                previous_close_cents: quote.previous_close_cents,
+                currency: quote.currency.clone(),
+                fetched_at,
+            },
+        );
+        while self.quotes.len() > MAX_CACHED_QUOTES {
+            let Some(oldest) = self
+                .quotes
+                .iter()
+                .min_by_key(|(_, quote)| quote.fetched_at)
+                .map(|(ticker, _)| ticker.clone())
+            else {
+                break;
+            };
+            self.quotes.remove(&oldest);
+        }
+    }
+
+    // the cached quote if it was fetched less than `ttl` ago
+    pub fn fresh(&self, ticker: &str, ttl: Duration, now: DateTime<Utc>) -> Option<CachedQuote> {
+        self.quotes
+            .get(&ticker.to_uppercase())
+            .filter(|quote| now - quote.fetched_at < ttl)
+            .cloned()
+    }
+}
+
+pub fn save_quote_cache(cache: &QuoteCache) {
+    // like the metadata cache, failing to save this only costs some fetching
+    if let Err(e) = cache.save() {
+        warn!(error = %e, "failed to save quote cache");
+    }
+}
+
+impl QuoteProvider {
+    pub fn is_fixture(&self) -> bool {
+        matches!(self, QuoteProvider::Fixture(_))
+    }
+
+    pub async fn quote(&self, ticker: &str) -> Result<TickerQuote, yf::YahooError> {
+        match self {
+            QuoteProvider::Yahoo(client) => {
+                let response = client.chart(ticker, "1d", QUOTE_RANGE).await?;
+                parse_quote(&response)
+            }
+            QuoteProvider::Fixture(fixture) => fixture.quote(ticker),
+        }
+    }
+
+    // the latest quote of each ticker, in the same order. Yahoo is asked for
+    // QUOTE_BATCH_SIZE at a time; a ticker a batch leaves out or can't price
+    // is fetched on its own, which also says why it failed. once a batch
+    // fails outright, e.g. without a crumb, the rest are all fetched singly
+    pub async fn latest_prices(
+        &self,
+        tickers: &[&str],
+    ) -> Vec<Result<TickerQuote, yf::YahooError>> {
+        let client = match self {
+            QuoteProvider::Yahoo(client) => client,
+            QuoteProvider::Fixture(fixture) => {
+                return tickers.iter().map(|ticker| fixture.quote(ticker)).collect()
+            }
+        };
+        let started = Instant::now();
+        let mut batched: HashMap<String, TickerQuote> = HashMap::new();
+        let mut batch_requests = 0;
+        for batch in tickers.chunks(QUOTE_BATCH_SIZE) {
+            batch_requests += 1;
+            match client.batch_quotes(batch).await {
+                Ok(quotes) => batched.extend(
+                    quotes
+                        .into_iter()
+                        .filter_map(|(symbol, quote)| Some((symbol, quote?))),
+                ),
+                Err(e) => {
+                    debug!(error = %e, "batch quotes failed, fetching tickers one at a time");
+                    break;
+                }
+            }
+        }
+        let mut quotes: Vec<Option<Result<TickerQuote, yf::YahooError>>> = tickers
+            .iter()
+            .map(|ticker| batched.get(&ticker.to_uppercase()).cloned().map(Ok))
+            .collect();
+        let singly: Vec<usize> = (0..tickers.len())
+            .filter(|index| quotes[*index].is_none())
+            .collect();
+        for chunk in singly.chunks(QUOTE_CONCURRENCY) {
+            let fetched = join_all(chunk.iter().map(|index| self.quote(tickers[*index]))).await;
+            for (index, quote) in chunk.iter().zip(fetched) {
+                quotes[*index] = Some(quote);
+            }
+        }
+        debug!(
+            tickers = tickers.len(),
+            batch_requests,
+            single_requests = singly.len(),
+            elapsed_ms = started.elapsed().as_millis() as u64,
+            "latest prices"
+        );
+        quotes.into_iter().flatten().collect()
+    }
+
+    // (date, close) for each bar of the given interval over the given range.
+    // a fixture has no history, so it only checks that the ticker exists
+    pub async fn closing_prices(
+        &self,
+        ticker: &str,
+        interval: &str,
+        range: &str,
+    ) -> Result<Vec<(NaiveDate, u32)>, yf::YahooError> {
+        match self {
+            QuoteProvider::Yahoo(client) => {
+                let response = client.chart(ticker, interval, range).await?;
+                let (_, divisor) = quote_currency(&response);
+                Ok(closing_prices(&response.quotes()?)
+                    .into_iter()
+                    .map(|(date, cents)| (date, (cents as f64 / divisor).round() as u32))
+                    .collect())
+            }
+            QuoteProvider::Fixture(fixture) => fixture.quote(ticker).map(|_| vec![]),
+        }
+    }
+
+    // daily closes and the dividends paid over a range, for reinvesting them.
+    // a fixture has neither
+    pub async fn dividend_history(
+        &self,
+        ticker: &str,
+        range: &str,
+    ) -> Result<DividendHistory, yf::YahooError> {
+        match self {
+            QuoteProvider::Yahoo(client) => {
+                let response = client.chart(ticker, "1d", range).await?;
+                let (_, divisor) = quote_currency(&response);
+                Ok(DividendHistory {
+                    closes: closing_prices(&response.quotes()?)
+                        .into_iter()
+                        .map(|(date, cents)| (date, (cents as f64 / divisor).round() as u32))
+                        .collect(),
+                    dividends: dividend_payments(&response.dividends()?, divisor),
+                })
+            }
+            QuoteProvider::Fixture(fixture) => {
+                fixture.quote(ticker).map(|_| DividendHistory::default())
+            }
+        }
+    }
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub struct DividendHistory {
+    // (date, close) oldest first
+    pub closes: Vec<(NaiveDate, u32)>,
+    // (ex-dividend date, cents per share) oldest first
+    pub dividends: Vec<(NaiveDate, f64)>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::quote_failure_reason;
+    use chrono::TimeZone;
+
+    fn quote(price_cents: u32) -> TickerQuote {
+        TickerQuote {
+            price_cents,
+            previous_close_cents: Some(price_cents - 1),
+            fifty_two_week_range_cents: None,
+            currency: Some("CAD".to_string()),
+        }
+    }
+
+    #[test]
+    fn fixture_accepts_plain_prices_and_previous_closes() {
+        let fixture = QuoteFixture::from_json(
+            r#"{"aapl": 190.25, "MSFT": {"price": 410.0, "previous_close": 405.5}}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            fixture.quote("AAPL").unwrap(),
+            TickerQuote {
+                price_cents: 19025,
+                previous_close_cents: None,
+                fifty_two_week_range_cents: None,
+                currency: None,
+            }
+        );
+        assert_eq!(
+            fixture.quote("msft").unwrap().previous_close_cents,
+            Some(40550)
+        );
+    }
+
+    #[test]
+    fn fixture_quotes_can_have_a_currency() {
+        let fixture =
+            QuoteFixture::from_json(r#"{"SHOP.TO": {"price": 95.5, "currency": "cad"}}"#).unwrap();
+        assert_eq!(
+            fixture.quote("shop.to").unwrap().currency.as_deref(),
+            Some("CAD")
+        );
+    }
+
+    #[test]
+    fn fixture_reads_toml() {
+        let fixture = QuoteFixture::from_toml("AAPL = 190.25\n\n[MSFT]\nprice = 410.0\n").unwrap();
+        assert_eq!(fixture.len(), 2);
+        assert_eq!(fixture.quote("MSFT").unwrap().price_cents, 41000);
+    }
+
+    #[test]
+    fn fixture_unknown_ticker_is_unknown_symbol() {
+        let fixture = QuoteFixture::from_json(r#"{"AAPL": 190.25}"#).unwrap();
+        let e = fixture.quote("NOPE").unwrap_err();
+        assert_eq!(quote_failure_reason(&e), "unknown symbol");
+    }
+
+    #[test]
+    fn fixture_rejects_non_positive_prices() {
+        assert!(QuoteFixture::from_json(r#"{"AAPL": 0}"#).is_err());
+        assert!(
+            QuoteFixture::from_json(r#"{"AAPL": {"price": 1, "previous_close": -2}}"#).is_err()
+        );
+    }
+
+    #[test]
+    fn quote_cache_serves_only_fresh_quotes() {
+        let fetched = Utc.with_ymd_and_hms(2024, 6, 1, 12, 0, 0).unwrap();
+        let mut cache = QuoteCache::default();
+        cache.insert("aapl", &quote(19025), fetched);
+        let ttl = Duration::minutes(15);
+        let cached = cache
+            .fresh("AAPL", ttl, fetched + Duration::minutes(14))
+            .unwrap();
+        assert_eq!(cached.price_cents, 19025);
+        assert_eq!(cached.previous_close_cents, Some(19024));
+        assert_eq!(cached.currency.as_deref(), Some("CAD"));
+        assert_eq!(
+            cache.fresh("AAPL", ttl, fetched + Duration::minutes(15)),
+            None
+        );
+        assert_eq!(cache.fresh("MSFT", ttl, fetched), None);
+    }
+
+    #[test]
+    fn quote_cache_round_trips_and_survives_corruption() {
+        let fetched = Utc.with_ymd_and_hms(2024, 6, 1, 12, 0, 0).unwrap();
+        let mut cache = QuoteCache::default();
+        cache.insert("AAPL", &quote(19025), fetched);
+        let json = serde_json::to_string(&cache).unwrap();
+        assert_eq!(QuoteCache::from_json(&json), cache);
+        assert!(QuoteCache::from_json("{\"quotes\": [tru").is_empty());
+    }
+
+    #[test]
+    fn quote_cache_drops_the_oldest_quotes() {
+        let start = Utc.with_ymd_and_hms(2024, 6, 1, 12, 0, 0).unwrap();
+        let mut cache = QuoteCache::default();
+        for i in 0..=MAX_CACHED_QUOTES {
+            cache.insert(
+                &format!("T{}", i),
+                &quote(100),
+                start + Duration::seconds(i as i64),
+            );
+        }
+        assert_eq!(cache.len(), MAX_CACHED_QUOTES);
+        let later = start + Duration::seconds(1);
+        assert_eq!(cache.fresh("T0", Duration::days(1), later), None);
+        assert!(cache.fresh("T1", Duration::days(1), later).is_some());
+    }
+}
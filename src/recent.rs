@@ -0,0 +1,122 @@
+// the portfolio files loaded or dumped most recently, offered as a menu when
+// `load` is given no filename. kept in the cache directory next to the
+// metadata cache
+use crate::error::PortfolioError;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::debug;
+
+pub const RECENT_FILES_LIMIT: usize = 10;
+
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq)]
+pub struct RecentFiles {
+    // most recent first
+    #[serde(default)]
+    paths: Vec<PathBuf>,
+}
+
+fn recent_path() -> Option<PathBuf> {
+    dirs_next::cache_dir().map(|dir| dir.join("portfolio-tracker").join("recent.json"))
+}
+
+impl RecentFiles {
+    // like the metadata cache, a missing or unreadable list just starts over
+    pub fn load() -> RecentFiles {
+        recent_path()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .and_then(|raw| serde_json::from_str(&raw).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> Result<(), PortfolioError> {
+        let path = recent_path()
+            .ok_or_else(|| PortfolioError::NotFound("platform cache directory".to_string()))?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let json = serde_json::to_string(self)?;
+        debug!(path = %path.display(), files = self.paths.len(), "saving recent files");
+        fs::write(path, json)?;
+        Ok(())
+    }
+
+    pub fn paths(&self) -> &[PathBuf] {
+        &self.paths
+    }
+
+    // moves the file to the top of the list. paths are made absolute so the
+    // list still works from another working directory
+    pub fn record(&mut self, path: &Path) {
+        let path = std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+        self.paths.retain(|existing| *existing != path);
+        self.paths.insert(0, path);
+        self.paths.truncate(RECENT_FILES_LIMIT);
+    }
+
+    // drops files that have been moved or deleted since, returning how many
+    pub fn prune_missing(&mut self) -> usize {
+        let before = self.paths.len();
+        self.paths.retain(|path| path.exists());
+        before - self.paths.len()
+    }
+}
+
+// shortens paths under the home directory to e.g. ~/port/main.json
+pub fn display_path(path: &Path, home: Option<&Path>) -> String {
+    match home.and_then(|home| path.strip_prefix(home).ok()) {
+        Some(rest) => Path::new("~").join(rest).display().to_string(),
+        None => path.display().to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn record_moves_to_the_top_and_caps_the_list() {
+        let mut recent = RecentFiles::default();
+        for n in 0..12 {
+            recent.record(Path::new(&format!("/p/{}.json", n)));
+        }
+        recent.record(Path::new("/p/5.json"));
+        assert_eq!(recent.paths().len(), RECENT_FILES_LIMIT);
+        assert_eq!(recent.paths()[0], Path::new("/p/5.json"));
+        assert_eq!(recent.paths()[1], Path::new("/p/11.json"));
+        assert_eq!(
+            recent
+                .paths()
+                .iter()
+                .filter(|path| **path == Path::new("/p/5.json"))
+                .count(),
+            1
+        );
+        assert!(!recent.paths().contains(&PathBuf::from("/p/1.json")));
+    }
+
+    #[test]
+    fn prune_drops_missing_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let kept = dir.path().join("main.json");
+        fs::write(&kept, "{}").unwrap();
+        let mut recent = RecentFiles::default();
+        recent.record(&dir.path().join("gone.json"));
+        recent.record(&kept);
+        assert_eq!(recent.prune_missing(), 1);
+        assert_eq!(recent.paths(), [kept]);
+    }
+
+    #[test]
+    fn paths_under_home_are_shortened() {
+        let home = Path::new("/home/me");
+        assert_eq!(
+            display_path(Path::new("/home/me/port/main.json"), Some(home)),
+            "~/port/main.json"
+        );
+        assert_eq!(
+            display_path(Path::new("/srv/main.json"), Some(home)),
+            "/srv/main.json"
+        );
+    }
+}
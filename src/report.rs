@@ -0,0 +1,422 @@
+// the markdown report printed by `report` and --report: the summary, assets,
+// sectors, performance and risks of a portfolio in one document, using only
+// `##` headers, `-` bullets and `|` tables so it renders the same on GitHub,
+// in Obsidian or in any other markdown viewer
+use crate::display::{
+    describe_drawdown, format_money, format_money_with_sign, format_optional_percent,
+    format_percent,
+};
+use crate::metadata::MetadataCache;
+use crate::portfolio::{
+    best_and_worst_held, cost_basis, days_held, is_asset_held, market_value, percent_change,
+    sector_allocation, stale_held_assets, upcoming_earnings, Asset, Portfolio, PortfolioAnalytics,
+    LONG_TERM_DAYS, UNKNOWN_SECTOR,
+};
+use chrono::{DateTime, NaiveDate, Utc};
+
+// a ticker worth more than this share of the held value is a risk
+const POSITION_RISK_PERCENT: f64 = 20.0;
+
+// and so is a sector worth more than this
+const SECTOR_RISK_PERCENT: f64 = 40.0;
+
+// earnings this close are listed as a risk
+const EARNINGS_SOON_DAYS: i64 = 14;
+
+// a `|` inside a cell would end it early, and a newline would end the row
+fn md_escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('|', "\\|")
+        .replace('\n', " ")
+}
+
+// numbers are right aligned, and the columns listed in `text` left aligned
+fn md_table(header: &[&str], text: &[usize], rows: &[Vec<String>]) -> String {
+    let mut table = format!("| {} |\n|", header.join(" | "));
+    for column in 0..header.len() {
+        table.push_str(if text.contains(&column) {
+            " --- |"
+        } else {
+            " ---: |"
+        });
+    }
+    table.push('\n');
+    for row in rows {
+        let cells: Vec<String> = row.iter().map(|cell| md_escape(cell)).collect();
+        table.push_str(&format!("| {} |\n", cells.join(" | ")));
+    }
+    table
+}
+
+pub fn share_percent(part: i64, whole: i64) -> f64 {
+    if whole == 0 {
+        return 0.0;
+    }
+    part as f64 / whole as f64 * 100.0
+}
+
+pub fn format_summary_md(analytics: &PortfolioAnalytics) -> String {
+    let change = format_optional_percent(analytics.return_percent());
+    md_table(
+        &[
+            "Held",
+            "Sold",
+            "Cost Basis",
+            "Market Value",
+            "Unrealized Gain/Loss",
+            "Return",
+            "Realized Gain/Loss",
+        ],
+        &[],
+        &[vec![
+            analytics.held.to_string(),
+            analytics.sold.to_string(),
+            format_money(analytics.total_cost),
+            format_money(analytics.total_value),
+            format_money_with_sign(analytics.unrealized_pnl),
+            change,
+            format_money_with_sign(analytics.realized_pnl),
+        ]],
+    )
+}
+
+// the same columns as the assets table
+pub fn format_assets_md(assets: &[Asset], today: NaiveDate) -> String {
+    if assets.is_empty() {
+        return "No assets.\n".to_string();
+    }
+    let rows: Vec<Vec<String>> = assets
+        .iter()
+        .map(|asset| {
+            vec![
+                asset.ticker.clone(),
+                format_money(asset.buy_price_cents as i64),
+                match asset.sell_price_cents {
+                    Some(_) => "N/A (sold)".to_string(),
+                    None => format_money(asset.current_price_cents as i64),
+                },
+                format_percent(percent_change(asset) as f64),
+                match asset.sell_price_cents {
+                    Some(sell_price) => format_money(sell_price as i64),
+                    None => "N/A (currently held)".to_string(),
+                },
+                asset.quantity.to_string(),
+                format_money(cost_basis(asset)),
+                days_held(asset, today).map_or_else(|| "unknown".to_string(), |d| d.to_string()),
+            ]
+        })
+        .collect();
+    md_table(
+        &[
+            "Ticker",
+            "Buy Price",
+            "Current Price",
+            "Percent Change",
+            "Sell Price",
+            "Quantity",
+            "Cost Basis",
+            "Days Held",
+        ],
+        &[0],
+        &rows,
+    )
+}
+
+pub fn format_sectors_md(assets: &[Asset], metadata: &MetadataCache) -> String {
+    let sectors = sector_allocation(assets, metadata);
+    if sectors.is_empty() {
+        return "No held assets.\n".to_string();
+    }
+    let total: i64 = sectors.iter().map(|sector| sector.market_value).sum();
+    let rows: Vec<Vec<String>> = sectors
+        .iter()
+        .map(|sector| {
+            vec![
+                sector.sector.clone(),
+                format_money(sector.market_value),
+                format!("{:.1}%", share_percent(sector.market_value, total)),
+                sector.tickers.join(", "),
+            ]
+        })
+        .collect();
+    md_table(
+        &["Sector", "Market Value", "Share", "Tickers"],
+        &[0, 3],
+        &rows,
+    )
+}
+
+pub fn format_performance_md(assets: &[Asset], analytics: &PortfolioAnalytics) -> String {
+    let mut lines = vec![
+        format!(
+            "- Unrealized gain/loss: {}",
+            format_money_with_sign(analytics.unrealized_pnl)
+        ),
+        format!(
+            "- Realized gain/loss: {}",
+            format_money_with_sign(analytics.realized_pnl)
+        ),
+        match analytics.irr {
+            Some(rate) => format!("- IRR: {:.1}% a year", rate * 100.0),
+            None => "- IRR: n/a, it needs a buy date on every asset".to_string(),
+        },
+        match &analytics.drawdown {
+            None => "- Max drawdown: n/a, there's under two days of value history".to_string(),
+            Some(drawdown) if drawdown.decline() == 0 => "- Max drawdown: none".to_string(),
+            Some(drawdown) => format!("- Max drawdown: {}", describe_drawdown(drawdown)),
+        },
+    ];
+    let (best, worst) = best_and_worst_held(assets);
+    for (label, index) in [("Best", best), ("Worst", worst)] {
+        if let Some(index) = index {
+            lines.push(format!(
+                "- {} performer: {} ({})",
+                label,
+                assets[index].ticker,
+                format_percent(percent_change(&assets[index]) as f64)
+            ));
+        }
+    }
+    lines.join("\n") + "\n"
+}
+
+pub fn format_risks_md(
+    assets: &[Asset],
+    analytics: &PortfolioAnalytics,
+    metadata: &MetadataCache,
+    stale_hours: u32,
+    now: DateTime<Utc>,
+) -> String {
+    let today = now.date_naive();
+    let held: Vec<&Asset> = assets.iter().filter(|asset| is_asset_held(asset)).collect();
+    let mut risks = vec![];
+
+    let mut by_ticker: Vec<(String, i64)> = vec![];
+    for asset in &held {
+        let ticker = asset.ticker.to_uppercase();
+        match by_ticker.iter_mut().find(|(t, _)| *t == ticker) {
+            Some((_, value)) => *value += market_value(asset),
+            None => by_ticker.push((ticker, market_value(asset))),
+        }
+    }
+    for (ticker, value) in &by_ticker {
+        let share = share_percent(*value, analytics.total_value);
+        if share > POSITION_RISK_PERCENT {
+            risks.push(format!(
+                "- Concentration: {} is {:.1}% of the held value",
+                ticker, share
+            ));
+        }
+    }
+
+    let mut sectors: Vec<(&String, &i64)> = analytics.by_sector.iter().collect();
+    sectors.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+    for (sector, value) in sectors {
+        let share = share_percent(*value, analytics.total_value);
+        if share > SECTOR_RISK_PERCENT && sector != UNKNOWN_SECTOR {
+            risks.push(format!(
+                "- Sector concentration: {} is {:.1}% of the held value",
+                sector, share
+            ));
+        }
+    }
+    if let Some(unknown) = analytics.by_sector.get(UNKNOWN_SECTOR) {
+        risks.push(format!(
+            "- Unknown sector: {:.1}% of the held value has no sector, so the sector figures are incomplete",
+            share_percent(*unknown, analytics.total_value)
+        ));
+    }
+
+    let losing: Vec<&str> = held
+        .iter()
+        .filter(|asset| asset.current_price_cents < asset.buy_price_cents)
+        .map(|asset| asset.ticker.as_str())
+        .collect();
+    if !losing.is_empty() {
+        risks.push(format!(
+            "- Below cost: {} held lot{} ({})",
+            losing.len(),
+            if losing.len() == 1 { "" } else { "s" },
+            losing.join(", ")
+        ));
+    }
+
+    let stale = stale_held_assets(assets, now, stale_hours);
+    if stale > 0 {
+        risks.push(format!(
+            "- Stale prices: {} held lot{} not refreshed in the last {} hours, so the values above may be out of date",
+            stale,
+            if stale == 1 { "" } else { "s" },
+            stale_hours
+        ));
+    }
+
+    let short_term = held
+        .iter()
+        .filter(|asset| days_held(asset, today).is_some_and(|days| days < LONG_TERM_DAYS))
+        .count();
+    if short_term > 0 {
+        risks.push(format!(
+            "- Short-term lots: {} held under {} days, whose gains are taxed as income if sold now",
+            short_term, LONG_TERM_DAYS
+        ));
+    }
+
+    let soon: Vec<String> = upcoming_earnings(assets, metadata, today)
+        .into_iter()
+        .filter(|(_, date)| (*date - today).num_days() <= EARNINGS_SOON_DAYS)
+        .map(|(ticker, date)| format!("{} on {}", ticker, date))
+        .collect();
+    if !soon.is_empty() {
+        risks.push(format!("- Earnings soon: {}", soon.join(", ")));
+    }
+
+    if risks.is_empty() {
+        return "- None found.\n".to_string();
+    }
+    risks.join("\n") + "\n"
+}
+
+pub fn markdown_report(
+    portfolio: &Portfolio,
+    analytics: &PortfolioAnalytics,
+    metadata: &MetadataCache,
+    stale_hours: u32,
+    now: DateTime<Utc>,
+) -> String {
+    let name = if portfolio.name.is_empty() {
+        "untitled"
+    } else {
+        &portfolio.name
+    };
+    let mut report = format!(
+        "# Portfolio \"{}\"\n\nGenerated on {}.\n",
+        name,
+        now.date_naive()
+    );
+    let today = now.date_naive();
+    let sections = [
+        ("Summary", format_summary_md(analytics)),
+        ("Assets", format_assets_md(&portfolio.assets, today)),
+        (
+            "Sector allocation",
+            format_sectors_md(&portfolio.assets, metadata),
+        ),
+        (
+            "Performance",
+            format_performance_md(&portfolio.assets, analytics),
+        ),
+        (
+            "Key risks",
+            format_risks_md(&portfolio.assets, analytics, metadata, stale_hours, now),
+        ),
+    ];
+    for (title, body) in sections {
+        report.push_str(&format!("\n## {}\n\n{}", title, body));
+    }
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::portfolio::AssetBuilder;
+    use chrono::TimeZone;
+
+    fn now() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 6, 1, 12, 0, 0).unwrap()
+    }
+
+    fn lot(ticker: &str, buy: u32, current: u32) -> Asset {
+        let mut asset = AssetBuilder::new(ticker, buy)
+            .current_price(current)
+            .buy_date(NaiveDate::from_ymd_opt(2020, 1, 1).unwrap())
+            .build()
+            .unwrap();
+        asset.price_updated_at = Some(now());
+        asset
+    }
+
+    #[test]
+    fn tables_escape_pipes_and_align_numbers() {
+        let table = md_table(
+            &["Name", "Value"],
+            &[0],
+            &[vec!["a|b".to_string(), "1".to_string()]],
+        );
+        assert_eq!(table, "| Name | Value |\n| --- | ---: |\n| a\\|b | 1 |\n");
+    }
+
+    #[test]
+    fn report_has_every_section() {
+        let portfolio = Portfolio {
+            name: "main".to_string(),
+            assets: vec![lot("AAPL", 100, 150)],
+            ..Portfolio::default()
+        };
+        let metadata = MetadataCache::default();
+        let analytics = PortfolioAnalytics::compute(&portfolio, &metadata, now().date_naive());
+        let report = markdown_report(&portfolio, &analytics, &metadata, 24, now());
+        assert!(report.starts_with("# Portfolio \"main\"\n"));
+        for section in [
+            "## Summary",
+            "## Assets",
+            "## Sector allocation",
+            "## Performance",
+            "## Key risks",
+        ] {
+            assert!(report.contains(section), "{}", section);
+        }
+        assert!(
+            report.contains("| AAPL | $1.00 | $1.50 | +50.00% |"),
+            "{}",
+            report
+        );
+        assert!(
+            report.contains("- Best performer: AAPL (+50.00%)"),
+            "{}",
+            report
+        );
+    }
+
+    #[test]
+    fn risks_flag_concentration_and_losses() {
+        let mut metadata = MetadataCache::default();
+        for ticker in ["AAPL", "MSFT", "KO", "PFE", "JPM"] {
+            metadata.entry(ticker).sector = Some(ticker.to_string());
+        }
+        let even: Vec<Asset> = ["AAPL", "MSFT", "KO", "PFE", "JPM"]
+            .iter()
+            .map(|ticker| lot(ticker, 100, 100))
+            .collect();
+        let analytics = |assets: &[Asset]| {
+            let portfolio = Portfolio {
+                assets: assets.to_vec(),
+                ..Portfolio::default()
+            };
+            PortfolioAnalytics::compute(&portfolio, &metadata, now().date_naive())
+        };
+        assert_eq!(
+            format_risks_md(&even, &analytics(&even), &metadata, 24, now()),
+            "- None found.\n"
+        );
+
+        let lopsided = vec![lot("AAPL", 100, 900), lot("MSFT", 200, 100)];
+        let risks = format_risks_md(&lopsided, &analytics(&lopsided), &metadata, 24, now());
+        assert!(
+            risks.contains("- Concentration: AAPL is 90.0%"),
+            "{}",
+            risks
+        );
+        assert!(
+            risks.contains("- Sector concentration: AAPL is 90.0%"),
+            "{}",
+            risks
+        );
+        assert!(
+            risks.contains("- Below cost: 1 held lot (MSFT)"),
+            "{}",
+            risks
+        );
+    }
+}
@@ -0,0 +1,210 @@
+use std::fs;
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
+use tempfile::TempDir;
+
+const PORTFOLIO_JSON: &str = r#"{"name":"main","assets":[{"ticker":"AAPL","buy_price_cents":15000,"buy_date":"2023-01-05","current_price_cents":18942,"sell_price_cents":null,"sell_date":null,"quantity":100,"price_updated_at":null},{"ticker":"VTI","buy_price_cents":20000,"buy_date":"2023-01-05","current_price_cents":22000,"sell_price_cents":null,"sell_date":null,"quantity":10,"price_updated_at":null}],"value_history":[],"price_basis":1}"#;
+
+const QUOTES_JSON: &str = r#"{"AAPL": 200, "MSFT": {"price": 410.5, "previous_close": 400}}"#;
+
+// runs the tracker against fixed quotes with its config and cache kept in
+// `home`, feeding it `commands` and returning what it printed
+fn run_with_quotes(home: &TempDir, commands: &str) -> String {
+    String::from_utf8(run_tracker(home, commands).stdout).unwrap()
+}
+
+// the same, keeping the exit status
+fn run_tracker(home: &TempDir, commands: &str) -> Output {
+    let quotes = home.path().join("quotes.json");
+    fs::write(&quotes, QUOTES_JSON).unwrap();
+    let mut child = Command::new(env!("CARGO_BIN_EXE_portfolio-tracker"))
+        .arg("--quotes-file")
+        .arg(&quotes)
+        .env("HOME", home.path())
+        .env_remove("XDG_CONFIG_HOME")
+        .env_remove("XDG_CACHE_HOME")
+        .env_remove("PORTFOLIO_TRACKER_FILE")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(commands.as_bytes())
+        .unwrap();
+    child.wait_with_output().unwrap()
+}
+
+#[test]
+fn refresh_takes_prices_from_the_quotes_file() {
+    let home = TempDir::new().unwrap();
+    let portfolio = home.path().join("main.json");
+    fs::write(&portfolio, PORTFOLIO_JSON).unwrap();
+    let output = run_with_quotes(
+        &home,
+        &format!("load {0}\nrefresh\ndump {0}\nexit\n", portfolio.display()),
+    );
+    assert!(output.contains("Using 2 fixed quotes"), "{}", output);
+    assert!(
+        output.contains("Updated 1 ticker") && output.contains("failed: VTI (unknown symbol)"),
+        "{}",
+        output
+    );
+    let saved = fs::read_to_string(&portfolio).unwrap();
+    assert!(
+        saved.contains(r#""current_price_cents":20000"#),
+        "{}",
+        saved
+    );
+    assert!(
+        saved.contains(r#""current_price_cents":22000"#),
+        "{}",
+        saved
+    );
+}
+
+#[test]
+fn piped_commands_exit_non_zero_when_a_ticker_fails_to_refresh() {
+    let home = TempDir::new().unwrap();
+    let portfolio = home.path().join("main.json");
+    fs::write(&portfolio, PORTFOLIO_JSON).unwrap();
+    // VTI isn't in the quotes file
+    let output = run_tracker(
+        &home,
+        &format!("load {}\nrefresh\nexit\n", portfolio.display()),
+    );
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("failed: VTI"), "{}", stdout);
+    assert_eq!(output.status.code(), Some(1), "{}", stdout);
+
+    let output = run_tracker(&home, &format!("load {}\nexit\n", portfolio.display()));
+    assert!(output.status.success());
+}
+
+#[test]
+fn new_looks_up_the_price_in_the_quotes_file() {
+    let home = TempDir::new().unwrap();
+    let output = run_with_quotes(&home, "new\nMSFT\n400\nheld\n3\n\nunknown\nassets\nexit\n");
+    assert!(output.contains("$410.50"), "{}", output);
+}
+
+#[test]
+fn buy_adds_a_lot_at_the_current_price_once_confirmed() {
+    let home = TempDir::new().unwrap();
+    let output = run_with_quotes(&home, "buy msft 2\nn\nbuy msft 3\ny\nassets\nexit\n");
+    assert!(output.contains("Asset not added."), "{}", output);
+    assert!(
+        output.contains("Added MSFT: 3 shares @ $410.50"),
+        "{}",
+        output
+    );
+    // only the confirmed lot was kept
+    assert_eq!(output.matches("│ MSFT").count(), 1, "{}", output);
+    assert!(output.contains("$1231.50"), "{}", output);
+}
+
+#[test]
+fn targets_are_shown_until_cleared() {
+    let home = TempDir::new().unwrap();
+    let output = run_with_quotes(
+        &home,
+        "buy aapl 1\ny\ntarget aapl\n1\n$250\nassets\ntarget aapl\n1\n\nassets\nexit\n",
+    );
+    // $200 has to rise 25% to reach $250
+    assert_eq!(output.matches("To Target").count(), 1, "{}", output);
+    assert!(output.contains("+25.00%"), "{}", output);
+    assert!(output.contains("Current target: $250.00"), "{}", output);
+}
+
+#[test]
+fn stop_losses_are_breached_on_refresh_until_acknowledged() {
+    let home = TempDir::new().unwrap();
+    // bought at $200, so a 10% stop is $180 and a $210 one is already hit
+    let output = run_with_quotes(
+        &home,
+        "buy aapl 1\ny\nstop aapl\n1\n10%\nstops\nstop aapl\n1\n$210\nrefresh\nstops ack\nrefresh\nexit\n",
+    );
+    assert!(output.contains("10% below buy ($180.00)"), "{}", output);
+    assert_eq!(
+        output.matches("STOP-LOSS BREACHED: AAPL").count(),
+        1,
+        "{}",
+        output
+    );
+    assert_eq!(
+        output.matches("not yet acknowledged").count(),
+        1,
+        "{}",
+        output
+    );
+    assert!(
+        output.contains("Acknowledged 1 breached stop"),
+        "{}",
+        output
+    );
+}
+
+#[test]
+fn watch_needs_a_terminal() {
+    let home = TempDir::new().unwrap();
+    let output = run_with_quotes(&home, "watch 60\nexit\n");
+    assert!(output.contains("it needs a terminal"), "{}", output);
+    assert!(!output.contains("Left watch mode"), "{}", output);
+}
+
+#[test]
+fn default_file_is_opened_on_start_and_dumped_to() {
+    let home = TempDir::new().unwrap();
+    let portfolio = home.path().join("main.json");
+    run_with_quotes(
+        &home,
+        &format!(
+            "set default-file {}\nconfig save\nexit\n",
+            portfolio.display()
+        ),
+    );
+    let output = run_with_quotes(&home, "buy aapl 3\ny\ndump\nexit\n");
+    assert!(output.contains("doesn't exist yet"), "{}", output);
+    assert!(portfolio.exists(), "{}", output);
+    let output = run_with_quotes(&home, "config show\nexit\n");
+    assert!(output.contains("Loaded 1 asset"), "{}", output);
+    assert!(
+        output.contains("(from the default-file setting)"),
+        "{}",
+        output
+    );
+}
+
+#[test]
+fn dump_keeps_to_the_loaded_file_over_the_default_file() {
+    let home = TempDir::new().unwrap();
+    let main = home.path().join("main.json");
+    let other = home.path().join("other.json");
+    fs::write(&main, PORTFOLIO_JSON).unwrap();
+    run_with_quotes(
+        &home,
+        &format!(
+            "buy aapl 3\ny\ndump {}\nset default-file {}\nconfig save\nexit\n",
+            other.display(),
+            main.display()
+        ),
+    );
+    let output = run_with_quotes(
+        &home,
+        &format!("load {}\nbuy msft 1\ny\ndump\nexit\n", other.display()),
+    );
+    assert_eq!(
+        fs::read_to_string(&main).unwrap(),
+        PORTFOLIO_JSON,
+        "{}",
+        output
+    );
+    assert!(
+        fs::read_to_string(&other).unwrap().contains("MSFT"),
+        "{}",
+        output
+    );
+}
@@ -0,0 +1,262 @@
+use chrono::NaiveDate;
+use portfolio_tracker::config::DateOrder;
+use portfolio_tracker::error::PortfolioError;
+use portfolio_tracker::persistence::{
+    dump_portfolio_to_path, load_portfolio_detecting, load_portfolio_from_path, FileFormat,
+};
+use portfolio_tracker::portfolio::Portfolio;
+use std::fs;
+use std::io::Write;
+use tempfile::NamedTempFile;
+
+const PORTFOLIO_JSON: &str = r#"{"name":"main","assets":[{"ticker":"AAPL","buy_price_cents":15000,"buy_date":"2023-01-05","current_price_cents":18942,"sell_price_cents":null,"sell_date":null,"quantity":100,"price_updated_at":null},{"ticker":"MSFT","buy_price_cents":25000,"buy_date":null,"current_price_cents":31000,"sell_price_cents":30000,"sell_date":"2023-06-30","quantity":5,"price_updated_at":null}],"value_history":[],"price_basis":1}"#;
+
+fn temp_file_with(contents: &str) -> NamedTempFile {
+    let mut file = NamedTempFile::new().unwrap();
+    file.write_all(contents.as_bytes()).unwrap();
+    file
+}
+
+#[test]
+fn load_then_dump_round_trips() {
+    let input = temp_file_with(PORTFOLIO_JSON);
+    let portfolio = load_portfolio_from_path(input.path()).unwrap();
+
+    assert_eq!(portfolio.assets.len(), 2);
+    assert_eq!(portfolio.assets[0].ticker, "AAPL");
+    assert_eq!(portfolio.assets[0].buy_price_cents, 15000);
+    assert_eq!(portfolio.assets[0].current_price_cents, 18942);
+    assert_eq!(portfolio.assets[0].sell_price_cents, None);
+    assert_eq!(portfolio.assets[0].quantity, 100);
+    assert_eq!(portfolio.assets[1].ticker, "MSFT");
+    assert_eq!(portfolio.assets[1].sell_price_cents, Some(30000));
+
+    let output = NamedTempFile::new().unwrap();
+    dump_portfolio_to_path(&portfolio, output.path()).unwrap();
+    assert_eq!(fs::read_to_string(output.path()).unwrap(), PORTFOLIO_JSON);
+}
+
+#[test]
+fn load_accepts_files_without_value_history() {
+    let input = temp_file_with(r#"{"assets":[]}"#);
+    let portfolio = load_portfolio_from_path(input.path()).unwrap();
+    assert!(portfolio.assets.is_empty());
+    assert!(portfolio.value_history.is_empty());
+}
+
+#[test]
+fn load_names_unnamed_portfolio_after_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("retirement.json");
+    fs::write(&path, r#"{"assets":[]}"#).unwrap();
+    assert_eq!(load_portfolio_from_path(&path).unwrap().name, "retirement");
+}
+
+#[test]
+fn load_nonexistent_file_fails() {
+    let dir = tempfile::tempdir().unwrap();
+    let result = load_portfolio_from_path(&dir.path().join("missing.json"));
+    assert!(
+        matches!(result, Err(PortfolioError::IoError(e)) if e.kind() == std::io::ErrorKind::NotFound)
+    );
+}
+
+#[test]
+fn load_malformed_json_fails() {
+    let input = temp_file_with(r#"{"assets": [{"ticker": "AAPL""#);
+    assert!(matches!(
+        load_portfolio_from_path(input.path()),
+        Err(PortfolioError::ParseError(_))
+    ));
+}
+
+#[test]
+fn dump_to_unwritable_path_fails() {
+    let dir = tempfile::tempdir().unwrap();
+    let portfolio = load_portfolio_from_path(temp_file_with(PORTFOLIO_JSON).path()).unwrap();
+    assert!(matches!(
+        dump_portfolio_to_path(&portfolio, &dir.path().join("no/such/dir.json")),
+        Err(PortfolioError::IoError(_))
+    ));
+}
+
+fn today() -> NaiveDate {
+    NaiveDate::from_ymd_opt(2024, 6, 1).unwrap()
+}
+
+#[test]
+fn load_detects_toml_whatever_the_extension() {
+    let mut input = tempfile::Builder::new().suffix(".bak").tempfile().unwrap();
+    input
+        .write_all(
+            b"name = \"backup\"\n\n[[assets]]\nticker = \"VTI\"\nbuy_price_cents = 20000\n\
+              current_price_cents = 21000\nquantity = 3\n",
+        )
+        .unwrap();
+    let loaded = load_portfolio_detecting(input.path(), None, DateOrder::Mdy, today()).unwrap();
+    assert_eq!(loaded.format, FileFormat::Toml);
+    assert_eq!(loaded.portfolio.name, "backup");
+    assert_eq!(loaded.portfolio.assets[0].ticker, "VTI");
+}
+
+#[test]
+fn load_detects_csv_with_the_expected_headers() {
+    let mut input = tempfile::Builder::new().suffix(".txt").tempfile().unwrap();
+    input
+        .write_all(b"ticker,quantity,buy_price,buy_date\nAAPL,10,150.00,2024-01-05\n")
+        .unwrap();
+    let loaded = load_portfolio_detecting(input.path(), None, DateOrder::Mdy, today()).unwrap();
+    assert_eq!(loaded.format, FileFormat::Csv);
+    assert_eq!(loaded.portfolio.assets.len(), 1);
+    assert_eq!(loaded.portfolio.assets[0].buy_price_cents, 15000);
+}
+
+#[test]
+fn load_of_unrecognized_content_lists_every_attempt() {
+    let input = temp_file_with("this is not a portfolio\n");
+    match load_portfolio_detecting(input.path(), None, DateOrder::Mdy, today()) {
+        Err(PortfolioError::ParseError(message)) => {
+            assert!(message.contains("JSON: "), "{}", message);
+            assert!(message.contains("TOML: "), "{}", message);
+            assert!(message.contains("CSV: "), "{}", message);
+        }
+        other => panic!("expected a parse error, got {:?}", other.map(|l| l.format)),
+    }
+}
+
+#[test]
+fn load_with_a_format_only_tries_that_one() {
+    let input = temp_file_with(PORTFOLIO_JSON);
+    let loaded = load_portfolio_detecting(
+        input.path(),
+        Some(FileFormat::Json),
+        DateOrder::Mdy,
+        today(),
+    )
+    .unwrap();
+    assert_eq!(loaded.format, FileFormat::Json);
+    match load_portfolio_detecting(
+        input.path(),
+        Some(FileFormat::Toml),
+        DateOrder::Mdy,
+        today(),
+    ) {
+        Err(PortfolioError::ParseError(message)) => {
+            assert!(!message.contains("JSON"), "{}", message)
+        }
+        other => panic!("expected a parse error, got {:?}", other.map(|l| l.format)),
+    }
+}
+
+#[test]
+fn gzipped_dump_holds_the_same_portfolio() {
+    let input = temp_file_with(PORTFOLIO_JSON);
+    let portfolio = load_portfolio_from_path(input.path()).unwrap();
+    let dir = tempfile::tempdir().unwrap();
+    let plain = dir.path().join("main.json");
+    let compressed = dir.path().join("main.json.gz");
+    dump_portfolio_to_path(&portfolio, &plain).unwrap();
+    dump_portfolio_to_path(&portfolio, &compressed).unwrap();
+
+    assert_eq!(&fs::read(&compressed).unwrap()[..2], &[0x1f, 0x8b]);
+    let from_plain = load_portfolio_from_path(&plain).unwrap();
+    let from_compressed = load_portfolio_from_path(&compressed).unwrap();
+    assert_eq!(
+        serde_json::to_string(&from_plain).unwrap(),
+        serde_json::to_string(&from_compressed).unwrap()
+    );
+}
+
+#[test]
+fn gzip_is_detected_whatever_the_extension() {
+    let input = temp_file_with(PORTFOLIO_JSON);
+    let portfolio = load_portfolio_from_path(input.path()).unwrap();
+    let dir = tempfile::tempdir().unwrap();
+    let compressed = dir.path().join("main.json.gz");
+    dump_portfolio_to_path(&portfolio, &compressed).unwrap();
+    let renamed = dir.path().join("backup.dat");
+    fs::rename(&compressed, &renamed).unwrap();
+
+    let loaded = load_portfolio_detecting(&renamed, None, DateOrder::Mdy, today()).unwrap();
+    assert_eq!(loaded.format, FileFormat::Json);
+    assert_eq!(loaded.portfolio.assets.len(), 2);
+}
+
+#[test]
+fn load_of_truncated_gzip_reports_corruption() {
+    let input = temp_file_with(PORTFOLIO_JSON);
+    let portfolio = load_portfolio_from_path(input.path()).unwrap();
+    let dir = tempfile::tempdir().unwrap();
+    let compressed = dir.path().join("main.json.gz");
+    dump_portfolio_to_path(&portfolio, &compressed).unwrap();
+    let bytes = fs::read(&compressed).unwrap();
+    fs::write(&compressed, &bytes[..bytes.len() / 2]).unwrap();
+
+    match load_portfolio_from_path(&compressed) {
+        Err(PortfolioError::Corrupted(_)) => {}
+        other => panic!(
+            "expected a corrupted file error, got {:?}",
+            other.map(|_| ())
+        ),
+    }
+}
+
+#[test]
+fn price_history_round_trips_and_is_left_out_when_empty() {
+    let input = temp_file_with(
+        r#"{"assets":[{"ticker":"AAPL","buy_price_cents":15000,"current_price_cents":18942,"sell_price_cents":null,"quantity":1,"price_history":[["2024-01-02",18500],["2024-01-03",18942]]}]}"#,
+    );
+    let portfolio = load_portfolio_from_path(input.path()).unwrap();
+    assert_eq!(
+        portfolio.assets[0].price_history,
+        vec![
+            (NaiveDate::from_ymd_opt(2024, 1, 2).unwrap(), 18500),
+            (NaiveDate::from_ymd_opt(2024, 1, 3).unwrap(), 18942),
+        ]
+    );
+    let output = NamedTempFile::new().unwrap();
+    dump_portfolio_to_path(&portfolio, output.path()).unwrap();
+    let dumped = fs::read_to_string(output.path()).unwrap();
+    assert!(dumped.contains(r#""price_history":[["2024-01-02",18500],["2024-01-03",18942]]"#));
+
+    let without = load_portfolio_from_path(temp_file_with(PORTFOLIO_JSON).path()).unwrap();
+    dump_portfolio_to_path(&without, output.path()).unwrap();
+    assert!(!fs::read_to_string(output.path())
+        .unwrap()
+        .contains("price_history"));
+}
+
+#[test]
+fn json_strings_round_trip() {
+    let portfolio = Portfolio::from_json_str(PORTFOLIO_JSON).unwrap();
+    assert_eq!(portfolio.name, "main");
+    assert_eq!(portfolio.assets.len(), 2);
+    assert_eq!(
+        portfolio.assets[0].buy_date,
+        NaiveDate::from_ymd_opt(2023, 1, 5)
+    );
+    assert_eq!(portfolio.to_json_str().unwrap(), PORTFOLIO_JSON);
+}
+
+#[test]
+fn json_strings_must_be_complete_portfolios() {
+    let parse_error = |json: &str| match Portfolio::from_json_str(json) {
+        Err(PortfolioError::ParseError(message)) => message,
+        other => panic!("expected a parse error, got {:?}", other),
+    };
+    assert!(parse_error(r#"{"assets": [{"ticker": "AAPL""#).contains("EOF"));
+    assert!(parse_error(r#"{"name": "main"}"#).contains("missing field `assets`"));
+    let without_quantity = r#"{"assets":[{"ticker":"AAPL","buy_price_cents":15000,"current_price_cents":18942,"sell_price_cents":null}]}"#;
+    assert!(parse_error(without_quantity).contains("missing field `quantity`"));
+    assert!(parse_error(r#"{"assets": "AAPL"}"#).contains("invalid type"));
+}
+
+#[test]
+fn json_strings_ignore_unknown_fields() {
+    let newer = PORTFOLIO_JSON.replacen(r#""name":"main","#, r#""name":"main","owner":"sam","#, 1);
+    let newer = newer.replacen(r#""quantity":100,"#, r#""quantity":100,"lot_id":7,"#, 1);
+    let portfolio = Portfolio::from_json_str(&newer).unwrap();
+    assert_eq!(portfolio.assets[0].quantity, 100);
+    // and they aren't written back
+    assert_eq!(portfolio.to_json_str().unwrap(), PORTFOLIO_JSON);
+}